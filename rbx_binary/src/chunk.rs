@@ -77,6 +77,11 @@ impl ChunkBuilder {
         }
     }
 
+    /// Returns the uncompressed contents written to the chunk so far.
+    pub fn data(&self) -> &[u8] {
+        &self.buffer
+    }
+
     /// Consume the chunk and write it to the given writer.
     pub fn dump<W: Write>(self, mut writer: W) -> io::Result<()> {
         writer.write_all(self.chunk_name)?;
@@ -0,0 +1,375 @@
+//! Utilities for checking that rbx_binary can faithfully read and write a set
+//! of files.
+//!
+//! This is the same round-trip process that rbx_binary's own test suite runs
+//! against its test files, exposed so that forks and downstream projects can
+//! run it against their own corpus of models and places.
+//!
+//! ```no_run
+//! let report = rbx_binary::conformance::check_dir("test-files")?;
+//!
+//! for file in report.failures() {
+//!     println!("{}: {:?}", file.path.display(), file.outcome);
+//! }
+//!
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use rbx_dom_weak::{
+    types::{Ref, Variant, VariantType},
+    Ustr, WeakDom,
+};
+
+use crate::{Deserializer, Serializer};
+
+/// Checks every binary model and place in `path` and its subdirectories using
+/// rbx_binary's default settings.
+pub fn check_dir<P: AsRef<Path>>(path: P) -> io::Result<ConformanceReport> {
+    ConformanceChecker::new().check_dir(path)
+}
+
+/// Checks a single binary model or place using rbx_binary's default settings.
+pub fn check_file<P: AsRef<Path>>(path: P) -> io::Result<FileReport> {
+    ConformanceChecker::new().check_file(path)
+}
+
+/// A configurable runner for conformance checks.
+///
+/// The [`Deserializer`] and [`Serializer`] used can be replaced, which allows
+/// checking against a custom reflection database.
+pub struct ConformanceChecker<'db> {
+    deserializer: Deserializer<'db>,
+    serializer: Serializer<'db>,
+}
+
+impl<'db> ConformanceChecker<'db> {
+    /// Create a new `ConformanceChecker` with the default settings.
+    pub fn new() -> Self {
+        Self {
+            deserializer: Deserializer::new(),
+            serializer: Serializer::new(),
+        }
+    }
+
+    /// Sets the deserializer used to decode files.
+    #[inline]
+    pub fn deserializer(self, deserializer: Deserializer<'db>) -> Self {
+        Self {
+            deserializer,
+            ..self
+        }
+    }
+
+    /// Sets the serializer used to re-encode files.
+    #[inline]
+    pub fn serializer(self, serializer: Serializer<'db>) -> Self {
+        Self { serializer, ..self }
+    }
+
+    /// Checks every file with an `rbxm` or `rbxl` extension in `path` and its
+    /// subdirectories. Files are checked in a stable, sorted order.
+    pub fn check_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<ConformanceReport> {
+        let mut paths = Vec::new();
+        let mut to_search = vec![path.as_ref().to_path_buf()];
+
+        while let Some(dir) = to_search.pop() {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+
+                if path.is_dir() {
+                    to_search.push(path);
+                } else if matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("rbxm" | "rbxl")
+                ) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        paths.sort();
+
+        let files = paths
+            .into_iter()
+            .map(|path| self.check_file(path))
+            .collect::<io::Result<_>>()?;
+
+        Ok(ConformanceReport { files })
+    }
+
+    /// Checks a single file.
+    pub fn check_file<P: AsRef<Path>>(&self, path: P) -> io::Result<FileReport> {
+        let path = path.as_ref();
+        let contents = fs::read(path)?;
+
+        let mut report = self.check_bytes(&contents);
+        report.path = path.to_path_buf();
+
+        Ok(report)
+    }
+
+    /// Checks a file that has already been read into memory. The `path` of the
+    /// returned report will be empty.
+    pub fn check_bytes(&self, contents: &[u8]) -> FileReport {
+        let mut report = FileReport {
+            path: PathBuf::new(),
+            outcome: Outcome::Passed,
+            instance_count: 0,
+            property_coverage: HashMap::new(),
+            mismatches: Vec::new(),
+        };
+
+        let original = match self.deserializer.deserialize(contents) {
+            Ok(dom) => dom,
+            Err(err) => {
+                report.outcome = Outcome::DecodeFailed(err.to_string());
+                return report;
+            }
+        };
+
+        for instance in original.descendants() {
+            if instance.referent() == original.root_ref() {
+                continue;
+            }
+
+            report.instance_count += 1;
+            for value in instance.properties.values() {
+                *report.property_coverage.entry(value.ty()).or_insert(0) += 1;
+            }
+        }
+
+        let mut encoded = Vec::new();
+        if let Err(err) =
+            self.serializer
+                .serialize(&mut encoded, &original, original.root().children())
+        {
+            report.outcome = Outcome::EncodeFailed(err.to_string());
+            return report;
+        }
+
+        let roundtripped = match self.deserializer.deserialize(encoded.as_slice()) {
+            Ok(dom) => dom,
+            Err(err) => {
+                report.outcome = Outcome::RedecodeFailed(err.to_string());
+                return report;
+            }
+        };
+
+        report.mismatches = compare_doms(&original, &roundtripped);
+        if !report.mismatches.is_empty() {
+            report.outcome = Outcome::Mismatched;
+        }
+
+        report
+    }
+}
+
+impl Default for ConformanceChecker<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The results of checking a set of files.
+#[derive(Debug, Clone)]
+pub struct ConformanceReport {
+    /// The report for each file that was checked.
+    pub files: Vec<FileReport>,
+}
+
+impl ConformanceReport {
+    /// Returns whether every file passed.
+    pub fn passed(&self) -> bool {
+        self.files.iter().all(FileReport::passed)
+    }
+
+    /// Returns an iterator over the files that did not pass.
+    pub fn failures(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|file| !file.passed())
+    }
+
+    /// Returns the number of properties of each type that were decoded across
+    /// every file.
+    pub fn property_coverage(&self) -> HashMap<VariantType, usize> {
+        let mut coverage = HashMap::new();
+
+        for file in &self.files {
+            for (&ty, &count) in &file.property_coverage {
+                *coverage.entry(ty).or_insert(0) += count;
+            }
+        }
+
+        coverage
+    }
+}
+
+/// The results of checking a single file.
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    /// The path of the file that was checked.
+    pub path: PathBuf,
+
+    /// How far the file made it through the round trip.
+    pub outcome: Outcome,
+
+    /// The number of instances contained in the file.
+    pub instance_count: usize,
+
+    /// The number of properties of each type that were decoded from the file.
+    pub property_coverage: HashMap<VariantType, usize>,
+
+    /// Differences between the decoded file and the decoded re-encoding of it.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl FileReport {
+    /// Returns whether the file survived the round trip unchanged.
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, Outcome::Passed)
+    }
+}
+
+/// How far a file made it through the decode, encode, decode round trip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The file was decoded, re-encoded, and decoded again with no differences.
+    Passed,
+
+    /// The file could not be decoded.
+    DecodeFailed(String),
+
+    /// The decoded file could not be encoded again.
+    EncodeFailed(String),
+
+    /// The re-encoded file could not be decoded.
+    RedecodeFailed(String),
+
+    /// The file round-tripped, but the result differed from the original. See
+    /// [`FileReport::mismatches`] for details.
+    Mismatched,
+}
+
+/// A difference found between a decoded file and its round-tripped version.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatch {
+    /// The tree structure differed at the instance with the given path.
+    Structure {
+        /// The full name of the instance in the original file.
+        instance: String,
+    },
+
+    /// A property was present in the original file but absent after the
+    /// round trip.
+    MissingProperty {
+        /// The full name of the instance in the original file.
+        instance: String,
+        /// The name of the property.
+        property: Ustr,
+    },
+
+    /// A property had a different value after the round trip.
+    ChangedProperty {
+        /// The full name of the instance in the original file.
+        instance: String,
+        /// The name of the property.
+        property: Ustr,
+        /// The value in the original file.
+        original: Variant,
+        /// The value after the round trip.
+        roundtripped: Variant,
+    },
+}
+
+/// Walks both doms in lockstep and returns any differences in structure or in
+/// properties present in `original`.
+fn compare_doms(original: &WeakDom, roundtripped: &WeakDom) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+
+    // Pair up instances first so that Ref properties can be compared.
+    let mut pairs = Vec::new();
+    let mut ref_map = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back((original.root_ref(), roundtripped.root_ref(), String::new()));
+
+    while let Some((left_ref, right_ref, path)) = queue.pop_front() {
+        let left = original.get_by_ref(left_ref).unwrap();
+        let right = roundtripped.get_by_ref(right_ref).unwrap();
+
+        if left.children().len() != right.children().len() {
+            mismatches.push(Mismatch::Structure { instance: path });
+            continue;
+        }
+
+        for (&left_child, &right_child) in left.children().iter().zip(right.children()) {
+            let left_instance = original.get_by_ref(left_child).unwrap();
+            let right_instance = roundtripped.get_by_ref(right_child).unwrap();
+
+            let child_path = if path.is_empty() {
+                left_instance.name.clone()
+            } else {
+                format!("{}.{}", path, left_instance.name)
+            };
+
+            if left_instance.class != right_instance.class
+                || left_instance.name != right_instance.name
+            {
+                mismatches.push(Mismatch::Structure {
+                    instance: child_path,
+                });
+                continue;
+            }
+
+            ref_map.insert(left_child, right_child);
+            pairs.push((left_child, right_child, child_path.clone()));
+            queue.push_back((left_child, right_child, child_path));
+        }
+    }
+
+    for (left_ref, right_ref, path) in pairs {
+        let left = original.get_by_ref(left_ref).unwrap();
+        let right = roundtripped.get_by_ref(right_ref).unwrap();
+
+        let mut property_names: Vec<_> = left.properties.keys().copied().collect();
+        property_names.sort();
+
+        for name in property_names {
+            let left_value = &left.properties[&name];
+
+            let right_value = match right.properties.get(&name) {
+                Some(value) => value,
+                None => {
+                    mismatches.push(Mismatch::MissingProperty {
+                        instance: path.clone(),
+                        property: name,
+                    });
+                    continue;
+                }
+            };
+
+            let equal = match (left_value, right_value) {
+                (Variant::Ref(left), Variant::Ref(right)) => {
+                    let mapped = ref_map.get(left).copied().unwrap_or_else(Ref::none);
+                    mapped == *right
+                }
+                _ => left_value == right_value,
+            };
+
+            if !equal {
+                mismatches.push(Mismatch::ChangedProperty {
+                    instance: path.clone(),
+                    property: name,
+                    original: left_value.clone(),
+                    roundtripped: right_value.clone(),
+                });
+            }
+        }
+    }
+
+    mismatches
+}
@@ -2,6 +2,8 @@ use std::io;
 
 use thiserror::Error;
 
+use rbx_dom_weak::BudgetError;
+
 use crate::types::InvalidTypeError;
 
 /// Represents an error that occurred during deserialization.
@@ -27,6 +29,12 @@ pub(crate) enum InnerError {
         source: io::Error,
     },
 
+    #[error(transparent)]
+    Budget {
+        #[from]
+        source: BudgetError,
+    },
+
     #[error("Invalid file header")]
     BadHeader,
 
@@ -80,6 +88,9 @@ pub(crate) enum InnerError {
         actual_type_id: u8,
     },
 
+    #[error("Invalid property data: Terrain.SmoothGrid could not be decoded: {source}")]
+    BadSmoothGrid { source: rbx_dom_weak::types::Error },
+
     #[error("'Content' type {0} is not implemented")]
     BadContentType(i32),
 }
@@ -2,14 +2,17 @@ mod error;
 mod header;
 mod state;
 
-use std::{io::Read, str};
+use std::{collections::HashSet, io::Read, str};
 
-use rbx_dom_weak::WeakDom;
+use rbx_dom_weak::{
+    types::{Ref, SharedString, Variant},
+    Diagnostics, DomBudget, InstanceBuilder, WeakDom,
+};
 use rbx_reflection::ReflectionDatabase;
 
 use self::state::DeserializerState;
 
-pub(crate) use self::header::FileHeader;
+pub(crate) use self::{error::InnerError, header::FileHeader};
 
 pub use self::error::Error;
 
@@ -48,6 +51,10 @@ pub use self::error::Error;
 /// [reflection_database]: Deserializer#method.reflection_database
 pub struct Deserializer<'db> {
     database: &'db ReflectionDatabase<'db>,
+    track_source_locations: bool,
+    decode_terrain: bool,
+    budget: DomBudget,
+    diagnostics: Diagnostics,
 }
 
 impl<'db> Deserializer<'db> {
@@ -55,13 +62,66 @@ impl<'db> Deserializer<'db> {
     pub fn new() -> Self {
         Self {
             database: rbx_reflection_database::get(),
+            track_source_locations: false,
+            decode_terrain: false,
+            budget: DomBudget::new(),
+            diagnostics: Diagnostics::default(),
         }
     }
 
     /// Sets what reflection database for the deserializer to use.
     #[inline]
     pub fn reflection_database(self, database: &'db ReflectionDatabase<'db>) -> Self {
-        Self { database }
+        Self { database, ..self }
+    }
+
+    /// Sets whether the deserializer should record where each instance was
+    /// declared. When enabled, every instance is given a
+    /// [`SourceLocation::Bytes`](rbx_dom_weak::SourceLocation::Bytes) in its
+    /// metadata, covering the `INST` chunk that declared it. This is off by
+    /// default.
+    #[inline]
+    pub fn track_source_locations(self, track_source_locations: bool) -> Self {
+        Self {
+            track_source_locations,
+            ..self
+        }
+    }
+
+    /// Sets whether the `SmoothGrid` property of `Terrain` is decoded into a
+    /// [`SmoothGrid`](rbx_dom_weak::types::SmoothGrid) instead of being left
+    /// as a `BinaryString`. Blobs that can't be decoded are still left as a
+    /// `BinaryString`. `MaterialColors` is always decoded. This is off by
+    /// default.
+    ///
+    /// `PhysicsGrid` is always left as a `BinaryString`, since its format
+    /// isn't known.
+    #[inline]
+    pub fn decode_terrain(self, decode_terrain: bool) -> Self {
+        Self {
+            decode_terrain,
+            ..self
+        }
+    }
+
+    /// Sets the [`DomBudget`] given to deserialized doms. Files that declare
+    /// more instances than the budget allows are rejected before any
+    /// instances are read, and other files that don't fit return an error
+    /// once their contents are known. By default, there are no limits.
+    #[inline]
+    pub fn budget(self, budget: DomBudget) -> Self {
+        Self { budget, ..self }
+    }
+
+    /// Sets where the deserializer reports problems it recovers from, like
+    /// properties it can't read and Ref properties that point to instances
+    /// missing from the file.
+    #[inline]
+    pub fn diagnostics(self, diagnostics: Diagnostics) -> Self {
+        Self {
+            diagnostics,
+            ..self
+        }
     }
 
     /// Deserialize a Roblox binary model or place from the given stream using
@@ -69,7 +129,114 @@ impl<'db> Deserializer<'db> {
     pub fn deserialize<R: Read>(&self, reader: R) -> Result<WeakDom, Error> {
         profiling::scope!("rbx_binary::deserialize");
 
-        let mut deserializer = DeserializerState::new(self, reader)?;
+        let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+        self.deserialize_tree(reader, &mut tree)?;
+
+        Ok(tree)
+    }
+
+    /// Deserialize a Roblox binary model or place from the given stream into
+    /// an existing `WeakDom`, reusing its allocated storage.
+    ///
+    /// Any instances already in `dom` are removed first, and its budget is
+    /// replaced with the one set by [`budget`](Deserializer::budget). If
+    /// deserialization fails, `dom` is left with only an empty `DataModel` as
+    /// its root, and none of the file's instances.
+    pub fn deserialize_into<R: Read>(&self, reader: R, dom: &mut WeakDom) -> Result<(), Error> {
+        profiling::scope!("rbx_binary::deserialize_into");
+
+        // A budget may be too small for even the root, so it's lifted while
+        // the dom is reset.
+        let reset = |dom: &mut WeakDom| {
+            dom.set_budget(DomBudget::new());
+            dom.reset(InstanceBuilder::new("DataModel"));
+            dom.set_budget(self.budget);
+        };
+
+        reset(dom);
+        let result = self.deserialize_tree(reader, dom);
+        if result.is_err() {
+            reset(dom);
+        }
+
+        result
+    }
+
+    /// Deserialize a Roblox binary model from the given stream, adding the
+    /// instances at the top level of the file to an existing `WeakDom` as
+    /// children of `parent`. Returns the referents of those instances.
+    ///
+    /// Instances are inserted directly into `dom`, so this avoids decoding
+    /// into a separate `WeakDom` and transferring the instances afterwards.
+    /// Ref properties are resolved to the referents of the newly inserted
+    /// instances. The budget of `dom` applies instead of the one set by
+    /// [`budget`](Deserializer::budget). If deserialization fails, `dom` is
+    /// left unchanged.
+    ///
+    /// ## Panics
+    /// Panics if `parent` does not refer to an instance in `dom`.
+    pub fn deserialize_into_parent<R: Read>(
+        &self,
+        reader: R,
+        dom: &mut WeakDom,
+        parent: Ref,
+    ) -> Result<Vec<Ref>, Error> {
+        profiling::scope!("rbx_binary::deserialize_into_parent");
+
+        assert!(
+            dom.get_by_ref(parent).is_some(),
+            "cannot deserialize into an instance that does not exist"
+        );
+
+        Ok(self.read_chunks(reader, dom)?.finish(dom, parent)?)
+    }
+
+    /// Finds the SharedStrings stored in the given binary model or place that
+    /// no property refers to, in the order they appear in the file.
+    ///
+    /// These strings are dropped when a decoded file is serialized again, since
+    /// [`Serializer`](crate::Serializer) only writes SharedStrings that are
+    /// used by a property.
+    pub fn orphaned_shared_strings<R: Read>(&self, reader: R) -> Result<Vec<SharedString>, Error> {
+        profiling::scope!("rbx_binary::orphaned_shared_strings");
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+        let mut deserializer = self.read_chunks(reader, &mut dom)?;
+        let shared_strings = deserializer.take_shared_strings();
+        deserializer.finish(&mut dom, root_ref)?;
+
+        let referenced: HashSet<&SharedString> = dom
+            .descendants()
+            .flat_map(|instance| instance.properties.values())
+            .filter_map(|value| match value {
+                Variant::SharedString(shared_string) => Some(shared_string),
+                _ => None,
+            })
+            .collect();
+
+        Ok(shared_strings
+            .iter()
+            .filter(|shared_string| !referenced.contains(shared_string))
+            .cloned()
+            .collect())
+    }
+
+    fn deserialize_tree<R: Read>(&self, reader: R, tree: &mut WeakDom) -> Result<(), Error> {
+        tree.set_budget(self.budget);
+
+        let root_ref = tree.root_ref();
+        self.read_chunks(reader, tree)?.finish(tree, root_ref)?;
+
+        Ok(())
+    }
+
+    fn read_chunks<R: Read>(
+        &self,
+        reader: R,
+        tree: &mut WeakDom,
+    ) -> Result<DeserializerState<'_, R>, Error> {
+        let mut deserializer = DeserializerState::new(self, reader, tree)?;
 
         loop {
             let chunk = deserializer.next_chunk()?;
@@ -91,7 +258,7 @@ impl<'db> Deserializer<'db> {
             }
         }
 
-        Ok(deserializer.finish())
+        Ok(deserializer)
     }
 }
 
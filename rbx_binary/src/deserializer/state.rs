@@ -1,4 +1,4 @@
-use std::{borrow::Cow, collections::VecDeque, convert::TryInto, io::Read};
+use std::{borrow::Cow, collections::VecDeque, convert::TryInto, io::Read, ops::Range};
 
 use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use rbx_dom_weak::{
@@ -7,16 +7,16 @@ use rbx_dom_weak::{
         ColorSequenceKeypoint, Content, ContentId, CustomPhysicalProperties, Enum, Faces, Font,
         FontStyle, FontWeight, MaterialColors, Matrix3, NumberRange, NumberSequence,
         NumberSequenceKeypoint, PhysicalProperties, Ray, Rect, Ref, SecurityCapabilities,
-        SharedString, Tags, UDim, UDim2, UniqueId, Variant, VariantType, Vector2, Vector3,
-        Vector3int16,
+        SharedString, SmoothGrid, Tags, UDim, UDim2, UniqueId, Variant, VariantType, Vector2,
+        Vector3, Vector3int16,
     },
-    InstanceBuilder, Ustr, WeakDom,
+    Diagnostic, DomBudget, InstanceBuilder, SourceLocation, Ustr, WeakDom,
 };
 use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
 use crate::{
     chunk::Chunk,
-    core::{find_property_descriptors, RbxReadExt},
+    core::{find_property_descriptors, CountingReader, RbxReadExt},
     types::Type,
 };
 
@@ -29,9 +29,12 @@ pub(super) struct DeserializerState<'db, R> {
     /// The input data encoded as a binary model.
     input: R,
 
-    /// The tree that instances should be written into. Eventually returned to
-    /// the user.
-    tree: WeakDom,
+    /// The number of bytes read from `input` so far.
+    position: u64,
+
+    /// The range of bytes in `input` occupied by the most recently read
+    /// chunk.
+    chunk_range: Range<u64>,
 
     /// The metadata contained in the file, which affects how some constructs
     /// are interpreted by Roblox.
@@ -55,6 +58,13 @@ pub(super) struct DeserializerState<'db, R> {
     /// deserializing this file. We use this map in order to ensure we only
     /// print one warning per unknown type ID when deserializing a file.
     unknown_type_ids: HashSet<u8>,
+
+    /// The budget of the tree that the file is being decoded into.
+    budget: DomBudget,
+
+    /// The estimated size of the tree's properties and every property
+    /// counted from the file so far, if the budget limits it.
+    property_bytes: Option<usize>,
 }
 
 /// Represents a unique instance class. Binary models define all their instance
@@ -80,6 +90,10 @@ struct Instance {
 
     /// Document-defined IDs for the children of this instance.
     children: Vec<i32>,
+
+    /// The estimated size of the properties added to this instance since
+    /// they were last counted against the budget, if the budget limits it.
+    property_bytes: Option<usize>,
 }
 
 /// Properties may be serialized under different names or types than
@@ -188,6 +202,9 @@ fn add_property(instance: &mut Instance, canonical_property: &CanonicalProperty,
             );
             match migration.perform(&value) {
                 Ok(new_value) => {
+                    if let Some(bytes) = &mut instance.property_bytes {
+                        *bytes += DomBudget::property_size(new_property_name, &new_value);
+                    }
                     instance.builder.add_property(new_property_name, new_value);
                     log::trace!(
                         "Successfully migrated property {old_property_name} to {new_property_name}"
@@ -202,6 +219,9 @@ fn add_property(instance: &mut Instance, canonical_property: &CanonicalProperty,
             };
         }
     } else {
+        if let Some(bytes) = &mut instance.property_bytes {
+            *bytes += DomBudget::property_size(&canonical_property.name, &value);
+        }
         instance
             .builder
             .add_property(canonical_property.name, value)
@@ -212,31 +232,63 @@ impl<'db, R: Read> DeserializerState<'db, R> {
     pub(super) fn new(
         deserializer: &'db Deserializer<'db>,
         mut input: R,
+        tree: &mut WeakDom,
     ) -> Result<Self, InnerError> {
-        let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
-
-        let header = FileHeader::decode(&mut input)?;
+        let mut header_reader = CountingReader::new(&mut input);
+        let header = FileHeader::decode(&mut header_reader)?;
+        let position = header_reader.count;
 
         let type_infos = HashMap::with_capacity(header.num_types as usize);
         let instances_by_ref = HashMap::with_capacity(1 + header.num_instances as usize);
 
+        // Reject files that declare more instances than the tree is allowed
+        // to hold before allocating any space for them. Counting the tree is
+        // linear in its size, so it's skipped when there's no limit.
+        if tree.budget().instance_limit().is_some() {
+            let existing_instances = tree.descendants().count();
+            tree.budget().check(
+                existing_instances + header.num_instances as usize,
+                tree.property_bytes(),
+            )?;
+        }
+
+        let property_bytes = tree
+            .budget()
+            .property_bytes_limit()
+            .map(|_| tree.property_bytes());
         tree.reserve(header.num_instances as usize);
 
         Ok(DeserializerState {
             deserializer,
             input,
-            tree,
+            position,
+            chunk_range: position..position,
             metadata: HashMap::new(),
             shared_strings: Vec::new(),
             type_infos,
             instances_by_ref,
             root_instance_refs: Vec::new(),
             unknown_type_ids: HashSet::new(),
+            budget: tree.budget(),
+            property_bytes,
         })
     }
 
+    /// Takes the SharedStrings read from the file so far, in the order that
+    /// they appeared.
+    pub(super) fn take_shared_strings(&mut self) -> Vec<SharedString> {
+        std::mem::take(&mut self.shared_strings)
+    }
+
     pub(super) fn next_chunk(&mut self) -> Result<Chunk, InnerError> {
-        Ok(Chunk::decode(&mut self.input)?)
+        let mut reader = CountingReader::new(&mut self.input);
+        let chunk = Chunk::decode(&mut reader)?;
+
+        let start = self.position;
+        self.position += reader.count;
+        self.chunk_range = start..self.position;
+
+        Ok(chunk)
     }
 
     #[profiling::function]
@@ -305,14 +357,22 @@ impl<'db, R: Read> DeserializerState<'db, R> {
         // TODO: Check object_format and check for service markers if it's 1?
 
         for &referent in &referents {
+            let mut builder =
+                InstanceBuilder::with_property_capacity(type_name.as_str(), prop_capacity);
+
+            if self.deserializer.track_source_locations {
+                builder.add_metadata(SourceLocation::Bytes {
+                    start: self.chunk_range.start,
+                    end: self.chunk_range.end,
+                });
+            }
+
             self.instances_by_ref.insert(
                 referent,
                 Instance {
-                    builder: InstanceBuilder::with_property_capacity(
-                        type_name.as_str(),
-                        prop_capacity,
-                    ),
+                    builder,
                     children: Vec::new(),
+                    property_bytes: self.property_bytes.map(|_| 0),
                 },
             );
         }
@@ -364,6 +424,13 @@ impl<'db, R: Read> DeserializerState<'db, R> {
                     );
                 }
 
+                self.deserializer
+                    .diagnostics
+                    .report(Diagnostic::PropertySkipped {
+                        class: type_info.type_name,
+                        property: prop_name.as_str().into(),
+                    });
+
                 return Ok(());
             }
         };
@@ -413,9 +480,26 @@ This may cause unexpected or broken behavior in your final results if you rely o
         ) {
             property
         } else {
+            self.deserializer
+                .diagnostics
+                .report(Diagnostic::PropertySkipped {
+                    class: type_info.type_name,
+                    property: prop_name.as_str().into(),
+                });
+
             return Ok(());
         };
 
+        if let Some(PropertySerialization::Migrate(migration)) = property.migration {
+            self.deserializer
+                .diagnostics
+                .report(Diagnostic::MigrationApplied {
+                    class: type_info.type_name,
+                    property: property.name,
+                    new_property: migration.new_property_name.as_str().into(),
+                });
+        }
+
         let canonical_type = property.ty;
 
         match binary_type {
@@ -449,10 +533,35 @@ This may cause unexpected or broken behavior in your final results if you rely o
                     }
                 }
                 VariantType::BinaryString => {
+                    let decode_grid = self.deserializer.decode_terrain
+                        && type_info.type_name == "Terrain"
+                        && property.name == "SmoothGrid";
+
                     for referent in &type_info.referents {
                         let instance = self.instances_by_ref.get_mut(referent).unwrap();
-                        let value: BinaryString = chunk.read_binary_string()?.into();
-                        add_property(instance, &property, value.into());
+                        let buffer = chunk.read_binary_string()?;
+
+                        if !decode_grid {
+                            add_property(instance, &property, BinaryString::from(buffer).into());
+                            continue;
+                        }
+
+                        match SmoothGrid::decode(&buffer) {
+                            Ok(value) => add_property(instance, &property, value.into()),
+                            Err(err) => {
+                                log::warn!(
+                                    "Failed to parse SmoothGrid on {} because {:?}; falling back to BinaryString.",
+                                    type_info.type_name,
+                                    err
+                                );
+
+                                add_property(
+                                    instance,
+                                    &property,
+                                    BinaryString::from(buffer).into(),
+                                );
+                            }
+                        }
                     }
                 }
                 VariantType::Tags => {
@@ -988,6 +1097,18 @@ rbx-dom may require changes to fully support this property. Please open an issue
                         let rbx_value = if let Some(instance) = self.instances_by_ref.get(&value) {
                             instance.builder.referent()
                         } else {
+                            // -1 is how a null Ref is written, so any other
+                            // missing referent is a broken reference.
+                            if value != -1 {
+                                let instance = &self.instances_by_ref[referent];
+                                self.deserializer
+                                    .diagnostics
+                                    .report(Diagnostic::RefCleared {
+                                        referent: instance.builder.referent(),
+                                        property: property.name,
+                                    });
+                            }
+
                             Ref::none()
                         };
 
@@ -1494,6 +1615,29 @@ rbx-dom may require changes to fully support this property. Please open an issue
             },
         }
 
+        self.check_property_budget(type_id)
+    }
+
+    /// Counts the properties decoded for instances of the given type against
+    /// the tree's budget. This happens after every PROP chunk so that a file
+    /// with too many properties is rejected before all of them are decoded.
+    fn check_property_budget(&mut self, type_id: u32) -> Result<(), InnerError> {
+        let mut property_bytes = match self.property_bytes {
+            Some(bytes) => bytes,
+            None => return Ok(()),
+        };
+
+        for referent in &self.type_infos[&type_id].referents {
+            let instance = self.instances_by_ref.get_mut(referent).unwrap();
+
+            if let Some(bytes) = instance.property_bytes.replace(0) {
+                property_bytes = property_bytes.saturating_add(bytes);
+            }
+        }
+
+        self.budget.check_property_bytes(property_bytes)?;
+        self.property_bytes = Some(property_bytes);
+
         Ok(())
     }
 
@@ -1542,9 +1686,17 @@ rbx-dom may require changes to fully support this property. Please open an issue
     }
 
     /// Combines together all the decoded information to build and emplace
-    /// instances in our tree.
+    /// instances in `tree` as children of `parent_ref`, returning the
+    /// referents of the instances at the top level of the file.
+    ///
+    /// If an instance doesn't fit in the tree's budget, every instance that
+    /// was already added is removed again before the error is returned.
     #[profiling::function]
-    pub(super) fn finish(mut self) -> WeakDom {
+    pub(super) fn finish(
+        mut self,
+        tree: &mut WeakDom,
+        parent_ref: Ref,
+    ) -> Result<Vec<Ref>, InnerError> {
         log::trace!("Constructing tree from deserialized data");
 
         // Track all the instances we need to construct. Order of construction
@@ -1553,22 +1705,36 @@ rbx-dom may require changes to fully support this property. Please open an issue
         let mut instances_to_construct = VecDeque::new();
 
         // Any instance with a parent of -1 will be at the top level of the
-        // tree. Because of the way rbx_dom_weak generally works, we need to
+        // file. Because of the way rbx_dom_weak generally works, we need to
         // start at the top of the tree to begin construction.
-        let root_ref = self.tree.root_ref();
         for &referent in &self.root_instance_refs {
-            instances_to_construct.push_back((referent, root_ref));
+            instances_to_construct.push_back((referent, parent_ref));
         }
 
-        while let Some((referent, parent_ref)) = instances_to_construct.pop_front() {
+        let mut top_level_refs = Vec::with_capacity(self.root_instance_refs.len());
+
+        while let Some((referent, instance_parent_ref)) = instances_to_construct.pop_front() {
             let instance = self.instances_by_ref.remove(&referent).unwrap();
-            let id = self.tree.insert(parent_ref, instance.builder);
+            let id = match tree.try_insert(instance_parent_ref, instance.builder) {
+                Ok(id) => id,
+                Err(err) => {
+                    for &id in &top_level_refs {
+                        tree.destroy(id);
+                    }
+
+                    return Err(err.into());
+                }
+            };
+
+            if instance_parent_ref == parent_ref {
+                top_level_refs.push(id);
+            }
 
             for referent in instance.children {
                 instances_to_construct.push_back((referent, id));
             }
         }
 
-        self.tree
+        Ok(top_level_refs)
     }
 }
@@ -0,0 +1,85 @@
+//! Describes how faithfully each property type survives a round trip through
+//! the binary format.
+//!
+//! Properties known to the reflection database are always read back as the
+//! type the database expects. Other properties are written as whatever type
+//! they hold, and a few of those types are read back as a different type or
+//! can't be written at all. Checking a dom before converting it from another
+//! format makes these losses visible instead of silent.
+//!
+//! ```
+//! use rbx_binary::{
+//!     fidelity::{check_fidelity, type_fidelity, Fidelity},
+//!     Serializer,
+//! };
+//! use rbx_dom_weak::{types::VariantType, InstanceBuilder, WeakDom};
+//!
+//! assert_eq!(type_fidelity(VariantType::Vector3), Fidelity::Exact);
+//! assert_eq!(type_fidelity(VariantType::Region3), Fidelity::Unsupported);
+//!
+//! let dom = WeakDom::new(InstanceBuilder::new("Folder").with_property("Note", "hello"));
+//! let lossy = check_fidelity(&dom, &Serializer::new());
+//!
+//! assert_eq!(lossy.len(), 1);
+//! assert_eq!(lossy[0].property, "Note");
+//! ```
+
+use rbx_dom_weak::{types::VariantType, WeakDom};
+
+use crate::{core::find_property_descriptors, types::Type, Serializer};
+
+pub use rbx_dom_weak::{Fidelity, LossyProperty};
+
+/// Returns how faithfully values of the given type are written by rbx_binary
+/// when the property isn't known to the reflection database.
+///
+/// Floating point values are written in their full binary form, so every type
+/// the format supports keeps its precision. Properties known to the reflection
+/// database are converted to the type the database expects and are always
+/// read back as that type.
+pub fn type_fidelity(ty: VariantType) -> Fidelity {
+    match ty {
+        VariantType::String
+        | VariantType::ContentId
+        | VariantType::Tags
+        | VariantType::Attributes
+        | VariantType::MaterialColors
+        | VariantType::SmoothGrid => Fidelity::Lossy {
+            reason: "written as a string, and read back as a BinaryString",
+        },
+        _ => match Type::from_rbx_type(ty) {
+            Some(_) => Fidelity::Exact,
+            None => Fidelity::Unsupported,
+        },
+    }
+}
+
+/// Lists every property in the dom that would be lost or changed by writing
+/// the dom with the given serializer and reading it back.
+///
+/// Properties are looked up in the serializer's reflection database, so this
+/// should be given the same serializer that will write the dom.
+pub fn check_fidelity(dom: &WeakDom, serializer: &Serializer) -> Vec<LossyProperty> {
+    let database = serializer.database;
+    let mut lossy = Vec::new();
+
+    for instance in dom.descendants() {
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_unstable_by_key(|(name, _)| name.as_str());
+
+        for (name, value) in properties {
+            if find_property_descriptors(database, instance.class, *name).is_some() {
+                continue;
+            }
+
+            let ty = value.ty();
+            let fidelity = type_fidelity(ty);
+
+            if fidelity != Fidelity::Exact {
+                lossy.push(LossyProperty::new(instance.referent(), *name, ty, fidelity));
+            }
+        }
+    }
+
+    lossy
+}
@@ -0,0 +1,158 @@
+//! Summarizes a binary model or place without decoding it into a `WeakDom`.
+//!
+//! Inspecting a file only reads its header, the names and sizes of its
+//! chunks, and the few properties needed to describe it. This is much cheaper
+//! than a full decode for large places.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! let input = BufReader::new(File::open("Place.rbxl")?);
+//! let stats = rbx_binary::inspect::inspect(input)?;
+//!
+//! println!("{} instances", stats.num_instances);
+//!
+//! if let Some(terrain) = &stats.terrain {
+//!     println!(
+//!         "{} terrain chunks in {} bytes",
+//!         terrain.chunk_count, terrain.encoded_size
+//!     );
+//! }
+//!
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::Read;
+
+use rbx_dom_weak::types::SmoothGrid;
+
+use crate::{
+    chunk::Chunk,
+    core::{CountingReader, RbxReadExt},
+    deserializer::{FileHeader, InnerError},
+    types::Type,
+    DecodeError,
+};
+
+/// Summary information about a binary model or place, created by [`inspect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileStats {
+    /// The number of classes the file's header declares.
+    pub num_types: u32,
+
+    /// The number of instances the file's header declares.
+    pub num_instances: u32,
+
+    /// Every chunk in the file, in the order they appear.
+    pub chunks: Vec<ChunkStats>,
+
+    /// Information about the file's terrain, if it contains a `Terrain`
+    /// instance with a `SmoothGrid` property.
+    pub terrain: Option<TerrainStats>,
+}
+
+/// The name and size of one chunk in a binary file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChunkStats {
+    /// The chunk's four-letter name, like `INST` or `PROP`.
+    pub name: String,
+
+    /// The number of bytes the chunk takes up in the file, including its
+    /// header.
+    pub size: u64,
+
+    /// The length of the chunk's data once decompressed.
+    pub len: usize,
+}
+
+/// Information about the terrain stored in a binary place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TerrainStats {
+    /// The number of terrain chunks in the `SmoothGrid` property.
+    pub chunk_count: usize,
+
+    /// The length of the `SmoothGrid` property's encoded value, in bytes.
+    pub encoded_size: usize,
+}
+
+/// Reads summary information about a binary model or place from a stream.
+///
+/// Instances are never created. The only property that is read is
+/// `Terrain.SmoothGrid`, whose chunks are counted without decoding their
+/// voxels. If a file contains more than one `Terrain`, their stats are added
+/// together.
+pub fn inspect<R: Read>(reader: R) -> Result<FileStats, DecodeError> {
+    Ok(inspect_file(reader)?)
+}
+
+fn inspect_file<R: Read>(mut reader: R) -> Result<FileStats, InnerError> {
+    let header = FileHeader::decode(&mut reader)?;
+
+    let mut chunks = Vec::new();
+    let mut terrain_type_id = None;
+    let mut terrain: Option<TerrainStats> = None;
+
+    loop {
+        let mut counting_reader = CountingReader::new(&mut reader);
+        let chunk = Chunk::decode(&mut counting_reader)?;
+
+        chunks.push(ChunkStats {
+            name: String::from_utf8_lossy(&chunk.name).into_owned(),
+            size: counting_reader.count,
+            len: chunk.data.len(),
+        });
+
+        match &chunk.name {
+            b"INST" => {
+                let mut data = chunk.data.as_slice();
+                let type_id = data.read_le_u32()?;
+                let type_name = data.read_binary_string()?;
+
+                if type_name == b"Terrain" {
+                    terrain_type_id = Some(type_id);
+                }
+            }
+            b"PROP" => {
+                let mut data = chunk.data.as_slice();
+                let type_id = data.read_le_u32()?;
+
+                if terrain_type_id != Some(type_id) {
+                    continue;
+                }
+
+                let prop_name = data.read_binary_string()?;
+                let binary_type = data.read_u8()?;
+
+                if prop_name != b"SmoothGrid" || binary_type != Type::String as u8 {
+                    continue;
+                }
+
+                let stats = terrain.get_or_insert(TerrainStats {
+                    chunk_count: 0,
+                    encoded_size: 0,
+                });
+
+                while !data.is_empty() {
+                    let value = data.read_binary_string()?;
+
+                    stats.chunk_count += SmoothGrid::count_chunks(&value)
+                        .map_err(|source| InnerError::BadSmoothGrid { source })?;
+                    stats.encoded_size += value.len();
+                }
+            }
+            b"END\0" => break,
+            _ => {}
+        }
+    }
+
+    Ok(FileStats {
+        num_types: header.num_types,
+        num_instances: header.num_instances,
+        chunks,
+        terrain,
+    })
+}
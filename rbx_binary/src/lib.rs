@@ -65,6 +65,13 @@ mod text_deserializer;
 #[cfg(test)]
 mod tests;
 
+pub mod conformance;
+pub mod fidelity;
+pub mod inspect;
+pub mod physics;
+pub mod quantize;
+pub mod rotations;
+
 use std::io::{Read, Write};
 
 use rbx_dom_weak::{types::Ref, WeakDom};
@@ -77,7 +84,9 @@ pub mod text_format {
 
 pub use crate::{
     deserializer::{Deserializer, Error as DecodeError},
-    serializer::{CompressionType, Error as EncodeError, Serializer},
+    serializer::{
+        CompressionType, Error as EncodeError, IncrementalOutput, PropertyOverride, Serializer,
+    },
 };
 
 /// Deserialize a Roblox binary model or place from a stream.
@@ -85,6 +94,18 @@ pub fn from_reader<R: Read>(reader: R) -> Result<WeakDom, DecodeError> {
     Deserializer::new().deserialize(reader)
 }
 
+/// Deserialize a Roblox binary model from a stream, adding its instances to
+/// an existing DOM as children of `parent`.
+///
+/// See [`Deserializer::deserialize_into_parent`] for details.
+pub fn from_reader_into<R: Read>(
+    reader: R,
+    dom: &mut WeakDom,
+    parent: Ref,
+) -> Result<Vec<Ref>, DecodeError> {
+    Deserializer::new().deserialize_into_parent(reader, dom, parent)
+}
+
 /// Serializes a subset of the given DOM to a binary format model or place,
 /// writing to something that implements the `std::io::Write` trait.
 pub fn to_writer<W: Write>(writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), EncodeError> {
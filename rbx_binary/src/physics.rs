@@ -0,0 +1,303 @@
+//! Estimates the mass and extent of models without running Roblox, so that
+//! build checks like "a vehicle must weigh less than 500" can run offline.
+//!
+//! Properties that a part doesn't have are filled in with their defaults from
+//! the reflection database, the same way Roblox fills them in when it loads
+//! the part.
+//!
+//! ```
+//! use rbx_binary::physics::mass_properties;
+//! use rbx_dom_weak::{
+//!     types::{Region3, Vector3},
+//!     InstanceBuilder, WeakDom,
+//! };
+//!
+//! let dom = WeakDom::new(
+//!     InstanceBuilder::new("Model")
+//!         .with_child(InstanceBuilder::new("Part").with_property("Size", Vector3::new(2.0, 2.0, 2.0))),
+//! );
+//! let properties = mass_properties(&dom, dom.root_ref());
+//!
+//! // Parts are plastic by default, which has a density of 0.7.
+//! assert_eq!(properties.mass, 5.6);
+//! assert_eq!(properties.center_of_mass, Some(Vector3::new(0.0, 0.0, 0.0)));
+//! assert_eq!(
+//!     properties.bounding_box,
+//!     Some(Region3::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0))),
+//! );
+//! ```
+
+use std::f32::consts::PI;
+
+use rbx_dom_weak::{
+    types::{CFrame, Enum, PhysicalProperties, Ref, Region3, Variant, Vector3},
+    Instance, WeakDom,
+};
+use rbx_reflection::{ClassDescriptor, ReflectionDatabase};
+
+/// The density of each material, used for parts without custom physical
+/// properties.
+static MATERIAL_DENSITIES: &[(&str, f32)] = &[
+    ("Plastic", 0.7),
+    ("SmoothPlastic", 0.7),
+    ("Neon", 0.7),
+    ("Wood", 0.35),
+    ("WoodPlanks", 0.35),
+    ("Marble", 2.563),
+    ("Basalt", 2.691),
+    ("Slate", 2.691),
+    ("CrackedLava", 2.691),
+    ("Concrete", 2.403),
+    ("Limestone", 2.691),
+    ("Granite", 2.691),
+    ("Pavement", 2.691),
+    ("Brick", 1.922),
+    ("Pebble", 2.403),
+    ("Cobblestone", 2.691),
+    ("Rock", 2.691),
+    ("Sandstone", 2.691),
+    ("CorrodedMetal", 7.85),
+    ("DiamondPlate", 7.85),
+    ("Foil", 2.7),
+    ("Metal", 7.85),
+    ("Grass", 0.9),
+    ("LeafyGrass", 0.9),
+    ("Sand", 1.602),
+    ("Fabric", 0.7),
+    ("Snow", 0.9),
+    ("Mud", 0.9),
+    ("Ground", 0.9),
+    ("Asphalt", 2.36),
+    ("Salt", 2.16),
+    ("Ice", 0.919),
+    ("Glacier", 0.919),
+    ("Glass", 2.4),
+    ("ForceField", 2.4),
+    ("Cardboard", 0.7),
+    ("Carpet", 1.1),
+    ("CeramicTiles", 2.4),
+    ("ClayRoofTiles", 2.0),
+    ("RoofShingles", 2.36),
+    ("Leather", 0.86),
+    ("Plaster", 0.75),
+    ("Rubber", 1.3),
+];
+
+/// The density used for materials that aren't in [`MATERIAL_DENSITIES`].
+const DEFAULT_DENSITY: f32 = 0.7;
+
+/// The combined mass and extent of the parts in a model, created by
+/// [`mass_properties`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct MassProperties {
+    /// The number of parts that were found.
+    pub parts: usize,
+
+    /// The total mass of every part that isn't `Massless`.
+    pub mass: f32,
+
+    /// The average position of the parts, weighted by their mass, or `None`
+    /// if the total mass is zero.
+    pub center_of_mass: Option<Vector3>,
+
+    /// The smallest axis-aligned box containing every part, including
+    /// `Massless` parts, or `None` if there are no parts.
+    pub bounding_box: Option<Region3>,
+}
+
+/// Computes the combined mass, center of mass, and bounding box of the
+/// instance with the given referent and all of its descendants.
+///
+/// Every instance whose class inherits from `BasePart`, other than `Terrain`,
+/// is counted as a part. Its volume is found from its `Size`:
+///
+/// * `Part`s use their `Shape`, so balls and cylinders are lighter than blocks
+///   of the same size.
+/// * `WedgePart`s fill half of their size, and `CornerWedgePart`s a third.
+/// * Parts of any other class, like `MeshPart`s, are treated as blocks that
+///   fill their size, so their mass is an upper bound.
+///
+/// The center of mass of each part is taken to be the position of its
+/// `CFrame`, which is exact for blocks, balls, and cylinders.
+///
+/// ## Panics
+/// Panics if `referent` does not refer to an instance in `dom`.
+pub fn mass_properties(dom: &WeakDom, referent: Ref) -> MassProperties {
+    let database = rbx_reflection_database::get();
+
+    let mut parts = 0;
+    let mut mass = 0.0;
+    let mut weighted_position = [0.0; 3];
+    let mut bounds: Option<([f32; 3], [f32; 3])> = None;
+
+    for instance in dom.descendants_of(referent) {
+        let class = match database.classes.get(instance.class.as_str()) {
+            Some(class) if is_part(database, class) => class,
+            _ => continue,
+        };
+        let part = Part {
+            database,
+            instance,
+            class,
+        };
+
+        let size = match part.property("Size") {
+            Some(Variant::Vector3(size)) => *size,
+            _ => continue,
+        };
+        let cframe = match part.property("CFrame") {
+            Some(Variant::CFrame(cframe)) => *cframe,
+            _ => continue,
+        };
+
+        parts += 1;
+
+        let (min, max) = part_bounds(&cframe, size);
+        bounds = Some(match bounds {
+            Some((old_min, old_max)) => (
+                [0, 1, 2].map(|axis| old_min[axis].min(min[axis])),
+                [0, 1, 2].map(|axis| old_max[axis].max(max[axis])),
+            ),
+            None => (min, max),
+        });
+
+        if let Some(Variant::Bool(true)) = part.property("Massless") {
+            continue;
+        }
+
+        let part_mass = part.density() * part.volume(size);
+        let position = cframe.position;
+
+        mass += part_mass;
+        weighted_position[0] += position.x * part_mass;
+        weighted_position[1] += position.y * part_mass;
+        weighted_position[2] += position.z * part_mass;
+    }
+
+    let center_of_mass = if mass > 0.0 {
+        Some(Vector3::new(
+            weighted_position[0] / mass,
+            weighted_position[1] / mass,
+            weighted_position[2] / mass,
+        ))
+    } else {
+        None
+    };
+
+    MassProperties {
+        parts,
+        mass,
+        center_of_mass,
+        bounding_box: bounds.map(|(min, max)| {
+            Region3::new(
+                Vector3::new(min[0], min[1], min[2]),
+                Vector3::new(max[0], max[1], max[2]),
+            )
+        }),
+    }
+}
+
+fn is_part(database: &ReflectionDatabase, class: &ClassDescriptor) -> bool {
+    class.name != "Terrain"
+        && database
+            .superclasses_iter(class)
+            .any(|superclass| superclass.name == "BasePart")
+}
+
+/// Returns the lowest and highest corners of the axis-aligned box around a
+/// part with the given `CFrame` and `Size`.
+fn part_bounds(cframe: &CFrame, size: Vector3) -> ([f32; 3], [f32; 3]) {
+    let half = [size.x / 2.0, size.y / 2.0, size.z / 2.0];
+    let center = [cframe.position.x, cframe.position.y, cframe.position.z];
+    let rows = [
+        cframe.orientation.x,
+        cframe.orientation.y,
+        cframe.orientation.z,
+    ];
+
+    let extent =
+        rows.map(|row| row.x.abs() * half[0] + row.y.abs() * half[1] + row.z.abs() * half[2]);
+
+    (
+        [0, 1, 2].map(|axis| center[axis] - extent[axis]),
+        [0, 1, 2].map(|axis| center[axis] + extent[axis]),
+    )
+}
+
+/// A part whose properties fall back to the defaults for its class.
+struct Part<'a> {
+    database: &'a ReflectionDatabase<'a>,
+    instance: &'a Instance,
+    class: &'a ClassDescriptor<'a>,
+}
+
+impl<'a> Part<'a> {
+    fn property(&self, name: &str) -> Option<&'a Variant> {
+        self.instance
+            .properties
+            .get(&name.into())
+            .or_else(|| self.database.find_default_property(self.class, name))
+    }
+
+    fn density(&self) -> f32 {
+        if let Some(Variant::PhysicalProperties(PhysicalProperties::Custom(custom))) =
+            self.property("CustomPhysicalProperties")
+        {
+            return custom.density;
+        }
+
+        let material = match self.property("Material") {
+            Some(Variant::Enum(material)) => *material,
+            _ => return DEFAULT_DENSITY,
+        };
+
+        self.enum_item_name("Material", material)
+            .and_then(|name| {
+                MATERIAL_DENSITIES
+                    .iter()
+                    .find(|(material, _)| *material == name)
+            })
+            .map_or(DEFAULT_DENSITY, |(_, density)| *density)
+    }
+
+    fn volume(&self, size: Vector3) -> f32 {
+        let block = size.x * size.y * size.z;
+
+        match self.class.name.as_ref() {
+            "WedgePart" => block / 2.0,
+            "CornerWedgePart" => block / 3.0,
+            "Part" => {
+                let shape = match self.property("Shape") {
+                    Some(Variant::Enum(shape)) => self.enum_item_name("PartType", *shape),
+                    _ => None,
+                };
+
+                match shape {
+                    Some("Ball") => {
+                        let radius = size.x.min(size.y).min(size.z) / 2.0;
+                        4.0 / 3.0 * PI * radius.powi(3)
+                    }
+                    Some("Cylinder") => {
+                        let radius = size.y.min(size.z) / 2.0;
+                        PI * radius * radius * size.x
+                    }
+                    Some("Wedge") => block / 2.0,
+                    Some("CornerWedge") => block / 3.0,
+                    _ => block,
+                }
+            }
+            _ => block,
+        }
+    }
+
+    fn enum_item_name(&self, enum_name: &str, value: Enum) -> Option<&'a str> {
+        self.database
+            .enums
+            .get(enum_name)?
+            .items
+            .iter()
+            .find(|(_, item)| **item == value.to_u32())
+            .map(|(name, _)| name.as_ref())
+    }
+}
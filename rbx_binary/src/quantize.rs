@@ -0,0 +1,81 @@
+//! A pass that rounds positions and sizes in a dom before it's serialized.
+//!
+//! Editing a place in Studio tends to introduce tiny amounts of floating point
+//! error into values that were not meant to change. Rounding them to a fixed
+//! number of decimal places before serializing keeps that error out of diffs.
+//!
+//! ```
+//! use rbx_binary::quantize::{quantize_vectors, DEFAULT_VECTOR_PROPERTIES};
+//! use rbx_dom_weak::{types::Vector3, InstanceBuilder, WeakDom};
+//!
+//! let mut dom = WeakDom::new(
+//!     InstanceBuilder::new("Part").with_property("Size", Vector3::new(4.000001, 1.0, 2.0)),
+//! );
+//!
+//! assert_eq!(quantize_vectors(&mut dom, 3, DEFAULT_VECTOR_PROPERTIES), 1);
+//! ```
+
+use rbx_dom_weak::{
+    types::{Variant, Vector3},
+    ustr, WeakDom,
+};
+
+/// Properties that are commonly affected by floating point error: sizes, and
+/// positions stored in `CFrame`s.
+pub const DEFAULT_VECTOR_PROPERTIES: &[&str] = &["Size", "CFrame", "Position", "Orientation"];
+
+/// Rounds every component of the named `Vector3` properties in a dom to
+/// `decimal_places` decimal places.
+///
+/// `CFrame` and `OptionalCFrame` properties with one of the given names have
+/// their position rounded. Their rotation is left alone; see
+/// [`quantize_rotations`][crate::rotations::quantize_rotations] for that.
+///
+/// Returns the number of properties that were changed.
+pub fn quantize_vectors(dom: &mut WeakDom, decimal_places: u32, properties: &[&str]) -> usize {
+    let scale = 10f64.powi(decimal_places as i32);
+    let names: Vec<_> = properties.iter().map(|name| ustr(name)).collect();
+
+    let referents: Vec<_> = dom
+        .descendants()
+        .map(|instance| instance.referent())
+        .collect();
+    let mut changed = 0;
+
+    for referent in referents {
+        let instance = dom.get_by_ref_mut(referent).unwrap();
+
+        for name in &names {
+            let vector = match instance.properties.get_mut(name) {
+                Some(Variant::Vector3(vector)) => vector,
+                Some(Variant::CFrame(cframe)) => &mut cframe.position,
+                Some(Variant::OptionalCFrame(Some(cframe))) => &mut cframe.position,
+                _ => continue,
+            };
+
+            let rounded = Vector3::new(
+                round(vector.x, scale),
+                round(vector.y, scale),
+                round(vector.z, scale),
+            );
+
+            if rounded != *vector {
+                *vector = rounded;
+                changed += 1;
+            }
+        }
+    }
+
+    changed
+}
+
+fn round(value: f32, scale: f64) -> f32 {
+    if !value.is_finite() {
+        return value;
+    }
+
+    // Dividing after rounding gives the closest f32 to the rounded decimal
+    // value, which is also how it will be printed. Adding zero turns negative
+    // zero into positive zero.
+    ((value as f64 * scale).round() / scale) as f32 + 0.0
+}
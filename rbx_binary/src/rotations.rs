@@ -0,0 +1,128 @@
+//! Utilities for making `CFrame` properties smaller when they are serialized.
+//!
+//! The binary format stores the rotation of a `CFrame` as a single byte when
+//! it is one of the 24 basic rotations that only point each axis along
+//! another axis, and as nine floats otherwise. Parts that were rotated in
+//! Studio often end up with rotations that are very slightly off of a basic
+//! rotation, which then can't use the compact form.
+//!
+//! ```
+//! use rbx_binary::rotations::{analyze_rotations, quantize_rotations};
+//! use rbx_dom_weak::{
+//!     types::{CFrame, Matrix3, Vector3},
+//!     InstanceBuilder, WeakDom,
+//! };
+//!
+//! let nearly_identity = Matrix3::new(
+//!     Vector3::new(1.0, 0.0, 0.00001),
+//!     Vector3::new(0.0, 1.0, 0.0),
+//!     Vector3::new(-0.00001, 0.0, 1.0),
+//! );
+//! let mut dom = WeakDom::new(InstanceBuilder::new("Part").with_property(
+//!     "CFrame",
+//!     CFrame::new(Vector3::new(0.0, 5.0, 0.0), nearly_identity),
+//! ));
+//!
+//! assert_eq!(analyze_rotations(&dom).basic_rotations, 0);
+//! assert_eq!(quantize_rotations(&mut dom, 0.001), 1);
+//! assert_eq!(analyze_rotations(&dom).basic_rotations, 1);
+//! ```
+
+use rbx_dom_weak::{
+    types::{CFrame, Variant},
+    WeakDom,
+};
+
+/// The number of bytes saved by writing a rotation as an ID instead of as
+/// nine floats.
+const BASIC_ROTATION_SAVINGS: usize = 9 * 4;
+
+/// How many `CFrame` properties in a dom can use the compact rotation
+/// encoding, created by [`analyze_rotations`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RotationReport {
+    /// The number of `CFrame` and non-empty `OptionalCFrame` properties.
+    pub cframes: usize,
+
+    /// The number of those properties whose rotation is exactly a basic
+    /// rotation.
+    pub basic_rotations: usize,
+}
+
+impl RotationReport {
+    /// The number of properties whose rotation is written as nine floats.
+    pub fn full_rotations(&self) -> usize {
+        self.cframes - self.basic_rotations
+    }
+
+    /// The number of bytes the compact encoding saves for these properties
+    /// before compression.
+    pub fn bytes_saved(&self) -> usize {
+        self.basic_rotations * BASIC_ROTATION_SAVINGS
+    }
+}
+
+/// Counts the `CFrame` properties in a dom and how many of them will be
+/// written with the compact rotation encoding.
+pub fn analyze_rotations(dom: &WeakDom) -> RotationReport {
+    let mut report = RotationReport::default();
+
+    for instance in dom.descendants() {
+        for value in instance.properties.values() {
+            if let Some(cframe) = as_cframe(value) {
+                report.cframes += 1;
+
+                if cframe.orientation.to_basic_rotation_id().is_some() {
+                    report.basic_rotations += 1;
+                }
+            }
+        }
+    }
+
+    report
+}
+
+/// Snaps the rotation of every `CFrame` property in a dom that is within
+/// `epsilon` of a basic rotation in each component, so that it will be
+/// written with the compact rotation encoding. Positions are not changed.
+///
+/// Returns the number of properties that were changed.
+pub fn quantize_rotations(dom: &mut WeakDom, epsilon: f32) -> usize {
+    let referents: Vec<_> = dom
+        .descendants()
+        .map(|instance| instance.referent())
+        .collect();
+    let mut changed = 0;
+
+    for referent in referents {
+        let instance = dom.get_by_ref_mut(referent).unwrap();
+
+        for value in instance.properties.values_mut() {
+            let cframe = match value {
+                Variant::CFrame(cframe) => cframe,
+                Variant::OptionalCFrame(Some(cframe)) => cframe,
+                _ => continue,
+            };
+
+            if cframe.orientation.to_basic_rotation_id().is_some() {
+                continue;
+            }
+
+            if let Some(orientation) = cframe.orientation.snap_to_basic_rotation(epsilon) {
+                cframe.orientation = orientation;
+                changed += 1;
+            }
+        }
+    }
+
+    changed
+}
+
+fn as_cframe(value: &Variant) -> Option<&CFrame> {
+    match value {
+        Variant::CFrame(cframe) => Some(cframe),
+        Variant::OptionalCFrame(cframe) => cframe.as_ref(),
+        _ => None,
+    }
+}
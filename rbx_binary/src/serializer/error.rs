@@ -59,4 +59,7 @@ pub(super) enum InnerError {
 
     #[error("The instance with referent {referent:?} was not present in the dom.")]
     InvalidInstanceId { referent: Ref },
+
+    #[error("The previously written file could not be read: {source}")]
+    BadPreviousFile { source: crate::DecodeError },
 }
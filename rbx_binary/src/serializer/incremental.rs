@@ -0,0 +1,152 @@
+use ahash::{HashMap, HashMapExt};
+use rbx_dom_weak::{types::Ref, Ustr};
+
+use crate::{
+    chunk::Chunk,
+    core::RbxReadExt,
+    deserializer::{FileHeader, InnerError as DecodeInnerError},
+    types::Type,
+};
+
+use super::error::InnerError;
+
+/// A file written by
+/// [`Serializer::serialize_incremental`](super::Serializer::serialize_incremental),
+/// which can be passed to the next call to reuse the parts of it that haven't
+/// changed.
+#[derive(Debug, Clone)]
+pub struct IncrementalOutput {
+    pub(super) contents: Vec<u8>,
+
+    /// The instances written for each class, in the order their properties
+    /// were written in. The file alone can't tell which instance was written
+    /// where, since instances of the same class can be reordered without
+    /// changing any of its INST or PRNT chunks.
+    pub(super) instances: HashMap<Ustr, Vec<Ref>>,
+}
+
+impl IncrementalOutput {
+    /// Returns the contents of the file.
+    pub fn contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    /// Consumes the output, returning the contents of the file.
+    pub fn into_contents(self) -> Vec<u8> {
+        self.contents
+    }
+}
+
+/// The chunks of a file written earlier, used by
+/// [`Serializer::serialize_incremental`](super::Serializer::serialize_incremental)
+/// to avoid compressing property data that hasn't changed.
+pub(super) struct PreviousFile {
+    /// The instances written for each class, in order.
+    class_instances: HashMap<Ustr, Vec<Ref>>,
+
+    /// The decompressed contents of every INST chunk, in the order they
+    /// appeared.
+    instances: Vec<Vec<u8>>,
+
+    /// The decompressed contents of the SSTR chunk, if there was one.
+    shared_strings: Option<Vec<u8>>,
+
+    /// The decompressed contents of the PRNT chunk, if there was one.
+    parents: Option<Vec<u8>>,
+
+    /// Every PROP chunk exactly as it was written, including its header,
+    /// keyed by the type ID and serialized property name it was written for.
+    /// Stored alongside the binary type of the property.
+    properties: HashMap<(u32, Ustr), (u8, Vec<u8>)>,
+}
+
+impl PreviousFile {
+    pub(super) fn read(previous: &IncrementalOutput) -> Result<Self, InnerError> {
+        Self::read_chunks(previous).map_err(|source| InnerError::BadPreviousFile {
+            source: source.into(),
+        })
+    }
+
+    fn read_chunks(previous: &IncrementalOutput) -> Result<Self, DecodeInnerError> {
+        let mut input = previous.contents.as_slice();
+        FileHeader::decode(&mut input)?;
+
+        let mut previous = PreviousFile {
+            class_instances: previous.instances.clone(),
+            instances: Vec::new(),
+            shared_strings: None,
+            parents: None,
+            properties: HashMap::new(),
+        };
+
+        loop {
+            let start = input;
+            let chunk = Chunk::decode(&mut input)?;
+            let raw = &start[..start.len() - input.len()];
+
+            match &chunk.name {
+                b"INST" => previous.instances.push(chunk.data),
+                b"SSTR" => previous.shared_strings = Some(chunk.data),
+                b"PRNT" => previous.parents = Some(chunk.data),
+                b"PROP" => {
+                    let mut data = chunk.data.as_slice();
+                    let type_id = data.read_le_u32()?;
+                    let prop_name = data.read_string()?;
+                    let prop_type = data.read_u8()?;
+
+                    previous.properties.insert(
+                        (type_id, Ustr::from(prop_name.as_str())),
+                        (prop_type, raw.to_vec()),
+                    );
+                }
+                b"END\0" => break,
+                _ => {}
+            }
+        }
+
+        Ok(previous)
+    }
+
+    /// Returns the decompressed contents of the INST chunk at the given
+    /// position in the file.
+    pub(super) fn instance_chunk(&self, index: usize) -> Option<&[u8]> {
+        self.instances.get(index).map(Vec::as_slice)
+    }
+
+    /// The number of INST chunks in the file.
+    pub(super) fn instance_chunk_count(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Returns the decompressed contents of the SSTR chunk, if the file had
+    /// one.
+    pub(super) fn shared_strings(&self) -> Option<&[u8]> {
+        self.shared_strings.as_deref()
+    }
+
+    /// Returns the decompressed contents of the PRNT chunk, if the file had
+    /// one.
+    pub(super) fn parent_chunk(&self) -> Option<&[u8]> {
+        self.parents.as_deref()
+    }
+
+    /// Returns the instances written for the given class, in order, if the
+    /// file had any.
+    pub(super) fn class_instances(&self, class: Ustr) -> Option<&[Ref]> {
+        self.class_instances.get(&class).map(Vec::as_slice)
+    }
+
+    /// Returns the PROP chunk written for the given property, if the file had
+    /// one with the same binary type.
+    pub(super) fn property_chunk(
+        &self,
+        type_id: u32,
+        prop_name: Ustr,
+        prop_type: Type,
+    ) -> Option<&[u8]> {
+        match self.properties.get(&(type_id, prop_name)) {
+            Some((previous_type, raw)) if *previous_type == prop_type as u8 => Some(raw),
+            _ => None,
+        }
+    }
+}
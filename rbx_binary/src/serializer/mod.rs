@@ -1,14 +1,19 @@
 mod error;
+mod incremental;
 mod state;
 
 use std::io::Write;
 
-use rbx_dom_weak::{types::Ref, WeakDom};
+use ahash::HashMap;
+use rbx_dom_weak::{
+    types::{Ref, Variant},
+    Diagnostics, Ustr, WeakDom,
+};
 use rbx_reflection::ReflectionDatabase;
 
-use self::state::SerializerState;
+use self::{error::InnerError, incremental::PreviousFile, state::SerializerState};
 
-pub use self::error::Error;
+pub use self::{error::Error, incremental::IncrementalOutput};
 
 /// A configurable serializer for Roblox binary models and places.
 ///
@@ -37,16 +42,32 @@ pub use self::error::Error;
 /// By default, the Serializer uses LZ4 compression, mimicking Roblox. This can
 /// be changed via [`compression_type`][compression_type].
 ///
+/// Tags are written in the order they appear in by default. To write them
+/// sorted instead, use [`canonical_ordering`][canonical_ordering].
+///
+/// Specific properties can be left out of the file or written with a
+/// different value via [`property_override`][property_override].
+///
+/// Files meant for older Roblox clients can be limited to the properties those
+/// clients understand via [`baseline_database`][baseline_database].
+///
 /// [ReflectionDatabase]: rbx_reflection::ReflectionDatabase
 /// [reflection_database]: Serializer#method.reflection_database
 /// [compression_type]: Serializer#method.compression_type
+/// [canonical_ordering]: Serializer#method.canonical_ordering
+/// [property_override]: Serializer#method.property_override
+/// [baseline_database]: Serializer#method.baseline_database
 //
 // future settings:
 // * recursive: bool = true
 #[non_exhaustive]
 pub struct Serializer<'db> {
-    database: &'db ReflectionDatabase<'db>,
+    pub(crate) database: &'db ReflectionDatabase<'db>,
     compression: CompressionType,
+    canonical_ordering: bool,
+    overrides: HashMap<(Ustr, Ustr), PropertyOverride>,
+    diagnostics: Diagnostics,
+    baseline: Option<&'db ReflectionDatabase<'db>>,
 }
 
 impl<'db> Serializer<'db> {
@@ -55,6 +76,10 @@ impl<'db> Serializer<'db> {
         Serializer {
             database: rbx_reflection_database::get(),
             compression: CompressionType::default(),
+            canonical_ordering: false,
+            overrides: HashMap::default(),
+            diagnostics: Diagnostics::default(),
+            baseline: None,
         }
     }
 
@@ -73,33 +98,187 @@ impl<'db> Serializer<'db> {
         }
     }
 
+    /// Sets whether `Tags` values should be written in sorted order, so that
+    /// the same set of tags always produces the same bytes regardless of the
+    /// order they were added in. This is off by default.
+    ///
+    /// `Attributes` are always written sorted by name.
+    #[inline]
+    pub fn canonical_ordering(self, canonical_ordering: bool) -> Self {
+        Self {
+            canonical_ordering,
+            ..self
+        }
+    }
+
+    /// Sets where the serializer reports problems it recovers from, like
+    /// properties it migrates and Ref properties that point to instances that
+    /// aren't being written.
+    #[inline]
+    pub fn diagnostics(self, diagnostics: Diagnostics) -> Self {
+        Self {
+            diagnostics,
+            ..self
+        }
+    }
+
+    /// Limits the file to what a client described by `baseline`, a reflection
+    /// database generated from an older Roblox release, is able to read.
+    ///
+    /// Properties that `baseline` doesn't know about, or that it expects to
+    /// have a different type, are left out and reported to
+    /// [`diagnostics`](Serializer::diagnostics) as skipped. This includes
+    /// every property of a class that `baseline` doesn't know about, though
+    /// the instances themselves are still written. Properties that `baseline`
+    /// serializes under a different name are written with that name instead.
+    ///
+    /// Property values are still converted using the serializer's
+    /// [`reflection_database`](Serializer::reflection_database), and the
+    /// compression type isn't changed.
+    #[inline]
+    pub fn baseline_database(self, baseline: &'db ReflectionDatabase<'db>) -> Self {
+        Self {
+            baseline: Some(baseline),
+            ..self
+        }
+    }
+
+    /// Sets what the serializer should do with the property named `property`
+    /// on instances of `class` or any of its subclasses, like leaving out
+    /// `BaseScript.LinkedSource` or blanking `Instance.HistoryId`.
+    ///
+    /// Properties are matched by their canonical name. The `Name` property is
+    /// always written. Setting an override for the same class and property
+    /// again replaces the previous one.
+    ///
+    /// ## Example
+    /// ```
+    /// use rbx_binary::{PropertyOverride, Serializer};
+    /// use rbx_dom_weak::types::{ContentId, Variant};
+    ///
+    /// let serializer = Serializer::new()
+    ///     .property_override("Instance", "HistoryId", PropertyOverride::Skip)
+    ///     .property_override(
+    ///         "BaseScript",
+    ///         "LinkedSource",
+    ///         PropertyOverride::Replace(Variant::ContentId(ContentId::new())),
+    ///     );
+    /// ```
+    pub fn property_override<C: Into<Ustr>, P: Into<Ustr>>(
+        mut self,
+        class: C,
+        property: P,
+        action: PropertyOverride,
+    ) -> Self {
+        self.overrides
+            .insert((class.into(), property.into()), action);
+        self
+    }
+
+    /// Finds the override for the given property on instances of the given
+    /// class, checking the overrides for its superclasses after its own.
+    pub(crate) fn find_override(&self, class: Ustr, property: Ustr) -> Option<&PropertyOverride> {
+        if self.overrides.is_empty() {
+            return None;
+        }
+
+        if let Some(action) = self.overrides.get(&(class, property)) {
+            return Some(action);
+        }
+
+        let descriptor = self.database.classes.get(class.as_str())?;
+        self.database
+            .superclasses_iter(descriptor)
+            .skip(1)
+            .find_map(|superclass| {
+                self.overrides
+                    .get(&(Ustr::from(superclass.name.as_ref()), property))
+            })
+    }
+
     /// Serialize a Roblox binary model or place into the given stream using
     /// this serializer.
     pub fn serialize<W: Write>(&self, writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
         profiling::scope!("rbx_binary::seserialize");
 
         let mut serializer = SerializerState::new(self, dom, writer);
+        Ok(write_file(&mut serializer, refs)?)
+    }
+
+    /// Serialize a Roblox binary model or place, reusing the compressed
+    /// property data of `previous` wherever it hasn't changed.
+    ///
+    /// `previous` should be the output of an earlier call with a serializer
+    /// with the same settings, from an earlier version of `dom`, and `changed`
+    /// should contain every instance whose name or properties have changed
+    /// since then. Property data is reused for each class whose instances are
+    /// all unchanged, but only if no instances have been added, removed,
+    /// reordered, or moved since `previous` was written. Otherwise, every
+    /// chunk is written again. If `previous` is `None`, every chunk is written.
+    ///
+    /// Reused chunks keep the compression they were written with. The output
+    /// is the same as [`serialize`](Serializer::serialize) would produce if the
+    /// compression type hasn't changed.
+    pub fn serialize_incremental(
+        &self,
+        dom: &WeakDom,
+        refs: &[Ref],
+        previous: Option<&IncrementalOutput>,
+        changed: &[Ref],
+    ) -> Result<IncrementalOutput, Error> {
+        profiling::scope!("rbx_binary::serialize_incremental");
+
+        let mut serializer = SerializerState::new(self, dom, Vec::new());
 
-        serializer.add_instances(refs)?;
-        serializer.generate_referents();
-        serializer.write_header()?;
-        serializer.serialize_metadata()?;
-        serializer.serialize_shared_strings()?;
-        serializer.serialize_instances()?;
-        serializer.serialize_properties()?;
-        serializer.serialize_parents()?;
-        serializer.serialize_end()?;
-
-        Ok(())
+        if let Some(previous) = previous {
+            serializer.reuse_chunks(PreviousFile::read(previous)?, changed);
+        }
+
+        write_file(&mut serializer, refs)?;
+
+        Ok(IncrementalOutput {
+            instances: serializer.class_instances(),
+            contents: serializer.into_output(),
+        })
     }
 }
 
+fn write_file<W: Write>(
+    serializer: &mut SerializerState<'_, '_, W>,
+    refs: &[Ref],
+) -> Result<(), InnerError> {
+    serializer.add_instances(refs)?;
+    serializer.generate_referents();
+    serializer.write_header()?;
+    serializer.serialize_metadata()?;
+    serializer.serialize_shared_strings()?;
+    serializer.serialize_instances()?;
+    serializer.serialize_properties()?;
+    serializer.serialize_parents()?;
+    serializer.serialize_end()?;
+
+    Ok(())
+}
+
 impl Default for Serializer<'_> {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// What a [`Serializer`] does with a property chosen by
+/// [`Serializer::property_override`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PropertyOverride {
+    /// Leave the property out of the file entirely.
+    Skip,
+
+    /// Write this value in place of the property's value on every instance.
+    /// The value must have the type the property is serialized as.
+    Replace(Variant),
+}
+
 /// Indicates the types of compression that files can be written with.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
 pub enum CompressionType {
@@ -5,7 +5,7 @@ use std::{
     io::Write,
 };
 
-use ahash::{HashMap, HashMapExt, HashSetExt};
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use rbx_dom_weak::{
     types::{
         Attributes, Axes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
@@ -14,7 +14,7 @@ use rbx_dom_weak::{
         PhysicalProperties, Ray, Rect, Ref, SecurityCapabilities, SharedString, Tags, UDim, UDim2,
         UniqueId, Variant, VariantType, Vector2, Vector3, Vector3int16,
     },
-    Instance, Ustr, UstrSet, WeakDom,
+    Diagnostic, Instance, Ustr, UstrSet, WeakDom,
 };
 
 use rbx_reflection::{
@@ -28,10 +28,11 @@ use crate::{
         find_property_descriptors, RbxWriteExt, FILE_MAGIC_HEADER, FILE_SIGNATURE, FILE_VERSION,
     },
     types::Type,
-    Serializer,
+    PropertyOverride, Serializer,
 };
 
 use super::error::InnerError;
+use super::incremental::PreviousFile;
 use super::CompressionType;
 
 static FILE_FOOTER: &[u8] = b"</roblox>";
@@ -67,6 +68,21 @@ pub(super) struct SerializerState<'dom, 'db, W> {
     /// A map of SharedStrings to where it is in the SSTR chunk. This is used
     /// for writing PROP chunks.
     shared_string_ids: HashMap<SharedString, u32>,
+
+    /// A file previously written from the same dom, whose PROP chunks can be
+    /// copied instead of being written again if their data hasn't changed.
+    previous: Option<PreviousFile>,
+
+    /// The instances whose properties changed since `previous` was written.
+    changed: HashSet<Ref>,
+
+    /// Whether the INST chunks, the instances of each class, and the parent of
+    /// each instance are the same as they were in `previous`. If they are,
+    /// every instance has the same referent and type ID as it did in that file.
+    instances_unchanged: bool,
+
+    /// Whether the SSTR chunk is the same as the one in `previous`.
+    shared_strings_unchanged: bool,
 }
 
 /// An instance class that our serializer knows about. We should have one struct
@@ -147,6 +163,11 @@ struct PropInfo<'db> {
     /// Color, Font -> FontFace), this field contains Some(PropertyMigration). Otherwise,
     /// it is None.
     migration: Option<&'db PropertyMigration>,
+
+    /// The value set by a `PropertyOverride::Replace` for this property, if
+    /// any. When present, it's written for every instance instead of the
+    /// instance's own value.
+    replacement: Option<&'db Variant>,
 }
 
 /// Contains all of the `TypeInfo` objects known to the serializer so far. This
@@ -209,6 +230,7 @@ impl<'dom, 'db> TypeInfos<'dom, 'db> {
                     aliases: UstrSet::new(),
                     default_value: Cow::Owned(Variant::String(String::new())),
                     migration: None,
+                    replacement: None,
                 },
             );
 
@@ -239,9 +261,22 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
             type_infos: TypeInfos::new(serializer.database),
             shared_strings: Vec::new(),
             shared_string_ids: HashMap::new(),
+            previous: None,
+            changed: HashSet::new(),
+            instances_unchanged: false,
+            shared_strings_unchanged: false,
         }
     }
 
+    /// Sets a previously written file to copy unchanged PROP chunks from, and
+    /// the instances whose properties have changed since it was written.
+    pub fn reuse_chunks(&mut self, previous: PreviousFile, changed: &[Ref]) {
+        self.previous = Some(previous);
+        self.changed = changed.iter().copied().collect();
+        self.instances_unchanged = true;
+        self.shared_strings_unchanged = true;
+    }
+
     /// Mark the given instance IDs and all of their descendants as intended for
     /// serialization with this serializer.
     #[profiling::function]
@@ -294,6 +329,36 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
             }
         }
 
+        // A default SharedString value is only written for instances that are
+        // missing the property, so it only belongs in the SSTR chunk if at
+        // least one instance is. Adding it unconditionally would leave an
+        // unused entry in the file.
+        for type_info in self.type_infos.values.values() {
+            for (prop_name, prop_info) in &type_info.properties {
+                let sstr = match prop_info.default_value.borrow() {
+                    Variant::SharedString(sstr) if prop_info.replacement.is_none() => sstr,
+                    _ => continue,
+                };
+
+                if self.shared_string_ids.contains_key(sstr) {
+                    continue;
+                }
+
+                let default_used = type_info.instances.iter().any(|instance| {
+                    !instance.properties.contains_key(prop_name)
+                        && !prop_info
+                            .aliases
+                            .iter()
+                            .any(|alias| instance.properties.contains_key(alias))
+                });
+
+                if default_used {
+                    self.shared_string_ids.insert(sstr.clone(), 0);
+                    self.shared_strings.push(sstr.clone());
+                }
+            }
+        }
+
         // Sort shared_strings by their hash, to ensure they are deterministically added
         // into the SSTR chunk, then assign them corresponding ids
         self.shared_strings.sort_by_key(SharedString::hash);
@@ -316,13 +381,17 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
     #[allow(clippy::map_entry)]
     #[profiling::function]
     pub fn collect_type_info(&mut self, instance: &'dom Instance) -> Result<(), InnerError> {
+        let serializer = self.serializer;
         let type_info = self.type_infos.get_or_create(instance.class);
         type_info.instances.push(instance);
 
         for (prop_name, prop_value) in &instance.properties {
-            // Discover and track any shared strings we come across.
+            // Discover and track any shared strings we come across, unless
+            // an override means that this value won't be written.
             if let Variant::SharedString(shared_string) = prop_value {
-                if !self.shared_string_ids.contains_key(shared_string) {
+                if !self.shared_string_ids.contains_key(shared_string)
+                    && !is_overridden(serializer, instance.class, *prop_name)
+                {
                     // We insert it with a dummy id of 0 so that we can check for contains_key.
                     // The actual id is set in `add_instances`
                     self.shared_string_ids.insert(shared_string.clone(), 0);
@@ -412,6 +481,42 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                 }
             }
 
+            let replacement = match serializer.find_override(instance.class, canonical_name) {
+                Some(PropertyOverride::Skip) => continue,
+                Some(PropertyOverride::Replace(value)) => Some(value),
+                None => None,
+            };
+
+            // Clients described by the baseline database can only read the
+            // properties it knows about, under the name it serializes them as.
+            let serialized_name = match serializer.baseline {
+                Some(baseline) => {
+                    match find_baseline_name(
+                        baseline,
+                        instance.class,
+                        canonical_name,
+                        serialized_ty,
+                    ) {
+                        Some(name) => name,
+                        None => {
+                            serializer.diagnostics.report(Diagnostic::PropertySkipped {
+                                class: instance.class,
+                                property: canonical_name,
+                            });
+                            continue;
+                        }
+                    }
+                }
+                None => serialized_name,
+            };
+
+            if let Some(Variant::SharedString(shared_string)) = replacement {
+                if !self.shared_string_ids.contains_key(shared_string) {
+                    self.shared_string_ids.insert(shared_string.clone(), 0);
+                    self.shared_strings.push(shared_string.clone())
+                }
+            }
+
             if !type_info.properties.contains_key(&canonical_name) {
                 let default_value = type_info
                     .class_descriptor
@@ -431,16 +536,6 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                         }
                     })?;
 
-                // There's no assurance that the default SharedString value
-                // will actually get serialized inside of the SSTR chunk, so we
-                // check here just to make sure.
-                if let Variant::SharedString(sstr) = default_value.borrow() {
-                    if !self.shared_string_ids.contains_key(sstr) {
-                        self.shared_string_ids.insert(sstr.clone(), 0);
-                        self.shared_strings.push(sstr.clone());
-                    }
-                }
-
                 let ser_type = Type::from_rbx_type(serialized_ty).ok_or_else(|| {
                     // This is a known value type, but rbx_binary doesn't have a
                     // binary type value for it. rbx_binary might be out of
@@ -460,6 +555,7 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                         aliases: UstrSet::new(),
                         default_value,
                         migration,
+                        replacement,
                     },
                 );
             }
@@ -472,6 +568,16 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
 
                 if !prop_info.aliases.contains(prop_name) {
                     prop_info.aliases.insert(*prop_name);
+
+                    if migration.is_some() && prop_info.replacement.is_none() {
+                        self.serializer
+                            .diagnostics
+                            .report(Diagnostic::MigrationApplied {
+                                class: instance.class,
+                                property: *prop_name,
+                                new_property: canonical_name,
+                            });
+                    }
                 }
 
                 prop_info.migration = migration;
@@ -525,6 +631,10 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
         log::trace!("Writing shared string chunk");
 
         if self.shared_strings.is_empty() {
+            if let Some(previous) = &self.previous {
+                self.shared_strings_unchanged = previous.shared_strings().is_none();
+            }
+
             return Ok(());
         }
 
@@ -539,6 +649,10 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
             chunk.write_binary_string(shared_string.data())?;
         }
 
+        if let Some(previous) = &self.previous {
+            self.shared_strings_unchanged = previous.shared_strings() == Some(chunk.data());
+        }
+
         chunk.dump(&mut self.output)?;
 
         Ok(())
@@ -550,7 +664,7 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
     pub fn serialize_instances(&mut self) -> Result<(), InnerError> {
         log::trace!("Writing instance chunks");
 
-        for (type_name, type_info) in &self.type_infos.values {
+        for (index, (type_name, type_info)) in self.type_infos.values.iter().enumerate() {
             log::trace!(
                 "Writing chunk for {} ({} instances)",
                 type_name,
@@ -592,12 +706,62 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                 }
             }
 
+            if let Some(previous) = &self.previous {
+                // Instances of the same class can swap places without changing
+                // the INST chunk, so the instances have to be compared too.
+                let referents = type_info
+                    .instances
+                    .iter()
+                    .map(|instance| instance.referent());
+
+                self.instances_unchanged &= previous.instance_chunk(index) == Some(chunk.data())
+                    && previous
+                        .class_instances(*type_name)
+                        .is_some_and(|instances| instances.iter().copied().eq(referents));
+            }
+
             chunk.dump(&mut self.output)?;
         }
 
+        if let Some(previous) = &self.previous {
+            self.instances_unchanged &=
+                previous.instance_chunk_count() == self.type_infos.values.len();
+
+            if self.instances_unchanged {
+                let parents = self.parent_chunk()?;
+                self.instances_unchanged = self
+                    .previous
+                    .as_ref()
+                    .is_some_and(|previous| previous.parent_chunk() == Some(parents.data()));
+            }
+        }
+
         Ok(())
     }
 
+    /// Consumes the serializer, returning the output it was writing to.
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// Returns the instances of each class in the order they were written in,
+    /// so that a later file can be checked against them.
+    pub fn class_instances(&self) -> HashMap<Ustr, Vec<Ref>> {
+        self.type_infos
+            .values
+            .iter()
+            .map(|(type_name, type_info)| {
+                let instances = type_info
+                    .instances
+                    .iter()
+                    .map(|instance| instance.referent())
+                    .collect();
+
+                (*type_name, instances)
+            })
+            .collect()
+    }
+
     /// Write out batch declarations of property values for the instances
     /// previously defined in the INST chunks. Property data is contained in
     /// chunks named PROP.
@@ -606,8 +770,40 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
         log::trace!("Writing properties");
 
         for (type_name, type_info) in &self.type_infos.values {
+            // PROP chunks from a previous file can only be reused if every
+            // instance still has the same referent, and none of the instances
+            // of this type have changed.
+            let previous = self.previous.as_ref().filter(|_| {
+                self.instances_unchanged
+                    && !type_info
+                        .instances
+                        .iter()
+                        .any(|instance| self.changed.contains(&instance.referent()))
+            });
+
             for (prop_name, prop_info) in &type_info.properties {
                 profiling::scope!("serialize property", prop_name.borrow());
+
+                // PROP chunks for SharedStrings refer to entries in the SSTR
+                // chunk, so it has to be the same too.
+                let reusable = previous
+                    .filter(|_| {
+                        prop_info.prop_type != Type::SharedString || self.shared_strings_unchanged
+                    })
+                    .and_then(|previous| {
+                        previous.property_chunk(
+                            type_info.type_id,
+                            prop_info.serialized_name,
+                            prop_info.prop_type,
+                        )
+                    });
+
+                if let Some(raw) = reusable {
+                    log::trace!("Reusing property {}.{}", type_name, prop_name);
+                    self.output.write_all(raw)?;
+                    continue;
+                }
+
                 log::trace!(
                     "Writing property {}.{} (type {:?})",
                     type_name,
@@ -625,6 +821,12 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                     .instances
                     .iter()
                     .map(|instance| {
+                        // Overridden values replace the value on every
+                        // instance, and are never migrated.
+                        if let Some(replacement) = prop_info.replacement {
+                            return Cow::Borrowed(replacement);
+                        }
+
                         // We store the Name property in a different field for
                         // convenience, but when serializing to the binary model
                         // format we need to handle it just like other properties.
@@ -653,7 +855,9 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                         Cow::Borrowed(prop_info.default_value.borrow())
                     })
                     .map(|value| {
-                        if let Some(migration) = prop_info.migration {
+                        if prop_info.replacement.is_some() {
+                            value
+                        } else if let Some(migration) = prop_info.migration {
                             match migration.perform(&value) {
                                 Ok(new_value) => Cow::Owned(new_value),
                                 Err(_) => value,
@@ -699,7 +903,11 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                                     chunk.write_binary_string(value.as_ref())?;
                                 }
                                 Variant::Tags(value) => {
-                                    let buf = value.encode();
+                                    let buf = if self.serializer.canonical_ordering {
+                                        value.encode_sorted()
+                                    } else {
+                                        value.encode()
+                                    };
                                     chunk.write_binary_string(&buf)?;
                                 }
                                 Variant::Attributes(value) => {
@@ -714,11 +922,14 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                                 Variant::MaterialColors(value) => {
                                     chunk.write_binary_string(&value.encode())?;
                                 }
+                                Variant::SmoothGrid(value) => {
+                                    chunk.write_binary_string(&value.encode())?;
+                                }
                                 _ => {
                                     return type_mismatch(
                                         i,
                                         &rbx_value,
-                                        "String, ContentId, Tags, Attributes, MaterialColors, or BinaryString",
+                                        "String, ContentId, Tags, Attributes, MaterialColors, SmoothGrid, or BinaryString",
                                     );
                                 }
                             }
@@ -987,6 +1198,15 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                                 if let Some(id) = self.id_to_referent.get(value) {
                                     buf.push(*id);
                                 } else {
+                                    if value.is_some() {
+                                        self.serializer.diagnostics.report(
+                                            Diagnostic::RefCleared {
+                                                referent: type_info.instances[i].referent(),
+                                                property: *prop_name,
+                                            },
+                                        );
+                                    }
+
                                     buf.push(-1);
                                 }
                             } else {
@@ -1297,6 +1517,12 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
     pub fn serialize_parents(&mut self) -> Result<(), InnerError> {
         log::trace!("Writing parent relationships");
 
+        self.parent_chunk()?.dump(&mut self.output)?;
+
+        Ok(())
+    }
+
+    fn parent_chunk(&self) -> Result<ChunkBuilder, InnerError> {
         let mut chunk = ChunkBuilder::new(b"PRNT", self.serializer.compression);
 
         chunk.write_u8(0)?; // PRNT version 0
@@ -1326,9 +1552,7 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
         chunk.write_referent_array(object_referents)?;
         chunk.write_referent_array(parent_referents)?;
 
-        chunk.dump(&mut self.output)?;
-
-        Ok(())
+        Ok(chunk)
     }
 
     /// Write the fixed, uncompressed end chunk used to verify that the file
@@ -1432,3 +1656,41 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
         })
     }
 }
+
+/// Returns whether an override skips or replaces the given property.
+/// Overrides are set by canonical name, so the property is looked up first in
+/// case it's stored under an alias.
+fn is_overridden(serializer: &Serializer, class: Ustr, prop_name: Ustr) -> bool {
+    if serializer.overrides.is_empty() {
+        return false;
+    }
+
+    let canonical_name = find_property_descriptors(serializer.database, class, prop_name)
+        .map(|descriptors| Ustr::from(descriptors.canonical.name.as_ref()))
+        .unwrap_or(prop_name);
+
+    serializer.find_override(class, canonical_name).is_some()
+}
+
+/// Returns the name that `baseline` serializes the given property as, if it
+/// knows about the property and serializes it as the same type.
+fn find_baseline_name(
+    baseline: &ReflectionDatabase,
+    class: Ustr,
+    canonical_name: Ustr,
+    serialized_ty: VariantType,
+) -> Option<Ustr> {
+    let serialized = find_property_descriptors(baseline, class, canonical_name)?.serialized?;
+
+    let baseline_ty = match &serialized.data_type {
+        DataType::Value(ty) => *ty,
+        DataType::Enum(_) => VariantType::Enum,
+        _ => return None,
+    };
+
+    if baseline_ty == serialized_ty {
+        Some(serialized.name.as_ref().into())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,51 @@
+use rbx_dom_weak::{
+    types::{Variant, VariantType},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::{
+    conformance::{ConformanceChecker, Outcome},
+    to_writer,
+};
+
+#[test]
+fn roundtrip_passes() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+        InstanceBuilder::new("StringValue").with_property("Value", "Hello"),
+        InstanceBuilder::new("IntValue").with_property("Value", 5i64),
+    ]));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let report = ConformanceChecker::new().check_bytes(&buffer);
+
+    assert_eq!(report.outcome, Outcome::Passed);
+    assert_eq!(report.instance_count, 3);
+    assert!(report.mismatches.is_empty());
+    assert_eq!(report.property_coverage.get(&VariantType::String), Some(&1));
+}
+
+#[test]
+fn garbage_fails_to_decode() {
+    let report = ConformanceChecker::new().check_bytes(b"definitely not a model file");
+
+    assert!(matches!(report.outcome, Outcome::DecodeFailed(_)));
+    assert!(!report.passed());
+}
+
+#[test]
+fn ref_properties_are_compared_by_position() {
+    let target = InstanceBuilder::new("Folder").with_name("Target");
+    let target_ref = target.referent();
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+        target,
+        InstanceBuilder::new("ObjectValue").with_property("Value", Variant::Ref(target_ref)),
+    ]));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let report = ConformanceChecker::new().check_bytes(&buffer);
+    assert_eq!(report.outcome, Outcome::Passed, "{:?}", report.mismatches);
+}
@@ -0,0 +1,335 @@
+use std::{convert::TryInto, io::Write};
+
+use rbx_dom_weak::{
+    types::{BinaryString, SharedString, SmoothGrid, TerrainMaterials, TerrainVec, Variant, Voxel},
+    ustr, DomBudget, InstanceBuilder, SourceLocation, WeakDom,
+};
+
+use crate::{
+    chunk::ChunkBuilder, core::RbxWriteExt, from_reader_into, to_writer, CompressionType,
+    Deserializer, Serializer,
+};
+
+/// Ensures that decoding into an existing dom replaces its contents.
+#[test]
+fn deserialize_into_existing_dom() {
+    let encode = |builder: InstanceBuilder| {
+        let tree = WeakDom::new(builder);
+        let mut buffer = Vec::new();
+        to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+        buffer
+    };
+
+    let first = encode(
+        InstanceBuilder::new("Folder")
+            .with_name("First")
+            .with_children([
+                InstanceBuilder::new("StringValue"),
+                InstanceBuilder::new("StringValue"),
+            ]),
+    );
+    let second = encode(InstanceBuilder::new("Model").with_name("Second"));
+
+    let deserializer = Deserializer::new();
+    let mut dom = WeakDom::default();
+
+    deserializer
+        .deserialize_into(first.as_slice(), &mut dom)
+        .expect("failed to decode model");
+    assert_eq!(dom.root().class, "DataModel");
+    assert_eq!(dom.descendants().count(), 4);
+
+    deserializer
+        .deserialize_into(second.as_slice(), &mut dom)
+        .expect("failed to decode model");
+    assert_eq!(dom.descendants().count(), 2);
+
+    let child = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    assert_eq!(child.name, "Second");
+    assert_eq!(child.class, "Model");
+}
+
+/// Ensures that a failed decode leaves the dom with only an empty DataModel.
+#[test]
+fn deserialize_into_failure() {
+    let mut dom =
+        WeakDom::new(InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")));
+    let result = Deserializer::new().deserialize_into(&b"not a model"[..], &mut dom);
+
+    assert!(result.is_err());
+    assert_eq!(dom.root().class, "DataModel");
+    assert!(dom.root().children().is_empty());
+    assert_eq!(dom.descendants().count(), 1);
+
+    // The same is true when the budget is too small for the file, or even
+    // for the root, and the budget is still replaced.
+    let tree =
+        WeakDom::new(InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let budgets = [
+        DomBudget::new().max_instances(2),
+        DomBudget::new().max_instances(0),
+        DomBudget::new().max_instances(0),
+    ];
+    for budget in budgets {
+        let result = Deserializer::new()
+            .budget(budget)
+            .deserialize_into(buffer.as_slice(), &mut dom);
+
+        assert!(result.is_err());
+        assert_eq!(dom.root().class, "DataModel");
+        assert_eq!(dom.descendants().count(), 1);
+        assert_eq!(dom.budget(), budget);
+    }
+}
+
+/// Ensures that a model can be decoded under an instance in an existing dom,
+/// and that Ref properties point at the newly inserted instances.
+#[test]
+fn deserialize_into_parent() {
+    let value = InstanceBuilder::new("ObjectValue").with_name("Value");
+    let target = InstanceBuilder::new("Part").with_name("Target");
+    let value_ref = value.referent();
+    let target_ref = target.referent();
+
+    let mut model = WeakDom::new(InstanceBuilder::new("Folder").with_children([value, target]));
+    model
+        .get_by_ref_mut(value_ref)
+        .unwrap()
+        .properties
+        .insert(ustr("Value"), Variant::Ref(target_ref));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &model, model.root().children()).expect("failed to encode model");
+
+    let mut dom =
+        WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+            InstanceBuilder::new("Workspace").with_child(InstanceBuilder::new("Model")),
+        ));
+    let workspace_ref = dom.root().children()[0];
+
+    let refs = from_reader_into(buffer.as_slice(), &mut dom, workspace_ref)
+        .expect("failed to decode model");
+    assert_eq!(refs.len(), 2);
+    assert_eq!(dom.descendants().count(), 5);
+
+    let workspace = dom.get_by_ref(workspace_ref).unwrap();
+    assert_eq!(workspace.children().len(), 3);
+    assert_eq!(&workspace.children()[1..], refs.as_slice());
+
+    let value = dom.get_by_ref(refs[0]).unwrap();
+    assert_eq!(value.name, "Value");
+    assert_eq!(value.parent(), workspace_ref);
+    assert_eq!(
+        value.properties.get(&ustr("Value")),
+        Some(&Variant::Ref(refs[1]))
+    );
+}
+
+/// Ensures that a failed decode into a parent leaves the dom unchanged.
+#[test]
+fn deserialize_into_parent_failure() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_children([InstanceBuilder::new("Part"), InstanceBuilder::new("Part")]),
+    );
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let root_ref = dom.root_ref();
+
+    assert!(from_reader_into(&b"not a model"[..], &mut dom, root_ref).is_err());
+    assert_eq!(dom.descendants().count(), 1);
+
+    // Three instances don't fit alongside the DataModel.
+    dom.set_budget(DomBudget::new().max_instances(3));
+    assert!(from_reader_into(buffer.as_slice(), &mut dom, root_ref).is_err());
+    assert_eq!(dom.descendants().count(), 1);
+
+    // The Folder fits, but its children are too deep, so the Folder has to be
+    // removed again.
+    dom.set_budget(DomBudget::new().max_depth(1));
+    assert!(from_reader_into(buffer.as_slice(), &mut dom, root_ref).is_err());
+    assert_eq!(dom.descendants().count(), 1);
+    assert!(dom.root().children().is_empty());
+}
+
+/// Ensures that SharedStrings no property refers to are reported, and that
+/// referenced ones are not.
+#[test]
+fn orphaned_shared_strings() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_property("UsedString", SharedString::new(b"used".to_vec())),
+    );
+
+    let mut buffer = Vec::new();
+    Serializer::new()
+        .compression_type(CompressionType::None)
+        .serialize(&mut buffer, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    let deserializer = Deserializer::new();
+    assert!(deserializer
+        .orphaned_shared_strings(buffer.as_slice())
+        .unwrap()
+        .is_empty());
+
+    // Add a second SSTR chunk after the serializer's own, so that existing
+    // indices stay valid. It starts after the 32 byte file header and the
+    // 16 byte chunk header of the first SSTR chunk.
+    let first_chunk_len = u32::from_le_bytes(buffer[40..44].try_into().unwrap()) as usize;
+    let insert_at = 32 + 16 + first_chunk_len;
+
+    let mut chunk = ChunkBuilder::new(b"SSTR", CompressionType::None);
+    chunk.write_le_u32(0).unwrap();
+    chunk.write_le_u32(1).unwrap();
+    chunk.write_all(&[0; 16]).unwrap();
+    chunk.write_binary_string(b"orphaned").unwrap();
+
+    let mut orphan_chunk = Vec::new();
+    chunk.dump(&mut orphan_chunk).unwrap();
+    buffer.splice(insert_at..insert_at, orphan_chunk);
+
+    let orphans = deserializer
+        .orphaned_shared_strings(buffer.as_slice())
+        .unwrap();
+    assert_eq!(orphans, vec![SharedString::new(b"orphaned".to_vec())]);
+
+    // The orphaned string is dropped when the file is written again.
+    let dom = deserializer.deserialize(buffer.as_slice()).unwrap();
+    let mut reencoded = Vec::new();
+    Serializer::new()
+        .serialize(&mut reencoded, &dom, dom.root().children())
+        .unwrap();
+    assert!(deserializer
+        .orphaned_shared_strings(reencoded.as_slice())
+        .unwrap()
+        .is_empty());
+}
+
+/// Ensures that instances record the INST chunk that declared them when
+/// source locations are tracked.
+#[test]
+fn track_source_locations() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("Folder"))
+            .with_child(InstanceBuilder::new("StringValue")),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let decoded = Deserializer::new()
+        .track_source_locations(true)
+        .deserialize(buffer.as_slice())
+        .unwrap();
+
+    let outer = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    let folder = decoded.get_by_ref(outer.children()[0]).unwrap();
+    let value = decoded.get_by_ref(outer.children()[1]).unwrap();
+
+    let range_of = |location: Option<&SourceLocation>| match location {
+        Some(&SourceLocation::Bytes { start, end }) => start as usize..end as usize,
+        other => panic!("expected a byte range, got {:?}", other),
+    };
+
+    let folder_range = range_of(outer.metadata.get());
+    assert_eq!(range_of(folder.metadata.get()), folder_range);
+    assert_eq!(&buffer[folder_range.clone()][..4], b"INST");
+
+    let value_range = range_of(value.metadata.get());
+    assert_ne!(value_range, folder_range);
+    assert_eq!(&buffer[value_range.clone()][..4], b"INST");
+    assert!(value_range.end <= buffer.len());
+
+    let untracked = Deserializer::new().deserialize(buffer.as_slice()).unwrap();
+    let outer = untracked
+        .get_by_ref(untracked.root().children()[0])
+        .unwrap();
+    assert!(outer.metadata.get::<SourceLocation>().is_none());
+}
+
+/// Ensures that files which don't fit in the deserializer's budget are
+/// rejected, and that files which do fit carry the budget with them.
+#[test]
+fn budget() {
+    let tree =
+        WeakDom::new(InstanceBuilder::new("Folder").with_child(
+            InstanceBuilder::new("StringValue").with_property("Value", "a".repeat(500)),
+        ));
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    // The file declares two instances, which don't fit alongside the
+    // DataModel at the root.
+    let error = Deserializer::new()
+        .budget(DomBudget::new().max_instances(2))
+        .deserialize(buffer.as_slice())
+        .unwrap_err();
+    assert!(error.to_string().contains("dom budget exceeded"));
+
+    assert!(Deserializer::new()
+        .budget(DomBudget::new().max_property_bytes(100))
+        .deserialize(buffer.as_slice())
+        .is_err());
+
+    let budget = DomBudget::new().max_instances(3);
+    let decoded = Deserializer::new()
+        .budget(budget)
+        .deserialize(buffer.as_slice())
+        .unwrap();
+    assert_eq!(decoded.budget(), budget);
+}
+
+/// Ensures that `Terrain.SmoothGrid` is only decoded when asked for, and that
+/// blobs that can't be decoded are left as they are.
+#[test]
+fn decode_terrain() {
+    let mut grid = SmoothGrid::new();
+    grid.write_voxel_world(
+        TerrainVec::new(0, -3, 40),
+        Voxel::solid(TerrainMaterials::Rock),
+    );
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder").with_children([
+            InstanceBuilder::new("Terrain").with_property("SmoothGrid", grid.clone()),
+            InstanceBuilder::new("Terrain")
+                .with_property("SmoothGrid", BinaryString::from(vec![1, 5, 0xFF])),
+        ]),
+    );
+
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).unwrap();
+
+    let smooth_grids = |deserializer: Deserializer| {
+        let dom = deserializer.deserialize(buffer.as_slice()).unwrap();
+        let folder = dom.get_by_ref(dom.root().children()[0]).unwrap();
+
+        folder
+            .children()
+            .iter()
+            .map(|&referent| {
+                dom.get_by_ref(referent).unwrap().properties[&ustr("SmoothGrid")].clone()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let encoded = BinaryString::from(grid.encode());
+    let broken = BinaryString::from(vec![1, 5, 0xFF]);
+
+    assert_eq!(
+        smooth_grids(Deserializer::new()),
+        [encoded.into(), broken.clone().into()]
+    );
+    assert_eq!(
+        smooth_grids(Deserializer::new().decode_terrain(true)),
+        [Variant::SmoothGrid(grid), broken.into()]
+    );
+}
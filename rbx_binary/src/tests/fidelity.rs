@@ -0,0 +1,93 @@
+use rbx_dom_weak::{
+    types::{Enum, EnumItem, Region3, Tags, VariantType, Vector3},
+    InstanceBuilder, WeakDom,
+};
+use rbx_reflection::ReflectionDatabase;
+
+use crate::{
+    fidelity::{check_fidelity, type_fidelity, Fidelity},
+    Serializer,
+};
+
+/// Ensures that only properties that can't be read back as they were written
+/// are reported, and that properties known to the reflection database are
+/// trusted to be read back as the right type.
+#[test]
+fn check_lossy_properties() {
+    let mut tags = Tags::new();
+    tags.push("Enemy");
+
+    let dom = WeakDom::new(
+        InstanceBuilder::new("Part")
+            .with_property("Size", Vector3::new(1.0, 2.0, 3.0))
+            .with_property("Tags", tags)
+            .with_property("Note", "hello")
+            .with_property(
+                "Choice",
+                EnumItem {
+                    ty: "Material".to_owned(),
+                    value: 256,
+                },
+            )
+            .with_property(
+                "Area",
+                Region3::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0)),
+            )
+            .with_property("Kind", Enum::from_u32(1)),
+    );
+
+    let lossy = check_fidelity(&dom, &Serializer::new());
+    let reported: Vec<_> = lossy
+        .iter()
+        .map(|property| (property.property.as_str(), property.ty))
+        .collect();
+
+    assert_eq!(
+        reported,
+        [
+            ("Area", VariantType::Region3),
+            ("Choice", VariantType::EnumItem),
+            ("Note", VariantType::String),
+        ]
+    );
+    assert_eq!(lossy[0].fidelity, Fidelity::Unsupported);
+    assert_eq!(lossy[1].fidelity, Fidelity::Unsupported);
+    assert!(matches!(lossy[2].fidelity, Fidelity::Lossy { .. }));
+
+    assert!(crate::to_writer(Vec::new(), &dom, &[dom.root_ref()]).is_err());
+}
+
+/// Ensures that properties are looked up in the serializer's reflection
+/// database rather than the default one.
+#[test]
+fn custom_database() {
+    let mut tags = Tags::new();
+    tags.push("Enemy");
+
+    let dom = WeakDom::new(InstanceBuilder::new("Part").with_property("Tags", tags));
+
+    assert!(check_fidelity(&dom, &Serializer::new()).is_empty());
+
+    let database = ReflectionDatabase::new();
+    let serializer = Serializer::new().reflection_database(&database);
+    let lossy = check_fidelity(&dom, &serializer);
+
+    assert_eq!(lossy.len(), 1);
+    assert_eq!(lossy[0].property, "Tags");
+    assert!(matches!(lossy[0].fidelity, Fidelity::Lossy { .. }));
+}
+
+#[test]
+fn type_table() {
+    assert_eq!(type_fidelity(VariantType::Font), Fidelity::Exact);
+    assert_eq!(type_fidelity(VariantType::Float32), Fidelity::Exact);
+    assert_eq!(type_fidelity(VariantType::BinaryString), Fidelity::Exact);
+    assert_eq!(
+        type_fidelity(VariantType::Vector2int16),
+        Fidelity::Unsupported
+    );
+    assert!(matches!(
+        type_fidelity(VariantType::ContentId),
+        Fidelity::Lossy { .. }
+    ));
+}
@@ -0,0 +1,46 @@
+use rbx_dom_weak::{
+    testing::DomGenerator,
+    types::{BinaryString, Variant},
+    DomViewer, WeakDom,
+};
+
+use crate::{from_reader, to_writer};
+
+fn round_trip(dom: &WeakDom) -> WeakDom {
+    let mut encoded = Vec::new();
+    to_writer(&mut encoded, dom, dom.root().children()).unwrap();
+    from_reader(encoded.as_slice()).unwrap()
+}
+
+/// Generated doms use every kind of property value the generator knows
+/// about, so they should survive being written and read back.
+#[test]
+fn generated_round_trip() {
+    for seed in 0..4 {
+        let mut dom = DomGenerator::new(seed).instances(300).generate();
+        let decoded = round_trip(&dom);
+
+        // Properties the reflection database doesn't know are read back as
+        // `BinaryString` when they were written as `String`.
+        let referents: Vec<_> = dom
+            .descendants()
+            .map(|instance| instance.referent())
+            .collect();
+        for referent in referents {
+            let instance = dom.get_by_ref_mut(referent).unwrap();
+
+            for value in instance.properties.values_mut() {
+                if let Variant::String(text) = value {
+                    *value = BinaryString::from(text.as_bytes().to_vec()).into();
+                }
+            }
+        }
+
+        assert_eq!(
+            format!("{:?}", DomViewer::new().view_children(&decoded)),
+            format!("{:?}", DomViewer::new().view_children(&dom)),
+            "dom generated from seed {} changed after a round trip",
+            seed
+        );
+    }
+}
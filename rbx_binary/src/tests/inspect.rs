@@ -0,0 +1,55 @@
+use rbx_dom_weak::{
+    types::{BinaryString, ChunkCoordinates, SmoothGrid, TerrainMaterials, TerrainVec, Voxel},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::{inspect::inspect, to_writer};
+
+/// Ensures that inspecting a place reports its chunks and terrain without
+/// decoding it.
+#[test]
+fn terrain_stats() {
+    let mut grid = SmoothGrid::new();
+    grid.fill_block(
+        TerrainVec::new(0, 0, 0),
+        TerrainVec::new(40, 3, 3),
+        Voxel::solid(TerrainMaterials::Grass),
+    );
+    assert!(grid.get_chunk(ChunkCoordinates::new(1, 0, 0)).is_some());
+    let encoded = grid.encode();
+
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Workspace").with_child(
+            InstanceBuilder::new("Terrain")
+                .with_property("SmoothGrid", BinaryString::from(encoded.clone())),
+        ),
+    );
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode place");
+
+    let stats = inspect(buffer.as_slice()).expect("failed to inspect place");
+
+    assert_eq!(stats.num_instances, 2);
+    assert_eq!(stats.chunks.first().unwrap().name, "INST");
+    assert_eq!(stats.chunks.last().unwrap().name, "END\0");
+    assert_eq!(
+        stats.chunks.iter().map(|chunk| chunk.size).sum::<u64>(),
+        buffer.len() as u64 - 32
+    );
+
+    let terrain = stats.terrain.expect("place should have terrain");
+    assert_eq!(terrain.chunk_count, 2);
+    assert_eq!(terrain.encoded_size, encoded.len());
+}
+
+/// Ensures that files without terrain report none.
+#[test]
+fn no_terrain() {
+    let tree = WeakDom::new(InstanceBuilder::new("Folder"));
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, &tree, &[tree.root_ref()]).expect("failed to encode model");
+
+    let stats = inspect(buffer.as_slice()).expect("failed to inspect model");
+    assert_eq!(stats.num_instances, 1);
+    assert_eq!(stats.terrain, None);
+}
@@ -1,5 +1,13 @@
+mod conformance;
 mod core_read_write;
+mod deserializer;
+mod fidelity;
+mod generated;
+mod inspect;
 mod models;
+mod physics;
 mod places;
+mod quantize;
+mod rotations;
 mod serializer;
 mod util;
@@ -0,0 +1,76 @@
+use rbx_dom_weak::{
+    types::{
+        CFrame, CustomPhysicalProperties, Enum, Matrix3, PhysicalProperties, Region3, Vector3,
+    },
+    InstanceBuilder, WeakDom,
+};
+
+use crate::physics::mass_properties;
+
+/// Ensures that shapes, materials, custom physical properties, and massless
+/// parts are all accounted for, and that rotated parts grow the bounding box.
+#[test]
+fn model_mass_properties() {
+    // A quarter turn around the Y axis, which swaps the X and Z sizes.
+    let turned = Matrix3::new(
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(-1.0, 0.0, 0.0),
+    );
+
+    let dom = WeakDom::new(
+        InstanceBuilder::new("Model").with_children([
+            // A block of metal, with a density of 7.85.
+            InstanceBuilder::new("Part")
+                .with_property("Size", Vector3::new(1.0, 1.0, 1.0))
+                .with_property("Material", Enum::from_u32(1088)),
+            // A wedge with a density of 2, which fills half of its size.
+            InstanceBuilder::new("WedgePart")
+                .with_property("Size", Vector3::new(4.0, 1.0, 1.0))
+                .with_property("CFrame", CFrame::new(Vector3::new(10.0, 0.0, 0.0), turned))
+                .with_property(
+                    "CustomPhysicalProperties",
+                    PhysicalProperties::Custom(CustomPhysicalProperties {
+                        density: 2.0,
+                        friction: 0.3,
+                        elasticity: 0.5,
+                        friction_weight: 1.0,
+                        elasticity_weight: 1.0,
+                    }),
+                ),
+            // A ball that only contributes to the bounding box.
+            InstanceBuilder::new("Part")
+                .with_property("Shape", Enum::from_u32(0))
+                .with_property("Size", Vector3::new(2.0, 2.0, 2.0))
+                .with_property(
+                    "CFrame",
+                    CFrame::new(Vector3::new(0.0, 5.0, 0.0), Matrix3::identity()),
+                )
+                .with_property("Massless", true),
+            InstanceBuilder::new("Folder"),
+        ]),
+    );
+
+    let properties = mass_properties(&dom, dom.root_ref());
+
+    assert_eq!(properties.parts, 3);
+    assert!((properties.mass - 11.85).abs() < 0.0001);
+
+    let center = properties.center_of_mass.unwrap();
+    assert!((center.x - 40.0 / 11.85).abs() < 0.0001);
+    assert_eq!((center.y, center.z), (0.0, 0.0));
+
+    assert_eq!(
+        properties.bounding_box,
+        Some(Region3::new(
+            Vector3::new(-1.0, -0.5, -2.0),
+            Vector3::new(10.5, 6.0, 2.0),
+        ))
+    );
+
+    let empty = WeakDom::new(InstanceBuilder::new("Folder"));
+    let properties = mass_properties(&empty, empty.root_ref());
+    assert_eq!(properties.parts, 0);
+    assert_eq!(properties.center_of_mass, None);
+    assert_eq!(properties.bounding_box, None);
+}
@@ -0,0 +1,57 @@
+use rbx_dom_weak::{
+    types::{CFrame, Matrix3, Variant, Vector3},
+    ustr, InstanceBuilder, WeakDom,
+};
+
+use crate::quantize::{quantize_vectors, DEFAULT_VECTOR_PROPERTIES};
+
+/// Ensures that quantizing only rounds the named properties, and leaves
+/// values that are already rounded alone.
+#[test]
+fn quantize_named_properties() {
+    let rotation = Matrix3::new(
+        Vector3::new(0.6, 0.0, 0.8),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(-0.8, 0.0, 0.6),
+    );
+
+    let mut dom = WeakDom::new(
+        InstanceBuilder::new("Part")
+            .with_property("Size", Vector3::new(4.000001, 0.999999, -0.0000002))
+            .with_property(
+                "CFrame",
+                CFrame::new(Vector3::new(10.12345, 0.5, 3.0), rotation),
+            )
+            .with_property("Velocity", Vector3::new(0.0000001, 0.0, 0.0))
+            .with_child(
+                InstanceBuilder::new("Part").with_property("Size", Vector3::new(1.0, 2.0, 3.0)),
+            ),
+    );
+
+    assert_eq!(quantize_vectors(&mut dom, 3, DEFAULT_VECTOR_PROPERTIES), 2);
+
+    let properties = &dom.root().properties;
+    assert_eq!(
+        properties.get(&ustr("Size")),
+        Some(&Variant::Vector3(Vector3::new(4.0, 1.0, 0.0)))
+    );
+    assert_eq!(
+        properties.get(&ustr("CFrame")),
+        Some(&Variant::CFrame(CFrame::new(
+            Vector3::new(10.123, 0.5, 3.0),
+            rotation
+        )))
+    );
+    assert_eq!(
+        properties.get(&ustr("Velocity")),
+        Some(&Variant::Vector3(Vector3::new(0.0000001, 0.0, 0.0)))
+    );
+
+    // Negative zero is normalized so that it doesn't print as "-0".
+    match properties.get(&ustr("Size")) {
+        Some(Variant::Vector3(size)) => assert!(size.z.is_sign_positive()),
+        other => panic!("unexpected Size {:?}", other),
+    }
+
+    assert_eq!(quantize_vectors(&mut dom, 3, DEFAULT_VECTOR_PROPERTIES), 0);
+}
@@ -0,0 +1,73 @@
+use rbx_dom_weak::{
+    types::{CFrame, Matrix3, Variant, Vector3},
+    InstanceBuilder, WeakDom,
+};
+
+use crate::{
+    rotations::{analyze_rotations, quantize_rotations},
+    CompressionType, Serializer,
+};
+
+/// Ensures that quantizing rotations only snaps rotations that are close to a
+/// basic rotation, and that snapped rotations are written compactly.
+#[test]
+fn quantize_shrinks_file() {
+    let nearly_flipped = Matrix3::new(
+        Vector3::new(-1.0, 0.0001, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(0.0001, 1.0, 0.0),
+    );
+    let tilted = Matrix3::new(
+        Vector3::new(0.8, -0.6, 0.0),
+        Vector3::new(0.6, 0.8, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+    );
+    let position = Vector3::new(1.0, 2.0, 3.0);
+
+    let mut dom = WeakDom::new(
+        InstanceBuilder::new("Model")
+            .with_property("WorldPivotData", Some(CFrame::new(position, tilted)))
+            .with_children([
+                InstanceBuilder::new("Part")
+                    .with_property("CFrame", CFrame::new(position, Matrix3::identity())),
+                InstanceBuilder::new("Part")
+                    .with_property("CFrame", CFrame::new(position, nearly_flipped)),
+            ]),
+    );
+
+    let report = analyze_rotations(&dom);
+    assert_eq!(report.cframes, 3);
+    assert_eq!(report.basic_rotations, 1);
+    assert_eq!(report.full_rotations(), 2);
+
+    let encode = |dom: &WeakDom| {
+        let mut buffer = Vec::new();
+        Serializer::new()
+            .compression_type(CompressionType::None)
+            .serialize(&mut buffer, dom, &[dom.root_ref()])
+            .expect("failed to encode model");
+        buffer
+    };
+    let before = encode(&dom);
+
+    assert_eq!(quantize_rotations(&mut dom, 0.001), 1);
+
+    let report = analyze_rotations(&dom);
+    assert_eq!(report.basic_rotations, 2);
+    assert_eq!(report.bytes_saved(), 72);
+    assert_eq!(encode(&dom).len(), before.len() - 36);
+
+    let part_ref = dom.root().children()[1];
+    let flipped = Matrix3::new(
+        Vector3::new(-1.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    assert_eq!(
+        dom.get_by_ref(part_ref)
+            .unwrap()
+            .properties
+            .get(&"CFrame".into()),
+        Some(&Variant::CFrame(CFrame::new(position, flipped)))
+    );
+}
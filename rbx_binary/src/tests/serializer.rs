@@ -1,9 +1,25 @@
+use std::{
+    convert::TryInto,
+    sync::{Arc, Mutex},
+};
+
 use rbx_dom_weak::{
-    types::{BrickColor, Color3, Color3uint8, Enum, Font, Ref, Region3, SharedString, Vector3},
-    InstanceBuilder, WeakDom,
+    types::{
+        BrickColor, Color3, Color3uint8, ContentId, Enum, Font, Ref, Region3, SharedString, Tags,
+        Variant, VariantType, Vector3,
+    },
+    ustr, Diagnostic, Diagnostics, InstanceBuilder, WeakDom,
+};
+
+use rbx_reflection::{
+    ClassDescriptor, DataType, PropertyDescriptor, PropertyKind, PropertySerialization,
+    ReflectionDatabase,
 };
 
-use crate::{text_deserializer::DecodedModel, to_writer};
+use crate::{
+    from_reader, text_deserializer::DecodedModel, to_writer, CompressionType, PropertyOverride,
+    Serializer,
+};
 
 /// A basic test to make sure we can serialize the simplest instance: a Folder.
 #[test]
@@ -193,3 +209,347 @@ fn default_shared_string() {
     let decoded = DecodedModel::from_reader(buf.as_slice());
     insta::assert_yaml_snapshot!(decoded);
 }
+
+/// Ensures that `canonical_ordering` makes the output independent of the
+/// order that tags were added in.
+#[test]
+fn canonical_tag_ordering() {
+    let encode = |members: &[&str], canonical_ordering: bool| {
+        let tags = Tags::from(
+            members
+                .iter()
+                .map(|&tag| tag.to_owned())
+                .collect::<Vec<_>>(),
+        );
+        let tree = WeakDom::new(InstanceBuilder::new("Folder").with_property("Tags", tags));
+
+        let mut buf = Vec::new();
+        Serializer::new()
+            .canonical_ordering(canonical_ordering)
+            .serialize(&mut buf, &tree, &[tree.root_ref()])
+            .expect("failed to encode model");
+        buf
+    };
+
+    assert_ne!(encode(&["b", "a"], false), encode(&["a", "b"], false));
+    assert_eq!(encode(&["b", "a"], true), encode(&["a", "b"], true));
+}
+
+/// Ensures that property overrides can skip and replace properties, and that
+/// they apply to subclasses of the class they're set for.
+#[test]
+fn property_overrides() {
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder").with_children([
+            InstanceBuilder::new("Script")
+                .with_property("Source", "print('hello')")
+                .with_property("LinkedSource", ContentId::from("rbxassetid://1")),
+            InstanceBuilder::new("StringValue").with_property("Value", "secret"),
+        ]),
+    );
+
+    let mut buf = Vec::new();
+    Serializer::new()
+        .property_override("ValueBase", "Value", PropertyOverride::Skip)
+        .property_override(
+            "Script",
+            "LinkedSource",
+            PropertyOverride::Replace(Variant::ContentId(ContentId::new())),
+        )
+        .serialize(&mut buf, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    let decoded = from_reader(buf.as_slice()).expect("failed to decode model");
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+
+    let script = decoded.get_by_ref(folder.children()[0]).unwrap();
+    assert_eq!(
+        script.properties.get(&ustr("LinkedSource")),
+        Some(&Variant::ContentId(ContentId::new()))
+    );
+    assert_eq!(
+        script.properties.get(&ustr("Source")),
+        Some(&Variant::String("print('hello')".to_owned()))
+    );
+
+    let value = decoded.get_by_ref(folder.children()[1]).unwrap();
+    assert_eq!(value.properties.get(&ustr("Value")), None);
+}
+
+/// Ensures that SharedStrings are left out of the file when an override for
+/// their property's canonical name skips them, even if they're stored under
+/// an alias.
+#[test]
+fn property_override_alias_shared_string() {
+    let mut class = ClassDescriptor::new("Blob");
+    class.properties.insert(
+        "Data".into(),
+        PropertyDescriptor::new("Data", DataType::Value(VariantType::SharedString)),
+    );
+
+    let mut alias =
+        PropertyDescriptor::new("LegacyData", DataType::Value(VariantType::SharedString));
+    alias.kind = PropertyKind::Alias {
+        alias_for: "Data".into(),
+    };
+    class.properties.insert("LegacyData".into(), alias);
+
+    let mut database = ReflectionDatabase::new();
+    database.classes.insert("Blob".into(), class);
+
+    let data = b"this blob should not be written".to_vec();
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Blob").with_property("LegacyData", SharedString::new(data.clone())),
+    );
+
+    let mut buf = Vec::new();
+    Serializer::new()
+        .reflection_database(&database)
+        .compression_type(CompressionType::None)
+        .property_override("Blob", "Data", PropertyOverride::Skip)
+        .serialize(&mut buf, &tree, &[tree.root_ref()])
+        .expect("failed to encode model");
+
+    assert!(!buf
+        .windows(data.len())
+        .any(|window| window == data.as_slice()));
+}
+
+/// Ensures that migrated properties and Refs to instances that aren't written
+/// are reported to the serializer's diagnostics.
+#[test]
+fn diagnostics() {
+    let outside = InstanceBuilder::new("Part");
+    let label = InstanceBuilder::new("TextLabel").with_property("Font", Enum::from_u32(3));
+    let value = InstanceBuilder::new("ObjectValue").with_property("Value", outside.referent());
+    let (label_ref, value_ref) = (label.referent(), value.referent());
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([outside, label, value]));
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&reported);
+    let diagnostics = Diagnostics::new(move |diagnostic: &Diagnostic| {
+        sink.lock().unwrap().push(diagnostic.clone());
+    });
+
+    let mut buf = Vec::new();
+    Serializer::new()
+        .diagnostics(diagnostics)
+        .serialize(&mut buf, &tree, &[label_ref, value_ref])
+        .expect("failed to encode model");
+
+    let reported = reported.lock().unwrap();
+    assert!(reported.contains(&Diagnostic::MigrationApplied {
+        class: ustr("TextLabel"),
+        property: ustr("Font"),
+        new_property: ustr("FontFace"),
+    }));
+    assert!(reported.contains(&Diagnostic::RefCleared {
+        referent: value_ref,
+        property: ustr("Value"),
+    }));
+}
+
+/// Ensures that a baseline database leaves out properties it doesn't know
+/// about or expects to be a different type, and renames properties it
+/// serializes under a different name.
+#[test]
+fn baseline_database() {
+    let mut baseline = rbx_reflection_database::get().clone();
+    let classes = &mut baseline.classes;
+
+    let string_value = classes.get_mut("StringValue").unwrap();
+    string_value.properties.remove("Value");
+
+    let int_value = classes.get_mut("IntValue").unwrap();
+    int_value.properties.get_mut("Value").unwrap().data_type =
+        DataType::Value(VariantType::Float64);
+
+    let bool_value = classes.get_mut("BoolValue").unwrap();
+    let mut old_value = bool_value.properties["Value"].clone();
+    old_value.name = "OldValue".into();
+    bool_value.properties.insert("OldValue".into(), old_value);
+    bool_value.properties.get_mut("Value").unwrap().kind = PropertyKind::Canonical {
+        serialization: PropertySerialization::SerializesAs("OldValue".into()),
+    };
+
+    let tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+        InstanceBuilder::new("StringValue").with_property("Value", "hello"),
+        InstanceBuilder::new("IntValue").with_property("Value", 5i64),
+        InstanceBuilder::new("BoolValue").with_property("Value", true),
+    ]));
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&reported);
+    let diagnostics = Diagnostics::new(move |diagnostic: &Diagnostic| {
+        sink.lock().unwrap().push(diagnostic.clone());
+    });
+
+    let mut buf = Vec::new();
+    Serializer::new()
+        .baseline_database(&baseline)
+        .diagnostics(diagnostics)
+        .serialize(&mut buf, &tree, tree.root().children())
+        .expect("failed to encode model");
+
+    let mut reported = reported.lock().unwrap().clone();
+    reported.sort_by_key(|diagnostic| diagnostic.to_string());
+    assert_eq!(
+        reported,
+        [
+            Diagnostic::PropertySkipped {
+                class: ustr("IntValue"),
+                property: ustr("Value"),
+            },
+            Diagnostic::PropertySkipped {
+                class: ustr("StringValue"),
+                property: ustr("Value"),
+            },
+        ]
+    );
+
+    let decoded = from_reader(buf.as_slice()).unwrap();
+    let children = decoded.root().children();
+    let property = |index: usize, name: &str| {
+        decoded
+            .get_by_ref(children[index])
+            .unwrap()
+            .properties
+            .get(&ustr(name))
+            .cloned()
+    };
+
+    assert_eq!(property(0, "Value"), None);
+    assert_eq!(property(1, "Value"), None);
+    assert_eq!(property(2, "OldValue"), Some(Variant::Bool(true)));
+}
+
+/// Ensures that incremental serialization copies the PROP chunks of classes
+/// without changed instances from the previous file, and writes everything
+/// else again.
+#[test]
+fn serialize_incremental() {
+    let value = InstanceBuilder::new("StringValue").with_property("Value", "before");
+    let value_ref = value.referent();
+    let mut tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+        InstanceBuilder::new("Part").with_property("Anchored", true),
+        InstanceBuilder::new("Part").with_property("Size", Vector3::new(1.0, 2.0, 3.0)),
+        value,
+    ]));
+
+    // The previous file is uncompressed so that reused chunks can be told
+    // apart from new ones, which are compressed.
+    let previous = Serializer::new()
+        .compression_type(CompressionType::None)
+        .serialize_incremental(&tree, &[tree.root_ref()], None, &[])
+        .expect("failed to encode model");
+
+    let encode = |tree: &WeakDom, changed: &[Ref]| {
+        Serializer::new()
+            .serialize_incremental(tree, &[tree.root_ref()], Some(&previous), changed)
+            .expect("failed to encode model")
+            .into_contents()
+    };
+
+    // Returns the number of PROP chunks that were reused and the number that
+    // were written again, going by whether they are compressed.
+    let count_reused = |buf: &[u8]| {
+        let (mut reused, mut written) = (0, 0);
+        let mut rest = &buf[32..];
+
+        while !rest.is_empty() {
+            let header = |offset: usize| {
+                u32::from_le_bytes(rest[offset..offset + 4].try_into().unwrap()) as usize
+            };
+            let (compressed_len, len) = (header(4), header(8));
+
+            if &rest[..4] == b"PROP" {
+                if compressed_len == 0 {
+                    reused += 1;
+                } else {
+                    written += 1;
+                }
+            }
+
+            let data_len = if compressed_len == 0 {
+                len
+            } else {
+                compressed_len
+            };
+            rest = &rest[16 + data_len..];
+        }
+
+        (reused, written)
+    };
+
+    let (reused, written) = count_reused(&encode(&tree, &[]));
+    assert!(reused > 0);
+    assert_eq!(written, 0);
+
+    tree.get_by_ref_mut(value_ref)
+        .unwrap()
+        .properties
+        .insert(ustr("Value"), Variant::String("after".to_owned()));
+
+    let buf = encode(&tree, &[value_ref]);
+    let (partly_reused, written) = count_reused(&buf);
+    assert!(partly_reused > 0 && written > 0);
+    assert_eq!(partly_reused + written, reused);
+
+    let decoded = from_reader(buf.as_slice()).expect("failed to decode model");
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    let decoded_value = decoded.get_by_ref(folder.children()[2]).unwrap();
+    assert_eq!(
+        decoded_value.properties.get(&ustr("Value")),
+        Some(&Variant::String("after".to_owned()))
+    );
+
+    // Adding an instance changes the referents of the instances after it, so
+    // nothing can be reused.
+    let folder_ref = tree.root_ref();
+    tree.insert(folder_ref, InstanceBuilder::new("Folder"));
+
+    let (reused, _) = count_reused(&encode(&tree, &[]));
+    assert_eq!(reused, 0);
+}
+
+/// Ensures that incremental serialization doesn't reuse PROP chunks when
+/// instances of the same class swap places, which leaves every INST and PRNT
+/// chunk the same.
+#[test]
+fn serialize_incremental_reordered() {
+    let first = InstanceBuilder::new("Part").with_property("Anchored", true);
+    let first_ref = first.referent();
+    let second = InstanceBuilder::new("Part").with_property("Size", Vector3::new(1.0, 2.0, 3.0));
+    let second_ref = second.referent();
+    let mut tree = WeakDom::new(InstanceBuilder::new("Folder").with_children([first, second]));
+
+    let serializer = Serializer::new();
+    let previous = serializer
+        .serialize_incremental(&tree, &[tree.root_ref()], None, &[])
+        .expect("failed to encode model");
+
+    let root_ref = tree.root_ref();
+    tree.transfer_within(first_ref, second_ref);
+    tree.transfer_within(first_ref, root_ref);
+
+    let output = serializer
+        .serialize_incremental(&tree, &[tree.root_ref()], Some(&previous), &[])
+        .expect("failed to encode model");
+
+    let decoded = from_reader(output.contents()).expect("failed to decode model");
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    let parts: Vec<_> = folder
+        .children()
+        .iter()
+        .map(|referent| decoded.get_by_ref(*referent).unwrap())
+        .collect();
+
+    assert_eq!(
+        parts[0].properties.get(&ustr("Size")),
+        Some(&Variant::Vector3(Vector3::new(1.0, 2.0, 3.0)))
+    );
+    assert_eq!(
+        parts[1].properties.get(&ustr("Anchored")),
+        Some(&Variant::Bool(true))
+    );
+}
@@ -0,0 +1,238 @@
+use std::{collections::HashSet, hash::BuildHasher};
+
+use rbx_types::{Content, ContentId, ContentType, Ref, Variant};
+use ustr::Ustr;
+
+use crate::{BudgetError, WeakDom};
+
+/// A property changed by [`WeakDom::replace_dead_assets`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetChange {
+    /// The instance the property belongs to.
+    pub referent: Ref,
+    /// The name of the property.
+    pub property: Ustr,
+    /// The dead asset ID the property referred to.
+    pub asset_id: u64,
+    /// The value of the property before it was changed.
+    pub old_value: Variant,
+    /// The value of the property after it was changed.
+    pub new_value: Variant,
+}
+
+impl WeakDom {
+    /// Finds every `ContentId` and `Content` property in the dom that refers
+    /// to one of the asset IDs in `dead`, like the `MeshId`, `TextureId`, and
+    /// `SoundId` of assets that were taken down, and replaces it.
+    ///
+    /// If `replacement` is `None`, the properties are emptied. Otherwise,
+    /// they're set to `replacement`, like a placeholder asset. Both
+    /// `rbxassetid://` URIs and `roblox.com/asset/?id=` URLs are recognized.
+    ///
+    /// Changes are counted against the DOM's [`DomBudget`][crate::DomBudget].
+    /// If making all of them would exceed it, none are made and an error is
+    /// returned. Otherwise, returns every change that was made, grouped by
+    /// instance and sorted by property name.
+    ///
+    /// ## Example
+    /// ```
+    /// use std::collections::HashSet;
+    ///
+    /// use rbx_dom_weak::{types::ContentId, InstanceBuilder, WeakDom};
+    ///
+    /// let mut dom = WeakDom::new(
+    ///     InstanceBuilder::new("Sound").with_property("SoundId", ContentId::from("rbxassetid://13")),
+    /// );
+    ///
+    /// let dead: HashSet<u64> = [13].iter().copied().collect();
+    /// let changes = dom.replace_dead_assets(&dead, None)?;
+    ///
+    /// assert_eq!(changes.len(), 1);
+    /// assert_eq!(changes[0].asset_id, 13);
+    /// # Ok::<(), rbx_dom_weak::BudgetError>(())
+    /// ```
+    pub fn replace_dead_assets<S: BuildHasher>(
+        &mut self,
+        dead: &HashSet<u64, S>,
+        replacement: Option<&str>,
+    ) -> Result<Vec<AssetChange>, BudgetError> {
+        let mut changes = Vec::new();
+
+        for instance in self.descendants() {
+            let start = changes.len();
+
+            for (name, value) in &instance.properties {
+                let uri = match value {
+                    Variant::ContentId(content_id) => content_id.as_str(),
+                    Variant::Content(content) => match content.value() {
+                        ContentType::Uri(uri) => uri.as_str(),
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+
+                let asset_id = match parse_asset_id(uri) {
+                    Some(asset_id) if dead.contains(&asset_id) => asset_id,
+                    _ => continue,
+                };
+
+                let new_value = match (value, replacement) {
+                    (Variant::ContentId(_), Some(uri)) => ContentId::from(uri).into(),
+                    (Variant::ContentId(_), None) => ContentId::new().into(),
+                    (_, Some(uri)) => Content::from_uri(uri).into(),
+                    (_, None) => Content::none().into(),
+                };
+
+                changes.push(AssetChange {
+                    referent: instance.referent(),
+                    property: *name,
+                    asset_id,
+                    old_value: value.clone(),
+                    new_value,
+                });
+            }
+
+            changes[start..].sort_by(|a, b| a.property.as_str().cmp(b.property.as_str()));
+        }
+
+        self.update_properties_all_or_none(
+            changes
+                .iter()
+                .map(|change| (change.referent, change.property, change.new_value.clone()))
+                .collect(),
+        )?;
+
+        Ok(changes)
+    }
+}
+
+/// Returns the asset ID in an `rbxassetid://` URI or a `roblox.com` asset
+/// URL, or `None` if `uri` isn't either.
+fn parse_asset_id(uri: &str) -> Option<u64> {
+    let uri = uri.trim();
+
+    let id = if let Some(id) = uri.strip_prefix("rbxassetid://") {
+        id
+    } else {
+        let (base, query) = uri.split_once('?')?;
+        let base = base.to_ascii_lowercase();
+
+        if !base.contains("roblox.com/asset") {
+            return None;
+        }
+
+        query.split('&').find_map(|pair| {
+            pair.strip_prefix("id=")
+                .or_else(|| pair.strip_prefix("ID="))
+        })?
+    };
+
+    id.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ustr::ustr;
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn replace_dead_assets() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder").with_children([
+                InstanceBuilder::new("MeshPart")
+                    .with_property("MeshId", ContentId::from("rbxassetid://1"))
+                    .with_property(
+                        "TextureID",
+                        ContentId::from("http://www.roblox.com/asset/?id=2"),
+                    )
+                    .with_property("MeshContent", Content::from_uri("rbxassetid://3")),
+                InstanceBuilder::new("Sound")
+                    .with_property("SoundId", ContentId::from("rbxassetid://1"))
+                    .with_property("Name", "rbxassetid://1"),
+            ]),
+        );
+
+        let dead: HashSet<u64> = [1, 2].iter().copied().collect();
+        let changes = dom
+            .replace_dead_assets(&dead, Some("rbxassetid://99"))
+            .unwrap();
+
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| (change.property.as_str(), change.asset_id))
+            .collect();
+        assert_eq!(summary, [("MeshId", 1), ("TextureID", 2), ("SoundId", 1)]);
+
+        let children = dom.root().children().to_vec();
+        let property = |dom: &WeakDom, index: usize, name: &str| {
+            dom.get_by_ref(children[index])
+                .unwrap()
+                .properties
+                .get(&ustr(name))
+                .cloned()
+        };
+
+        assert_eq!(
+            property(&dom, 0, "TextureID"),
+            Some(ContentId::from("rbxassetid://99").into())
+        );
+        assert_eq!(
+            property(&dom, 0, "MeshContent"),
+            Some(Content::from_uri("rbxassetid://3").into())
+        );
+        assert_eq!(property(&dom, 1, "Name"), Some("rbxassetid://1".into()));
+
+        let dead: HashSet<u64> = [3].iter().copied().collect();
+        dom.replace_dead_assets(&dead, None).unwrap();
+        assert_eq!(
+            property(&dom, 0, "MeshContent"),
+            Some(Content::none().into())
+        );
+    }
+
+    #[test]
+    fn replace_dead_assets_over_budget() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder").with_children([
+                InstanceBuilder::new("Sound")
+                    .with_property("SoundId", ContentId::from("rbxassetid://1")),
+                InstanceBuilder::new("Sound")
+                    .with_property("SoundId", ContentId::from("rbxassetid://1")),
+            ]),
+        );
+
+        // Leave room for only one of the two changes.
+        let used = dom.property_bytes();
+        dom.set_budget(crate::DomBudget::new().max_property_bytes(used + 10));
+
+        let dead: HashSet<u64> = [1].iter().copied().collect();
+        assert!(dom
+            .replace_dead_assets(&dead, Some("rbxassetid://123456789"))
+            .is_err());
+
+        for &child in dom.root().children() {
+            assert_eq!(
+                dom.get_by_ref(child)
+                    .unwrap()
+                    .properties
+                    .get(&ustr("SoundId")),
+                Some(&ContentId::from("rbxassetid://1").into())
+            );
+        }
+        assert_eq!(dom.property_bytes(), used);
+    }
+
+    #[test]
+    fn parse_asset_ids() {
+        assert_eq!(parse_asset_id("rbxassetid://123"), Some(123));
+        assert_eq!(
+            parse_asset_id("https://www.roblox.com/asset/?id=456"),
+            Some(456)
+        );
+        assert_eq!(parse_asset_id("rbxasset://textures/sky.png"), None);
+        assert_eq!(parse_asset_id(""), None);
+    }
+}
@@ -0,0 +1,404 @@
+use std::{error::Error, fmt};
+
+use rbx_types::{Attributes, Error as TypesError, Ref, Variant, VariantType};
+use ustr::{ustr, Ustr};
+
+use crate::{Instance, WeakDom};
+
+/// Describes the attributes that instances in a [`WeakDom`] are expected to
+/// have, checked by [`WeakDom::check_attributes`].
+///
+/// ## Example
+/// ```
+/// use rbx_dom_weak::{
+///     types::{Attributes, VariantType},
+///     AttributeRule, AttributeSchema, InstanceBuilder, WeakDom,
+/// };
+///
+/// let schema = AttributeSchema::new()
+///     .class("Model")
+///     .attribute(AttributeRule::required("Health", VariantType::Float64).range(0.0, 100.0))
+///     .attribute(AttributeRule::optional("Team", VariantType::String));
+///
+/// let dom = WeakDom::new(
+///     InstanceBuilder::new("Model")
+///         .with_property("Attributes", Attributes::new().with("Health", 150.0)),
+/// );
+///
+/// let violations = dom.check_attributes(&schema);
+/// assert_eq!(violations.len(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AttributeSchema {
+    class: Option<Ustr>,
+    rules: Vec<AttributeRule>,
+}
+
+impl AttributeSchema {
+    /// Creates a schema that applies to every instance and doesn't expect any
+    /// attributes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the schema to instances with the given ClassName. Subclasses
+    /// aren't included.
+    pub fn class<S: Into<Ustr>>(self, class: S) -> Self {
+        Self {
+            class: Some(class.into()),
+            ..self
+        }
+    }
+
+    /// Adds a rule for an attribute. Violations are reported in the order
+    /// rules were added.
+    pub fn attribute(mut self, rule: AttributeRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    fn applies_to(&self, instance: &Instance) -> bool {
+        match self.class {
+            Some(class) => instance.class == class,
+            None => true,
+        }
+    }
+}
+
+/// What an [`AttributeSchema`] expects of a single attribute.
+#[derive(Debug, Clone)]
+pub struct AttributeRule {
+    name: String,
+    ty: VariantType,
+    required: bool,
+    range: Option<(f64, f64)>,
+}
+
+impl AttributeRule {
+    /// Creates a rule for an attribute that every instance must have, with a
+    /// value of the given type.
+    pub fn required<S: Into<String>>(name: S, ty: VariantType) -> Self {
+        Self {
+            name: name.into(),
+            ty,
+            required: true,
+            range: None,
+        }
+    }
+
+    /// Creates a rule for an attribute that instances may leave out, but that
+    /// must have a value of the given type when present.
+    pub fn optional<S: Into<String>>(name: S, ty: VariantType) -> Self {
+        Self {
+            required: false,
+            ..Self::required(name, ty)
+        }
+    }
+
+    /// Requires the attribute's value to be between `min` and `max`
+    /// inclusive. This only applies to numeric attributes.
+    pub fn range(self, min: f64, max: f64) -> Self {
+        Self {
+            range: Some((min, max)),
+            ..self
+        }
+    }
+}
+
+/// An instance that doesn't match an [`AttributeSchema`], found by
+/// [`WeakDom::check_attributes`].
+///
+/// Every violation includes the full name of the instance, which is the names
+/// of it and its ancestors joined by periods.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AttributeViolation {
+    /// A required attribute is missing.
+    Missing {
+        /// The instance the attribute is missing from.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// The name of the attribute.
+        attribute: String,
+    },
+
+    /// An attribute has a value of the wrong type.
+    WrongType {
+        /// The instance the attribute belongs to.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// The name of the attribute.
+        attribute: String,
+        /// The type the schema expects.
+        expected: VariantType,
+        /// The type of the attribute's value.
+        actual: VariantType,
+    },
+
+    /// A numeric attribute is outside of the range the schema allows.
+    OutOfRange {
+        /// The instance the attribute belongs to.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// The name of the attribute.
+        attribute: String,
+        /// The attribute's value.
+        value: f64,
+        /// The lowest value the schema allows.
+        min: f64,
+        /// The highest value the schema allows.
+        max: f64,
+    },
+
+    /// The instance's `Attributes` property couldn't be read, so none of its
+    /// attributes were checked.
+    InvalidAttributes {
+        /// The instance the property belongs to.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// Why the property couldn't be read, if it was a blob.
+        source: Option<TypesError>,
+    },
+}
+
+impl fmt::Display for AttributeViolation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeViolation::Missing {
+                path, attribute, ..
+            } => write!(
+                formatter,
+                "{} is missing the required attribute {}",
+                path, attribute
+            ),
+            AttributeViolation::WrongType {
+                path,
+                attribute,
+                expected,
+                actual,
+                ..
+            } => write!(
+                formatter,
+                "Attribute {} of {} should be {:?}, but is {:?}",
+                attribute, path, expected, actual
+            ),
+            AttributeViolation::OutOfRange {
+                path,
+                attribute,
+                value,
+                min,
+                max,
+                ..
+            } => write!(
+                formatter,
+                "Attribute {} of {} is {}, which is outside of the range {} to {}",
+                attribute, path, value, min, max
+            ),
+            AttributeViolation::InvalidAttributes { path, source, .. } => match source {
+                Some(source) => write!(formatter, "{} has invalid Attributes: {}", path, source),
+                None => write!(
+                    formatter,
+                    "{} has an Attributes property that is not an Attributes blob",
+                    path
+                ),
+            },
+        }
+    }
+}
+
+impl Error for AttributeViolation {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AttributeViolation::InvalidAttributes {
+                source: Some(source),
+                ..
+            } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl WeakDom {
+    /// Checks the attributes of every instance in the dom that `schema`
+    /// applies to, and returns every way they don't match it.
+    ///
+    /// Attributes that the schema has no rule for are allowed. Instances are
+    /// checked in the same order as [`WeakDom::descendants`].
+    pub fn check_attributes(&self, schema: &AttributeSchema) -> Vec<AttributeViolation> {
+        let mut violations = Vec::new();
+
+        for instance in self.descendants() {
+            if schema.applies_to(instance) {
+                self.check_instance_attributes(instance, schema, &mut violations);
+            }
+        }
+
+        violations
+    }
+
+    fn check_instance_attributes(
+        &self,
+        instance: &Instance,
+        schema: &AttributeSchema,
+        violations: &mut Vec<AttributeViolation>,
+    ) {
+        let referent = instance.referent();
+
+        let decoded;
+        let attributes = match instance.properties.get(&ustr("Attributes")) {
+            Some(Variant::Attributes(attributes)) => Some(attributes),
+            Some(Variant::BinaryString(blob)) => {
+                match Attributes::from_reader(blob.as_ref() as &[u8]) {
+                    Ok(attributes) => {
+                        decoded = attributes;
+                        Some(&decoded)
+                    }
+                    Err(source) => {
+                        violations.push(AttributeViolation::InvalidAttributes {
+                            referent,
+                            path: self.full_name(referent),
+                            source: Some(source),
+                        });
+                        return;
+                    }
+                }
+            }
+            Some(_) => {
+                violations.push(AttributeViolation::InvalidAttributes {
+                    referent,
+                    path: self.full_name(referent),
+                    source: None,
+                });
+                return;
+            }
+            None => None,
+        };
+
+        for rule in &schema.rules {
+            let value = match attributes.and_then(|attributes| attributes.get(rule.name.as_str())) {
+                Some(value) => value,
+                None => {
+                    if rule.required {
+                        violations.push(AttributeViolation::Missing {
+                            referent,
+                            path: self.full_name(referent),
+                            attribute: rule.name.clone(),
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            if value.ty() != rule.ty {
+                violations.push(AttributeViolation::WrongType {
+                    referent,
+                    path: self.full_name(referent),
+                    attribute: rule.name.clone(),
+                    expected: rule.ty,
+                    actual: value.ty(),
+                });
+                continue;
+            }
+
+            if let (Some((min, max)), Some(number)) = (rule.range, numeric_value(value)) {
+                // NaN is never inside of a range.
+                if !(number >= min && number <= max) {
+                    violations.push(AttributeViolation::OutOfRange {
+                        referent,
+                        path: self.full_name(referent),
+                        attribute: rule.name.clone(),
+                        value: number,
+                        min,
+                        max,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns the names of the instance and all of its ancestors, joined by
+    /// periods.
+    pub(crate) fn full_name(&self, referent: Ref) -> String {
+        let mut components = Vec::new();
+        let mut current = referent;
+
+        while let Some(instance) = self.get_by_ref(current) {
+            components.push(instance.name.as_str());
+            current = instance.parent();
+        }
+
+        components.reverse();
+        components.join(".")
+    }
+}
+
+fn numeric_value(value: &Variant) -> Option<f64> {
+    match value {
+        Variant::Float32(value) => Some(f64::from(*value)),
+        Variant::Float64(value) => Some(*value),
+        Variant::Int32(value) => Some(f64::from(*value)),
+        Variant::Int64(value) => Some(*value as f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::BinaryString;
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn check_attributes() {
+        let schema = AttributeSchema::new()
+            .class("Model")
+            .attribute(AttributeRule::required("Health", VariantType::Float64).range(0.0, 100.0))
+            .attribute(AttributeRule::optional("Team", VariantType::String));
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Workspace").with_children([
+                InstanceBuilder::new("Model")
+                    .with_name("Valid")
+                    .with_property(
+                        "Attributes",
+                        Attributes::new().with("Health", 50.0).with("Extra", true),
+                    ),
+                InstanceBuilder::new("Model")
+                    .with_name("Broken")
+                    .with_property(
+                        "Attributes",
+                        Attributes::new()
+                            .with("Health", f64::NAN)
+                            .with("Team", 3i32),
+                    )
+                    .with_child(InstanceBuilder::new("Model").with_name("Empty")),
+                InstanceBuilder::new("Model")
+                    .with_name("Corrupt")
+                    .with_property("Attributes", BinaryString::from(vec![1, 0, 0])),
+                InstanceBuilder::new("Part").with_name("Ignored"),
+            ]),
+        );
+
+        let messages: Vec<_> = dom
+            .check_attributes(&schema)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            messages,
+            [
+                "Attribute Health of Workspace.Broken is NaN, which is outside of the range 0 to 100",
+                "Attribute Team of Workspace.Broken should be String, but is Int32",
+                "Workspace.Corrupt has invalid Attributes: missing attribute list length",
+                "Workspace.Broken.Empty is missing the required attribute Health",
+            ]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::{error::Error, fmt, mem::size_of};
+
+use rbx_types::{ColorSequenceKeypoint, NumberSequenceKeypoint, Variant};
+
+/// Limits on how large a [`WeakDom`][crate::WeakDom] is allowed to grow.
+///
+/// Budgets are checked by [`WeakDom::insert`][crate::WeakDom::insert],
+/// [`WeakDom::try_insert`][crate::WeakDom::try_insert],
+/// [`WeakDom::try_set_property`][crate::WeakDom::try_set_property],
+/// [`WeakDom::transfer`][crate::WeakDom::transfer],
+/// [`WeakDom::try_transfer`][crate::WeakDom::try_transfer], and
+/// [`WeakDom::transfer_within`][crate::WeakDom::transfer_within] before the
+/// dom is changed, so a rejected operation leaves the dom as it was.
+///
+/// Property sizes are an estimate of the memory used by each property's name
+/// and value, including any data the value stores on the heap. They're only
+/// counted while the budget limits them. Instances borrowed mutably, like
+/// with [`WeakDom::get_by_ref_mut`][crate::WeakDom::get_by_ref_mut], are
+/// counted again the next time the budget is checked, so changes made
+/// directly to [`Instance::properties`][crate::Instance::properties] are
+/// counted too.
+///
+/// By default, a budget has no limits.
+///
+/// ```
+/// use rbx_dom_weak::{DomBudget, InstanceBuilder, WeakDom};
+///
+/// let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+/// dom.set_budget(DomBudget::new().max_instances(2));
+///
+/// let root_ref = dom.root_ref();
+/// assert!(dom.try_insert(root_ref, InstanceBuilder::new("Folder")).is_ok());
+/// assert!(dom.try_insert(root_ref, InstanceBuilder::new("Folder")).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DomBudget {
+    max_instances: Option<usize>,
+    max_property_bytes: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+impl DomBudget {
+    /// Creates a `DomBudget` with no limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the largest number of instances the dom may contain, including
+    /// its root.
+    pub fn max_instances(self, max_instances: usize) -> Self {
+        Self {
+            max_instances: Some(max_instances),
+            ..self
+        }
+    }
+
+    /// Sets the largest total estimated size, in bytes, of every property in
+    /// the dom.
+    pub fn max_property_bytes(self, max_property_bytes: usize) -> Self {
+        Self {
+            max_property_bytes: Some(max_property_bytes),
+            ..self
+        }
+    }
+
+    /// Sets the deepest an instance may be placed in the dom. The root has a
+    /// depth of 0, its children have a depth of 1, and so on.
+    ///
+    /// Very deep hierarchies are slow for both rbx-dom and Roblox Studio to
+    /// work with, and are almost always a mistake.
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        Self {
+            max_depth: Some(max_depth),
+            ..self
+        }
+    }
+
+    /// Returns the largest number of instances the dom may contain, if there
+    /// is a limit.
+    pub fn instance_limit(&self) -> Option<usize> {
+        self.max_instances
+    }
+
+    /// Returns the largest total estimated size of every property in the
+    /// dom, if there is a limit.
+    pub fn property_bytes_limit(&self) -> Option<usize> {
+        self.max_property_bytes
+    }
+
+    /// Returns the deepest an instance may be placed in the dom, if there is
+    /// a limit.
+    pub fn depth_limit(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Returns `true` if this budget has no limits.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_instances.is_none()
+            && self.max_property_bytes.is_none()
+            && self.max_depth.is_none()
+    }
+
+    /// Checks whether a dom with the given number of instances and property
+    /// bytes fits within this budget.
+    pub fn check(&self, instances: usize, property_bytes: usize) -> Result<(), BudgetError> {
+        if let Some(limit) = self.max_instances {
+            if instances > limit {
+                return Err(BudgetError::TooManyInstances {
+                    limit,
+                    requested: instances,
+                });
+            }
+        }
+
+        self.check_property_bytes(property_bytes)
+    }
+
+    /// Checks whether a dom with the given number of property bytes fits
+    /// within this budget, regardless of how many instances it has.
+    pub fn check_property_bytes(&self, property_bytes: usize) -> Result<(), BudgetError> {
+        match self.max_property_bytes {
+            Some(limit) if property_bytes > limit => Err(BudgetError::TooManyPropertyBytes {
+                limit,
+                requested: property_bytes,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks whether an instance placed at the given depth fits within this
+    /// budget.
+    pub fn check_depth(&self, depth: usize) -> Result<(), BudgetError> {
+        match self.max_depth {
+            Some(limit) if depth > limit => Err(BudgetError::TooDeep { limit, depth }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the estimated size, in bytes, of a property with the given
+    /// name and value, as it's counted against a budget.
+    pub fn property_size(name: &str, value: &Variant) -> usize {
+        property_size(name, value)
+    }
+}
+
+/// An error returned when an operation would take a
+/// [`WeakDom`][crate::WeakDom] over its [`DomBudget`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BudgetError {
+    /// The dom would contain more instances than its budget allows.
+    TooManyInstances {
+        /// The largest number of instances allowed.
+        limit: usize,
+        /// The number of instances the dom would have contained.
+        requested: usize,
+    },
+
+    /// The dom's properties would be larger than its budget allows.
+    TooManyPropertyBytes {
+        /// The largest total property size allowed, in bytes.
+        limit: usize,
+        /// The total property size the dom would have had, in bytes.
+        requested: usize,
+    },
+
+    /// An instance would have been placed deeper in the dom than its budget
+    /// allows.
+    TooDeep {
+        /// The deepest an instance may be placed.
+        limit: usize,
+        /// The depth the instance would have had. Depths are only counted
+        /// until they pass the limit, so the real depth may be greater.
+        depth: usize,
+    },
+}
+
+impl fmt::Display for BudgetError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetError::TooManyInstances { limit, requested } => write!(
+                formatter,
+                "dom budget exceeded: {} instances requested, but at most {} are allowed",
+                requested, limit
+            ),
+            BudgetError::TooManyPropertyBytes { limit, requested } => write!(
+                formatter,
+                "dom budget exceeded: {} bytes of properties requested, but at most {} are allowed",
+                requested, limit
+            ),
+            BudgetError::TooDeep { limit, depth } => write!(
+                formatter,
+                "dom budget exceeded: an instance would be {} levels deep, but at most {} are allowed",
+                depth, limit
+            ),
+        }
+    }
+}
+
+impl Error for BudgetError {}
+
+/// Estimates the number of bytes used by a property with the given name and
+/// value.
+pub(crate) fn property_size(name: &str, value: &Variant) -> usize {
+    name.len() + variant_size(value)
+}
+
+fn variant_size(value: &Variant) -> usize {
+    let heap_size = match value {
+        Variant::String(value) => value.len(),
+        Variant::BinaryString(value) => AsRef::<[u8]>::as_ref(value).len(),
+        Variant::SharedString(value) => value.data().len(),
+        Variant::ContentId(value) => value.as_str().len(),
+        Variant::Tags(value) => value.iter().map(str::len).sum(),
+        Variant::NumberSequence(value) => {
+            value.keypoints.len() * size_of::<NumberSequenceKeypoint>()
+        }
+        Variant::ColorSequence(value) => value.keypoints.len() * size_of::<ColorSequenceKeypoint>(),
+        Variant::Attributes(value) => value
+            .iter()
+            .map(|(name, value)| property_size(name, value))
+            .sum(),
+        _ => 0,
+    };
+
+    size_of::<Variant>() + heap_size
+}
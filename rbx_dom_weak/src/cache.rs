@@ -0,0 +1,366 @@
+use std::{collections::VecDeque, convert::TryInto, error::Error, fmt};
+
+use ahash::{AHashMap, AHashSet};
+use rbx_types::{Ref, Variant};
+use serde::{Deserialize, Serialize};
+use ustr::{ustr, Ustr};
+
+use crate::{Instance, InstanceMetadata, WeakDom};
+
+/// The first bytes of every cache.
+const CACHE_MAGIC: &[u8; 8] = b"RBXDOMWC";
+
+/// The version of the cache format. This must be increased whenever the
+/// layout of `CachedDom` or `CachedInstance` changes.
+const CACHE_VERSION: u32 = 1;
+
+/// The borrowed form of a cached dom, used when writing.
+#[derive(Serialize)]
+struct CachedDomRef<'a> {
+    root_ref: Ref,
+    instances: Vec<CachedInstanceRef<'a>>,
+}
+
+#[derive(Serialize)]
+struct CachedInstanceRef<'a> {
+    referent: Ref,
+    parent: Ref,
+    children: &'a [Ref],
+    name: &'a str,
+    class: Ustr,
+    properties: Vec<(Ustr, &'a Variant)>,
+}
+
+/// The owned form of a cached dom, used when reading. It must serialize the
+/// same way as `CachedDomRef`.
+#[derive(Deserialize)]
+struct CachedDom {
+    root_ref: Ref,
+    instances: Vec<CachedInstance>,
+}
+
+#[derive(Deserialize)]
+struct CachedInstance {
+    referent: Ref,
+    parent: Ref,
+    children: Vec<Ref>,
+    name: String,
+    class: Ustr,
+    properties: Vec<(Ustr, Variant)>,
+}
+
+impl WeakDom {
+    /// Encodes the WeakDom into a compact cache that can be loaded again with
+    /// [`WeakDom::from_cache_bytes`] much faster than a Roblox file can be
+    /// decoded.
+    ///
+    /// The cache format is internal to rbx_dom_weak and is not understood by
+    /// Roblox. Caches are versioned, and caches written by a different version
+    /// of the format are rejected when loaded. Instance metadata and the
+    /// WeakDom's budget are not included.
+    pub fn to_cache_bytes(&self) -> Vec<u8> {
+        let cached = CachedDomRef {
+            root_ref: self.root_ref(),
+            instances: self
+                .descendants()
+                .map(|instance| CachedInstanceRef {
+                    referent: instance.referent(),
+                    parent: instance.parent(),
+                    children: instance.children(),
+                    name: &instance.name,
+                    class: instance.class,
+                    properties: instance
+                        .properties
+                        .iter()
+                        .map(|(key, value)| (*key, value))
+                        .collect(),
+                })
+                .collect(),
+        };
+
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(CACHE_MAGIC);
+        buffer.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+
+        bincode::serialize_into(&mut buffer, &cached)
+            .expect("serializing a cache into memory should not fail");
+
+        buffer
+    }
+
+    /// Loads a WeakDom from a cache created by [`WeakDom::to_cache_bytes`].
+    pub fn from_cache_bytes(bytes: &[u8]) -> Result<WeakDom, CacheError> {
+        if bytes.len() < CACHE_MAGIC.len() + 4 || &bytes[..CACHE_MAGIC.len()] != CACHE_MAGIC {
+            return Err(CacheError::BadMagic);
+        }
+
+        let (version, payload) = bytes[CACHE_MAGIC.len()..].split_at(4);
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != CACHE_VERSION {
+            return Err(CacheError::UnsupportedVersion(version));
+        }
+
+        let cached: CachedDom =
+            bincode::deserialize(payload).map_err(|err| CacheError::Malformed(err.to_string()))?;
+
+        let mut instances = AHashMap::with_capacity(cached.instances.len());
+        let mut unique_ids = AHashSet::new();
+
+        for instance in cached.instances {
+            let properties = instance.properties.into_iter().collect();
+            let instance = Instance {
+                referent: instance.referent,
+                children: instance.children,
+                parent: instance.parent,
+                name: instance.name,
+                class: instance.class,
+                properties,
+                metadata: InstanceMetadata::new(),
+            };
+
+            // WeakDom::from_raw panics on these, but a corrupted cache should
+            // produce an error instead.
+            if let Some(value) = instance.properties.get(&ustr("UniqueId")) {
+                match value {
+                    Variant::UniqueId(id) if unique_ids.insert(*id) => {}
+                    _ => return Err(CacheError::Malformed("invalid UniqueId".to_owned())),
+                }
+            }
+
+            if instances.insert(instance.referent, instance).is_some() {
+                return Err(CacheError::Malformed("duplicate referent".to_owned()));
+            }
+        }
+
+        validate_hierarchy(cached.root_ref, &instances)?;
+
+        Ok(WeakDom::from_raw(cached.root_ref, instances))
+    }
+}
+
+/// Checks that the parent and children of every instance in a cache agree
+/// with each other and that every instance is a descendant of the root, since
+/// the rest of rbx_dom_weak assumes the tree is well-formed.
+fn validate_hierarchy(
+    root_ref: Ref,
+    instances: &AHashMap<Ref, Instance>,
+) -> Result<(), CacheError> {
+    let root = instances
+        .get(&root_ref)
+        .ok_or_else(|| CacheError::Malformed("missing root instance".to_owned()))?;
+
+    if root.parent.is_some() {
+        return Err(CacheError::Malformed(
+            "root instance has a parent".to_owned(),
+        ));
+    }
+
+    for instance in instances.values() {
+        for child_ref in &instance.children {
+            match instances.get(child_ref) {
+                Some(child) if child.parent == instance.referent => {}
+                Some(_) => {
+                    return Err(CacheError::Malformed(format!(
+                        "instance {} is a child of an instance that is not its parent",
+                        child_ref
+                    )))
+                }
+                None => {
+                    return Err(CacheError::Malformed(format!(
+                        "missing child instance {}",
+                        child_ref
+                    )))
+                }
+            }
+        }
+    }
+
+    // Every child was checked to point back at its parent, so walking down
+    // from the root visits each instance at most once unless one is listed
+    // twice by its parent. Anything not visited is orphaned or part of a
+    // cycle.
+    let mut visited = AHashSet::with_capacity(instances.len());
+    let mut queue = VecDeque::new();
+    queue.push_back(root_ref);
+
+    while let Some(referent) = queue.pop_front() {
+        if !visited.insert(referent) {
+            return Err(CacheError::Malformed(format!(
+                "instance {} is listed as a child more than once",
+                referent
+            )));
+        }
+
+        queue.extend(instances[&referent].children.iter().copied());
+    }
+
+    if visited.len() != instances.len() {
+        return Err(CacheError::Malformed(
+            "some instances are not descendants of the root".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// An error that can occur when loading a cache with
+/// [`WeakDom::from_cache_bytes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CacheError {
+    /// The bytes do not start with the cache header.
+    BadMagic,
+
+    /// The cache was written with a version of the format that this version
+    /// of rbx_dom_weak cannot read.
+    UnsupportedVersion(u32),
+
+    /// The contents of the cache could not be decoded.
+    Malformed(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::BadMagic => write!(formatter, "not a WeakDom cache"),
+            CacheError::UnsupportedVersion(version) => write!(
+                formatter,
+                "unsupported WeakDom cache version {}, expected {}",
+                version, CACHE_VERSION
+            ),
+            CacheError::Malformed(message) => {
+                write!(formatter, "malformed WeakDom cache: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for CacheError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::{Attributes, UniqueId, Vector3};
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn round_trip() {
+        let mut attributes = Attributes::new();
+        attributes.insert("Health".to_owned(), Variant::Float64(100.0));
+
+        let part = InstanceBuilder::new("Part")
+            .with_name("Block")
+            .with_property("Size", Vector3::new(4.0, 1.0, 2.0))
+            .with_property("Attributes", attributes)
+            .with_property("UniqueId", UniqueId::now().unwrap());
+        let part_ref = part.referent();
+
+        let value = InstanceBuilder::new("ObjectValue").with_property("Value", part_ref);
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Workspace")
+                    .with_child(part)
+                    .with_child(value),
+            ),
+        );
+
+        let loaded = WeakDom::from_cache_bytes(&dom.to_cache_bytes()).unwrap();
+
+        assert_eq!(loaded.root_ref(), dom.root_ref());
+        assert_eq!(loaded.descendants().count(), dom.descendants().count());
+
+        for instance in dom.descendants() {
+            let other = loaded.get_by_ref(instance.referent()).unwrap();
+
+            assert_eq!(other.name, instance.name);
+            assert_eq!(other.class, instance.class);
+            assert_eq!(other.parent(), instance.parent());
+            assert_eq!(other.children(), instance.children());
+            assert_eq!(other.properties, instance.properties);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let bytes = dom.to_cache_bytes();
+
+        assert_eq!(
+            WeakDom::from_cache_bytes(b"not a cache").unwrap_err(),
+            CacheError::BadMagic
+        );
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[8] = 0xff;
+        assert_eq!(
+            WeakDom::from_cache_bytes(&wrong_version).unwrap_err(),
+            CacheError::UnsupportedVersion(0xff)
+        );
+
+        assert!(matches!(
+            WeakDom::from_cache_bytes(&bytes[..bytes.len() - 1]),
+            Err(CacheError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_broken_hierarchy() {
+        fn encode(root_ref: Ref, links: &[(Ref, Ref, &[Ref])]) -> Vec<u8> {
+            let cached = CachedDomRef {
+                root_ref,
+                instances: links
+                    .iter()
+                    .map(|&(referent, parent, children)| CachedInstanceRef {
+                        referent,
+                        parent,
+                        children,
+                        name: "Folder",
+                        class: ustr("Folder"),
+                        properties: Vec::new(),
+                    })
+                    .collect(),
+            };
+
+            let mut buffer = Vec::new();
+            buffer.extend_from_slice(CACHE_MAGIC);
+            buffer.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+            bincode::serialize_into(&mut buffer, &cached).unwrap();
+            buffer
+        }
+
+        let root = Ref::new();
+        let a = Ref::new();
+        let b = Ref::new();
+
+        let valid = encode(root, &[(root, Ref::none(), &[a]), (a, root, &[])]);
+        assert!(WeakDom::from_cache_bytes(&valid).is_ok());
+
+        let cases = [
+            // A child that doesn't point back at its parent.
+            encode(root, &[(root, Ref::none(), &[a]), (a, Ref::none(), &[])]),
+            // A child that doesn't exist.
+            encode(root, &[(root, Ref::none(), &[a])]),
+            // An instance whose parent doesn't list it as a child.
+            encode(root, &[(root, Ref::none(), &[]), (a, root, &[])]),
+            // A child listed twice.
+            encode(root, &[(root, Ref::none(), &[a, a]), (a, root, &[])]),
+            // A cycle that isn't connected to the root.
+            encode(
+                root,
+                &[(root, Ref::none(), &[]), (a, b, &[b]), (b, a, &[a])],
+            ),
+            // A root with a parent.
+            encode(root, &[(root, a, &[]), (a, Ref::none(), &[root])]),
+        ];
+
+        for bytes in &cases {
+            assert!(matches!(
+                WeakDom::from_cache_bytes(bytes),
+                Err(CacheError::Malformed(_))
+            ));
+        }
+    }
+}
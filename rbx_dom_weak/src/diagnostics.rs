@@ -0,0 +1,142 @@
+use std::{fmt, sync::Arc};
+
+use rbx_types::Ref;
+use ustr::Ustr;
+
+/// Something unusual that was recovered from while reading, writing, or
+/// changing a dom. Diagnostics are reported through [`Diagnostics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    /// A property was left out because it couldn't be read or written, or
+    /// because the reflection database doesn't know it and the caller asked
+    /// for unknown properties to be ignored.
+    PropertySkipped {
+        /// The class of the instance the property belongs to.
+        class: Ustr,
+        /// The name of the property.
+        property: Ustr,
+    },
+
+    /// A property was converted into a different property, like
+    /// `BrickColor` into `Color`, as directed by the reflection database.
+    MigrationApplied {
+        /// The class of the instance the property belongs to.
+        class: Ustr,
+        /// The name of the property before it was migrated.
+        property: Ustr,
+        /// The name of the property it was migrated to.
+        new_property: Ustr,
+    },
+
+    /// A Ref property pointed to an instance that wasn't available, and was
+    /// replaced with `Ref::none()`.
+    RefCleared {
+        /// The instance the property belongs to.
+        referent: Ref,
+        /// The name of the property.
+        property: Ustr,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::PropertySkipped { class, property } => {
+                write!(formatter, "Skipped property {}.{}", class, property)
+            }
+            Diagnostic::MigrationApplied {
+                class,
+                property,
+                new_property,
+            } => write!(
+                formatter,
+                "Migrated property {}.{} to {}",
+                class, property, new_property
+            ),
+            Diagnostic::RefCleared { referent, property } => write!(
+                formatter,
+                "Cleared Ref property {} of instance {} because it pointed to an unavailable instance",
+                property, referent
+            ),
+        }
+    }
+}
+
+/// Receives the [`Diagnostic`]s reported through a [`Diagnostics`] handle.
+///
+/// This is implemented for any closure that takes a `&Diagnostic`.
+pub trait DiagnosticSink: Send + Sync {
+    /// Called once for every diagnostic that is reported.
+    fn report(&self, diagnostic: &Diagnostic);
+}
+
+impl<F> DiagnosticSink for F
+where
+    F: Fn(&Diagnostic) + Send + Sync,
+{
+    fn report(&self, diagnostic: &Diagnostic) {
+        self(diagnostic)
+    }
+}
+
+/// A cheaply cloneable handle to a [`DiagnosticSink`], passed to rbx_dom_weak,
+/// rbx_binary, and rbx_xml to find out about problems they recovered from.
+///
+/// By default, a handle has no sink and diagnostics are dropped. The same
+/// diagnostic may be reported many times if it happens more than once.
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+///
+/// use rbx_dom_weak::{Diagnostic, Diagnostics};
+///
+/// let reported = Arc::new(Mutex::new(Vec::new()));
+/// let sink = Arc::clone(&reported);
+/// let diagnostics = Diagnostics::new(move |diagnostic: &Diagnostic| {
+///     sink.lock().unwrap().push(diagnostic.to_string());
+/// });
+///
+/// diagnostics.report(Diagnostic::PropertySkipped {
+///     class: "Part".into(),
+///     property: "Mystery".into(),
+/// });
+///
+/// assert_eq!(*reported.lock().unwrap(), ["Skipped property Part.Mystery"]);
+/// ```
+#[derive(Clone, Default)]
+pub struct Diagnostics {
+    sink: Option<Arc<dyn DiagnosticSink>>,
+}
+
+impl Diagnostics {
+    /// Creates a handle that reports diagnostics to the given sink.
+    pub fn new<S: DiagnosticSink + 'static>(sink: S) -> Self {
+        Self {
+            sink: Some(Arc::new(sink)),
+        }
+    }
+
+    /// Returns `true` if this handle has a sink to report diagnostics to.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    /// Reports a diagnostic to the sink, if there is one.
+    #[inline]
+    pub fn report(&self, diagnostic: Diagnostic) {
+        if let Some(sink) = &self.sink {
+            sink.report(&diagnostic);
+        }
+    }
+}
+
+impl fmt::Debug for Diagnostics {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Diagnostics")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
@@ -2,9 +2,13 @@ use std::collections::VecDeque;
 
 use ahash::{AHashMap, AHashSet};
 use rbx_types::{Ref, UniqueId, Variant};
-use ustr::ustr;
+use ustr::{ustr, Ustr, UstrMap};
 
-use crate::instance::{Instance, InstanceBuilder};
+use crate::{
+    budget::{property_size, BudgetError, DomBudget},
+    diagnostics::{Diagnostic, Diagnostics},
+    instance::{Instance, InstanceBuilder},
+};
 
 /// Represents a DOM containing one or more Roblox instances.
 ///
@@ -18,6 +22,14 @@ pub struct WeakDom {
     instances: AHashMap<Ref, Instance>,
     root_ref: Ref,
     unique_ids: AHashSet<UniqueId>,
+    budget: DomBudget,
+    // The estimated size of every property in the dom, left out of instances
+    // in `uncounted`. This is only kept while the budget limits it.
+    property_bytes: Option<usize>,
+    // Instances that have been borrowed mutably since their properties were
+    // last counted.
+    uncounted: AHashSet<Ref>,
+    diagnostics: Diagnostics,
 }
 
 impl WeakDom {
@@ -27,6 +39,10 @@ impl WeakDom {
             instances: AHashMap::new(),
             root_ref: builder.referent,
             unique_ids: AHashSet::new(),
+            budget: DomBudget::new(),
+            property_bytes: None,
+            uncounted: AHashSet::new(),
+            diagnostics: Diagnostics::default(),
         };
 
         dom.insert(Ref::none(), builder);
@@ -75,6 +91,10 @@ impl WeakDom {
             instances,
             root_ref,
             unique_ids,
+            budget: DomBudget::new(),
+            property_bytes: None,
+            uncounted: AHashSet::new(),
+            diagnostics: Diagnostics::default(),
         }
     }
 
@@ -84,6 +104,77 @@ impl WeakDom {
         self.instances.reserve(additional);
     }
 
+    /// Removes every instance from the WeakDom and replaces its root with the
+    /// given [`InstanceBuilder`], keeping the WeakDom's allocated storage.
+    ///
+    /// This is useful when loading many files in a row, since the storage of
+    /// one WeakDom can be reused for each of them. The WeakDom's
+    /// [`DomBudget`] and [`Diagnostics`] are kept.
+    ///
+    /// ## Panics
+    /// Panics if `builder` does not fit within the WeakDom's budget.
+    pub fn reset(&mut self, builder: InstanceBuilder) {
+        self.instances.clear();
+        self.unique_ids.clear();
+        self.uncounted.clear();
+        self.property_bytes = self.property_bytes.map(|_| 0);
+        self.root_ref = builder.referent;
+
+        self.insert(Ref::none(), builder);
+    }
+
+    /// Returns the limits this WeakDom is allowed to grow to.
+    pub fn budget(&self) -> DomBudget {
+        self.budget
+    }
+
+    /// Sets the limits this WeakDom is allowed to grow to. Instances already
+    /// in the WeakDom are kept even if they exceed the new budget, but no
+    /// more can be added until enough are removed.
+    pub fn set_budget(&mut self, budget: DomBudget) {
+        if budget.property_bytes_limit().is_none() {
+            self.property_bytes = None;
+            self.uncounted.clear();
+        } else if self.property_bytes.is_none() {
+            self.property_bytes = Some(self.instances.values().map(instance_property_bytes).sum());
+        }
+
+        self.budget = budget;
+    }
+
+    /// Returns the handle that this WeakDom reports [`Diagnostic`]s to.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Sets the handle that this WeakDom reports [`Diagnostic`]s to. Ref
+    /// properties that are cleared while cloning instances into this WeakDom
+    /// are reported as [`Diagnostic::RefCleared`].
+    pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    /// Returns the estimated size, in bytes, of every property in the
+    /// WeakDom, as counted against its [`DomBudget`].
+    ///
+    /// Property sizes are only kept up to date while the budget limits them,
+    /// so otherwise this has to look at every instance.
+    pub fn property_bytes(&self) -> usize {
+        match self.property_bytes {
+            Some(bytes) => {
+                let uncounted: usize = self
+                    .uncounted
+                    .iter()
+                    .filter_map(|referent| self.instances.get(referent))
+                    .map(instance_property_bytes)
+                    .sum();
+
+                add_bytes(bytes, uncounted)
+            }
+            None => self.instances.values().map(instance_property_bytes).sum(),
+        }
+    }
+
     /// Consumes the WeakDom, returning its underlying root ref and backing
     /// storage. This method is useful when tree-preserving operations are too
     /// slow.
@@ -103,7 +194,7 @@ impl WeakDom {
 
     /// Returns a _mutable_ reference to the root instance of the `WeakDom`.
     pub fn root_mut(&mut self) -> &mut Instance {
-        self.instances.get_mut(&self.root_ref).unwrap()
+        self.get_by_ref_mut(self.root_ref).unwrap()
     }
 
     /// Returns a reference to an instance by referent, or `None` if it is not
@@ -115,7 +206,17 @@ impl WeakDom {
     /// Returns a _mutable_ reference to an instance by referent, or `None` if
     /// it is not found.
     pub fn get_by_ref_mut(&mut self, referent: Ref) -> Option<&mut Instance> {
-        self.instances.get_mut(&referent)
+        let instance = self.instances.get_mut(&referent)?;
+
+        // The instance's properties can be changed through the reference we
+        // return, so they're left out until the budget is next checked.
+        if let Some(bytes) = self.property_bytes {
+            if self.uncounted.insert(referent) {
+                self.property_bytes = Some(sub_bytes(bytes, instance_property_bytes(instance)));
+            }
+        }
+
+        Some(instance)
     }
 
     /// Returns the [`UniqueId`] for the Instance with the provided referent, if it
@@ -138,6 +239,38 @@ impl WeakDom {
         self.descendants_of(self.root_ref)
     }
 
+    /// Returns up to `n` referents of instances in the WeakDom that match
+    /// `filter`, chosen at random.
+    ///
+    /// The choice is entirely determined by `seed` and the contents of the
+    /// WeakDom, so the same sample will be returned across runs. Referents are
+    /// returned in the same order that [`WeakDom::descendants`] visits them.
+    pub fn sample_instances<F>(&self, n: usize, seed: u64, mut filter: F) -> Vec<Ref>
+    where
+        F: FnMut(&Instance) -> bool,
+    {
+        let mut state = seed;
+        let mut reservoir: Vec<(usize, Ref)> = Vec::with_capacity(n);
+
+        let matching = self.descendants().filter(|instance| filter(instance));
+        for (i, instance) in matching.enumerate() {
+            if reservoir.len() < n {
+                reservoir.push((i, instance.referent()));
+            } else {
+                let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+                if j < n {
+                    reservoir[j] = (i, instance.referent());
+                }
+            }
+        }
+
+        reservoir.sort_unstable_by_key(|(i, _)| *i);
+        reservoir
+            .into_iter()
+            .map(|(_, referent)| referent)
+            .collect()
+    }
+
     /// Returns an iterator that goes through the descendants of a particular
     /// [`Ref`]. The passed `Ref` *must* be a part of this `WeakDom`.
     ///
@@ -155,12 +288,78 @@ impl WeakDom {
         }
     }
 
+    /// Returns an iterator that goes through the ancestors of a particular
+    /// [`Ref`], starting with its parent and ending with the root of the DOM.
+    /// The passed `Ref` *must* be a part of this `WeakDom`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `referent` is not a member of this DOM.
+    #[inline]
+    pub fn ancestors(&self, referent: Ref) -> WeakDomAncestors<'_> {
+        let instance = self
+            .get_by_ref(referent)
+            .expect("the referent provided to `ancestors` must be a part of the DOM");
+
+        WeakDomAncestors {
+            dom: self,
+            next: instance.parent(),
+        }
+    }
+
     /// Insert a new instance into the DOM with the given parent. The parent is allowed to
     /// be the none Ref.
     ///
     /// ## Panics
     /// Panics if `parent_ref` is some and does not refer to an instance in the DOM.
+    ///
+    /// Will also panic if the instance and its descendants do not fit within
+    /// the DOM's [`DomBudget`]. Use [`WeakDom::try_insert`] to handle this case.
     pub fn insert(&mut self, parent_ref: Ref, root_builder: InstanceBuilder) -> Ref {
+        self.try_insert(parent_ref, root_builder)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Insert a new instance into the DOM with the given parent, or return an
+    /// error without changing the DOM if the instance and its descendants do
+    /// not fit within the DOM's [`DomBudget`].
+    ///
+    /// ## Panics
+    /// Panics if `parent_ref` is some and does not refer to an instance in the DOM.
+    pub fn try_insert(
+        &mut self,
+        parent_ref: Ref,
+        root_builder: InstanceBuilder,
+    ) -> Result<Ref, BudgetError> {
+        if !self.budget.is_unlimited() {
+            let mut property_bytes = self.count_property_bytes();
+            let mut instances = 0;
+            let mut height = 0;
+            let mut to_visit = vec![(&root_builder, 0)];
+
+            while let Some((builder, level)) = to_visit.pop() {
+                instances += 1;
+                height = height.max(level);
+
+                if let Some(bytes) = property_bytes {
+                    let added = builder
+                        .properties
+                        .iter()
+                        .map(|(name, value)| property_size(name, value))
+                        .sum();
+                    property_bytes = Some(add_bytes(bytes, added));
+                }
+
+                to_visit.extend(builder.children.iter().map(|child| (child, level + 1)));
+            }
+
+            self.budget.check(
+                self.instances.len() + instances,
+                property_bytes.unwrap_or(0),
+            )?;
+            self.check_depth(parent_ref, height)?;
+        }
+
         fn insert(
             dom: &mut WeakDom,
             builder: InstanceBuilder,
@@ -176,6 +375,7 @@ impl WeakDom {
                     name: builder.name,
                     class: builder.class,
                     properties: builder.properties.into_iter().collect(),
+                    metadata: builder.metadata,
                 },
             );
 
@@ -213,7 +413,139 @@ impl WeakDom {
             }
         }
 
-        root_referent
+        Ok(root_referent)
+    }
+
+    /// Sets a property on the instance with the given referent, returning the
+    /// property's previous value. Unlike editing
+    /// [`Instance::properties`] directly, the change is counted against the
+    /// DOM's [`DomBudget`], and an error is returned without changing the
+    /// instance if it would not fit.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in the DOM.
+    pub fn try_set_property<K: Into<Ustr>, V: Into<Variant>>(
+        &mut self,
+        referent: Ref,
+        key: K,
+        value: V,
+    ) -> Result<Option<Variant>, BudgetError> {
+        let key = key.into();
+        let value = value.into();
+        let property_bytes = self.count_property_bytes();
+
+        let instance = self
+            .instances
+            .get_mut(&referent)
+            .unwrap_or_else(|| panic!("cannot set a property on an instance that does not exist"));
+
+        if let Some(bytes) = property_bytes {
+            let old_size = instance
+                .properties
+                .get(&key)
+                .map(|old| property_size(&key, old))
+                .unwrap_or(0);
+            let bytes = add_bytes(sub_bytes(bytes, old_size), property_size(&key, &value));
+
+            self.budget.check_property_bytes(bytes)?;
+            self.property_bytes = Some(bytes);
+        }
+
+        Ok(instance.properties.insert(key, value))
+    }
+
+    /// Applies a batch of property writes, given as the referent of an
+    /// instance, a property name, and a value. Consecutive writes to the same
+    /// instance only look it up once, so grouping writes by instance is
+    /// fastest.
+    ///
+    /// Writes are counted against the DOM's [`DomBudget`]. If a write would
+    /// exceed it, that write and every write after it are skipped and an
+    /// error is returned. Writes before it are kept.
+    ///
+    /// ## Panics
+    /// Panics if any referent does not refer to an instance in the DOM.
+    pub fn update_properties<I, K>(&mut self, updates: I) -> Result<(), BudgetError>
+    where
+        I: IntoIterator<Item = (Ref, K, Variant)>,
+        K: Into<Ustr>,
+    {
+        let mut property_bytes = self.count_property_bytes();
+        let mut current: Option<(Ref, &mut Instance)> = None;
+
+        for (referent, key, value) in updates {
+            if current.as_ref().map(|(current_ref, _)| *current_ref) != Some(referent) {
+                let instance = self.instances.get_mut(&referent).unwrap_or_else(|| {
+                    panic!("cannot update properties of an instance that does not exist")
+                });
+                current = Some((referent, instance));
+            }
+
+            // Unwrap is safe because we just made sure current is set.
+            let (_, instance) = current.as_mut().unwrap();
+            let key = key.into();
+
+            if let Some(bytes) = property_bytes {
+                let old_size = instance
+                    .properties
+                    .get(&key)
+                    .map(|old| property_size(&key, old))
+                    .unwrap_or(0);
+                let bytes = add_bytes(sub_bytes(bytes, old_size), property_size(&key, &value));
+
+                self.budget.check_property_bytes(bytes)?;
+                self.property_bytes = Some(bytes);
+                property_bytes = Some(bytes);
+            }
+
+            instance.properties.insert(key, value);
+        }
+
+        Ok(())
+    }
+
+    /// Applies a batch of writes like [`WeakDom::update_properties`], but
+    /// checks the DOM's [`DomBudget`] against its size after every write
+    /// before making any of them, so either every write is applied or none
+    /// are. Each property may only be written once.
+    ///
+    /// ## Panics
+    /// Panics if any referent does not refer to an instance in the DOM.
+    pub(crate) fn update_properties_all_or_none(
+        &mut self,
+        updates: Vec<(Ref, Ustr, Variant)>,
+    ) -> Result<(), BudgetError> {
+        let mut added = 0;
+        let mut removed = 0;
+
+        for (referent, key, value) in &updates {
+            let instance = self.instances.get(referent).unwrap_or_else(|| {
+                panic!("cannot update properties of an instance that does not exist")
+            });
+
+            if self.property_bytes.is_some() {
+                removed += instance
+                    .properties
+                    .get(key)
+                    .map(|old| property_size(key, old))
+                    .unwrap_or(0);
+                added += property_size(key, value);
+            }
+        }
+
+        if let Some(bytes) = self.count_property_bytes() {
+            let bytes = add_bytes(sub_bytes(bytes, removed), added);
+            self.budget.check_property_bytes(bytes)?;
+            self.property_bytes = Some(bytes);
+        }
+
+        for (referent, key, value) in updates {
+            // Unwrap is safe because every referent was checked above.
+            let instance = self.instances.get_mut(&referent).unwrap();
+            instance.properties.insert(key, value);
+        }
+
+        Ok(())
     }
 
     /// Destroy the instance with the given referent.
@@ -259,12 +591,52 @@ impl WeakDom {
     /// `dest_parent_ref` does not refer to an instance in `other_dom`.
     ///
     /// Will also panic if `referent` refers to the root instance in this
-    /// `WeakDom`.
+    /// `WeakDom`, or if the instance and its descendants do not fit within
+    /// `dest`'s [`DomBudget`]. Use [`WeakDom::try_transfer`] to handle this
+    /// case.
     pub fn transfer(&mut self, referent: Ref, dest: &mut WeakDom, dest_parent_ref: Ref) {
+        self.try_transfer(referent, dest, dest_parent_ref)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Move the instance with the given referent to a new `WeakDom` in the
+    /// same way as [`WeakDom::transfer`], or return an error without changing
+    /// either DOM if the instance and its descendants do not fit within
+    /// `dest`'s [`DomBudget`].
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in `self`, if
+    /// `dest_parent_ref` does not refer to an instance in `other_dom`, or if
+    /// `referent` refers to the root instance in this `WeakDom`.
+    pub fn try_transfer(
+        &mut self,
+        referent: Ref,
+        dest: &mut WeakDom,
+        dest_parent_ref: Ref,
+    ) -> Result<(), BudgetError> {
         if referent == self.root_ref {
             panic!("cannot transfer the root instance of WeakDom");
         }
 
+        if !dest.budget.is_unlimited() {
+            let mut property_bytes = dest.count_property_bytes();
+            let mut instances = 0;
+
+            for instance in self.descendants_of(referent) {
+                instances += 1;
+
+                if let Some(bytes) = property_bytes {
+                    property_bytes = Some(add_bytes(bytes, instance_property_bytes(instance)));
+                }
+            }
+
+            dest.budget.check(
+                dest.instances.len() + instances,
+                property_bytes.unwrap_or(0),
+            )?;
+            dest.check_depth(dest_parent_ref, self.height_of(referent))?;
+        }
+
         let mut instance = self.inner_remove(referent);
 
         // Remove the instance being moved from its parent's list of children.
@@ -301,6 +673,8 @@ impl WeakDom {
             panic!("cannot move an instance into an instance that does not exist")
         });
         dest_parent.children.push(referent);
+
+        Ok(())
     }
 
     /// Move the instance with the given referent to a new parent within the
@@ -314,12 +688,19 @@ impl WeakDom {
     /// `self`.
     ///
     /// Will also panic if `referent` refers to the root instance in this
-    /// `WeakDom`.
+    /// `WeakDom`, or if moving the instance would place one of its
+    /// descendants deeper than the [`DomBudget`] allows.
     pub fn transfer_within(&mut self, referent: Ref, dest_parent_ref: Ref) {
         if referent == self.root_ref {
             panic!("cannot transfer the root instance of WeakDom");
         }
 
+        if self.budget.depth_limit().is_some() {
+            if let Err(err) = self.check_depth(dest_parent_ref, self.height_of(referent)) {
+                panic!("{}", err);
+            }
+        }
+
         let instance = self
             .instances
             .get_mut(&referent)
@@ -351,12 +732,26 @@ impl WeakDom {
     /// Any Ref properties that point to instances contained in the subtree are
     /// rewritten to point to the cloned instances.
     pub fn clone_within(&mut self, referent: Ref) -> Ref {
+        self.clone_within_with(referent, |_, _| {})
+    }
+
+    /// Like [`WeakDom::clone_within`], but calls `transform` with each
+    /// original instance and the properties of its clone before the clone is
+    /// inserted. This allows properties to be changed while copying without
+    /// walking the cloned subtree again afterwards.
+    ///
+    /// Ref properties set by `transform` that point to instances in the
+    /// subtree are rewritten like any other Ref property.
+    pub fn clone_within_with<F>(&mut self, referent: Ref, mut transform: F) -> Ref
+    where
+        F: FnMut(&Instance, &mut UstrMap<Variant>),
+    {
         let mut ctx = CloneContext::default();
-        let root_builder = ctx.clone_ref_as_builder(self, referent);
+        let root_builder = ctx.clone_ref_as_builder(self, referent, &mut transform);
         let root_ref = self.insert(Ref::none(), root_builder);
 
         while let Some((cloned_parent, uncloned_child)) = ctx.queue.pop_front() {
-            let builder = ctx.clone_ref_as_builder(self, uncloned_child);
+            let builder = ctx.clone_ref_as_builder(self, uncloned_child, &mut transform);
             self.insert(cloned_parent, builder);
         }
 
@@ -377,12 +772,27 @@ impl WeakDom {
     /// properties will not necessarily be preserved in the destination dom. If you're
     /// cloning multiple instances, prefer `clone_multiple_into_external` instead!
     pub fn clone_into_external(&self, referent: Ref, dest: &mut WeakDom) -> Ref {
+        self.clone_into_external_with(referent, dest, |_, _| {})
+    }
+
+    /// Like [`WeakDom::clone_into_external`], but calls `transform` with each
+    /// original instance and the properties of its clone before the clone is
+    /// inserted into `dest`.
+    pub fn clone_into_external_with<F>(
+        &self,
+        referent: Ref,
+        dest: &mut WeakDom,
+        mut transform: F,
+    ) -> Ref
+    where
+        F: FnMut(&Instance, &mut UstrMap<Variant>),
+    {
         let mut ctx = CloneContext::default();
-        let root_builder = ctx.clone_ref_as_builder(self, referent);
+        let root_builder = ctx.clone_ref_as_builder(self, referent, &mut transform);
         let root_ref = dest.insert(Ref::none(), root_builder);
 
         while let Some((cloned_parent, uncloned_child)) = ctx.queue.pop_front() {
-            let builder = ctx.clone_ref_as_builder(self, uncloned_child);
+            let builder = ctx.clone_ref_as_builder(self, uncloned_child, &mut transform);
             dest.insert(cloned_parent, builder);
         }
 
@@ -393,16 +803,31 @@ impl WeakDom {
     /// Similar to `clone_into_external`, but clones multiple subtrees all at once. This
     /// method will preserve Ref properties that point across the cloned subtrees.
     pub fn clone_multiple_into_external(&self, referents: &[Ref], dest: &mut WeakDom) -> Vec<Ref> {
+        self.clone_multiple_into_external_with(referents, dest, |_, _| {})
+    }
+
+    /// Like [`WeakDom::clone_multiple_into_external`], but calls `transform`
+    /// with each original instance and the properties of its clone before the
+    /// clone is inserted into `dest`.
+    pub fn clone_multiple_into_external_with<F>(
+        &self,
+        referents: &[Ref],
+        dest: &mut WeakDom,
+        mut transform: F,
+    ) -> Vec<Ref>
+    where
+        F: FnMut(&Instance, &mut UstrMap<Variant>),
+    {
         let mut ctx = CloneContext::default();
         let mut root_refs = Vec::with_capacity(referents.len());
 
         for referent in referents {
-            let builder = ctx.clone_ref_as_builder(self, *referent);
+            let builder = ctx.clone_ref_as_builder(self, *referent, &mut transform);
             root_refs.push(dest.insert(Ref::none(), builder));
         }
 
         while let Some((cloned_parent, uncloned_child)) = ctx.queue.pop_front() {
-            let builder = ctx.clone_ref_as_builder(self, uncloned_child);
+            let builder = ctx.clone_ref_as_builder(self, uncloned_child, &mut transform);
             dest.insert(cloned_parent, builder);
         }
 
@@ -410,7 +835,70 @@ impl WeakDom {
         root_refs
     }
 
+    /// Returns how many levels below the root the deepest instance in the
+    /// WeakDom is. A WeakDom that only contains its root has a depth of 0.
+    pub fn depth(&self) -> usize {
+        self.height_of(self.root_ref)
+    }
+
+    /// Checks that no instance in the WeakDom is deeper than `max_depth`, and
+    /// if so, limits the WeakDom's [`DomBudget`] to that depth so that no
+    /// instance can be placed any deeper.
+    ///
+    /// Returns an error without changing the budget if the WeakDom is already
+    /// deeper than `max_depth`.
+    pub fn enforce_max_depth(&mut self, max_depth: usize) -> Result<(), BudgetError> {
+        let budget = self.budget.max_depth(max_depth);
+        budget.check_depth(self.depth())?;
+        self.budget = budget;
+
+        Ok(())
+    }
+
+    /// Checks that a subtree `height` levels tall can be placed under
+    /// `parent_ref` without exceeding the depth limit of the budget.
+    fn check_depth(&self, parent_ref: Ref, height: usize) -> Result<(), BudgetError> {
+        let limit = match self.budget.depth_limit() {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        // Only walk far enough up the tree to know that the limit is passed,
+        // so that checking a pathologically deep tree stays cheap.
+        let mut depth = 0;
+        let mut current = parent_ref;
+        while let Some(instance) = self.instances.get(&current) {
+            depth += 1;
+            current = instance.parent;
+
+            if depth > limit {
+                break;
+            }
+        }
+
+        self.budget.check_depth(depth + height)
+    }
+
+    /// Returns how many levels below `referent` its deepest descendant is.
+    fn height_of(&self, referent: Ref) -> usize {
+        let mut height = 0;
+        let mut to_visit = vec![(referent, 0)];
+
+        while let Some((referent, level)) = to_visit.pop() {
+            height = height.max(level);
+
+            if let Some(instance) = self.instances.get(&referent) {
+                to_visit.extend(instance.children.iter().map(|&child| (child, level + 1)));
+            }
+        }
+
+        height
+    }
+
     fn inner_insert(&mut self, referent: Ref, instance: Instance) {
+        if let Some(bytes) = self.property_bytes {
+            self.property_bytes = Some(add_bytes(bytes, instance_property_bytes(&instance)));
+        }
         self.instances.insert(referent, instance);
 
         // We need to ensure that the value of the Instance.UniqueId property does
@@ -450,8 +938,31 @@ impl WeakDom {
             self.unique_ids.remove(unique_id);
         }
 
+        // Instances that were borrowed mutably aren't part of the count.
+        if let Some(bytes) = self.property_bytes {
+            if !self.uncounted.remove(&referent) {
+                self.property_bytes = Some(sub_bytes(bytes, instance_property_bytes(&instance)));
+            }
+        }
+
         instance
     }
+
+    /// Counts the properties of instances that were borrowed mutably since
+    /// they were last counted, and returns the estimated size of every
+    /// property in the WeakDom, or `None` if the budget doesn't limit it.
+    fn count_property_bytes(&mut self) -> Option<usize> {
+        let mut bytes = self.property_bytes?;
+
+        for referent in self.uncounted.drain() {
+            if let Some(instance) = self.instances.get(&referent) {
+                bytes = add_bytes(bytes, instance_property_bytes(instance));
+            }
+        }
+
+        self.property_bytes = Some(bytes);
+        Some(bytes)
+    }
 }
 
 /// A struct for iterating through the descendants of an Instance in a
@@ -477,16 +988,75 @@ impl<'a> Iterator for WeakDomDescendants<'a> {
     }
 }
 
+/// A struct for iterating through the ancestors of an Instance in a
+/// [`WeakDom`].
+///
+/// See: [`WeakDom::ancestors`].
+#[derive(Debug)]
+pub struct WeakDomAncestors<'a> {
+    dom: &'a WeakDom,
+    next: Ref,
+}
+
+impl<'a> Iterator for WeakDomAncestors<'a> {
+    type Item = &'a Instance;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let instance = self.dom.get_by_ref(self.next)?;
+        self.next = instance.parent();
+        Some(instance)
+    }
+}
+
 impl Default for WeakDom {
     fn default() -> WeakDom {
         WeakDom {
             instances: AHashMap::new(),
             root_ref: Ref::none(),
             unique_ids: AHashSet::new(),
+            budget: DomBudget::new(),
+            property_bytes: None,
+            uncounted: AHashSet::new(),
+            diagnostics: Diagnostics::default(),
         }
     }
 }
 
+/// A small, fast pseudorandom number generator used for sampling. It's
+/// important that this stays stable so that samples are reproducible.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Returns the estimated size of every property of `instance`.
+fn instance_property_bytes(instance: &Instance) -> usize {
+    instance
+        .properties
+        .iter()
+        .map(|(name, value)| property_size(name, value))
+        .sum()
+}
+
+/// Adds properties to a count of property bytes.
+fn add_bytes(bytes: usize, added: usize) -> usize {
+    bytes
+        .checked_add(added)
+        .expect("estimated property size overflowed")
+}
+
+/// Removes properties from a count of property bytes. The properties must
+/// have been counted, so a count that goes below zero is a bug.
+fn sub_bytes(bytes: usize, removed: usize) -> usize {
+    bytes
+        .checked_sub(removed)
+        .expect("property byte count is missing properties that were removed")
+}
+
 #[derive(Debug, Default)]
 struct CloneContext {
     queue: VecDeque<(Ref, Ref)>,
@@ -513,12 +1083,14 @@ impl CloneContext {
             }
         }
 
+        let diagnostics = dest.diagnostics.clone();
+
         for (_, new_ref) in self.ref_rewrites.iter() {
             let instance = dest
                 .get_by_ref_mut(*new_ref)
                 .expect("Cannot rewrite refs on an instance that does not exist");
 
-            for prop_value in instance.properties.values_mut() {
+            for (prop_name, prop_value) in instance.properties.iter_mut() {
                 if let Variant::Ref(original_ref) = prop_value {
                     if let Some(new_ref) = self.ref_rewrites.get(original_ref) {
                         // If the ref points to an instance contained within the
@@ -527,6 +1099,13 @@ impl CloneContext {
                     } else if !existing_dest_refs.contains(original_ref) {
                         // If the ref points to an instance that does not exist
                         // in the destination WeakDom, rewrite it as none
+                        if original_ref.is_some() {
+                            diagnostics.report(Diagnostic::RefCleared {
+                                referent: *new_ref,
+                                property: *prop_name,
+                            });
+                        }
+
                         *prop_value = Variant::Ref(Ref::none())
                     }
                 }
@@ -539,15 +1118,28 @@ impl CloneContext {
     /// referent.
     ///
     /// This method only clones the instance's class name, name, and properties; it
-    /// does not clone any children.
-    fn clone_ref_as_builder(&mut self, source: &WeakDom, original_ref: Ref) -> InstanceBuilder {
+    /// does not clone any children. The cloned properties are passed through
+    /// `transform` first.
+    fn clone_ref_as_builder<F>(
+        &mut self,
+        source: &WeakDom,
+        original_ref: Ref,
+        transform: &mut F,
+    ) -> InstanceBuilder
+    where
+        F: FnMut(&Instance, &mut UstrMap<Variant>),
+    {
         let instance = source
             .get_by_ref(original_ref)
             .expect("Cannot clone an instance that does not exist");
 
-        let builder = InstanceBuilder::new(instance.class)
+        let mut properties = instance.properties.clone();
+        transform(instance, &mut properties);
+
+        let mut builder = InstanceBuilder::new(instance.class)
             .with_name(instance.name.to_string())
-            .with_properties(instance.properties.clone());
+            .with_properties(properties);
+        builder.metadata = instance.metadata.clone();
 
         let new_ref = builder.referent;
 
@@ -735,6 +1327,283 @@ mod test {
         insta::assert_yaml_snapshot!(viewer.view(&other_dom));
     }
 
+    #[test]
+    fn clone_with_transform() {
+        let dom = {
+            let target = InstanceBuilder::new("Part").with_name("Target");
+            let target_ref = target.referent;
+
+            WeakDom::new(
+                InstanceBuilder::new("Folder")
+                    .with_name("Prefab")
+                    .with_child(target)
+                    .with_child(
+                        InstanceBuilder::new("ObjectValue")
+                            .with_property("Value", "rbxassetid://1")
+                            .with_property("Original", target_ref),
+                    ),
+            )
+        };
+
+        let mut other_dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let mut visited = Vec::new();
+
+        let cloned =
+            dom.clone_into_external_with(dom.root_ref(), &mut other_dom, |instance, properties| {
+                visited.push(instance.name.clone());
+                properties.insert(ustr("Stamped"), Variant::Bool(true));
+
+                if let Some(Variant::String(value)) = properties.get_mut(&ustr("Value")) {
+                    *value = value.replace("rbxassetid://1", "rbxassetid://2");
+                }
+
+                // Refs added by the transform are rewritten like any other.
+                if let Some(Variant::Ref(original)) = properties.remove(&ustr("Original")) {
+                    properties.insert(ustr("Copied"), Variant::Ref(original));
+                }
+            });
+
+        assert_eq!(visited, ["Prefab", "Target", "ObjectValue"]);
+
+        let root = other_dom.get_by_ref(cloned).unwrap();
+        let target_ref = root.children()[0];
+        let value = other_dom.get_by_ref(root.children()[1]).unwrap();
+
+        assert_eq!(
+            root.properties.get(&ustr("Stamped")),
+            Some(&Variant::Bool(true))
+        );
+        assert_eq!(
+            value.properties.get(&ustr("Value")),
+            Some(&Variant::String("rbxassetid://2".to_owned()))
+        );
+        assert_eq!(value.properties.get(&ustr("Original")), None);
+        assert_eq!(
+            value.properties.get(&ustr("Copied")),
+            Some(&Variant::Ref(target_ref))
+        );
+
+        // The source is left untouched.
+        let original = dom.get_by_ref(dom.root().children()[1]).unwrap();
+        assert_eq!(
+            original.properties.get(&ustr("Value")),
+            Some(&Variant::String("rbxassetid://1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn builder_from_subtree() {
+        let outside = InstanceBuilder::new("Part").with_name("Outside");
+        let outside_ref = outside.referent;
+
+        let dom = {
+            let target = InstanceBuilder::new("Part").with_name("Target");
+            let target_ref = target.referent;
+
+            WeakDom::new(
+                InstanceBuilder::new("DataModel")
+                    .with_child(outside)
+                    .with_child(
+                        InstanceBuilder::new("Folder")
+                            .with_name("Prefab")
+                            .with_metadata(7u32)
+                            .with_child(
+                                InstanceBuilder::new("Model")
+                                    .with_child(target)
+                                    .with_child(InstanceBuilder::new("Part").with_name("Second")),
+                            )
+                            .with_child(
+                                InstanceBuilder::new("ObjectValue")
+                                    .with_property("Value", target_ref)
+                                    .with_property("Outside", outside_ref),
+                            ),
+                    ),
+            )
+        };
+        let prefab_ref = dom.root().children()[1];
+        let prefab = dom.get_by_ref(prefab_ref).unwrap();
+
+        let shallow = InstanceBuilder::from_instance(prefab);
+        assert_ne!(shallow.referent(), prefab_ref);
+        assert_eq!(shallow.name, "Prefab");
+        assert!(shallow.children.is_empty());
+        assert_eq!(shallow.metadata.get::<u32>(), Some(&7));
+
+        let mut other_dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let copy_ref = other_dom.insert(
+            other_dom.root_ref(),
+            InstanceBuilder::from_subtree(&dom, prefab_ref),
+        );
+
+        let copy = other_dom.get_by_ref(copy_ref).unwrap();
+        let model = other_dom.get_by_ref(copy.children()[0]).unwrap();
+        let names: Vec<_> = model
+            .children()
+            .iter()
+            .map(|child| other_dom.get_by_ref(*child).unwrap().name.as_str())
+            .collect();
+        assert_eq!(names, ["Target", "Second"]);
+
+        // Refs inside the subtree point to the copies, and refs outside of it
+        // are left alone.
+        let value = other_dom.get_by_ref(copy.children()[1]).unwrap();
+        assert_eq!(
+            value.properties.get(&ustr("Value")),
+            Some(&Variant::Ref(model.children()[0]))
+        );
+        assert_eq!(
+            value.properties.get(&ustr("Outside")),
+            Some(&Variant::Ref(outside_ref))
+        );
+    }
+
+    #[test]
+    fn update_properties() {
+        let first = InstanceBuilder::new("StringValue");
+        let first_ref = first.referent;
+        let second = InstanceBuilder::new("IntValue");
+        let second_ref = second.referent;
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_child(first)
+                .with_child(second),
+        );
+
+        dom.update_properties(vec![
+            (first_ref, "Value", Variant::String("a".to_owned())),
+            (first_ref, "Value", Variant::String("b".to_owned())),
+            (second_ref, "Value", Variant::Int64(5)),
+            (first_ref, "Tagged", Variant::Bool(true)),
+        ])
+        .unwrap();
+
+        let first = dom.get_by_ref(first_ref).unwrap();
+        assert_eq!(
+            first.properties.get(&ustr("Value")),
+            Some(&Variant::String("b".to_owned()))
+        );
+        assert_eq!(
+            first.properties.get(&ustr("Tagged")),
+            Some(&Variant::Bool(true))
+        );
+        assert_eq!(
+            dom.get_by_ref(second_ref)
+                .unwrap()
+                .properties
+                .get(&ustr("Value")),
+            Some(&Variant::Int64(5))
+        );
+
+        let used = dom.property_bytes();
+        dom.set_budget(DomBudget::new().max_property_bytes(used));
+
+        // Replacing a value with one of the same size fits, but growing the
+        // DOM doesn't, and stops the batch.
+        let result = dom.update_properties(vec![
+            (second_ref, "Value", Variant::Int64(6)),
+            (second_ref, "Extra", Variant::Bool(false)),
+            (first_ref, "Tagged", Variant::Bool(false)),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(dom.property_bytes(), used);
+
+        let second = dom.get_by_ref(second_ref).unwrap();
+        assert_eq!(
+            second.properties.get(&ustr("Value")),
+            Some(&Variant::Int64(6))
+        );
+        assert_eq!(second.properties.get(&ustr("Extra")), None);
+        assert_eq!(
+            dom.get_by_ref(first_ref)
+                .unwrap()
+                .properties
+                .get(&ustr("Tagged")),
+            Some(&Variant::Bool(true))
+        );
+    }
+
+    #[test]
+    fn reset() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder")
+                .with_name("Old Root")
+                .with_child(
+                    InstanceBuilder::new("Part")
+                        .with_property("UniqueId", UniqueId::new(0xA, 0xB, 0xC)),
+                ),
+        );
+        let old_root = dom.root_ref();
+
+        dom.reset(InstanceBuilder::new("DataModel"));
+
+        assert!(dom.get_by_ref(old_root).is_none());
+        assert_eq!(dom.descendants().count(), 1);
+        assert_eq!(dom.root().class, "DataModel");
+        assert!(dom.root().parent().is_none());
+        assert!(dom.unique_ids.is_empty());
+    }
+
+    #[test]
+    fn sample_instances() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Folder").with_children((0..100).map(|i| {
+                let class = if i % 2 == 0 { "Part" } else { "Model" };
+                InstanceBuilder::new(class).with_name(i.to_string())
+            })),
+        );
+
+        let is_part = |instance: &Instance| instance.class == "Part";
+
+        let sample = dom.sample_instances(10, 1234, is_part);
+        assert_eq!(sample.len(), 10);
+        assert_eq!(sample, dom.sample_instances(10, 1234, is_part));
+        assert_ne!(sample, dom.sample_instances(10, 4321, is_part));
+
+        for referent in &sample {
+            assert_eq!(dom.get_by_ref(*referent).unwrap().class, "Part");
+        }
+
+        // Samples come back in traversal order.
+        let positions: Vec<_> = sample
+            .iter()
+            .map(|referent| dom.root().children().iter().position(|r| r == referent))
+            .collect();
+        let mut sorted_positions = positions.clone();
+        sorted_positions.sort();
+        assert_eq!(positions, sorted_positions);
+
+        // Asking for more instances than match returns all of them.
+        assert_eq!(dom.sample_instances(1000, 1234, is_part).len(), 50);
+        assert!(dom.sample_instances(0, 1234, is_part).is_empty());
+    }
+
+    #[test]
+    fn metadata_follows_instances() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Origin(&'static str);
+
+        let mut source = WeakDom::new(InstanceBuilder::new("Folder"));
+        let mut dest = WeakDom::new(InstanceBuilder::new("Folder"));
+
+        let original = source.insert(
+            source.root_ref(),
+            InstanceBuilder::new("Part")
+                .with_metadata(Origin("part.rbxm"))
+                .with_child(InstanceBuilder::new("Decal").with_metadata(Origin("decal.rbxm"))),
+        );
+
+        let cloned = source.clone_into_external(original, &mut dest);
+        let cloned_part = dest.get_by_ref(cloned).unwrap();
+        let cloned_decal = dest.get_by_ref(cloned_part.children()[0]).unwrap();
+        assert_eq!(cloned_part.metadata.get(), Some(&Origin("part.rbxm")));
+        assert_eq!(cloned_decal.metadata.get(), Some(&Origin("decal.rbxm")));
+
+        source.transfer(original, &mut dest, cloned);
+        let transferred = dest.get_by_ref(original).unwrap();
+        assert_eq!(transferred.metadata.get(), Some(&Origin("part.rbxm")));
+    }
+
     #[test]
     fn large_depth_tree() {
         // We've had issues with stack overflows when creating WeakDoms with
@@ -884,6 +1753,30 @@ mod test {
         assert!(descendants_2.next().is_none());
     }
 
+    #[test]
+    fn ancestors() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("ROOT"));
+
+        let child = dom.insert(dom.root_ref(), InstanceBuilder::new("Folder"));
+        let grandchild = dom.insert(child, InstanceBuilder::new("Folder"));
+        let other = dom.insert(dom.root_ref(), InstanceBuilder::new("Folder"));
+
+        let mut ancestors = dom.ancestors(grandchild);
+        assert_eq!(ancestors.next().unwrap().referent(), child);
+        assert_eq!(ancestors.next().unwrap().referent(), dom.root_ref());
+        assert!(ancestors.next().is_none());
+
+        assert_eq!(dom.ancestors(other).count(), 1);
+        assert!(dom.ancestors(dom.root_ref()).next().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "must be a part of the DOM")]
+    fn ancestors_of_missing_instance() {
+        let dom = WeakDom::new(InstanceBuilder::new("ROOT"));
+        dom.ancestors(Ref::new());
+    }
+
     #[test]
     fn from_raw() {
         let mut dom = WeakDom::new(InstanceBuilder::new("ROOT"));
@@ -992,4 +1885,187 @@ mod test {
 
         let _ = WeakDom::from_raw(root, tree);
     }
+
+    #[test]
+    fn budget_instances() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        dom.set_budget(DomBudget::new().max_instances(3));
+
+        let root_ref = dom.root_ref();
+        let too_big = InstanceBuilder::new("Folder")
+            .with_child(InstanceBuilder::new("Folder"))
+            .with_child(InstanceBuilder::new("Folder"));
+
+        assert_eq!(
+            dom.try_insert(root_ref, too_big),
+            Err(BudgetError::TooManyInstances {
+                limit: 3,
+                requested: 4
+            })
+        );
+        assert_eq!(dom.descendants().count(), 1);
+
+        let folder = dom
+            .try_insert(root_ref, InstanceBuilder::new("Folder"))
+            .unwrap();
+        dom.try_insert(root_ref, InstanceBuilder::new("Folder"))
+            .unwrap();
+
+        // Removing an instance frees up room in the budget again.
+        dom.destroy(folder);
+        dom.try_insert(root_ref, InstanceBuilder::new("Folder"))
+            .unwrap();
+    }
+
+    #[test]
+    fn budget_property_bytes() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let root_ref = dom.root_ref();
+
+        let child = dom.insert(
+            root_ref,
+            InstanceBuilder::new("StringValue").with_property("Value", "a".repeat(100)),
+        );
+        let used = dom.property_bytes();
+        assert!(used >= 100);
+
+        dom.set_budget(DomBudget::new().max_property_bytes(used + 50));
+
+        let result = dom.try_insert(
+            root_ref,
+            InstanceBuilder::new("StringValue").with_property("Value", "b".repeat(100)),
+        );
+        assert!(matches!(
+            result,
+            Err(BudgetError::TooManyPropertyBytes { .. })
+        ));
+        assert_eq!(dom.property_bytes(), used);
+
+        assert!(dom
+            .try_set_property(child, "Value", "c".repeat(200))
+            .is_err());
+        assert_eq!(
+            dom.try_set_property(child, "Value", "d".repeat(50)),
+            Ok(Some(Variant::String("a".repeat(100))))
+        );
+        assert_eq!(dom.property_bytes(), used - 50);
+
+        // Changes made directly to an instance's properties are counted the
+        // next time the budget is checked.
+        dom.get_by_ref_mut(child)
+            .unwrap()
+            .properties
+            .insert(ustr("Value"), Variant::String("e".repeat(140)));
+        assert_eq!(dom.property_bytes(), used + 40);
+        assert!(dom
+            .try_set_property(child, "Other", "f".repeat(20))
+            .is_err());
+
+        dom.destroy(child);
+        assert_eq!(dom.property_bytes(), 0);
+    }
+
+    #[test]
+    fn budget_transfer() {
+        let mut source =
+            WeakDom::new(InstanceBuilder::new("DataModel").with_child(
+                InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Part")),
+            ));
+        let mut dest = WeakDom::new(InstanceBuilder::new("DataModel"));
+        dest.set_budget(DomBudget::new().max_instances(2));
+
+        let folder = source.root().children()[0];
+        let dest_root = dest.root_ref();
+        assert!(matches!(
+            source.try_transfer(folder, &mut dest, dest_root),
+            Err(BudgetError::TooManyInstances { .. })
+        ));
+
+        assert_eq!(source.root().children(), &[folder]);
+        assert!(dest.root().children().is_empty());
+    }
+
+    #[test]
+    fn budget_depth() {
+        let chain = |length: usize| {
+            let mut builder = InstanceBuilder::new("Folder");
+            for _ in 1..length {
+                builder = InstanceBuilder::new("Folder").with_child(builder);
+            }
+            builder
+        };
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel").with_child(chain(3)));
+        assert_eq!(dom.depth(), 3);
+
+        assert_eq!(
+            dom.enforce_max_depth(2),
+            Err(BudgetError::TooDeep { limit: 2, depth: 3 })
+        );
+        assert_eq!(dom.budget(), DomBudget::new());
+
+        dom.enforce_max_depth(4).unwrap();
+        let root_ref = dom.root_ref();
+        let mut deepest = dom.try_insert(root_ref, chain(4)).unwrap();
+        assert!(matches!(
+            dom.try_insert(root_ref, chain(5)),
+            Err(BudgetError::TooDeep { limit: 4, .. })
+        ));
+
+        // Inserting under a deep instance counts its ancestors too.
+        while let Some(&child) = dom.get_by_ref(deepest).unwrap().children().first() {
+            deepest = child;
+        }
+        assert!(matches!(
+            dom.try_insert(deepest, InstanceBuilder::new("Part")),
+            Err(BudgetError::TooDeep { limit: 4, .. })
+        ));
+        assert_eq!(dom.depth(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "dom budget exceeded")]
+    fn budget_depth_transfer_within() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("DataModel")
+                .with_child(InstanceBuilder::new("Folder"))
+                .with_child(InstanceBuilder::new("Model").with_child(InstanceBuilder::new("Part"))),
+        );
+        dom.enforce_max_depth(2).unwrap();
+
+        let folder = dom.root().children()[0];
+        let model = dom.root().children()[1];
+        dom.transfer_within(model, folder);
+    }
+
+    #[test]
+    fn clone_reports_cleared_refs() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::Diagnostic;
+
+        let outside = InstanceBuilder::new("Part").with_name("Outside");
+        let part = InstanceBuilder::new("ObjectValue")
+            .with_property("Value", outside.referent)
+            .with_property("Nothing", Ref::none());
+        let part_ref = part.referent;
+        let dom = WeakDom::new(InstanceBuilder::new("Folder").with_children([outside, part]));
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reported);
+
+        let mut other_dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        other_dom.set_diagnostics(Diagnostics::new(move |diagnostic: &Diagnostic| {
+            sink.lock().unwrap().push(diagnostic.clone());
+        }));
+        let cloned = dom.clone_into_external(part_ref, &mut other_dom);
+
+        assert_eq!(
+            *reported.lock().unwrap(),
+            [Diagnostic::RefCleared {
+                referent: cloned,
+                property: ustr("Value"),
+            }]
+        );
+    }
 }
@@ -0,0 +1,52 @@
+use rbx_types::{Ref, VariantType};
+use ustr::Ustr;
+
+/// How faithfully a property type is written by a serializer, like rbx_binary
+/// or rbx_xml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Fidelity {
+    /// Values of this type are read back exactly as they were written.
+    Exact,
+
+    /// Values of this type are written, but can't always be read back
+    /// unchanged.
+    Lossy {
+        /// A short description of what is lost.
+        reason: &'static str,
+    },
+
+    /// Values of this type can't be written, and serializing a property of
+    /// this type fails.
+    Unsupported,
+}
+
+/// A property that would not survive being written by a serializer
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LossyProperty {
+    /// The instance the property belongs to.
+    pub referent: Ref,
+
+    /// The name of the property.
+    pub property: Ustr,
+
+    /// The type of the property's value.
+    pub ty: VariantType,
+
+    /// How the property would be written. This is never [`Fidelity::Exact`].
+    pub fidelity: Fidelity,
+}
+
+impl LossyProperty {
+    /// Creates a `LossyProperty` describing the given property.
+    pub fn new(referent: Ref, property: Ustr, ty: VariantType, fidelity: Fidelity) -> Self {
+        Self {
+            referent,
+            property,
+            ty,
+            fidelity,
+        }
+    }
+}
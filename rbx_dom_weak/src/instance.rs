@@ -1,6 +1,11 @@
+use std::{any::Any, collections::VecDeque};
+
+use ahash::AHashMap;
 use rbx_types::{Ref, Variant};
 use ustr::{Ustr, UstrMap};
 
+use crate::{metadata::InstanceMetadata, WeakDom};
+
 /**
 Represents an instance that can be turned into a new
 [`WeakDom`][crate::WeakDom], or inserted into an existing one.
@@ -37,6 +42,7 @@ pub struct InstanceBuilder {
     pub(crate) class: Ustr,
     pub(crate) properties: Vec<(Ustr, Variant)>,
     pub(crate) children: Vec<InstanceBuilder>,
+    pub(crate) metadata: InstanceMetadata,
 }
 
 impl InstanceBuilder {
@@ -52,6 +58,7 @@ impl InstanceBuilder {
             class,
             properties: Vec::new(),
             children: Vec::new(),
+            metadata: InstanceMetadata::new(),
         }
     }
 
@@ -67,6 +74,7 @@ impl InstanceBuilder {
             class,
             properties: Vec::with_capacity(capacity),
             children: Vec::new(),
+            metadata: InstanceMetadata::new(),
         }
     }
 
@@ -78,9 +86,86 @@ impl InstanceBuilder {
             class: Ustr::default(),
             properties: Vec::new(),
             children: Vec::new(),
+            metadata: InstanceMetadata::new(),
         }
     }
 
+    /// Create a new `InstanceBuilder` with the same class, name, properties,
+    /// and metadata as the given instance, but a new referent and no children.
+    ///
+    /// Ref properties are copied as they are. To copy an instance along with
+    /// its descendants, use [`InstanceBuilder::from_subtree`].
+    pub fn from_instance(instance: &Instance) -> Self {
+        InstanceBuilder {
+            referent: Ref::new(),
+            name: instance.name.clone(),
+            class: instance.class,
+            properties: instance
+                .properties
+                .iter()
+                .map(|(key, value)| (*key, value.clone()))
+                .collect(),
+            children: Vec::new(),
+            metadata: instance.metadata.clone(),
+        }
+    }
+
+    /// Create a new `InstanceBuilder` that copies the instance with the given
+    /// referent and all of its descendants out of `dom`, like
+    /// [`InstanceBuilder::from_instance`] does for a single instance.
+    ///
+    /// Any Ref properties that point to instances contained in the subtree are
+    /// rewritten to point to their copies. Other Ref properties are copied as
+    /// they are, so they stay valid if the builder is inserted back into
+    /// `dom`.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in `dom`.
+    pub fn from_subtree(dom: &WeakDom, referent: Ref) -> Self {
+        let mut ref_rewrites = AHashMap::new();
+
+        // Copy every instance in the subtree breadth-first, keeping track of
+        // each copy's parent by its position in `builders`.
+        let mut builders: Vec<(usize, InstanceBuilder)> = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((0, referent));
+
+        while let Some((parent_index, original_ref)) = queue.pop_front() {
+            let instance = dom
+                .get_by_ref(original_ref)
+                .expect("Cannot copy an instance that does not exist");
+            let builder = InstanceBuilder::from_instance(instance);
+            let index = builders.len();
+
+            ref_rewrites.insert(original_ref, builder.referent);
+            queue.extend(instance.children().iter().map(|child| (index, *child)));
+            builders.push((parent_index, builder));
+        }
+
+        for (_, builder) in &mut builders {
+            for (_, value) in &mut builder.properties {
+                if let Variant::Ref(original_ref) = value {
+                    if let Some(new_ref) = ref_rewrites.get(original_ref) {
+                        *value = Variant::Ref(*new_ref);
+                    }
+                }
+            }
+        }
+
+        // Children always come after their parent and siblings stay in order,
+        // so walking backwards finishes every builder before it's attached to
+        // its parent. Children are attached in reverse, then put back in order.
+        while builders.len() > 1 {
+            let (parent_index, mut builder) = builders.pop().unwrap();
+            builder.children.reverse();
+            builders[parent_index].1.children.push(builder);
+        }
+
+        let (_, mut root) = builders.pop().unwrap();
+        root.children.reverse();
+        root
+    }
+
     /// Return the referent of the instance that the `InstanceBuilder` refers to.
     pub fn referent(&self) -> Ref {
         self.referent
@@ -161,6 +246,19 @@ impl InstanceBuilder {
         self.properties.extend(props);
     }
 
+    /// Attach a metadata value to the `InstanceBuilder`, replacing any value of
+    /// the same type. See [`InstanceMetadata`] for details.
+    pub fn with_metadata<T: Any + Clone + Send + Sync>(mut self, value: T) -> Self {
+        self.metadata.insert(value);
+        self
+    }
+
+    /// Attach a metadata value to the `InstanceBuilder`, replacing any value of
+    /// the same type. See [`InstanceMetadata`] for details.
+    pub fn add_metadata<T: Any + Clone + Send + Sync>(&mut self, value: T) {
+        self.metadata.insert(value);
+    }
+
     /// Add a new child to the `InstanceBuilder`.
     pub fn with_child(mut self, child: InstanceBuilder) -> Self {
         self.children.push(child);
@@ -212,6 +310,9 @@ pub struct Instance {
 
     /// Any properties stored on the object that are not `Name` or `ClassName`.
     pub properties: UstrMap<Variant>,
+
+    /// Data attached to the instance by tools, which is never serialized.
+    pub metadata: InstanceMetadata,
 }
 
 impl Instance {
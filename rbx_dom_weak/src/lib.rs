@@ -41,18 +41,50 @@
 
 #![deny(missing_docs)]
 
+mod assets;
+mod attribute_schema;
+mod budget;
+#[cfg(feature = "cache")]
+mod cache;
+mod diagnostics;
 mod dom;
+mod fidelity;
 mod instance;
+mod metadata;
+mod palette;
+mod ref_graph;
+mod replace;
+mod script_source;
+mod template;
+mod terrain;
 mod viewer;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "cache")]
+pub use crate::cache::CacheError;
+
 pub use rbx_types as types;
 
 pub use ahash::AHashMap;
 pub use ustr::{ustr, Ustr, UstrMap, UstrSet};
 
 pub use crate::{
+    assets::AssetChange,
+    attribute_schema::{AttributeRule, AttributeSchema, AttributeViolation},
+    budget::{BudgetError, DomBudget},
+    diagnostics::{Diagnostic, DiagnosticSink, Diagnostics},
     dom::WeakDom,
+    fidelity::{Fidelity, LossyProperty},
     instance::{Instance, InstanceBuilder},
+    metadata::{InstanceMetadata, SourceLocation},
+    palette::ColorPalette,
+    ref_graph::{RefEdgeKind, RefGraph, RefGraphEdge, RefGraphNode},
+    replace::StringMatcher,
+    script_source::{InvalidUtf8, ScriptSourceError},
+    template::{TemplateError, TemplateParams},
+    terrain::TerrainIssue,
     viewer::{DomViewer, ViewedInstance},
 };
 
@@ -0,0 +1,203 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+};
+
+use ahash::AHashMap;
+
+/// Arbitrary data attached to an [`Instance`][crate::Instance] that is never
+/// written to a file.
+///
+/// `InstanceMetadata` holds at most one value of each type, which makes it a
+/// good place for tools to keep information like where an instance was loaded
+/// from. Metadata follows its instance when it's moved between doms and is
+/// copied when the instance is cloned.
+///
+/// ```
+/// use rbx_dom_weak::{InstanceBuilder, WeakDom};
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct SourcePath(String);
+///
+/// let dom = WeakDom::new(
+///     InstanceBuilder::new("Folder").with_metadata(SourcePath("src/init.lua".to_owned())),
+/// );
+///
+/// assert_eq!(
+///     dom.root().metadata.get::<SourcePath>(),
+///     Some(&SourcePath("src/init.lua".to_owned())),
+/// );
+/// ```
+#[derive(Default)]
+pub struct InstanceMetadata {
+    entries: AHashMap<TypeId, Box<dyn MetadataValue>>,
+}
+
+impl InstanceMetadata {
+    /// Creates an empty `InstanceMetadata`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of type `T`, if one is present.
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.entries
+            .get(&TypeId::of::<T>())
+            .and_then(|value| (**value).as_any().downcast_ref())
+    }
+
+    /// Returns the value of type `T` mutably, if one is present.
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.entries
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| (**value).as_any_mut().downcast_mut())
+    }
+
+    /// Inserts a value, returning the previous value of the same type if there
+    /// was one.
+    pub fn insert<T: Any + Clone + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.entries
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.into_any().downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// Removes the value of type `T`, returning it if it was present.
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.entries
+            .remove(&TypeId::of::<T>())
+            .and_then(|old| old.into_any().downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// Returns `true` if a value of type `T` is present.
+    pub fn contains<T: Any>(&self) -> bool {
+        self.entries.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns the number of values present.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no values are present.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every value.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Clone for InstanceMetadata {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self
+                .entries
+                .iter()
+                .map(|(key, value)| (*key, (**value).clone_box()))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Debug for InstanceMetadata {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("InstanceMetadata")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+/// Where an instance was defined in the file it was decoded from.
+///
+/// rbx_binary and rbx_xml attach this to instances as [`InstanceMetadata`]
+/// when asked to track source locations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SourceLocation {
+    /// A range of bytes in a binary file. `end` is exclusive.
+    Bytes {
+        /// The offset of the first byte in the range.
+        start: u64,
+        /// The offset of the byte after the range.
+        end: u64,
+    },
+
+    /// A range of lines in a text file. Lines are counted from 1, and `end`
+    /// is inclusive.
+    Lines {
+        /// The first line in the range.
+        start: usize,
+        /// The last line in the range.
+        end: usize,
+    },
+}
+
+/// Object-safe helper that lets boxed metadata values be cloned and
+/// downcast.
+trait MetadataValue: Any + Send + Sync {
+    fn clone_box(&self) -> Box<dyn MetadataValue>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Any + Clone + Send + Sync> MetadataValue for T {
+    fn clone_box(&self) -> Box<dyn MetadataValue> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Origin(&'static str);
+
+    #[test]
+    fn insert_get_remove() {
+        let mut metadata = InstanceMetadata::new();
+        assert!(metadata.is_empty());
+
+        assert_eq!(metadata.insert(Origin("a.rbxm")), None);
+        assert_eq!(metadata.insert(5u32), None);
+        assert_eq!(metadata.len(), 2);
+
+        assert_eq!(metadata.get::<Origin>(), Some(&Origin("a.rbxm")));
+        assert_eq!(metadata.insert(Origin("b.rbxm")), Some(Origin("a.rbxm")));
+
+        *metadata.get_mut::<u32>().unwrap() += 1;
+        assert_eq!(metadata.remove::<u32>(), Some(6));
+        assert!(!metadata.contains::<u32>());
+        assert_eq!(metadata.get::<String>(), None);
+    }
+
+    #[test]
+    fn clone_is_deep() {
+        let mut metadata = InstanceMetadata::new();
+        metadata.insert(vec![1, 2, 3]);
+
+        let mut cloned = metadata.clone();
+        cloned.get_mut::<Vec<i32>>().unwrap().push(4);
+
+        assert_eq!(metadata.get::<Vec<i32>>(), Some(&vec![1, 2, 3]));
+        assert_eq!(cloned.get::<Vec<i32>>(), Some(&vec![1, 2, 3, 4]));
+    }
+}
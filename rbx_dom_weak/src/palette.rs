@@ -0,0 +1,175 @@
+use std::{collections::HashMap, hash::BuildHasher, slice};
+
+use ahash::AHashMap;
+use rbx_types::{BrickColor, Color3, Color3uint8, Variant};
+
+use crate::WeakDom;
+
+/// Every color used by the properties in a [`WeakDom`], created by
+/// [`WeakDom::color_palette`].
+///
+/// `Color3`, `Color3uint8`, and `BrickColor` properties are all counted by
+/// their 8-bit RGB color, so a `Color3` and a `BrickColor` that look the same
+/// are the same entry in the palette.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColorPalette {
+    colors: Vec<(Color3uint8, usize)>,
+}
+
+impl ColorPalette {
+    /// Returns the number of distinct colors in the palette.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns `true` if the dom had no color properties.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Returns the number of properties that use the given color.
+    pub fn count(&self, color: Color3uint8) -> usize {
+        self.colors
+            .iter()
+            .find(|(other, _)| *other == color)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Returns an iterator over every color in the palette and the number of
+    /// properties that use it, from most used to least used.
+    pub fn iter(&self) -> slice::Iter<'_, (Color3uint8, usize)> {
+        self.colors.iter()
+    }
+}
+
+impl WeakDom {
+    /// Collects the colors used by every `Color3`, `Color3uint8`, and
+    /// `BrickColor` property in the dom into a [`ColorPalette`].
+    ///
+    /// `Color3` values outside of the 0 to 1 range are clamped when they are
+    /// converted to 8-bit color.
+    pub fn color_palette(&self) -> ColorPalette {
+        let mut counts: AHashMap<Color3uint8, usize> = AHashMap::new();
+
+        for instance in self.descendants() {
+            for value in instance.properties.values() {
+                if let Some(color) = property_color(value) {
+                    *counts.entry(color).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut colors: Vec<_> = counts.into_iter().collect();
+        colors.sort_by_key(|(color, count)| (usize::MAX - count, color.r, color.g, color.b));
+
+        ColorPalette { colors }
+    }
+
+    /// Replaces every color property whose color is a key in `mapping` with
+    /// the corresponding value, keeping the type of the property. Colors are
+    /// matched the same way as in [`WeakDom::color_palette`].
+    ///
+    /// `BrickColor` properties can only hold a fixed set of colors, so they are
+    /// replaced with the `BrickColor` nearest to the new color.
+    ///
+    /// Returns the number of properties that were changed.
+    pub fn remap_colors<S: BuildHasher>(
+        &mut self,
+        mapping: &HashMap<Color3uint8, Color3uint8, S>,
+    ) -> usize {
+        let referents: Vec<_> = self
+            .descendants()
+            .map(|instance| instance.referent())
+            .collect();
+        let mut changed = 0;
+
+        for referent in referents {
+            let instance = self.get_by_ref_mut(referent).unwrap();
+
+            for value in instance.properties.values_mut() {
+                let new_color = match property_color(value).and_then(|color| mapping.get(&color)) {
+                    Some(new_color) => *new_color,
+                    None => continue,
+                };
+
+                *value = match value {
+                    Variant::Color3(_) => Variant::Color3(Color3::from(new_color)),
+                    Variant::Color3uint8(_) => Variant::Color3uint8(new_color),
+                    _ => Variant::BrickColor(BrickColor::nearest(new_color)),
+                };
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+}
+
+fn property_color(value: &Variant) -> Option<Color3uint8> {
+    match value {
+        Variant::Color3(color) => Some(Color3uint8::from(*color)),
+        Variant::Color3uint8(color) => Some(*color),
+        Variant::BrickColor(color) => Some(color.to_color3uint8()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ustr::ustr;
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn palette_and_remap() {
+        let red = Color3uint8::new(255, 0, 0);
+        let blue = Color3uint8::new(0, 0, 255);
+        let dark = Color3uint8::new(10, 10, 10);
+
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Model").with_children([
+                InstanceBuilder::new("Part")
+                    .with_property("Color", red)
+                    .with_property("BrickColor", BrickColor::ReallyRed),
+                InstanceBuilder::new("PointLight")
+                    .with_property("Color", Color3::new(1.0, 0.0, 0.0)),
+                InstanceBuilder::new("Part").with_property("Color", dark),
+            ]),
+        );
+
+        let palette = dom.color_palette();
+        assert_eq!(palette.len(), 2);
+        assert_eq!(palette.count(red), 3);
+        assert_eq!(palette.count(dark), 1);
+        assert_eq!(palette.count(blue), 0);
+        assert_eq!(palette.iter().next(), Some(&(red, 3)));
+
+        let mut mapping = HashMap::new();
+        mapping.insert(red, blue);
+        assert_eq!(dom.remap_colors(&mapping), 3);
+
+        let palette = dom.color_palette();
+        assert_eq!(palette.count(red), 0);
+        assert_eq!(palette.count(blue), 3);
+
+        let light = dom
+            .descendants()
+            .find(|instance| instance.class == "PointLight")
+            .unwrap();
+        assert_eq!(
+            light.properties.get(&ustr("Color")),
+            Some(&Variant::Color3(Color3::new(0.0, 0.0, 1.0)))
+        );
+
+        let part = dom
+            .descendants()
+            .find(|instance| instance.properties.contains_key(&ustr("BrickColor")))
+            .unwrap();
+        assert_eq!(
+            part.properties.get(&ustr("BrickColor")),
+            Some(&Variant::BrickColor(BrickColor::ReallyBlue))
+        );
+    }
+}
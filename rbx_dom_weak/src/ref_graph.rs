@@ -0,0 +1,226 @@
+use std::io::{self, Write};
+
+use rbx_types::{Ref, Variant};
+use ustr::Ustr;
+
+use crate::WeakDom;
+
+/// The instances in a [`WeakDom`] and the links between them, created by
+/// [`WeakDom::ref_graph`].
+///
+/// Every instance is a node. Every instance except the root has an edge to
+/// its parent, and every Ref property that points to an instance in the dom
+/// is an edge labeled with the property's name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefGraph {
+    nodes: Vec<RefGraphNode>,
+    edges: Vec<RefGraphEdge>,
+}
+
+/// An instance in a [`RefGraph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefGraphNode {
+    /// The referent of the instance.
+    pub referent: Ref,
+    /// The name of the instance.
+    pub name: String,
+    /// The ClassName of the instance.
+    pub class: Ustr,
+}
+
+/// A link from one instance to another in a [`RefGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefGraphEdge {
+    /// The instance the link belongs to.
+    pub from: Ref,
+    /// The instance the link points to.
+    pub to: Ref,
+    /// Why the instances are linked.
+    pub kind: RefEdgeKind,
+}
+
+/// What a [`RefGraphEdge`] represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefEdgeKind {
+    /// `to` is the parent of `from`.
+    Parent,
+    /// The Ref property of `from` with this name points to `to`.
+    Property(Ustr),
+}
+
+impl RefGraph {
+    /// Returns every instance in the graph, in the same order as
+    /// [`WeakDom::descendants`].
+    pub fn nodes(&self) -> &[RefGraphNode] {
+        &self.nodes
+    }
+
+    /// Returns every link in the graph. The links that belong to each
+    /// instance are together, with its parent first and then its Ref
+    /// properties sorted by name.
+    pub fn edges(&self) -> &[RefGraphEdge] {
+        &self.edges
+    }
+
+    /// Writes the graph in Graphviz's DOT language. Parent links are drawn
+    /// as dashed lines, and Ref properties as solid lines labeled with the
+    /// property's name.
+    pub fn write_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "digraph dom {{")?;
+
+        for node in &self.nodes {
+            writeln!(
+                writer,
+                "    \"{}\" [label=\"{}\\n({})\"];",
+                node.referent,
+                escape(&node.name),
+                escape(&node.class)
+            )?;
+        }
+
+        for edge in &self.edges {
+            match edge.kind {
+                RefEdgeKind::Parent => writeln!(
+                    writer,
+                    "    \"{}\" -> \"{}\" [style=dashed];",
+                    edge.from, edge.to
+                )?,
+                RefEdgeKind::Property(name) => writeln!(
+                    writer,
+                    "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                    edge.from,
+                    edge.to,
+                    escape(&name)
+                )?,
+            }
+        }
+
+        writeln!(writer, "}}")
+    }
+}
+
+impl WeakDom {
+    /// Builds a [`RefGraph`] of the instances in the dom, for seeing how
+    /// welds, constraints, and ObjectValues tie a place together.
+    ///
+    /// Ref properties that are `Ref::none()` or that point to instances that
+    /// aren't in the dom are left out.
+    pub fn ref_graph(&self) -> RefGraph {
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+
+        for instance in self.descendants() {
+            let referent = instance.referent();
+
+            nodes.push(RefGraphNode {
+                referent,
+                name: instance.name.clone(),
+                class: instance.class,
+            });
+
+            if self.get_by_ref(instance.parent()).is_some() {
+                edges.push(RefGraphEdge {
+                    from: referent,
+                    to: instance.parent(),
+                    kind: RefEdgeKind::Parent,
+                });
+            }
+
+            let mut properties: Vec<_> = instance
+                .properties
+                .iter()
+                .filter_map(|(name, value)| match value {
+                    Variant::Ref(target) if self.get_by_ref(*target).is_some() => {
+                        Some((*name, *target))
+                    }
+                    _ => None,
+                })
+                .collect();
+            properties.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+            edges.extend(properties.into_iter().map(|(name, target)| RefGraphEdge {
+                from: referent,
+                to: target,
+                kind: RefEdgeKind::Property(name),
+            }));
+        }
+
+        RefGraph { nodes, edges }
+    }
+}
+
+/// Escapes a string for use inside of a quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn ref_graph() {
+        let part0 = InstanceBuilder::new("Part").with_name("Part0");
+        let part1 = InstanceBuilder::new("Part").with_name("Part \"1\"");
+        let weld = InstanceBuilder::new("WeldConstraint")
+            .with_property("Part0", part0.referent())
+            .with_property("Part1", part1.referent())
+            .with_property("Missing", Ref::new())
+            .with_property("Empty", Ref::none());
+        let (part0_ref, part1_ref, weld_ref) =
+            (part0.referent(), part1.referent(), weld.referent());
+
+        let dom = WeakDom::new(InstanceBuilder::new("Model").with_children([part0, part1, weld]));
+        let root = dom.root_ref();
+
+        let graph = dom.ref_graph();
+        assert_eq!(graph.nodes().len(), 4);
+        assert_eq!(
+            graph.edges(),
+            [
+                RefGraphEdge {
+                    from: part0_ref,
+                    to: root,
+                    kind: RefEdgeKind::Parent,
+                },
+                RefGraphEdge {
+                    from: part1_ref,
+                    to: root,
+                    kind: RefEdgeKind::Parent,
+                },
+                RefGraphEdge {
+                    from: weld_ref,
+                    to: root,
+                    kind: RefEdgeKind::Parent,
+                },
+                RefGraphEdge {
+                    from: weld_ref,
+                    to: part0_ref,
+                    kind: RefEdgeKind::Property("Part0".into()),
+                },
+                RefGraphEdge {
+                    from: weld_ref,
+                    to: part1_ref,
+                    kind: RefEdgeKind::Property("Part1".into()),
+                },
+            ]
+        );
+
+        let mut dot = Vec::new();
+        graph.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+
+        assert!(dot.starts_with("digraph dom {\n"));
+        assert!(dot.contains(&format!(
+            "    \"{}\" [label=\"Part \\\"1\\\"\\n(Part)\"];\n",
+            part1_ref
+        )));
+        assert!(dot.contains(&format!(
+            "    \"{}\" -> \"{}\" [label=\"Part1\"];\n",
+            weld_ref, part1_ref
+        )));
+        assert!(dot.ends_with("}\n"));
+    }
+}
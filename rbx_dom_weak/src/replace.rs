@@ -0,0 +1,253 @@
+use std::borrow::Cow;
+
+use rbx_types::{Content, ContentId, ContentType, Variant};
+use ustr::Ustr;
+
+use crate::{BudgetError, Instance, WeakDom};
+
+/// Finds and replaces text for [`WeakDom::replace_in_strings`].
+///
+/// This is implemented for `str`, which replaces every occurrence of the
+/// string, and for `regex::Regex` when the `regex` feature is enabled, which
+/// replaces every match and expands capture groups like `$1` in the
+/// replacement.
+pub trait StringMatcher {
+    /// Returns `text` with every match replaced by `replacement`, or `text`
+    /// itself if nothing matched.
+    fn replace<'t>(&self, text: &'t str, replacement: &str) -> Cow<'t, str>;
+
+    /// Returns `replacement` if the whole of `text` is a match, or `None`
+    /// otherwise. This is used for asset IDs, where replacing part of one
+    /// would turn it into a different asset.
+    fn replace_whole(&self, text: &str, replacement: &str) -> Option<String>;
+}
+
+impl StringMatcher for str {
+    fn replace<'t>(&self, text: &'t str, replacement: &str) -> Cow<'t, str> {
+        if self.is_empty() || !text.contains(self) {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(text.replace(self, replacement))
+        }
+    }
+
+    fn replace_whole(&self, text: &str, replacement: &str) -> Option<String> {
+        if text == self {
+            Some(replacement.to_owned())
+        } else {
+            None
+        }
+    }
+}
+
+impl StringMatcher for String {
+    fn replace<'t>(&self, text: &'t str, replacement: &str) -> Cow<'t, str> {
+        StringMatcher::replace(self.as_str(), text, replacement)
+    }
+
+    fn replace_whole(&self, text: &str, replacement: &str) -> Option<String> {
+        StringMatcher::replace_whole(self.as_str(), text, replacement)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl StringMatcher for regex::Regex {
+    fn replace<'t>(&self, text: &'t str, replacement: &str) -> Cow<'t, str> {
+        self.replace_all(text, replacement)
+    }
+
+    fn replace_whole(&self, text: &str, replacement: &str) -> Option<String> {
+        let captures = self.captures(text)?;
+        let whole = captures.get(0)?;
+
+        if whole.start() != 0 || whole.end() != text.len() {
+            return None;
+        }
+
+        let mut replaced = String::new();
+        captures.expand(replacement, &mut replaced);
+        Some(replaced)
+    }
+}
+
+impl WeakDom {
+    /// Replaces text matched by `matcher` with `replacement` in every
+    /// `String`, `ContentId`, and `Content` property of every instance in the
+    /// dom, like renaming an asset ID or a prefix across a whole place.
+    /// Script sources are `String` properties, so they're included.
+    ///
+    /// `ContentId` and `Content` properties are only changed if `matcher`
+    /// matches their whole value, so that replacing `rbxassetid://123` leaves
+    /// `rbxassetid://1234` alone.
+    ///
+    /// Only properties for which `filter` returns `true` are changed. It's
+    /// called with the instance and the name of the property.
+    ///
+    /// Changes are counted against the DOM's [`DomBudget`][crate::DomBudget].
+    /// If a change would exceed it, that change and every change after it are
+    /// skipped and an error is returned. Otherwise, returns the number of
+    /// properties that were changed.
+    ///
+    /// ## Example
+    /// ```
+    /// use rbx_dom_weak::{types::Variant, ustr, InstanceBuilder, WeakDom};
+    ///
+    /// let mut dom = WeakDom::new(
+    ///     InstanceBuilder::new("Folder").with_child(
+    ///         InstanceBuilder::new("StringValue").with_property("Value", "RedTeam_Spawn"),
+    ///     ),
+    /// );
+    ///
+    /// let changed = dom.replace_in_strings("RedTeam_", "BlueTeam_", |_, _| true)?;
+    /// assert_eq!(changed, 1);
+    ///
+    /// let value = dom.get_by_ref(dom.root().children()[0]).unwrap();
+    /// assert_eq!(
+    ///     value.properties.get(&ustr("Value")),
+    ///     Some(&Variant::String("BlueTeam_Spawn".to_owned())),
+    /// );
+    /// # Ok::<(), rbx_dom_weak::BudgetError>(())
+    /// ```
+    pub fn replace_in_strings<M, F>(
+        &mut self,
+        matcher: &M,
+        replacement: &str,
+        mut filter: F,
+    ) -> Result<usize, BudgetError>
+    where
+        M: StringMatcher + ?Sized,
+        F: FnMut(&Instance, Ustr) -> bool,
+    {
+        let mut updates = Vec::new();
+
+        for instance in self.descendants() {
+            for (name, value) in &instance.properties {
+                let (text, is_asset) = match value {
+                    Variant::String(text) => (text.as_str(), false),
+                    Variant::ContentId(content_id) => (content_id.as_str(), true),
+                    Variant::Content(content) => match content.value() {
+                        ContentType::Uri(uri) => (uri.as_str(), true),
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+
+                let replaced = if is_asset {
+                    matcher.replace_whole(text, replacement)
+                } else {
+                    match matcher.replace(text, replacement) {
+                        Cow::Owned(replaced) => Some(replaced),
+                        Cow::Borrowed(_) => None,
+                    }
+                };
+
+                let replaced = match replaced {
+                    Some(replaced) if replaced != text => replaced,
+                    _ => continue,
+                };
+
+                if !filter(instance, *name) {
+                    continue;
+                }
+
+                let new_value = match value {
+                    Variant::String(_) => Variant::String(replaced),
+                    Variant::ContentId(_) => Variant::ContentId(ContentId::from(replaced)),
+                    _ => Variant::Content(Content::from_uri(replaced)),
+                };
+
+                updates.push((instance.referent(), *name, new_value));
+            }
+        }
+
+        let changed = updates.len();
+        self.update_properties(updates)?;
+
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::Ref;
+    use ustr::ustr;
+
+    use crate::InstanceBuilder;
+
+    #[test]
+    fn replace_in_strings() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder").with_children([
+                InstanceBuilder::new("Decal")
+                    .with_property("Texture", ContentId::from("rbxassetid://123"))
+                    .with_property("Tooltip", "rbxassetid://123"),
+                InstanceBuilder::new("MeshPart")
+                    .with_property("MeshContent", Content::from_uri("rbxassetid://1234"))
+                    .with_property("TextureContent", Content::from_referent(Ref::none())),
+                InstanceBuilder::new("Script")
+                    .with_property("Source", "print('rbxassetid://123')")
+                    .with_property("Count", 123),
+                InstanceBuilder::new("MeshPart")
+                    .with_property("MeshContent", Content::from_uri("rbxassetid://123")),
+            ]),
+        );
+
+        let changed = dom
+            .replace_in_strings("rbxassetid://123", "rbxassetid://456", |instance, _| {
+                instance.class != "Script"
+            })
+            .unwrap();
+        assert_eq!(changed, 3);
+
+        let children = dom.root().children().to_vec();
+        let property = |index: usize, name: &str| {
+            dom.get_by_ref(children[index])
+                .unwrap()
+                .properties
+                .get(&ustr(name))
+                .cloned()
+        };
+
+        assert_eq!(
+            property(0, "Texture"),
+            Some(Variant::ContentId(ContentId::from("rbxassetid://456")))
+        );
+        assert_eq!(
+            property(0, "Tooltip"),
+            Some(Variant::String("rbxassetid://456".to_owned()))
+        );
+        assert_eq!(
+            property(1, "MeshContent"),
+            Some(Variant::Content(Content::from_uri("rbxassetid://1234")))
+        );
+        assert_eq!(
+            property(3, "MeshContent"),
+            Some(Variant::Content(Content::from_uri("rbxassetid://456")))
+        );
+        assert_eq!(
+            property(2, "Source"),
+            Some(Variant::String("print('rbxassetid://123')".to_owned()))
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn replace_in_strings_regex() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("StringValue").with_property("Value", "Team_Red, Team_Blue"),
+        );
+
+        let pattern = regex::Regex::new(r"Team_(\w+)").unwrap();
+        let changed = dom
+            .replace_in_strings(&pattern, "${1}Team", |_, _| true)
+            .unwrap();
+        assert_eq!(changed, 1);
+
+        assert_eq!(
+            dom.root().properties.get(&ustr("Value")),
+            Some(&Variant::String("RedTeam, BlueTeam".to_owned()))
+        );
+    }
+}
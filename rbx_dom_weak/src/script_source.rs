@@ -0,0 +1,172 @@
+use std::{borrow::Cow, error::Error, fmt, str::Utf8Error};
+
+use rbx_types::{Variant, VariantType};
+use ustr::ustr;
+
+use crate::Instance;
+
+/// What [`Instance::script_source`] should do when a script's `Source` is not
+/// valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum InvalidUtf8 {
+    /// Return an error.
+    #[default]
+    Error,
+
+    /// Replace each invalid sequence with `U+FFFD REPLACEMENT CHARACTER`.
+    Replace,
+}
+
+impl Instance {
+    /// Returns the `Source` property of a script with its line endings
+    /// normalized to `\n`, or `None` if the instance has no `Source`.
+    ///
+    /// `Source` is a `ProtectedString` in Roblox files, which may be stored as
+    /// either a `String` or a `BinaryString` depending on how the instance was
+    /// created. Both are handled here. A `BinaryString` that is not valid
+    /// UTF-8 is handled according to `invalid_utf8`.
+    pub fn script_source(
+        &self,
+        invalid_utf8: InvalidUtf8,
+    ) -> Result<Option<Cow<'_, str>>, ScriptSourceError> {
+        let source = match self.properties.get(&ustr("Source")) {
+            Some(Variant::String(value)) => Cow::Borrowed(value.as_str()),
+            Some(Variant::BinaryString(value)) => {
+                let bytes: &[u8] = value.as_ref();
+
+                match (std::str::from_utf8(bytes), invalid_utf8) {
+                    (Ok(value), _) => Cow::Borrowed(value),
+                    (Err(_), InvalidUtf8::Replace) => String::from_utf8_lossy(bytes),
+                    (Err(err), InvalidUtf8::Error) => {
+                        return Err(ScriptSourceError::InvalidUtf8(err))
+                    }
+                }
+            }
+            Some(other) => return Err(ScriptSourceError::WrongType(other.ty())),
+            None => return Ok(None),
+        };
+
+        Ok(Some(normalize_line_endings(source)))
+    }
+
+    /// Sets the `Source` property of a script, normalizing its line endings to
+    /// `\n`. The value is always stored as a `String`, replacing any
+    /// `BinaryString` that was there before.
+    pub fn set_script_source<S: AsRef<str>>(&mut self, source: S) {
+        let source = normalize_line_endings(Cow::Borrowed(source.as_ref())).into_owned();
+
+        self.properties
+            .insert(ustr("Source"), Variant::String(source));
+    }
+}
+
+/// Replaces `\r\n` and lone `\r` with `\n`, only allocating if there's
+/// anything to replace.
+fn normalize_line_endings(source: Cow<'_, str>) -> Cow<'_, str> {
+    if !source.contains('\r') {
+        return source;
+    }
+
+    Cow::Owned(source.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// An error returned by [`Instance::script_source`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ScriptSourceError {
+    /// `Source` was stored as bytes that are not valid UTF-8.
+    InvalidUtf8(Utf8Error),
+
+    /// `Source` was stored as a type other than `String` or `BinaryString`.
+    WrongType(VariantType),
+}
+
+impl fmt::Display for ScriptSourceError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptSourceError::InvalidUtf8(err) => {
+                write!(formatter, "script Source is not valid UTF-8: {}", err)
+            }
+            ScriptSourceError::WrongType(ty) => write!(
+                formatter,
+                "script Source should be a String or BinaryString, but it was {:?}",
+                ty
+            ),
+        }
+    }
+}
+
+impl Error for ScriptSourceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScriptSourceError::InvalidUtf8(err) => Some(err),
+            ScriptSourceError::WrongType(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::BinaryString;
+
+    use crate::{InstanceBuilder, WeakDom};
+
+    #[test]
+    fn read_and_normalize() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Script").with_property("Source", "print(1)\r\nprint(2)\rend"),
+        );
+
+        assert_eq!(
+            dom.root().script_source(InvalidUtf8::Error).unwrap(),
+            Some(Cow::Borrowed("print(1)\nprint(2)\nend"))
+        );
+
+        let folder = WeakDom::new(InstanceBuilder::new("Folder"));
+        assert_eq!(folder.root().script_source(InvalidUtf8::Error), Ok(None));
+    }
+
+    #[test]
+    fn binary_source() {
+        let valid = WeakDom::new(
+            InstanceBuilder::new("ModuleScript")
+                .with_property("Source", BinaryString::from(b"return {}".to_vec())),
+        );
+        assert_eq!(
+            valid.root().script_source(InvalidUtf8::Error).unwrap(),
+            Some(Cow::Borrowed("return {}"))
+        );
+
+        let mut invalid = WeakDom::new(
+            InstanceBuilder::new("ModuleScript")
+                .with_property("Source", BinaryString::from(b"x = \"\xff\"".to_vec())),
+        );
+        assert!(matches!(
+            invalid.root().script_source(InvalidUtf8::Error),
+            Err(ScriptSourceError::InvalidUtf8(_))
+        ));
+        assert_eq!(
+            invalid.root().script_source(InvalidUtf8::Replace).unwrap(),
+            Some(Cow::Borrowed("x = \"\u{fffd}\""))
+        );
+
+        invalid.root_mut().set_script_source("x = 1\r\n");
+        assert_eq!(
+            invalid.root().properties.get(&ustr("Source")),
+            Some(&Variant::String("x = 1\n".to_owned()))
+        );
+    }
+
+    #[test]
+    fn wrong_type() {
+        let dom = WeakDom::new(InstanceBuilder::new("Script").with_property("Source", 5i32));
+
+        assert_eq!(
+            dom.root().script_source(InvalidUtf8::Replace),
+            Err(ScriptSourceError::WrongType(VariantType::Int32))
+        );
+    }
+}
@@ -0,0 +1,506 @@
+use std::{collections::VecDeque, error::Error, fmt, str::FromStr};
+
+use ahash::AHashMap;
+use rbx_types::{Color3, Color3uint8, Enum, Ref, Variant, VariantType, Vector2, Vector3};
+use ustr::{ustr, Ustr};
+
+use crate::{InstanceBuilder, WeakDom};
+
+/// The values substituted into a template by
+/// [`InstanceBuilder::from_template`], keyed by parameter name.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateParams {
+    values: AHashMap<String, Variant>,
+}
+
+impl TemplateParams {
+    /// Creates an empty `TemplateParams`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of the parameter with the given name.
+    pub fn get(&self, name: &str) -> Option<&Variant> {
+        self.values.get(name)
+    }
+
+    /// Sets the value of a parameter.
+    /// Will return the value that used to be there if one existed.
+    pub fn insert(&mut self, name: String, value: Variant) -> Option<Variant> {
+        self.values.insert(name, value)
+    }
+
+    /// Sets the value of a parameter.
+    /// Will overwrite the value that used to be there if one existed.
+    pub fn with<K: Into<String>, V: Into<Variant>>(mut self, name: K, value: V) -> Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// A placeholder in a template that couldn't be filled in, returned by
+/// [`InstanceBuilder::from_template`].
+///
+/// Every error includes the instance in the template that the placeholder
+/// belongs to, its full name, and the name of the property the placeholder
+/// is in. Placeholders in the names of instances use the property `Name`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TemplateError {
+    /// The placeholder names a parameter that wasn't given.
+    MissingParameter {
+        /// The instance in the template.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// The property the placeholder is in.
+        property: Ustr,
+        /// The name of the parameter.
+        parameter: String,
+    },
+
+    /// The placeholder asks for a type that parameters can't be converted to.
+    UnknownType {
+        /// The instance in the template.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// The property the placeholder is in.
+        property: Ustr,
+        /// The name of the parameter.
+        parameter: String,
+        /// The type the placeholder asks for.
+        ty: String,
+    },
+
+    /// The parameter's value couldn't be converted to the type the
+    /// placeholder asks for.
+    InvalidValue {
+        /// The instance in the template.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// The property the placeholder is in.
+        property: Ustr,
+        /// The name of the parameter.
+        parameter: String,
+        /// The type the placeholder asks for.
+        ty: VariantType,
+        /// The parameter's value.
+        value: Box<Variant>,
+    },
+
+    /// The placeholder is part of a longer string, but its value can't be
+    /// written as text.
+    NotText {
+        /// The instance in the template.
+        referent: Ref,
+        /// The full name of the instance.
+        path: String,
+        /// The property the placeholder is in.
+        property: Ustr,
+        /// The name of the parameter.
+        parameter: String,
+        /// The type of the parameter's value.
+        ty: VariantType,
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::MissingParameter {
+                path,
+                property,
+                parameter,
+                ..
+            } => write!(
+                formatter,
+                "Property {} of {} uses the parameter {}, which was not given",
+                property, path, parameter
+            ),
+            TemplateError::UnknownType {
+                path,
+                property,
+                parameter,
+                ty,
+                ..
+            } => write!(
+                formatter,
+                "Property {} of {} converts the parameter {} to {}, which is not a supported type",
+                property, path, parameter, ty
+            ),
+            TemplateError::InvalidValue {
+                path,
+                property,
+                parameter,
+                ty,
+                value,
+                ..
+            } => write!(
+                formatter,
+                "Property {} of {} needs the parameter {} to be a {:?}, but it is {:?}",
+                property, path, parameter, ty, value
+            ),
+            TemplateError::NotText {
+                path,
+                property,
+                parameter,
+                ty,
+                ..
+            } => write!(
+                formatter,
+                "Property {} of {} uses the parameter {} inside of a string, but it is a {:?}",
+                property, path, parameter, ty
+            ),
+        }
+    }
+}
+
+impl Error for TemplateError {}
+
+/// Why a placeholder couldn't be filled in, before it's known where the
+/// placeholder is.
+enum Failure {
+    MissingParameter(String),
+    UnknownType(String, String),
+    InvalidValue(String, VariantType, Box<Variant>),
+    NotText(String, VariantType),
+}
+
+impl Failure {
+    fn locate(self, dom: &WeakDom, referent: Ref, property: Ustr) -> TemplateError {
+        let path = dom.full_name(referent);
+
+        match self {
+            Failure::MissingParameter(parameter) => TemplateError::MissingParameter {
+                referent,
+                path,
+                property,
+                parameter,
+            },
+            Failure::UnknownType(parameter, ty) => TemplateError::UnknownType {
+                referent,
+                path,
+                property,
+                parameter,
+                ty,
+            },
+            Failure::InvalidValue(parameter, ty, value) => TemplateError::InvalidValue {
+                referent,
+                path,
+                property,
+                parameter,
+                ty,
+                value,
+            },
+            Failure::NotText(parameter, ty) => TemplateError::NotText {
+                referent,
+                path,
+                property,
+                parameter,
+                ty,
+            },
+        }
+    }
+}
+
+impl InstanceBuilder {
+    /// Copies the instance with the given referent and all of its descendants
+    /// out of `dom` like [`InstanceBuilder::from_subtree`], filling in the
+    /// placeholders in their names and `String` properties from `params`.
+    ///
+    /// A placeholder is the name of a parameter wrapped in double braces, like
+    /// `{{team}}`. When a property's whole value is a single placeholder, the
+    /// property takes on the parameter's value and type, so a `Color3`
+    /// parameter turns the property into a `Color3`. Otherwise, the
+    /// parameter is written into the string, which only works for strings,
+    /// numbers, and bools.
+    ///
+    /// A placeholder can also name a type after a colon, like
+    /// `{{team_color:Color3}}`. `String` parameters are then parsed into that
+    /// type, which is useful for parameters read from the command line or a
+    /// config file. The supported types are `String`, `Bool`, `Int32`,
+    /// `Int64`, `Float32`, `Float64`, `Enum`, and the comma-separated types
+    /// `Color3`, `Color3uint8`, `Vector2`, and `Vector3`.
+    ///
+    /// ## Example
+    /// ```
+    /// use rbx_dom_weak::{
+    ///     types::{Color3, Variant},
+    ///     ustr, InstanceBuilder, TemplateParams, WeakDom,
+    /// };
+    ///
+    /// let template = WeakDom::new(
+    ///     InstanceBuilder::new("SpawnLocation")
+    ///         .with_name("{{team}}Spawn")
+    ///         .with_property("TeamColor", "{{color:Color3}}"),
+    /// );
+    ///
+    /// let params = TemplateParams::new()
+    ///     .with("team", "Red")
+    ///     .with("color", "1, 0, 0");
+    ///
+    /// let dom = WeakDom::new(InstanceBuilder::from_template(&template, template.root_ref(), &params)?);
+    /// assert_eq!(dom.root().name, "RedSpawn");
+    /// assert_eq!(
+    ///     dom.root().properties.get(&ustr("TeamColor")),
+    ///     Some(&Variant::Color3(Color3::new(1.0, 0.0, 0.0))),
+    /// );
+    /// # Ok::<(), rbx_dom_weak::TemplateError>(())
+    /// ```
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in `dom`.
+    pub fn from_template(
+        dom: &WeakDom,
+        referent: Ref,
+        params: &TemplateParams,
+    ) -> Result<Self, TemplateError> {
+        let mut root = InstanceBuilder::from_subtree(dom, referent);
+
+        // `from_subtree` copies instances in the same breadth-first order
+        // that `descendants_of` visits them in.
+        let mut originals = dom.descendants_of(referent);
+        let mut queue = VecDeque::new();
+        queue.push_back(&mut root);
+
+        while let Some(builder) = queue.pop_front() {
+            let original = originals.next().unwrap().referent();
+
+            // Names are always strings, so their placeholders never change
+            // type.
+            if let Some(Variant::String(name)) = fill_placeholders(&builder.name, params, false)
+                .map_err(|failure| failure.locate(dom, original, ustr("Name")))?
+            {
+                builder.name = name;
+            }
+
+            for (property, value) in &mut builder.properties {
+                if let Variant::String(template) = value {
+                    if let Some(filled) = fill_placeholders(template, params, true)
+                        .map_err(|failure| failure.locate(dom, original, *property))?
+                    {
+                        *value = filled;
+                    }
+                }
+            }
+
+            queue.extend(builder.children.iter_mut());
+        }
+
+        Ok(root)
+    }
+}
+
+/// Fills in the placeholders in `template`, or returns `None` if it doesn't
+/// have any. If `keep_type` is set and the template is a single placeholder,
+/// the parameter's value is returned as it is. Otherwise, the result is a
+/// string.
+fn fill_placeholders(
+    template: &str,
+    params: &TemplateParams,
+    keep_type: bool,
+) -> Result<Option<Variant>, Failure> {
+    let mut output = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        let placeholder = &rest[start + 2..end];
+        let (name, ty) = match placeholder.split_once(':') {
+            Some((name, ty)) => (name.trim(), Some(ty.trim())),
+            None => (placeholder.trim(), None),
+        };
+        let value = resolve(name, ty, params)?;
+
+        if keep_type && start == 0 && end + 2 == template.len() {
+            return Ok(Some(value));
+        }
+
+        output.push_str(&rest[..start]);
+        match value {
+            Variant::String(value) => output.push_str(&value),
+            Variant::Bool(value) => output.push_str(&value.to_string()),
+            Variant::Int32(value) => output.push_str(&value.to_string()),
+            Variant::Int64(value) => output.push_str(&value.to_string()),
+            Variant::Float32(value) => output.push_str(&value.to_string()),
+            Variant::Float64(value) => output.push_str(&value.to_string()),
+            other => return Err(Failure::NotText(name.to_owned(), other.ty())),
+        }
+        rest = &rest[end + 2..];
+    }
+
+    if rest.len() == template.len() {
+        return Ok(None);
+    }
+
+    output.push_str(rest);
+    Ok(Some(Variant::String(output)))
+}
+
+/// Returns the value of the parameter `name`, converted to the type named by
+/// `ty` if there is one.
+fn resolve(name: &str, ty: Option<&str>, params: &TemplateParams) -> Result<Variant, Failure> {
+    let value = params
+        .get(name)
+        .ok_or_else(|| Failure::MissingParameter(name.to_owned()))?;
+
+    let ty = match ty {
+        Some(ty) => ty,
+        None => return Ok(value.clone()),
+    };
+
+    let ty =
+        parseable_type(ty).ok_or_else(|| Failure::UnknownType(name.to_owned(), ty.to_owned()))?;
+
+    if value.ty() == ty {
+        return Ok(value.clone());
+    }
+
+    let invalid = || Failure::InvalidValue(name.to_owned(), ty, Box::new(value.clone()));
+    match value {
+        Variant::String(text) => parse_variant(ty, text.trim()).ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+fn parseable_type(name: &str) -> Option<VariantType> {
+    Some(match name {
+        "String" => VariantType::String,
+        "Bool" => VariantType::Bool,
+        "Int32" => VariantType::Int32,
+        "Int64" => VariantType::Int64,
+        "Float32" => VariantType::Float32,
+        "Float64" => VariantType::Float64,
+        "Enum" => VariantType::Enum,
+        "Color3" => VariantType::Color3,
+        "Color3uint8" => VariantType::Color3uint8,
+        "Vector2" => VariantType::Vector2,
+        "Vector3" => VariantType::Vector3,
+        _ => return None,
+    })
+}
+
+fn parse_variant(ty: VariantType, text: &str) -> Option<Variant> {
+    Some(match ty {
+        VariantType::String => Variant::String(text.to_owned()),
+        VariantType::Bool => Variant::Bool(text.parse().ok()?),
+        VariantType::Int32 => Variant::Int32(text.parse().ok()?),
+        VariantType::Int64 => Variant::Int64(text.parse().ok()?),
+        VariantType::Float32 => Variant::Float32(text.parse().ok()?),
+        VariantType::Float64 => Variant::Float64(text.parse().ok()?),
+        VariantType::Enum => Variant::Enum(Enum::from_u32(text.parse().ok()?)),
+        VariantType::Color3 => {
+            let [r, g, b] = components(text)?;
+            Variant::Color3(Color3::new(r, g, b))
+        }
+        VariantType::Color3uint8 => {
+            let [r, g, b] = components(text)?;
+            Variant::Color3uint8(Color3uint8::new(r, g, b))
+        }
+        VariantType::Vector2 => {
+            let [x, y] = components(text)?;
+            Variant::Vector2(Vector2::new(x, y))
+        }
+        VariantType::Vector3 => {
+            let [x, y, z] = components(text)?;
+            Variant::Vector3(Vector3::new(x, y, z))
+        }
+        _ => return None,
+    })
+}
+
+/// Parses exactly `N` comma-separated values.
+fn components<T: FromStr + Default + Copy, const N: usize>(text: &str) -> Option<[T; N]> {
+    let mut output = [T::default(); N];
+    let mut parts = text.split(',');
+
+    for slot in &mut output {
+        *slot = parts.next()?.trim().parse().ok()?;
+    }
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_template() {
+        let template = WeakDom::new(
+            InstanceBuilder::new("Model")
+                .with_name("{{team}}Base")
+                .with_child(
+                    InstanceBuilder::new("Part")
+                        .with_property("Color", "{{color}}")
+                        .with_property("Size", "{{size:Vector3}}")
+                        .with_property("Label", "{{team}} team, level {{level}}")
+                        .with_property("Plain", "No {{placeholders"),
+                ),
+        );
+
+        let params = TemplateParams::new()
+            .with("team", "Red")
+            .with("color", Color3::new(1.0, 0.0, 0.0))
+            .with("size", "4, 1, 2")
+            .with("level", 3);
+
+        let dom = WeakDom::new(
+            InstanceBuilder::from_template(&template, template.root_ref(), &params).unwrap(),
+        );
+        assert_eq!(dom.root().name, "RedBase");
+
+        let part = dom.get_by_ref(dom.root().children()[0]).unwrap();
+        let property = |name: &str| part.properties.get(&ustr(name)).cloned();
+        assert_eq!(
+            property("Color"),
+            Some(Variant::Color3(Color3::new(1.0, 0.0, 0.0)))
+        );
+        assert_eq!(
+            property("Size"),
+            Some(Variant::Vector3(Vector3::new(4.0, 1.0, 2.0)))
+        );
+        assert_eq!(
+            property("Label"),
+            Some(Variant::String("Red team, level 3".to_owned()))
+        );
+        assert_eq!(
+            property("Plain"),
+            Some(Variant::String("No {{placeholders".to_owned()))
+        );
+
+        let error = |params: &TemplateParams| {
+            InstanceBuilder::from_template(&template, template.root_ref(), params)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error(
+                &TemplateParams::new()
+                    .with("team", "Red")
+                    .with("size", "4, 1, 2")
+                    .with("level", 3)
+            ),
+            "Property Color of {{team}}Base.Part uses the parameter color, which was not given"
+        );
+        assert_eq!(
+            error(&params.clone().with("color", "red").with("team", Vector2::new(0.0, 0.0))),
+            "Property Name of {{team}}Base uses the parameter team inside of a string, but it is a Vector2"
+        );
+        assert_eq!(
+            error(&params.clone().with("size", "4, 1")),
+            "Property Size of {{team}}Base.Part needs the parameter size to be a Vector3, but it is String(\"4, 1\")"
+        );
+    }
+}
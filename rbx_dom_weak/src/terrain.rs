@@ -0,0 +1,278 @@
+use std::{collections::BTreeSet, error::Error, fmt};
+
+use rbx_types::{
+    Error as TypesError, MaterialColors, Ref, SmoothGrid, TerrainDiagnostic, TerrainMaterials,
+    Variant,
+};
+use ustr::ustr;
+
+use crate::{Instance, WeakDom};
+
+/// A problem with the terrain data of a `Terrain` instance, found by
+/// [`WeakDom::check_terrain`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TerrainIssue {
+    /// The `MaterialColors` property is not a valid `MaterialColors` blob for
+    /// the current set of materials.
+    InvalidMaterialColors {
+        /// The `Terrain` instance the property belongs to.
+        referent: Ref,
+        /// Why the blob couldn't be read.
+        source: TypesError,
+    },
+
+    /// The `MaterialColors` property has a type other than `MaterialColors`
+    /// or `BinaryString`.
+    MaterialColorsWrongType {
+        /// The `Terrain` instance the property belongs to.
+        referent: Ref,
+    },
+
+    /// The `SmoothGrid` property couldn't be decoded, so the materials it uses
+    /// are unknown.
+    InvalidSmoothGrid {
+        /// The `Terrain` instance the property belongs to.
+        referent: Ref,
+        /// Why the blob couldn't be decoded.
+        source: TypesError,
+    },
+
+    /// A material used by the `SmoothGrid` property has no entry in the
+    /// `MaterialColors` property, so it will be drawn with its default color.
+    MissingMaterialColor {
+        /// The `Terrain` instance the properties belong to.
+        referent: Ref,
+        /// The material without a color.
+        material: TerrainMaterials,
+    },
+
+    /// The `SmoothGrid` property holds voxel data that Roblox would reject or
+    /// change, found by [`SmoothGrid::validate`].
+    InvalidVoxels {
+        /// The `Terrain` instance the property belongs to.
+        referent: Ref,
+        /// What is wrong with the data.
+        diagnostic: TerrainDiagnostic,
+    },
+}
+
+impl fmt::Display for TerrainIssue {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerrainIssue::InvalidMaterialColors { referent, source } => write!(
+                formatter,
+                "Terrain {} has invalid MaterialColors: {}",
+                referent, source
+            ),
+            TerrainIssue::MaterialColorsWrongType { referent } => write!(
+                formatter,
+                "Terrain {} has a MaterialColors property that is not a MaterialColors blob",
+                referent
+            ),
+            TerrainIssue::InvalidSmoothGrid { referent, source } => write!(
+                formatter,
+                "Terrain {} has an invalid SmoothGrid: {}",
+                referent, source
+            ),
+            TerrainIssue::MissingMaterialColor { referent, material } => write!(
+                formatter,
+                "Terrain {} uses the material {:?}, which has no entry in its MaterialColors",
+                referent, material
+            ),
+            TerrainIssue::InvalidVoxels {
+                referent,
+                diagnostic,
+            } => write!(
+                formatter,
+                "Terrain {} has bad voxel data: {}",
+                referent, diagnostic
+            ),
+        }
+    }
+}
+
+impl Error for TerrainIssue {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TerrainIssue::InvalidMaterialColors { source, .. }
+            | TerrainIssue::InvalidSmoothGrid { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl WeakDom {
+    /// Checks that the `MaterialColors` property of every `Terrain` instance
+    /// in the dom is a valid blob, that it has a color for every material
+    /// used by the instance's `SmoothGrid`, and that the `SmoothGrid` passes
+    /// [`SmoothGrid::validate`].
+    ///
+    /// Terrain with mismatched properties still loads, but is drawn with the
+    /// wrong colors, so these problems are returned instead of being treated
+    /// as errors. A `Terrain` without a `SmoothGrid` property is skipped.
+    pub fn check_terrain(&self) -> Vec<TerrainIssue> {
+        let mut issues = Vec::new();
+
+        for instance in self.descendants() {
+            if instance.class == "Terrain" {
+                check_instance(instance, &mut issues);
+            }
+        }
+
+        issues
+    }
+}
+
+fn check_instance(instance: &Instance, issues: &mut Vec<TerrainIssue>) {
+    let referent = instance.referent();
+
+    let colors = match instance.properties.get(&ustr("MaterialColors")) {
+        Some(Variant::MaterialColors(colors)) => Some(colors.clone()),
+        Some(Variant::BinaryString(blob)) => match MaterialColors::decode(blob.as_ref()) {
+            Ok(colors) => Some(colors),
+            Err(source) => {
+                issues.push(TerrainIssue::InvalidMaterialColors { referent, source });
+                return;
+            }
+        },
+        Some(_) => {
+            issues.push(TerrainIssue::MaterialColorsWrongType { referent });
+            return;
+        }
+        None => None,
+    };
+
+    let decoded;
+    let grid = match instance.properties.get(&ustr("SmoothGrid")) {
+        Some(Variant::SmoothGrid(grid)) => grid,
+        Some(Variant::BinaryString(blob)) => match SmoothGrid::decode(blob.as_ref()) {
+            Ok(grid) => {
+                decoded = grid;
+                &decoded
+            }
+            Err(source) => {
+                issues.push(TerrainIssue::InvalidSmoothGrid { referent, source });
+                return;
+            }
+        },
+        _ => return,
+    };
+
+    issues.extend(
+        grid.validate()
+            .into_iter()
+            .map(|diagnostic| TerrainIssue::InvalidVoxels {
+                referent,
+                diagnostic,
+            }),
+    );
+
+    let materials: BTreeSet<TerrainMaterials> = grid
+        .chunks()
+        .flat_map(|(_, chunk)| chunk.iter_voxels())
+        .filter_map(|(_, voxel)| voxel.material)
+        .collect();
+
+    for material in materials {
+        let has_color = matches!(&colors, Some(colors) if colors.contains(material));
+
+        if !has_color {
+            issues.push(TerrainIssue::MissingMaterialColor { referent, material });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rbx_types::{BinaryString, Color3uint8, TerrainVec, Voxel};
+
+    use crate::InstanceBuilder;
+
+    fn smooth_grid(materials: &[TerrainMaterials]) -> BinaryString {
+        let mut grid = SmoothGrid::new();
+        for (x, material) in materials.iter().enumerate() {
+            grid.write_voxel_world(TerrainVec::new(x as i32, 0, 0), Voxel::solid(*material));
+        }
+
+        BinaryString::from(grid.encode())
+    }
+
+    #[test]
+    fn check_terrain() {
+        let mut colors = MaterialColors::new();
+        colors.set_color(TerrainMaterials::Grass, Color3uint8::new(0, 255, 0));
+
+        let mut wet_rock = SmoothGrid::new();
+        wet_rock.write_voxel_world(
+            TerrainVec::new(0, 0, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Rock),
+                occupancy: 255,
+                water_occupancy: 100,
+            },
+        );
+
+        let dom = WeakDom::new(
+            InstanceBuilder::new("Workspace").with_children([
+                InstanceBuilder::new("Terrain")
+                    .with_name("Complete")
+                    .with_property(
+                        "MaterialColors",
+                        BinaryString::from(MaterialColors::new().encode()),
+                    )
+                    .with_property("SmoothGrid", smooth_grid(&[TerrainMaterials::Rock])),
+                InstanceBuilder::new("Terrain")
+                    .with_name("Partial")
+                    .with_property("MaterialColors", colors)
+                    .with_property(
+                        "SmoothGrid",
+                        smooth_grid(&[TerrainMaterials::Grass, TerrainMaterials::Sand]),
+                    ),
+                InstanceBuilder::new("Terrain")
+                    .with_name("Truncated")
+                    .with_property("MaterialColors", BinaryString::from(vec![0; 60]))
+                    .with_property("SmoothGrid", smooth_grid(&[TerrainMaterials::Rock])),
+                InstanceBuilder::new("Terrain")
+                    .with_name("BadGrid")
+                    .with_property("SmoothGrid", BinaryString::from(vec![1, 5, 0, 31])),
+                InstanceBuilder::new("Terrain")
+                    .with_name("WetRock")
+                    .with_property(
+                        "MaterialColors",
+                        BinaryString::from(MaterialColors::new().encode()),
+                    )
+                    .with_property("SmoothGrid", BinaryString::from(wet_rock.encode())),
+            ]),
+        );
+
+        let name_of = |referent: &Ref| dom.get_by_ref(*referent).unwrap().name.as_str();
+        let issues = dom.check_terrain();
+        assert_eq!(issues.len(), 4);
+
+        assert!(matches!(
+            &issues[0],
+            TerrainIssue::MissingMaterialColor {
+                referent,
+                material: TerrainMaterials::Sand,
+            } if name_of(referent) == "Partial"
+        ));
+        assert!(matches!(
+            &issues[1],
+            TerrainIssue::InvalidMaterialColors { referent, .. } if name_of(referent) == "Truncated"
+        ));
+        assert!(matches!(
+            &issues[2],
+            TerrainIssue::InvalidSmoothGrid { referent, .. } if name_of(referent) == "BadGrid"
+        ));
+        assert!(matches!(
+            &issues[3],
+            TerrainIssue::InvalidVoxels {
+                referent,
+                diagnostic: TerrainDiagnostic::WaterInFullSolid { water_occupancy: 100, .. },
+            } if name_of(referent) == "WetRock"
+        ));
+    }
+}
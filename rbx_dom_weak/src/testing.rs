@@ -0,0 +1,374 @@
+//! Generators for doms and terrain that are the same every time they're given
+//! the same seed, for tests and benchmarks that need large or varied inputs
+//! without checking in fixture files.
+//!
+//! This module is only available with the `testing` feature.
+//!
+//! ```
+//! use rbx_dom_weak::testing::{DomGenerator, TerrainGenerator};
+//!
+//! let dom = DomGenerator::new(42).instances(500).max_depth(8).generate();
+//! assert_eq!(dom.descendants().count(), 501);
+//!
+//! let grid = TerrainGenerator::new(42).chunks(4).generate();
+//! assert_eq!(grid.chunks().count(), 4);
+//! ```
+
+use rbx_types::{
+    BinaryString, CFrame, Color3, Matrix3, Ref, SmoothGrid, TerrainMaterials, TerrainVec, Variant,
+    Vector2, Vector3, Voxel, CHUNK_SIZE,
+};
+use ustr::{ustr, Ustr};
+
+use crate::{InstanceBuilder, WeakDom};
+
+/// The classes [`DomGenerator`] uses if it isn't given any.
+const DEFAULT_CLASSES: &[&str] = &[
+    "Folder",
+    "Model",
+    "Part",
+    "Script",
+    "StringValue",
+    "Configuration",
+];
+
+/// The number of kinds of property value [`DomGenerator`] can create.
+const PROPERTY_KINDS: u64 = 12;
+
+/// Generates a [`WeakDom`] from a seed.
+///
+/// Instances are placed under random parents up to the maximum depth, and
+/// given random classes from the class list. Every instance of the same class
+/// has the same properties, named `Property0`, `Property1`, and so on, with
+/// the same types, so generated doms can be written by formats that store
+/// properties by class. Values vary between instances, and include strings,
+/// numbers, vectors, colors, CFrames, binary strings, and Refs to other
+/// generated instances.
+///
+/// Numbers are multiples of a power of two so they survive being written as
+/// text.
+#[derive(Debug, Clone)]
+pub struct DomGenerator {
+    seed: u64,
+    instances: usize,
+    max_depth: usize,
+    classes: Vec<Ustr>,
+    max_properties: usize,
+}
+
+impl DomGenerator {
+    /// Creates a `DomGenerator` with the given seed, which generates 100
+    /// instances up to 5 levels deep with up to 6 properties each.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            instances: 100,
+            max_depth: 5,
+            classes: DEFAULT_CLASSES.iter().map(|class| ustr(class)).collect(),
+            max_properties: 6,
+        }
+    }
+
+    /// Sets how many instances are generated, not including the root.
+    pub fn instances(self, instances: usize) -> Self {
+        Self { instances, ..self }
+    }
+
+    /// Sets how deep instances can be placed, where the children of the root
+    /// are at depth 1. A depth of 0 is treated as 1.
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    /// Sets the classes instances are picked from. An empty list uses the
+    /// default classes.
+    pub fn classes<I, S>(self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            classes: classes
+                .into_iter()
+                .map(|class| ustr(class.as_ref()))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Sets the most properties any class can be given.
+    pub fn max_properties(self, max_properties: usize) -> Self {
+        Self {
+            max_properties,
+            ..self
+        }
+    }
+
+    /// Generates a dom with a `Folder` named `Generated` as its root.
+    pub fn generate(&self) -> WeakDom {
+        let mut rng = Rng::new(self.seed);
+        let classes: Vec<Ustr> = if self.classes.is_empty() {
+            DEFAULT_CLASSES.iter().map(|class| ustr(class)).collect()
+        } else {
+            self.classes.clone()
+        };
+
+        let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_name("Generated"));
+
+        // Every instance that can still have children, and its depth.
+        let mut parents = vec![(dom.root_ref(), 0)];
+        let mut created = Vec::with_capacity(self.instances);
+
+        for index in 0..self.instances {
+            let (parent, depth) = parents[rng.below(parents.len())];
+            let class = classes[rng.below(classes.len())];
+
+            let mut builder = InstanceBuilder::new(class).with_name(format!("{}{}", class, index));
+
+            for (property, kind) in self.class_properties(class).into_iter().enumerate() {
+                let value = random_value(&mut rng, kind, &created);
+                builder = builder.with_property(format!("Property{}", property), value);
+            }
+
+            let referent = dom.insert(parent, builder);
+            created.push(referent);
+
+            if depth + 1 < self.max_depth.max(1) {
+                parents.push((referent, depth + 1));
+            }
+        }
+
+        dom
+    }
+
+    /// Returns the kind of each property instances of `class` have. This
+    /// depends only on the seed and class, not on the order of generation.
+    fn class_properties(&self, class: Ustr) -> Vec<u64> {
+        let mut rng = Rng::new(self.seed ^ hash_str(class.as_str()));
+        let count = rng.below(self.max_properties + 1);
+
+        (0..count)
+            .map(|_| rng.next_u64() % PROPERTY_KINDS)
+            .collect()
+    }
+}
+
+/// Generates a [`SmoothGrid`] from a seed.
+///
+/// The terrain is rolling hills made from value noise, with sand in the
+/// valleys, grass on the slopes, rock at the peaks, and water filling the
+/// valleys up to a fixed level. Each chunk is one column of chunks along the
+/// ground, laid out in a square starting at the origin, so the grid has
+/// exactly the requested number of chunks.
+#[derive(Debug, Clone)]
+pub struct TerrainGenerator {
+    seed: u64,
+    chunks: usize,
+}
+
+impl TerrainGenerator {
+    /// The height water is filled up to, in voxels.
+    const WATER_LEVEL: i32 = 8;
+
+    /// The distance between noise samples, in voxels.
+    const NOISE_SCALE: i32 = 8;
+
+    /// Creates a `TerrainGenerator` with the given seed, which generates 4
+    /// chunks.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, chunks: 4 }
+    }
+
+    /// Sets how many chunks are generated.
+    pub fn chunks(self, chunks: usize) -> Self {
+        Self { chunks, ..self }
+    }
+
+    /// Generates a grid.
+    pub fn generate(&self) -> SmoothGrid {
+        let mut grid = SmoothGrid::new();
+        let size = CHUNK_SIZE as i32;
+
+        let mut side = 1;
+        while side * side < self.chunks {
+            side += 1;
+        }
+
+        for index in 0..self.chunks {
+            let (chunk_x, chunk_z) = ((index % side) as i32, (index / side) as i32);
+
+            for z in chunk_z * size..(chunk_z + 1) * size {
+                for x in chunk_x * size..(chunk_x + 1) * size {
+                    // Heights range from 4 to 28 voxels, so every voxel stays
+                    // in the bottom chunk.
+                    let height = 4.0 + self.noise(x, z) * 24.0;
+                    let top = height.floor() as i32;
+
+                    let material = match top {
+                        top if top < Self::WATER_LEVEL + 2 => TerrainMaterials::Sand,
+                        top if top < 20 => TerrainMaterials::Grass,
+                        _ => TerrainMaterials::Rock,
+                    };
+
+                    for y in 0..=top.max(Self::WATER_LEVEL) {
+                        let occupancy = if y < top {
+                            u8::MAX
+                        } else if y == top {
+                            ((height - height.floor()) * u8::MAX as f32).round() as u8
+                        } else {
+                            0
+                        };
+
+                        let voxel = Voxel {
+                            material: Some(material).filter(|_| occupancy > 0),
+                            occupancy,
+                            water_occupancy: if y <= Self::WATER_LEVEL && occupancy < u8::MAX {
+                                u8::MAX
+                            } else {
+                                0
+                            },
+                        };
+
+                        grid.write_voxel_world(TerrainVec::new(x, y, z), voxel);
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Returns smooth noise between 0 and 1 for the column at `x` and `z`.
+    fn noise(&self, x: i32, z: i32) -> f32 {
+        let scale = Self::NOISE_SCALE;
+        let (cell_x, cell_z) = (x.div_euclid(scale), z.div_euclid(scale));
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let tx = smooth(x.rem_euclid(scale) as f32 / scale as f32);
+        let tz = smooth(z.rem_euclid(scale) as f32 / scale as f32);
+
+        let corner = |dx: i32, dz: i32| {
+            let mut rng =
+                Rng::new(self.seed ^ hash_str(&format!("{},{}", cell_x + dx, cell_z + dz)));
+            rng.next_f32()
+        };
+
+        let bottom = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+        let top = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+
+        bottom + (top - bottom) * tz
+    }
+}
+
+/// Creates a random value of the given kind. Refs point to a random instance
+/// in `instances`, or nothing if it's empty.
+fn random_value(rng: &mut Rng, kind: u64, instances: &[Ref]) -> Variant {
+    match kind {
+        0 => {
+            let length = rng.below(16);
+            let text: String = (0..length)
+                .map(|_| (b'a' + rng.below(26) as u8) as char)
+                .collect();
+            text.into()
+        }
+        1 => (rng.next_u64() & 1 == 1).into(),
+        2 => (rng.next_u64() as i32).into(),
+        3 => (rng.next_u64() as i64).into(),
+        4 => rng.next_f32().into(),
+        5 => f64::from(rng.next_f32() * 1000.0).into(),
+        6 => Vector2::new(rng.next_f32(), rng.next_f32()).into(),
+        7 => random_vector3(rng).into(),
+        8 => Color3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()).into(),
+        9 => CFrame::new(random_vector3(rng), Matrix3::identity()).into(),
+        10 => {
+            let length = rng.below(32);
+            let bytes: Vec<u8> = (0..length).map(|_| rng.next_u64() as u8).collect();
+            BinaryString::from(bytes).into()
+        }
+        _ if instances.is_empty() => Ref::none().into(),
+        _ => instances[rng.below(instances.len())].into(),
+    }
+}
+
+fn random_vector3(rng: &mut Rng) -> Vector3 {
+    Vector3::new(
+        rng.next_f32() * 512.0 - 256.0,
+        rng.next_f32() * 512.0 - 256.0,
+        rng.next_f32() * 512.0 - 256.0,
+    )
+}
+
+/// A small, fast random number generator (SplitMix64). It's not suitable for
+/// anything but tests, but it gives the same numbers on every platform.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number from `0` up to but not including `bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns a number from `0` up to but not including `1`, which is a
+    /// multiple of `1 / 1024`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() % 1024) as f32 / 1024.0
+    }
+}
+
+/// Hashes a string with FNV-1a, which doesn't change between platforms or
+/// Rust versions like the standard library's hasher can.
+fn hash_str(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::DomViewer;
+
+    #[test]
+    fn generators_are_deterministic() {
+        let generator = DomGenerator::new(7).instances(200).max_depth(3);
+        let first = generator.generate();
+        let second = generator.generate();
+
+        assert_eq!(
+            format!("{:?}", DomViewer::new().view(&first)),
+            format!("{:?}", DomViewer::new().view(&second))
+        );
+        assert_ne!(
+            format!("{:?}", DomViewer::new().view(&first)),
+            format!(
+                "{:?}",
+                DomViewer::new().view(&DomGenerator::new(8).generate())
+            )
+        );
+
+        // Nothing is deeper than the maximum depth.
+        for instance in first.descendants() {
+            assert!(first.ancestors(instance.referent()).count() <= 3);
+        }
+
+        let grid = TerrainGenerator::new(7).chunks(5).generate();
+        assert_eq!(grid.chunks().count(), 5);
+        assert_eq!(grid, TerrainGenerator::new(7).chunks(5).generate());
+        assert_ne!(grid, TerrainGenerator::new(8).chunks(5).generate());
+    }
+}
@@ -251,6 +251,43 @@ impl Matrix3 {
         }
     }
 
+    /// Returns the basic rotation that this matrix is within `epsilon` of in
+    /// every component, if there is one.
+    ///
+    /// Basic rotations are the 24 rotations that only point each axis along
+    /// another axis. They can be encoded using only a rotation ID, which is
+    /// much smaller than the full matrix, but only if they are exact. Snapping
+    /// a matrix that is very close to a basic rotation lets it use the compact
+    /// encoding.
+    pub fn snap_to_basic_rotation(&self, epsilon: f32) -> Option<Matrix3> {
+        let snap = |value: f32| {
+            let rounded = value.round();
+            if rounded.abs() <= 1.0 && (value - rounded).abs() <= epsilon {
+                Some(rounded)
+            } else {
+                None
+            }
+        };
+        let snap_vector = |vector: Vector3| {
+            Some(Vector3::new(
+                snap(vector.x)?,
+                snap(vector.y)?,
+                snap(vector.z)?,
+            ))
+        };
+
+        let snapped = Matrix3::new(
+            snap_vector(self.x)?,
+            snap_vector(self.y)?,
+            snap_vector(self.z)?,
+        );
+
+        // Snapping can produce a matrix that isn't a rotation, like one with
+        // two identical rows.
+        let id = snapped.to_basic_rotation_id()?;
+        Matrix3::from_basic_rotation_id(id).ok()
+    }
+
     pub fn from_basic_rotation_id(id: u8) -> Result<Matrix3, Error> {
         match id {
             0x02 => Ok(Matrix3::identity()),
@@ -413,7 +450,7 @@ impl From<Color3uint8> for Color3 {
 ///   colors.
 ///
 /// [BasePart.Color]: https://developer.roblox.com/en-us/api-reference/property/BasePart/Color
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Color3uint8 {
     pub r: u8,
     pub g: u8,
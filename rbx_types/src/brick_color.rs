@@ -72,6 +72,27 @@ macro_rules! make_brick_color {
                     )+
                 }
             }
+
+            /// Finds the BrickColor whose color is closest to the given color.
+            ///
+            /// This is roughly equivalent to `BrickColor.new(color3)` from
+            /// within Roblox.
+            pub fn nearest(color: Color3uint8) -> BrickColor {
+                let distance = |brick_color: &BrickColor| {
+                    let other = brick_color.to_color3uint8();
+                    let dr = i32::from(color.r) - i32::from(other.r);
+                    let dg = i32::from(color.g) - i32::from(other.g);
+                    let db = i32::from(color.b) - i32::from(other.b);
+
+                    dr * dr + dg * dg + db * db
+                };
+
+                [$(BrickColor::$enum,)+]
+                    .iter()
+                    .copied()
+                    .min_by_key(distance)
+                    .unwrap()
+            }
         }
 
         impl fmt::Display for BrickColor {
@@ -345,6 +366,17 @@ mod tests {
     fn from_number() {
         assert_eq!(BrickColor::from_number(1030), Some(BrickColor::PastelBrown));
     }
+
+    #[test]
+    fn nearest() {
+        let pastel_brown = BrickColor::PastelBrown.to_color3uint8();
+        assert_eq!(BrickColor::nearest(pastel_brown), BrickColor::PastelBrown);
+
+        assert_eq!(
+            BrickColor::nearest(Color3uint8::new(250, 5, 5)),
+            BrickColor::ReallyRed
+        );
+    }
 }
 
 #[cfg(all(test, feature = "serde"))]
@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-use crate::{AttributeError, MaterialColorsError, Matrix3Error, UniqueIdError};
+use crate::{AttributeError, MaterialColorsError, Matrix3Error, TerrainError, UniqueIdError};
 
 /// Represents an error that occurred when using a fallible method.
 #[derive(Debug, Error)]
@@ -33,6 +33,14 @@ impl From<MaterialColorsError> for Error {
     }
 }
 
+impl From<TerrainError> for Error {
+    fn from(source: TerrainError) -> Self {
+        Self {
+            source: Box::new(source.into()),
+        }
+    }
+}
+
 impl From<UniqueIdError> for Error {
     fn from(source: UniqueIdError) -> Self {
         Self {
@@ -52,6 +60,9 @@ enum InnerError {
     #[error(transparent)]
     MaterialColors(#[from] MaterialColorsError),
 
+    #[error(transparent)]
+    Terrain(#[from] TerrainError),
+
     #[error(transparent)]
     UniqueId(#[from] UniqueIdError),
 }
@@ -13,11 +13,13 @@ mod faces;
 mod font;
 mod lister;
 mod material_colors;
+mod material_palette;
 mod physical_properties;
 mod referent;
 mod security_capabilities;
 mod shared_string;
 mod tags;
+mod terrain;
 mod unique_id;
 mod variant;
 
@@ -31,10 +33,12 @@ pub use error::*;
 pub use faces::*;
 pub use font::*;
 pub use material_colors::*;
+pub use material_palette::*;
 pub use physical_properties::*;
 pub use referent::*;
 pub use security_capabilities::*;
 pub use shared_string::*;
 pub use tags::*;
+pub use terrain::*;
 pub use unique_id::*;
 pub use variant::*;
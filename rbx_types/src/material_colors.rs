@@ -1,8 +1,11 @@
-use std::{collections::BTreeMap, str::FromStr};
+use std::{collections::BTreeMap, convert::TryFrom, str::FromStr};
 
 use thiserror::Error;
 
-use crate::Color3uint8;
+use crate::{
+    terrain::{FIRST_SOLID_ID, RUN_MATERIAL_MASK},
+    Color3uint8,
+};
 
 use crate::Error as CrateError;
 
@@ -21,7 +24,8 @@ pub struct MaterialColors {
 
 impl MaterialColors {
     /// Constructs a new `MaterialColors` where all colors are their default
-    /// values.
+    /// values. No colors are set, so [`contains`](Self::contains) is `false`
+    /// for every material.
     #[inline]
     pub fn new() -> Self {
         Self {
@@ -29,6 +33,21 @@ impl MaterialColors {
         }
     }
 
+    /// Constructs a `MaterialColors` with every material set to the color
+    /// Studio gives it, which is exactly what the `MaterialColors` property
+    /// of a new `Terrain` instance contains.
+    ///
+    /// This encodes to the same blob as [`MaterialColors::new`], but is equal
+    /// to that blob once it's decoded, and reports every material as set.
+    pub fn studio_default() -> Self {
+        Self {
+            inner: MATERIAL_ORDER
+                .iter()
+                .map(|&material| (material, material.default_color()))
+                .collect(),
+        }
+    }
+
     /// Retrieves the set color for the given material, or the default if
     /// none is set.
     #[inline]
@@ -40,12 +59,34 @@ impl MaterialColors {
         }
     }
 
-    /// Sets the color for the given material.
+    /// Returns whether a color has been set for the given material, instead
+    /// of it using its default color. Every material has a color set in a
+    /// decoded `MaterialColors`.
+    #[inline]
+    pub fn contains(&self, material: TerrainMaterials) -> bool {
+        self.inner.contains_key(&material)
+    }
+
+    /// Sets the color for the given material. Colors can't be stored for
+    /// `TerrainMaterials::Unknown`, so they're ignored.
     #[inline]
     pub fn set_color(&mut self, material: TerrainMaterials, color: Color3uint8) {
+        if let TerrainMaterials::Unknown(_) = material {
+            return;
+        }
+
         self.inner.insert(material, color);
     }
 
+    /// Returns an iterator over every material and its color, including
+    /// materials that are using their default color. Materials are returned
+    /// in the order they are encoded in.
+    pub fn iter(&self) -> impl Iterator<Item = (TerrainMaterials, Color3uint8)> + '_ {
+        MATERIAL_ORDER
+            .iter()
+            .map(move |&material| (material, self.get_color(material)))
+    }
+
     /// Encodes the `MaterialColors` into a binary blob that can be understood
     /// by Roblox.
     pub fn encode(&self) -> Vec<u8> {
@@ -100,6 +141,55 @@ pub(crate) enum MaterialColorsError {
     /// TerrainMaterial.
     #[error("cannot convert `{0}` into TerrainMaterial")]
     UnknownMaterial(String),
+    /// The argument provided to `try_from` was not the `SmoothGrid` ID of a
+    /// TerrainMaterial.
+    #[error("cannot convert {0} into TerrainMaterial (expected it to be from 2 to 22)")]
+    UnknownMaterialId(u8),
+    /// The argument provided to `UnknownMaterial::try_from` belonged to a
+    /// known material, or can't be stored in a `SmoothGrid`.
+    #[error("{0} is not the ID of an unknown material (expected it to be from 23 to 31)")]
+    NotUnknownMaterialId(u8),
+}
+
+/// The ID of a solid material this crate doesn't know about yet, held by
+/// [`TerrainMaterials::Unknown`]. IDs are numbered the same way as in the
+/// `SmoothGrid` format, and are always past the last known material, so
+/// every ID can be written back to a `SmoothGrid` blob.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub struct UnknownMaterial(pub(crate) u8);
+
+impl UnknownMaterial {
+    /// Returns the material's ID in the `SmoothGrid` format.
+    pub fn id(&self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for UnknownMaterial {
+    type Error = CrateError;
+
+    /// Converts a `SmoothGrid` material ID into an `UnknownMaterial`. IDs of
+    /// known materials are rejected, along with IDs too large to be written.
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        let first = FIRST_SOLID_ID + MATERIAL_ORDER.len() as u8;
+
+        if (first..=RUN_MATERIAL_MASK).contains(&id) {
+            Ok(Self(id))
+        } else {
+            Err(MaterialColorsError::NotUnknownMaterialId(id).into())
+        }
+    }
+}
+
+impl From<UnknownMaterial> for u8 {
+    fn from(material: UnknownMaterial) -> Self {
+        material.0
+    }
 }
 
 /// Constructs an enum named `TerrainMaterials` for all values contained in
@@ -116,27 +206,49 @@ macro_rules! material_colors {
 
         /// A list of all `TerrainMaterials` in the order they must be read
         /// and written.
-        const MATERIAL_ORDER: [TerrainMaterials; 21] = [$(TerrainMaterials::$name,)*];
+        pub(crate) const MATERIAL_ORDER: [TerrainMaterials; 21] = TerrainMaterials::ALL;
 
         /// All materials that are represented by `MaterialColors`.
+        ///
+        /// Roblox adds materials from time to time, so this enum may gain
+        /// more variants without a breaking change.
         #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
         #[cfg_attr(
             feature = "serde",
             derive(serde::Serialize, serde::Deserialize),
         )]
+        #[non_exhaustive]
         pub enum TerrainMaterials {
             $(
                 $name,
             )*
+            /// A material this crate doesn't know about yet, like one added to
+            /// Roblox after this version was released. These are created by
+            /// [`SmoothGrid::decode`][crate::SmoothGrid::decode], and are
+            /// written back with the same ID.
+            ///
+            /// Unknown materials have no color in `MaterialColors`.
+            Unknown(UnknownMaterial),
         }
 
         impl TerrainMaterials {
+            /// Every known `TerrainMaterial`, in the same order they're stored
+            /// in `MaterialColors`.
+            pub const ALL: [TerrainMaterials; 21] = [$(TerrainMaterials::$name,)*];
+
+            /// Returns an iterator over every known `TerrainMaterial`, in the same
+            /// order as [`TerrainMaterials::ALL`].
+            pub fn iter() -> impl Iterator<Item = TerrainMaterials> {
+                Self::ALL.iter().copied()
+            }
+
             /// Returns the default color for the given `TerrainMaterial`.
             pub fn default_color(&self) -> Color3uint8 {
                 match self {
                     $(
                         Self::$name => Color3uint8::new($r, $g, $b),
                     )*
+                    Self::Unknown(_) => Color3uint8::new(127, 127, 127),
                 }
             }
         }
@@ -144,14 +256,35 @@ macro_rules! material_colors {
         impl FromStr for TerrainMaterials {
             type Err = CrateError;
 
+            /// Parses a `TerrainMaterial` from its name, like `Grass`, or from
+            /// the name of the matching `Enum.Material` item, like
+            /// `Enum.Material.Grass`. Names are case-sensitive.
             fn from_str(s: &str) -> Result<Self, Self::Err> {
-                match s {$(
+                let name = s.strip_prefix("Enum.Material.").unwrap_or(s);
+
+                match name {$(
                     stringify!($name) => Ok(Self::$name),
                 )*
                     _ => Err(MaterialColorsError::UnknownMaterial(s.to_string()).into()),
                 }
             }
         }
+
+        impl TryFrom<u8> for TerrainMaterials {
+            type Error = CrateError;
+
+            /// Converts the ID of a `TerrainMaterial` in the `SmoothGrid`
+            /// format into that material. These are the same IDs used by
+            /// [`Volume::material`][crate::Volume::material], where `0` is air,
+            /// `1` is water, and solid materials are numbered from `2` in the
+            /// order of [`TerrainMaterials::ALL`].
+            fn try_from(id: u8) -> Result<Self, Self::Error> {
+                id.checked_sub(FIRST_SOLID_ID)
+                    .and_then(|index| Self::ALL.get(index as usize))
+                    .copied()
+                    .ok_or_else(|| MaterialColorsError::UnknownMaterialId(id).into())
+            }
+        }
     };
 }
 
@@ -235,6 +368,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn studio_default() {
+        let blob = base64::decode("AAAAAAAAan8/P39rf2Y/ilY+j35fi21PZmxvZbDqw8faiVpHOi4kHh4lZlw76JxKc3trhHtagcLgc4RKxr21zq2UlJSM").unwrap();
+        let colors = MaterialColors::studio_default();
+
+        assert_eq!(colors, MaterialColors::decode(&blob).unwrap());
+        assert_eq!(colors.encode(), blob);
+        assert!(MATERIAL_ORDER
+            .iter()
+            .all(|&material| colors.contains(material)));
+    }
+
     #[test]
     fn decode_sequential() {
         use std::convert::TryFrom;
@@ -282,6 +427,21 @@ mod test {
         assert_eq!(blob, "AAAAAAAAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg5Ojs8PT4/");
     }
 
+    #[test]
+    fn round_trip() {
+        let mut colors = MaterialColors::new();
+        colors.set_color(TerrainMaterials::Snow, Color3uint8::new(1, 2, 3));
+        colors.set_color(TerrainMaterials::Pavement, Color3uint8::new(4, 5, 6));
+
+        let decoded = MaterialColors::decode(&colors.encode()).unwrap();
+        assert!(decoded.iter().eq(colors.iter()));
+        assert_eq!(decoded.iter().count(), MATERIAL_ORDER.len());
+        assert_eq!(
+            decoded.get_color(TerrainMaterials::Snow),
+            Color3uint8::new(1, 2, 3)
+        );
+    }
+
     #[test]
     fn from_str_materials() {
         assert!(TerrainMaterials::from_str("Grass").is_ok());
@@ -290,9 +450,40 @@ mod test {
         assert!(TerrainMaterials::from_str("Asphalt").is_ok());
         assert!(TerrainMaterials::from_str("Salt").is_ok());
         assert!(TerrainMaterials::from_str("Pavement").is_ok());
+        assert_eq!(
+            TerrainMaterials::from_str("Enum.Material.LeafyGrass").unwrap(),
+            TerrainMaterials::LeafyGrass
+        );
 
         assert!(TerrainMaterials::from_str("A name I am certain Roblox will never add").is_err());
         // `from_str` is case-sensitive
         assert!(TerrainMaterials::from_str("gRaSs").is_err());
+        assert!(TerrainMaterials::from_str("Enum.Material.Plastic").is_err());
+    }
+
+    #[test]
+    fn materials_from_id() {
+        for (index, material) in TerrainMaterials::iter().enumerate() {
+            assert_eq!(
+                TerrainMaterials::try_from(index as u8 + 2).unwrap(),
+                material
+            );
+        }
+
+        assert_eq!(TerrainMaterials::iter().count(), 21);
+        assert!(TerrainMaterials::try_from(0).is_err());
+        assert!(TerrainMaterials::try_from(1).is_err());
+        assert!(TerrainMaterials::try_from(23).is_err());
+    }
+
+    #[test]
+    fn unknown_material_ids() {
+        assert_eq!(UnknownMaterial::try_from(23).unwrap().id(), 23);
+        assert_eq!(UnknownMaterial::try_from(31).unwrap().id(), 31);
+
+        // Known materials and IDs that can't be written aren't unknown.
+        assert!(UnknownMaterial::try_from(1).is_err());
+        assert!(UnknownMaterial::try_from(22).is_err());
+        assert!(UnknownMaterial::try_from(32).is_err());
     }
 }
@@ -0,0 +1,195 @@
+use std::{borrow::Borrow, collections::BTreeMap, ops::Index};
+
+use crate::{Color3uint8, MaterialColors, TerrainMaterials};
+
+/// Maps names like `"cliff"` or `"path"` to terrain materials and,
+/// optionally, the colors those materials should have.
+///
+/// Terrain tools can fill with `palette["cliff"]` instead of a specific
+/// material, so that the same tool can build terrain with a different theme
+/// just by being given a different palette.
+///
+/// ```
+/// use rbx_types::{
+///     Color3uint8, MaterialPalette, SmoothGrid, TerrainMaterials, TerrainVec, Vector3,
+/// };
+///
+/// let desert = MaterialPalette::new()
+///     .with("ground", TerrainMaterials::Sand)
+///     .with_color("cliff", TerrainMaterials::Sandstone, Color3uint8::new(190, 120, 80));
+///
+/// let mut grid = SmoothGrid::new();
+/// grid.fill_ball(Vector3::new(0.0, 0.0, 0.0), 8.0, desert["cliff"]);
+///
+/// let voxel = grid.get_voxel_world(TerrainVec::new(0, 0, 0)).unwrap();
+/// assert_eq!(voxel.material, Some(TerrainMaterials::Sandstone));
+///
+/// let colors = desert.material_colors();
+/// assert_eq!(
+///     colors.get_color(TerrainMaterials::Sandstone),
+///     Color3uint8::new(190, 120, 80),
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct MaterialPalette {
+    entries: BTreeMap<String, PaletteEntry>,
+}
+
+/// A single named material in a [`MaterialPalette`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct PaletteEntry {
+    /// The material used for this name.
+    pub material: TerrainMaterials,
+
+    /// The color the material should have, or `None` to leave it as it is.
+    pub color: Option<Color3uint8>,
+}
+
+impl MaterialPalette {
+    /// Creates an empty `MaterialPalette`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the entry with the given name.
+    pub fn get<K: Borrow<str>>(&self, name: K) -> Option<&PaletteEntry> {
+        self.entries.get(name.borrow())
+    }
+
+    /// Returns the material with the given name.
+    pub fn material<K: Borrow<str>>(&self, name: K) -> Option<TerrainMaterials> {
+        self.get(name).map(|entry| entry.material)
+    }
+
+    /// Inserts an entry with the given name.
+    /// Will return the entry that used to be there if one existed.
+    pub fn insert(&mut self, name: String, entry: PaletteEntry) -> Option<PaletteEntry> {
+        self.entries.insert(name, entry)
+    }
+
+    /// Inserts a material with the given name, without changing its color.
+    /// Will overwrite the entry that used to be there if one existed.
+    pub fn with<K: Into<String>>(mut self, name: K, material: TerrainMaterials) -> Self {
+        self.entries.insert(
+            name.into(),
+            PaletteEntry {
+                material,
+                color: None,
+            },
+        );
+        self
+    }
+
+    /// Inserts a material with the given name and color.
+    /// Will overwrite the entry that used to be there if one existed.
+    pub fn with_color<K: Into<String>>(
+        mut self,
+        name: K,
+        material: TerrainMaterials,
+        color: Color3uint8,
+    ) -> Self {
+        self.entries.insert(
+            name.into(),
+            PaletteEntry {
+                material,
+                color: Some(color),
+            },
+        );
+        self
+    }
+
+    /// Removes the entry with the given name.
+    /// Will return the entry that was there if one existed.
+    pub fn remove<K: Borrow<str>>(&mut self, name: K) -> Option<PaletteEntry> {
+        self.entries.remove(name.borrow())
+    }
+
+    /// Returns an iterator over every name and its entry, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PaletteEntry)> + '_ {
+        self.entries
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry))
+    }
+
+    /// Returns the number of entries in the palette.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the palette has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sets the color of every material in the palette that has one. If two
+    /// entries give the same material different colors, the entry whose name
+    /// sorts last wins.
+    pub fn apply_colors(&self, colors: &mut MaterialColors) {
+        for entry in self.entries.values() {
+            if let Some(color) = entry.color {
+                colors.set_color(entry.material, color);
+            }
+        }
+    }
+
+    /// Returns a `MaterialColors` with the colors from the palette and the
+    /// default color for every other material, suitable for the
+    /// `MaterialColors` property of `Terrain`.
+    pub fn material_colors(&self) -> MaterialColors {
+        let mut colors = MaterialColors::new();
+        self.apply_colors(&mut colors);
+        colors
+    }
+}
+
+impl<K: Borrow<str>> Index<K> for MaterialPalette {
+    type Output = TerrainMaterials;
+
+    /// Returns the material with the given name.
+    ///
+    /// ## Panics
+    /// Panics if the palette has no entry with the given name.
+    fn index(&self, name: K) -> &TerrainMaterials {
+        let name = name.borrow();
+
+        match self.entries.get(name) {
+            Some(entry) => &entry.material,
+            None => panic!("material palette has no entry named {:?}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn apply_colors() {
+        let red = Color3uint8::new(255, 0, 0);
+        let blue = Color3uint8::new(0, 0, 255);
+
+        let palette = MaterialPalette::new()
+            .with("ground", TerrainMaterials::Grass)
+            .with_color("a", TerrainMaterials::Rock, red)
+            .with_color("b", TerrainMaterials::Rock, blue);
+
+        assert_eq!(palette["ground"], TerrainMaterials::Grass);
+        assert_eq!(palette.material("missing"), None);
+
+        let mut colors = MaterialColors::new();
+        palette.apply_colors(&mut colors);
+
+        assert_eq!(colors.get_color(TerrainMaterials::Rock), blue);
+        assert!(!colors.contains(TerrainMaterials::Grass));
+    }
+}
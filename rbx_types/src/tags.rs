@@ -51,6 +51,14 @@ impl Tags {
         self.members.join("\0").into_bytes()
     }
 
+    /// Encodes tags into a buffer like [`Tags::encode`], but with the tags
+    /// sorted so that the output does not depend on the order they were added.
+    pub fn encode_sorted(&self) -> Vec<u8> {
+        let mut members: Vec<&str> = self.members.iter().map(String::as_str).collect();
+        members.sort_unstable();
+        members.join("\0").into_bytes()
+    }
+
     /// Returns the number of strings stored within this `Tags`.
     pub fn len(&self) -> usize {
         self.members.len()
@@ -109,6 +117,14 @@ mod test {
         assert_eq!(tags.encode(), value);
     }
 
+    #[test]
+    fn encode_sorted() {
+        let tags = Tags::from(vec!["pz".to_owned(), "ez".to_owned()]);
+
+        assert_eq!(tags.encode(), b"pz\0ez");
+        assert_eq!(tags.encode_sorted(), b"ez\0pz");
+    }
+
     #[test]
     fn decode_empty() {
         let input = b"";
@@ -0,0 +1,5769 @@
+use std::{
+    collections::{btree_map, BTreeMap, BTreeSet},
+    convert::TryFrom,
+    io::{self, Read, Write},
+    iter::Enumerate,
+    mem,
+    ops::{Add, Sub},
+    slice, vec,
+};
+
+use thiserror::Error;
+
+use crate::{
+    material_colors::MATERIAL_ORDER, Axes, Error as CrateError, Region3, TerrainMaterials,
+    UnknownMaterial, Vector3,
+};
+
+mod brush;
+mod lenient;
+mod locks;
+#[cfg(feature = "terrain_mesh")]
+pub mod mesh;
+mod region;
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+pub use brush::{Brush, BrushShape, Falloff};
+pub use lenient::{DecodeWarning, RecoveredGrid};
+pub use locks::{ChunkLock, ChunkLocks, LockConflict};
+pub use region::TerrainRegionData;
+
+/// The farthest from the origin, in voxels, that Roblox allows terrain along
+/// any axis.
+const MAX_VOXEL_COORDINATE: i32 = 1 << 23;
+
+/// The number of voxels along each side of a chunk.
+pub const CHUNK_SIZE: usize = 32;
+
+/// The length of each side of a voxel, in studs.
+pub const VOXEL_SIZE: f32 = 4.0;
+
+/// The number of voxels contained in a chunk.
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+/// The first byte of every `SmoothGrid` blob.
+const SMOOTH_GRID_VERSION: u8 = 1;
+
+/// The first byte of `SmoothGrid` blobs written by older versions of Roblox.
+/// These are the same as version 1 blobs, except that voxels can't have both
+/// a solid material and water.
+const LEGACY_SMOOTH_GRID_VERSION: u8 = 0;
+
+/// The second byte of every `SmoothGrid` blob, which is the base 2 logarithm
+/// of `CHUNK_SIZE`.
+const CHUNK_SIZE_LOG2: u8 = 5;
+
+/// The base 2 logarithm of the largest chunks that can be read from or written
+/// to a blob. Roblox only uses `CHUNK_SIZE_LOG2`, so larger chunks are only
+/// rejected to keep the memory used by a single chunk reasonable.
+const MAX_CHUNK_SIZE_LOG2: u8 = 7;
+
+/// The voxel material ID used for empty voxels.
+const AIR_ID: u8 = 0;
+
+/// The voxel material ID used for voxels that only contain water.
+const WATER_ID: u8 = 1;
+
+/// Solid materials are numbered after air and water, in `MATERIAL_ORDER`.
+pub(crate) const FIRST_SOLID_ID: u8 = 2;
+
+pub(crate) const RUN_MATERIAL_MASK: u8 = 0b0001_1111;
+const RUN_HAS_WATER: u8 = 0b0010_0000;
+const RUN_HAS_OCCUPANCY: u8 = 0b0100_0000;
+const RUN_HAS_COUNT: u8 = 0b1000_0000;
+
+/// The maximum number of voxels a single run can describe.
+const MAX_RUN_LENGTH: usize = 256;
+
+/// The number of chunks encoded at once by the `rayon` feature, which limits
+/// how many encoded chunks are held in memory before being written.
+#[cfg(feature = "rayon")]
+const PARALLEL_ENCODE_BATCH: usize = 1024;
+
+/// The offsets of every voxel in the 3×3×3 block around a voxel, including
+/// the voxel itself.
+const NEIGHBORHOOD: [(i32, i32, i32); 27] = {
+    let mut offsets = [(0, 0, 0); 27];
+    let mut index = 0;
+    while index < 27 {
+        offsets[index] = (
+            index as i32 % 3 - 1,
+            index as i32 / 3 % 3 - 1,
+            index as i32 / 9 - 1,
+        );
+        index += 1;
+    }
+    offsets
+};
+
+/// Returned for voxels in chunks that have not been written.
+static EMPTY_VOXEL: Voxel = Voxel {
+    material: None,
+    occupancy: 0,
+    water_occupancy: 0,
+};
+
+/// A single voxel of smooth terrain.
+///
+/// The default `Voxel` is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Voxel {
+    /// The solid material in this voxel, or `None` if the voxel only contains
+    /// air or water.
+    pub material: Option<TerrainMaterials>,
+
+    /// How much of the voxel is filled by `material`, where `255` is
+    /// completely full. Ignored if `material` is `None`.
+    pub occupancy: u8,
+
+    /// How much of the voxel is filled by water, where `255` is completely
+    /// full.
+    pub water_occupancy: u8,
+}
+
+impl Voxel {
+    /// Creates a voxel completely filled with the given material.
+    pub fn solid(material: TerrainMaterials) -> Self {
+        Self {
+            material: Some(material),
+            occupancy: u8::MAX,
+            water_occupancy: 0,
+        }
+    }
+
+    /// Creates a voxel that only contains water.
+    pub fn water(water_occupancy: u8) -> Self {
+        Self {
+            material: None,
+            occupancy: 0,
+            water_occupancy,
+        }
+    }
+
+    /// Returns `true` if this voxel contains no material or water.
+    pub fn is_empty(&self) -> bool {
+        self.water_occupancy == 0 && (self.material.is_none() || self.occupancy == 0)
+    }
+}
+
+/// The position of a voxel within a [`Chunk`]. Each component is between `0`
+/// and `31` inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct VoxelCoordinates {
+    x: u8,
+    y: u8,
+    z: u8,
+}
+
+impl VoxelCoordinates {
+    /// Creates a new `VoxelCoordinates`, returning an error if any component
+    /// is outside of a chunk instead of clamping it like
+    /// [`VoxelCoordinates::new`].
+    pub fn try_new(x: u8, y: u8, z: u8) -> Result<Self, CrateError> {
+        if [x, y, z]
+            .iter()
+            .any(|&component| usize::from(component) >= CHUNK_SIZE)
+        {
+            return Err(TerrainError::InvalidVoxelCoordinates(x, y, z).into());
+        }
+
+        Ok(Self { x, y, z })
+    }
+
+    /// Creates a new `VoxelCoordinates`, clamping each component to the size
+    /// of a chunk.
+    pub fn new(x: u8, y: u8, z: u8) -> Self {
+        let max = (CHUNK_SIZE - 1) as u8;
+
+        Self {
+            x: x.min(max),
+            y: y.min(max),
+            z: z.min(max),
+        }
+    }
+
+    /// The X component of these coordinates.
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    /// The Y component of these coordinates.
+    pub fn y(&self) -> u8 {
+        self.y
+    }
+
+    /// The Z component of these coordinates.
+    pub fn z(&self) -> u8 {
+        self.z
+    }
+
+    /// Returns these coordinates moved by the given number of voxels along
+    /// each axis, or `None` if that would leave the chunk.
+    pub fn offset(&self, x: i32, y: i32, z: i32) -> Option<Self> {
+        let move_by = |component: u8, delta: i32| {
+            i32::from(component)
+                .checked_add(delta)
+                .and_then(|moved| u8::try_from(moved).ok())
+                .filter(|&moved| usize::from(moved) < CHUNK_SIZE)
+        };
+
+        Some(Self {
+            x: move_by(self.x, x)?,
+            y: move_by(self.y, y)?,
+            z: move_by(self.z, z)?,
+        })
+    }
+
+    /// Voxels are stored with X changing fastest, then Z, then Y.
+    fn index(&self) -> usize {
+        self.x as usize + self.z as usize * CHUNK_SIZE + self.y as usize * CHUNK_SIZE * CHUNK_SIZE
+    }
+
+    /// The inverse of `index`.
+    fn from_index(index: usize) -> Self {
+        Self {
+            x: (index % CHUNK_SIZE) as u8,
+            z: (index / CHUNK_SIZE % CHUNK_SIZE) as u8,
+            y: (index / (CHUNK_SIZE * CHUNK_SIZE)) as u8,
+        }
+    }
+}
+
+/// The position of a [`Chunk`] within a [`SmoothGrid`], measured in chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ChunkCoordinates {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl ChunkCoordinates {
+    /// Creates a new `ChunkCoordinates`.
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Creates a new `ChunkCoordinates`, returning an error if the chunk is
+    /// outside of the range Roblox allows terrain to be placed in.
+    pub fn try_new(x: i32, y: i32, z: i32) -> Result<Self, CrateError> {
+        let position = Self { x, y, z };
+
+        if position.is_in_range() {
+            Ok(position)
+        } else {
+            Err(TerrainError::ChunkOutOfRange(position).into())
+        }
+    }
+
+    /// Returns whether every voxel in the chunk is within the range Roblox
+    /// allows terrain to be placed in.
+    fn is_in_range(&self) -> bool {
+        self.is_in_range_for(CHUNK_SIZE_LOG2)
+    }
+
+    /// Returns whether every voxel in the chunk is within the range Roblox
+    /// allows terrain to be placed in, for chunks that are
+    /// `2^chunk_size_log2` voxels on each side.
+    fn is_in_range_for(&self, chunk_size_log2: u8) -> bool {
+        let size = 1i64 << chunk_size_log2;
+
+        [self.x, self.y, self.z].iter().all(|&component| {
+            let lowest = i64::from(component) * size;
+            lowest >= -i64::from(MAX_VOXEL_COORDINATE)
+                && lowest + size <= i64::from(MAX_VOXEL_COORDINATE)
+        })
+    }
+
+    /// Returns whether the position of every voxel in the chunk fits in a
+    /// [`TerrainVec`].
+    fn fits_in_terrain_vec(&self) -> bool {
+        let last = (CHUNK_SIZE - 1) as u8;
+
+        TerrainVec::try_from_chunk(*self, VoxelCoordinates::default()).is_ok()
+            && TerrainVec::try_from_chunk(*self, VoxelCoordinates::new(last, last, last)).is_ok()
+    }
+
+    /// Returns the position of the chunk containing the given point in world
+    /// space, measured in studs.
+    pub fn from_world(position: Vector3) -> Self {
+        TerrainVec::from_world(position).chunk_coordinates()
+    }
+}
+
+/// The position of a voxel within a [`SmoothGrid`], measured in voxels.
+///
+/// This is the same position Roblox uses for voxels in world space, divided by
+/// the size of a voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct TerrainVec {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl TerrainVec {
+    /// Creates a new `TerrainVec`.
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Creates a `TerrainVec` from the position of a chunk and the position
+    /// of a voxel within that chunk.
+    ///
+    /// Chunks can be far enough from the origin that their voxels' positions
+    /// don't fit in an `i32`. Those components are clamped to the range of an
+    /// `i32` instead. Use [`TerrainVec::try_from_chunk`] to catch them.
+    pub fn from_chunk(chunk: ChunkCoordinates, voxel: VoxelCoordinates) -> Self {
+        let component = |chunk: i32, voxel: u8| {
+            let position = i64::from(chunk) * CHUNK_SIZE as i64 + i64::from(voxel);
+            position.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+        };
+
+        Self {
+            x: component(chunk.x, voxel.x),
+            y: component(chunk.y, voxel.y),
+            z: component(chunk.z, voxel.z),
+        }
+    }
+
+    /// Creates a `TerrainVec` in the same way as [`TerrainVec::from_chunk`],
+    /// returning an error if the voxel's position doesn't fit in an `i32`
+    /// instead of clamping it.
+    pub fn try_from_chunk(
+        chunk: ChunkCoordinates,
+        voxel: VoxelCoordinates,
+    ) -> Result<Self, CrateError> {
+        let component = |chunk: i32, voxel: u8| {
+            chunk
+                .checked_mul(CHUNK_SIZE as i32)?
+                .checked_add(i32::from(voxel))
+        };
+
+        match (
+            component(chunk.x, voxel.x),
+            component(chunk.y, voxel.y),
+            component(chunk.z, voxel.z),
+        ) {
+            (Some(x), Some(y), Some(z)) => Ok(Self { x, y, z }),
+            _ => Err(TerrainError::ChunkOutOfRange(chunk).into()),
+        }
+    }
+
+    /// Returns the position of the voxel containing the given point in world
+    /// space, measured in studs.
+    ///
+    /// Points are rounded down, so a point on the boundary between two voxels
+    /// belongs to the higher of the two, and `-0.5` belongs to the voxel at `-1`.
+    pub fn from_world(position: Vector3) -> Self {
+        let to_voxel = |value: f32| (value / VOXEL_SIZE).floor() as i32;
+
+        Self::new(
+            to_voxel(position.x),
+            to_voxel(position.y),
+            to_voxel(position.z),
+        )
+    }
+
+    /// Returns the center of this voxel in world space, measured in studs.
+    pub fn world_center(&self) -> Vector3 {
+        Vector3::new(
+            (self.x as f32 + 0.5) * VOXEL_SIZE,
+            (self.y as f32 + 0.5) * VOXEL_SIZE,
+            (self.z as f32 + 0.5) * VOXEL_SIZE,
+        )
+    }
+
+    /// Returns the position of the chunk containing this voxel.
+    pub fn chunk_coordinates(&self) -> ChunkCoordinates {
+        let size = CHUNK_SIZE as i32;
+
+        ChunkCoordinates::new(
+            self.x.div_euclid(size),
+            self.y.div_euclid(size),
+            self.z.div_euclid(size),
+        )
+    }
+
+    /// Returns the position of this voxel within its chunk.
+    pub fn voxel_coordinates(&self) -> VoxelCoordinates {
+        let size = CHUNK_SIZE as i32;
+
+        VoxelCoordinates::new(
+            self.x.rem_euclid(size) as u8,
+            self.y.rem_euclid(size) as u8,
+            self.z.rem_euclid(size) as u8,
+        )
+    }
+}
+
+/// Implements arithmetic and stepping between neighbors for coordinate types
+/// with `i32` components.
+macro_rules! coordinate_ops {
+    ($($name:ident),*) => {
+        $(
+            impl $name {
+                /// Returns these coordinates moved by the given amount along
+                /// each axis, or `None` if any component would overflow.
+                pub fn offset(&self, x: i32, y: i32, z: i32) -> Option<Self> {
+                    Some(Self::new(
+                        self.x.checked_add(x)?,
+                        self.y.checked_add(y)?,
+                        self.z.checked_add(z)?,
+                    ))
+                }
+
+                /// Returns the six coordinates that share a face with these,
+                /// in the order +X, -X, +Y, -Y, +Z, -Z. Neighbors past the
+                /// limits of an `i32` are `None`.
+                pub fn neighbors(&self) -> [Option<Self>; 6] {
+                    [
+                        self.offset(1, 0, 0),
+                        self.offset(-1, 0, 0),
+                        self.offset(0, 1, 0),
+                        self.offset(0, -1, 0),
+                        self.offset(0, 0, 1),
+                        self.offset(0, 0, -1),
+                    ]
+                }
+            }
+
+            /// Adds each component. Like adding `i32`s, this panics on
+            /// overflow in debug builds and wraps in release builds. Use
+            /// `offset` to check for overflow instead.
+            impl Add for $name {
+                type Output = Self;
+
+                fn add(self, other: Self) -> Self {
+                    Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+                }
+            }
+
+            /// Subtracts each component. Like subtracting `i32`s, this
+            /// panics on overflow in debug builds and wraps in release builds.
+            impl Sub for $name {
+                type Output = Self;
+
+                fn sub(self, other: Self) -> Self {
+                    Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+                }
+            }
+        )*
+    };
+}
+
+coordinate_ops!(TerrainVec, ChunkCoordinates);
+
+/// A cube of voxels, `CHUNK_SIZE` voxels along each side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Every voxel in the chunk, indexed by `VoxelCoordinates::index`. This
+    /// always holds exactly `CHUNK_VOLUME` voxels.
+    voxels: Box<[Voxel]>,
+}
+
+impl Chunk {
+    /// Creates a new chunk where every voxel is empty.
+    pub fn new() -> Self {
+        Self::new_with_base(Voxel::default())
+    }
+
+    /// Creates a new chunk where every voxel is `base`.
+    pub fn new_with_base(base: Voxel) -> Self {
+        Self {
+            voxels: vec![base; CHUNK_VOLUME].into_boxed_slice(),
+        }
+    }
+
+    /// Returns the voxel at the given position.
+    pub fn get_voxel(&self, position: VoxelCoordinates) -> &Voxel {
+        &self.voxels[position.index()]
+    }
+
+    /// Replaces the voxel at the given position.
+    pub fn write_voxel(&mut self, position: VoxelCoordinates, voxel: Voxel) {
+        self.voxels[position.index()] = voxel;
+    }
+
+    /// Replaces every voxel in the chunk with the given material, filled to
+    /// `occupancy`. Any water in the chunk is removed.
+    pub fn fill(&mut self, material: TerrainMaterials, occupancy: u8) {
+        self.voxels.fill(Voxel {
+            material: Some(material),
+            occupancy,
+            water_occupancy: 0,
+        });
+    }
+
+    /// Creates a chunk from one value per voxel for each part of a voxel, in
+    /// the same layout returned by [`Chunk::material_channel`],
+    /// [`Chunk::occupancy_channel`], and [`Chunk::water_channel`].
+    ///
+    /// ## Panics
+    /// Panics if any of the channels doesn't have exactly one value for each
+    /// voxel in a chunk.
+    pub fn from_channels(
+        material: &[Option<TerrainMaterials>],
+        occupancy: &[u8],
+        water: &[u8],
+    ) -> Self {
+        assert!(
+            material.len() == CHUNK_VOLUME
+                && occupancy.len() == CHUNK_VOLUME
+                && water.len() == CHUNK_VOLUME,
+            "chunk channels must have {} values each",
+            CHUNK_VOLUME
+        );
+
+        let voxels = material
+            .iter()
+            .zip(occupancy)
+            .zip(water)
+            .map(|((&material, &occupancy), &water_occupancy)| Voxel {
+                material,
+                occupancy,
+                water_occupancy,
+            })
+            .collect();
+
+        Self { voxels }
+    }
+
+    /// Returns the material of every voxel in the chunk, including empty
+    /// ones, as one dense array of `CHUNK_SIZE`³ values.
+    ///
+    /// Values are in the order voxels are stored, with X changing fastest,
+    /// then Z, then Y, so the voxel at `(x, y, z)` is at index
+    /// `x + z * CHUNK_SIZE + y * CHUNK_SIZE * CHUNK_SIZE`. Voxels are stored
+    /// together, so this is a copy.
+    pub fn material_channel(&self) -> Vec<Option<TerrainMaterials>> {
+        self.voxels.iter().map(|voxel| voxel.material).collect()
+    }
+
+    /// Returns the occupancy of every voxel in the chunk, in the same layout
+    /// as [`Chunk::material_channel`].
+    pub fn occupancy_channel(&self) -> Vec<u8> {
+        self.voxels.iter().map(|voxel| voxel.occupancy).collect()
+    }
+
+    /// Returns the water occupancy of every voxel in the chunk, in the same
+    /// layout as [`Chunk::material_channel`].
+    pub fn water_channel(&self) -> Vec<u8> {
+        self.voxels
+            .iter()
+            .map(|voxel| voxel.water_occupancy)
+            .collect()
+    }
+
+    /// Encodes the voxels of the chunk the same way they're stored in a
+    /// [`SmoothGrid`] blob, without the chunk's position, replacing the
+    /// contents of `buffer`. The buffer's allocation is reused, so chunks that
+    /// change often can be encoded without allocating each time.
+    pub fn encode_into(&self, buffer: &mut Vec<u8>) {
+        buffer.clear();
+        encode_chunk_voxels(buffer, &self.voxels, EncodeOptions::default());
+    }
+
+    /// Returns an iterator over every voxel in the chunk that isn't empty,
+    /// along with its position. Voxels are visited in the order they are
+    /// stored, with X changing fastest, then Z, then Y.
+    pub fn iter_voxels(&self) -> ChunkVoxels<'_> {
+        ChunkVoxels {
+            inner: self.voxels.iter().enumerate(),
+        }
+    }
+
+    /// Returns an iterator over mutable references to every voxel in the
+    /// chunk that isn't empty, along with its position, in the same order as
+    /// [`Chunk::iter_voxels`].
+    pub fn iter_voxels_mut(&mut self) -> ChunkVoxelsMut<'_> {
+        ChunkVoxelsMut {
+            inner: self.voxels.iter_mut().enumerate(),
+        }
+    }
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An iterator over the voxels in a [`Chunk`] that aren't empty, created by
+/// [`Chunk::iter_voxels`].
+#[derive(Debug, Clone)]
+pub struct ChunkVoxels<'a> {
+    inner: Enumerate<slice::Iter<'a, Voxel>>,
+}
+
+impl<'a> Iterator for ChunkVoxels<'a> {
+    type Item = (VoxelCoordinates, &'a Voxel);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .find(|(_, voxel)| !voxel.is_empty())
+            .map(|(index, voxel)| (VoxelCoordinates::from_index(index), voxel))
+    }
+}
+
+/// A mutable iterator over the voxels in a [`Chunk`] that aren't empty,
+/// created by [`Chunk::iter_voxels_mut`].
+#[derive(Debug)]
+pub struct ChunkVoxelsMut<'a> {
+    inner: Enumerate<slice::IterMut<'a, Voxel>>,
+}
+
+impl<'a> Iterator for ChunkVoxelsMut<'a> {
+    type Item = (VoxelCoordinates, &'a mut Voxel);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .find(|(_, voxel)| !voxel.is_empty())
+            .map(|(index, voxel)| (VoxelCoordinates::from_index(index), voxel))
+    }
+}
+
+/// Represents the voxel data of Roblox's smooth terrain, as stored in the
+/// `Terrain.SmoothGrid` property.
+///
+/// ## Serde
+/// With the `serde` feature, formats that aren't human-readable store the
+/// same blob as [`SmoothGrid::encode`]. Human-readable formats like JSON get a
+/// versioned form meant to be kept in source control, where each chunk lists
+/// its position and its voxels as runs of identical voxels:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "chunks": [
+///     {
+///       "position": [0, 0, 0],
+///       "voxels": [
+///         { "count": 2, "material": "Rock", "occupancy": 255 },
+///         { "count": 1, "water": 128 },
+///         { "count": 32765 }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Chunks are listed in the same order as [`SmoothGrid::chunks`], and voxels
+/// in the order they're stored, with X changing fastest, then Z, then Y. The
+/// runs of each chunk must add up to exactly [`CHUNK_SIZE`]³ voxels. Fields
+/// that would be `0` or have no material can be left out.
+#[derive(Debug, Clone, Default)]
+pub struct SmoothGrid {
+    /// A `BTreeMap` is used so that chunks are always encoded in the same
+    /// order.
+    chunks: BTreeMap<ChunkCoordinates, Chunk>,
+
+    /// The chunks changed since dirty chunks were last encoded, or `None` if
+    /// changes aren't being tracked.
+    dirty: Option<BTreeSet<ChunkCoordinates>>,
+}
+
+// Which chunks are dirty isn't part of what the terrain looks like, so it's
+// left out when comparing grids.
+impl PartialEq for SmoothGrid {
+    fn eq(&self, other: &Self) -> bool {
+        self.chunks == other.chunks
+    }
+}
+
+impl Eq for SmoothGrid {}
+
+impl SmoothGrid {
+    /// Creates a new `SmoothGrid` with no chunks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turns tracking of which chunks have changed on or off, so that only
+    /// those chunks need to be encoded again with
+    /// [`SmoothGrid::encode_dirty`]. Tracking is off for new and decoded
+    /// grids.
+    ///
+    /// Turning tracking on starts with no chunks marked as dirty, and turning
+    /// it off forgets which chunks were.
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.dirty = if enabled {
+            Some(self.dirty.take().unwrap_or_default())
+        } else {
+            None
+        };
+    }
+
+    /// Returns whether the chunk at `position` has been marked as dirty since
+    /// dirty chunks were last encoded. Always returns `false` if tracking is
+    /// off.
+    pub fn is_dirty(&self, position: ChunkCoordinates) -> bool {
+        self.dirty
+            .as_ref()
+            .is_some_and(|dirty| dirty.contains(&position))
+    }
+
+    /// Returns an iterator over the position of every dirty chunk, in the
+    /// same order as [`SmoothGrid::chunks`].
+    pub fn dirty_chunks(&self) -> impl Iterator<Item = ChunkCoordinates> + '_ {
+        self.dirty.iter().flatten().copied()
+    }
+
+    /// Encodes every chunk that has changed since the last call, like
+    /// [`Chunk::encode_into`] does, and marks them all as clean again. Chunks
+    /// that were removed are included as `None`.
+    ///
+    /// Chunks are marked as dirty by every method that writes to the grid,
+    /// including [`SmoothGrid::get_chunk_mut`] and
+    /// [`SmoothGrid::get_or_insert_chunk_with`] even if the chunk isn't actually changed
+    /// through them, so some of the chunks returned may be unchanged.
+    ///
+    /// Returns nothing if tracking hasn't been turned on with
+    /// [`SmoothGrid::set_dirty_tracking`].
+    pub fn encode_dirty(&mut self) -> BTreeMap<ChunkCoordinates, Option<Vec<u8>>> {
+        let dirty = match &mut self.dirty {
+            Some(dirty) => mem::take(dirty),
+            None => return BTreeMap::new(),
+        };
+
+        dirty
+            .into_iter()
+            .map(|position| {
+                let payload = self.chunks.get(&position).map(|chunk| {
+                    let mut buffer = Vec::new();
+                    chunk.encode_into(&mut buffer);
+                    buffer
+                });
+
+                (position, payload)
+            })
+            .collect()
+    }
+
+    /// Marks the chunk at `position` as dirty if changes are being tracked.
+    fn mark_dirty(&mut self, position: ChunkCoordinates) {
+        if let Some(dirty) = &mut self.dirty {
+            dirty.insert(position);
+        }
+    }
+
+    /// Marks every chunk in the grid as dirty if changes are being tracked.
+    fn mark_all_dirty(&mut self) {
+        if let Some(dirty) = &mut self.dirty {
+            dirty.extend(self.chunks.keys().copied());
+        }
+    }
+
+    /// Returns the chunk at the given position, if there is one.
+    pub fn get_chunk(&self, position: ChunkCoordinates) -> Option<&Chunk> {
+        self.chunks.get(&position)
+    }
+
+    /// Returns the chunk at the given position mutably, if there is one.
+    pub fn get_chunk_mut(&mut self, position: ChunkCoordinates) -> Option<&mut Chunk> {
+        self.mark_dirty(position);
+        self.chunks.get_mut(&position)
+    }
+
+    /// Returns the chunk at the given position mutably, first inserting the
+    /// chunk returned by `create` if there isn't one. This only looks up the
+    /// chunk once.
+    ///
+    /// ```
+    /// use rbx_types::{Chunk, ChunkCoordinates, SmoothGrid, TerrainMaterials, Voxel, VoxelCoordinates};
+    ///
+    /// let mut grid = SmoothGrid::new();
+    /// let chunk = grid.get_or_insert_chunk_with(ChunkCoordinates::new(0, 0, 0), Chunk::new);
+    ///
+    /// chunk.write_voxel(VoxelCoordinates::new(0, 0, 0), Voxel::solid(TerrainMaterials::Rock));
+    /// ```
+    pub fn get_or_insert_chunk_with<F>(
+        &mut self,
+        position: ChunkCoordinates,
+        create: F,
+    ) -> &mut Chunk
+    where
+        F: FnOnce() -> Chunk,
+    {
+        self.mark_dirty(position);
+        self.chunks.entry(position).or_insert_with(create)
+    }
+
+    /// Inserts a chunk at the given position, returning the chunk that was
+    /// there before if one existed.
+    pub fn write_chunk(&mut self, position: ChunkCoordinates, chunk: Chunk) -> Option<Chunk> {
+        self.mark_dirty(position);
+        self.chunks.insert(position, chunk)
+    }
+
+    /// Removes the chunk at the given position, returning it if it existed.
+    pub fn remove_chunk(&mut self, position: ChunkCoordinates) -> Option<Chunk> {
+        self.mark_dirty(position);
+        self.chunks.remove(&position)
+    }
+
+    /// Returns the voxel at the given position, or `None` if the chunk
+    /// containing it has not been written.
+    pub fn get_voxel_world(&self, position: TerrainVec) -> Option<&Voxel> {
+        self.chunks
+            .get(&position.chunk_coordinates())
+            .map(|chunk| chunk.get_voxel(position.voxel_coordinates()))
+    }
+
+    /// Replaces the voxel at the given position. If the chunk containing it
+    /// has not been written, an empty chunk is created first.
+    pub fn write_voxel_world(&mut self, position: TerrainVec, voxel: Voxel) {
+        self.get_or_insert_chunk_with(position.chunk_coordinates(), Chunk::new)
+            .write_voxel(position.voxel_coordinates(), voxel);
+    }
+
+    /// Reads every voxel between `min` and `max` inclusive into a
+    /// [`VoxelRegion`]. Voxels in chunks that have not been written are
+    /// empty. If `min` is greater than `max` along any axis, the region is
+    /// empty.
+    pub fn read_voxels(&self, min: TerrainVec, max: TerrainVec) -> VoxelRegion {
+        let mut region = VoxelRegion::new(min, max);
+        if region.is_empty() {
+            return region;
+        }
+
+        let min_chunk = min.chunk_coordinates();
+        let max_chunk = max.chunk_coordinates();
+        let range = min_chunk..=max_chunk;
+
+        for (&chunk_position, chunk) in self.chunks.range(range) {
+            // The range is ordered by X first, so chunks outside of the
+            // region along Y or Z can still show up in it.
+            if chunk_position.y < min_chunk.y
+                || chunk_position.y > max_chunk.y
+                || chunk_position.z < min_chunk.z
+                || chunk_position.z > max_chunk.z
+            {
+                continue;
+            }
+
+            let (lower, upper) = chunk_overlap(chunk_position, min, max);
+
+            for y in lower.y..=upper.y {
+                for z in lower.z..=upper.z {
+                    for x in lower.x..=upper.x {
+                        let position = TerrainVec::new(x, y, z);
+                        let voxel = chunk.get_voxel(position.voxel_coordinates());
+                        let index = region.index(position).unwrap();
+
+                        region.materials[index] = voxel.material;
+                        region.occupancy[index] = voxel.occupancy;
+                        region.water_occupancy[index] = voxel.water_occupancy;
+                    }
+                }
+            }
+        }
+
+        region
+    }
+
+    /// Writes a dense 3D array of voxels whose lowest corner is at `min`,
+    /// like `Terrain:WriteVoxelChannels`. `dims` is the number of voxels
+    /// along the X, Y, and Z axes.
+    ///
+    /// The arrays are laid out the same way as a [`VoxelRegion`], so a
+    /// region returned by [`SmoothGrid::read_voxels`] can be written back
+    /// unchanged. Each chunk the array covers is written in one pass, which
+    /// is much faster than writing voxels one at a time. Chunks that have
+    /// not been written are created, even if every voxel written to them is
+    /// empty.
+    ///
+    /// ## Panics
+    /// Panics if any of the arrays doesn't have exactly one value for each
+    /// voxel, or if the array extends past the largest `TerrainVec`.
+    pub fn write_voxels(
+        &mut self,
+        min: TerrainVec,
+        materials: &[Option<TerrainMaterials>],
+        occupancy: &[u8],
+        water_occupancy: &[u8],
+        dims: [usize; 3],
+    ) {
+        let volume = dims[0] * dims[1] * dims[2];
+        assert!(
+            materials.len() == volume
+                && occupancy.len() == volume
+                && water_occupancy.len() == volume,
+            "voxel arrays must have {} values each",
+            volume
+        );
+
+        if volume == 0 {
+            return;
+        }
+
+        let end = |min: i32, size: usize| {
+            i32::try_from(size - 1)
+                .ok()
+                .and_then(|offset| min.checked_add(offset))
+                .expect("voxel array extends past the largest TerrainVec")
+        };
+        let max = TerrainVec::new(
+            end(min.x, dims[0]),
+            end(min.y, dims[1]),
+            end(min.z, dims[2]),
+        );
+
+        let min_chunk = min.chunk_coordinates();
+        let max_chunk = max.chunk_coordinates();
+
+        for chunk_y in min_chunk.y..=max_chunk.y {
+            for chunk_z in min_chunk.z..=max_chunk.z {
+                for chunk_x in min_chunk.x..=max_chunk.x {
+                    let chunk_position = ChunkCoordinates::new(chunk_x, chunk_y, chunk_z);
+                    self.mark_dirty(chunk_position);
+                    let chunk = self.chunks.entry(chunk_position).or_default();
+                    let (lower, upper) = chunk_overlap(chunk_position, min, max);
+
+                    for y in lower.y..=upper.y {
+                        for z in lower.z..=upper.z {
+                            for x in lower.x..=upper.x {
+                                let position = TerrainVec::new(x, y, z);
+                                let index = (x - min.x) as usize
+                                    + (y - min.y) as usize * dims[0]
+                                    + (z - min.z) as usize * dims[0] * dims[1];
+
+                                chunk.voxels[position.voxel_coordinates().index()] = Voxel {
+                                    material: materials[index],
+                                    occupancy: occupancy[index],
+                                    water_occupancy: water_occupancy[index],
+                                };
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Packs the voxels in `region` into a [`Volume`], a dense 3D array that
+    /// can be uploaded as a texture or fed to tools that don't understand
+    /// chunks. `region` is measured in studs, and is expanded outwards to the
+    /// edges of the voxels it touches.
+    ///
+    /// Each texel of the volume covers a cube of `resolution` voxels along
+    /// each side, starting from the lowest corner of the region, and is
+    /// combined the same way as [`SmoothGrid::downsample`]. Texels along the
+    /// upper edges of the region may cover fewer voxels if the region isn't a
+    /// multiple of `resolution` voxels wide.
+    ///
+    /// ## Panics
+    /// Panics if `resolution` is `0`.
+    pub fn to_volume(&self, region: Region3, resolution: u8) -> Volume {
+        assert!(
+            resolution > 0,
+            "cannot create a volume with a resolution of 0"
+        );
+
+        let (min, max) = region_voxels(region);
+        let resolution = usize::from(resolution);
+
+        let length =
+            |min: i32, max: i32| usize::try_from(i64::from(max) - i64::from(min) + 1).unwrap_or(0);
+        let voxels = [
+            length(min.x, max.x),
+            length(min.y, max.y),
+            length(min.z, max.z),
+        ];
+        let dims = voxels.map(|voxels| voxels.div_ceil(resolution));
+
+        let mut texels: Vec<DownsampledVoxel> = std::iter::repeat_with(Default::default)
+            .take(dims[0] * dims[1] * dims[2])
+            .collect();
+
+        let texel =
+            |position: i32, min: i32| (i64::from(position) - i64::from(min)) as usize / resolution;
+        for (position, voxel) in self.iter_region(min, max) {
+            let x = texel(position.x, min.x);
+            let y = texel(position.y, min.y);
+            let z = texel(position.z, min.z);
+
+            texels[x + y * dims[0] + z * dims[0] * dims[1]].add(voxel);
+        }
+
+        let mut volume = Volume {
+            dims,
+            occupancy: Vec::with_capacity(texels.len()),
+            material: Vec::with_capacity(texels.len()),
+        };
+
+        // Texels along the upper edges can be cut off by the region.
+        let covered =
+            |index: usize, axis: usize| resolution.min(voxels[axis] - index * resolution) as u64;
+
+        for (index, texel) in texels.iter().enumerate() {
+            let x = index % dims[0];
+            let y = index / dims[0] % dims[1];
+            let z = index / (dims[0] * dims[1]);
+
+            let voxel = texel.finish(covered(x, 0) * covered(y, 1) * covered(z, 2));
+            let (id, occupancy) = match voxel.material {
+                _ if voxel.is_empty() => (AIR_ID, 0),
+                None => (WATER_ID, voxel.water_occupancy),
+                Some(material) => (material_id(material), voxel.occupancy),
+            };
+
+            volume.material.push(id);
+            volume.occupancy.push(occupancy);
+        }
+
+        volume
+    }
+
+    /// Returns an iterator over every voxel between `min` and `max` inclusive,
+    /// along with its position. Voxels in chunks that have not been written
+    /// are empty. If `min` is greater than `max` along any axis, the iterator
+    /// is empty.
+    ///
+    /// Unlike [`SmoothGrid::read_voxels`], nothing is copied. Each chunk is
+    /// looked up once, when the iterator reaches it. Chunks are visited with
+    /// X changing fastest, then Z, then Y, and so are the voxels within each
+    /// chunk.
+    pub fn iter_region(&self, min: TerrainVec, max: TerrainVec) -> RegionVoxels<'_> {
+        let is_empty = min.x > max.x || min.y > max.y || min.z > max.z;
+
+        RegionVoxels {
+            grid: self,
+            min,
+            max,
+            chunk: None,
+            lower: min,
+            upper: min,
+            position: None,
+            next_chunk: if is_empty {
+                None
+            } else {
+                Some(min.chunk_coordinates())
+            },
+        }
+    }
+
+    /// Returns the lowest and highest voxels, inclusive, of the smallest box
+    /// that contains every voxel in the grid that isn't empty, or `None` if
+    /// the whole grid is empty. Water counts as terrain.
+    ///
+    /// The chunks at either corner can be found with
+    /// [`TerrainVec::chunk_coordinates`]. Chunks outside of the range Roblox
+    /// allows terrain to be placed in are ignored, since their voxels'
+    /// positions may not fit in a `TerrainVec`. [`SmoothGrid::validate`]
+    /// reports them.
+    pub fn bounds(&self) -> Option<(TerrainVec, TerrainVec)> {
+        let mut bounds: Option<(TerrainVec, TerrainVec)> = None;
+
+        for (chunk_position, chunk) in &self.chunks {
+            if !chunk_position.is_in_range() {
+                continue;
+            }
+
+            for (voxel_position, _) in chunk.iter_voxels() {
+                let position = TerrainVec::from_chunk(*chunk_position, voxel_position);
+
+                bounds = Some(match bounds {
+                    Some((min, max)) => (
+                        TerrainVec::new(
+                            min.x.min(position.x),
+                            min.y.min(position.y),
+                            min.z.min(position.z),
+                        ),
+                        TerrainVec::new(
+                            max.x.max(position.x),
+                            max.y.max(position.y),
+                            max.z.max(position.z),
+                        ),
+                    ),
+                    None => (position, position),
+                });
+            }
+        }
+
+        bounds
+    }
+
+    /// Finds the top solid voxel of every column between `min` and `max`
+    /// inclusive and records its height and material in a [`Heightmap`].
+    /// Voxels above `max` and below `min` are ignored.
+    pub fn heightmap(&self, min: TerrainVec, max: TerrainVec) -> Heightmap {
+        Heightmap::from_region(&self.read_voxels(min, max))
+    }
+
+    /// Returns the height of the top of the highest solid voxel in the column
+    /// of voxels at `x` and `z`, measured in studs, or `None` if the column has
+    /// no solid voxels.
+    ///
+    /// The occupancy of the top voxel is included in the same way as
+    /// [`SmoothGrid::heightmap`], so a voxel that is half full is half a voxel
+    /// shorter than one that is completely full.
+    pub fn column_height(&self, x: i32, z: i32) -> Option<f32> {
+        let column = TerrainVec::new(x, 0, z).voxel_coordinates();
+
+        for (chunk_position, chunk) in self.column_chunks(x, z).rev() {
+            for local_y in (0..CHUNK_SIZE as u8).rev() {
+                let voxel_position = VoxelCoordinates::new(column.x(), local_y, column.z());
+                let occupancy = solid_occupancy(chunk.get_voxel(voxel_position));
+
+                if occupancy > 0 {
+                    let y = TerrainVec::from_chunk(*chunk_position, voxel_position).y;
+                    let top = y as f32 + f32::from(occupancy) / f32::from(u8::MAX);
+                    return Some(top * VOXEL_SIZE);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Makes the column of voxels at `x` and `z` solid `material` from a
+    /// height of `0` up to `height`, measured in studs, and removes the solid
+    /// material above `height`. This treats the terrain as a heightfield, like
+    /// a heightmap import, and creates chunks as needed.
+    ///
+    /// The voxel that `height` falls inside of is filled in proportion to how
+    /// much of it is below `height`. Water is kept wherever solid material
+    /// doesn't fill a voxel completely. Voxels below `0` are never changed, so
+    /// a `height` of `0` or less only clears the column above `0`.
+    pub fn set_column(&mut self, x: i32, z: i32, height: f32, material: TerrainMaterials) {
+        let height = height / VOXEL_SIZE;
+        let filled_top = (height.ceil() as i32).max(0);
+        let written_top = self
+            .column_chunks(x, z)
+            .next_back()
+            .map(|(position, _)| position.y * CHUNK_SIZE as i32 + CHUNK_SIZE as i32)
+            .unwrap_or(0);
+
+        for y in 0..filled_top.max(written_top) {
+            let position = TerrainVec::new(x, y, z);
+            let fill = (height - y as f32).clamp(0.0, 1.0);
+            let occupancy = (fill * u8::MAX as f32).round() as u8;
+
+            let existing = self.get_voxel_world(position).copied();
+            let voxel = match (existing, occupancy) {
+                (None, 0) => continue,
+                (Some(existing), 0) => with_solid_occupancy(existing, 0),
+                (_, u8::MAX) => Voxel::solid(material),
+                (existing, occupancy) => Voxel {
+                    material: Some(material),
+                    occupancy,
+                    water_occupancy: existing.unwrap_or_default().water_occupancy,
+                },
+            };
+
+            if existing != Some(voxel) {
+                self.write_voxel_world(position, voxel);
+            }
+        }
+    }
+
+    /// Returns every chunk that holds part of the column of voxels at `x` and
+    /// `z`, from lowest to highest.
+    fn column_chunks(
+        &self,
+        x: i32,
+        z: i32,
+    ) -> impl DoubleEndedIterator<Item = (&ChunkCoordinates, &Chunk)> {
+        let column = TerrainVec::new(x, 0, z).chunk_coordinates();
+
+        // The map is ordered by X, then Y, then Z, so chunks in other columns
+        // with the same X show up in the range too.
+        self.chunks
+            .range(
+                ChunkCoordinates::new(column.x, i32::MIN, column.z)
+                    ..=ChunkCoordinates::new(column.x, i32::MAX, column.z),
+            )
+            .filter(move |(position, _)| position.z == column.z)
+    }
+
+    /// Returns the solid material of the voxel containing `position`, which is
+    /// measured in studs, along with how full of it the voxel is from `0.0`
+    /// to `1.0`.
+    ///
+    /// Returns `None` if the voxel has no solid material, including if it only
+    /// contains water.
+    pub fn sample(&self, position: Vector3) -> Option<(TerrainMaterials, f32)> {
+        let voxel = self.get_voxel_world(TerrainVec::from_world(position))?;
+
+        match voxel.material {
+            Some(material) if voxel.occupancy > 0 => {
+                Some((material, f32::from(voxel.occupancy) / f32::from(u8::MAX)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Casts a ray from `origin` along `direction`, both measured in studs,
+    /// and returns the first voxel it hits that contains any solid material.
+    /// Like `WorldRoot:Raycast`, the length of `direction` is how far the ray
+    /// goes.
+    ///
+    /// Voxels are treated as solid cubes no matter their occupancy, so a ray
+    /// can hit a voxel slightly before it would reach the surface Roblox
+    /// draws. Water is ignored.
+    pub fn raycast(&self, origin: Vector3, direction: Vector3) -> Option<TerrainHit> {
+        let origin = [origin.x, origin.y, origin.z];
+        let direction = [direction.x, direction.y, direction.z];
+        if origin
+            .iter()
+            .chain(&direction)
+            .any(|value| !value.is_finite())
+        {
+            return None;
+        }
+
+        // Everything from here on is measured in voxels, where the ray goes
+        // from `t = 0` to `t = 1`. Voxels are tracked as `i64` and positions
+        // as `f64` so that rays far from the origin don't overflow or lose
+        // their place.
+        let start = origin.map(|value| f64::from(value / VOXEL_SIZE));
+        let delta = direction.map(|value| f64::from(value / VOXEL_SIZE));
+
+        // The ray is clipped to the box around every chunk, so it never
+        // visits more voxels than the grid spans, however long it is.
+        let (lower, upper) = self.voxel_extent()?;
+        let mut t = 0.0;
+        let mut t_exit = 1.0;
+        let mut entered_through = None;
+
+        for axis in 0..3 {
+            let (low, high) = (lower[axis] as f64, upper[axis] as f64);
+
+            if delta[axis] == 0.0 {
+                if start[axis] < low || start[axis] >= high {
+                    return None;
+                }
+                continue;
+            }
+
+            let a = (low - start[axis]) / delta[axis];
+            let b = (high - start[axis]) / delta[axis];
+            let (enter, exit) = if a < b { (a, b) } else { (b, a) };
+
+            if enter > t {
+                t = enter;
+                entered_through = Some(axis);
+            }
+            t_exit = exit.min(t_exit);
+        }
+
+        if t > t_exit {
+            return None;
+        }
+
+        let mut step = [0i64; 3];
+        let mut voxel = [0i64; 3];
+        for axis in 0..3 {
+            step[axis] = if delta[axis] > 0.0 {
+                1
+            } else if delta[axis] < 0.0 {
+                -1
+            } else {
+                0
+            };
+
+            // Rounding can leave the clipped start just outside of the box.
+            let position = (start[axis] + delta[axis] * t).floor() as i64;
+            voxel[axis] = position.clamp(lower[axis], upper[axis] - 1);
+        }
+
+        // Where the ray crosses the next voxel boundary along an axis. This
+        // is worked out from the voxel each time instead of being added up,
+        // which would stop moving once it grew large enough.
+        let next_boundary = |voxel: i64, axis: usize| match step[axis] {
+            1 => (voxel as f64 + 1.0 - start[axis]) / delta[axis],
+            -1 => (voxel as f64 - start[axis]) / delta[axis],
+            _ => f64::INFINITY,
+        };
+
+        loop {
+            let hit = match (
+                i32::try_from(voxel[0]),
+                i32::try_from(voxel[1]),
+                i32::try_from(voxel[2]),
+            ) {
+                (Ok(x), Ok(y), Ok(z)) => {
+                    let position = TerrainVec::new(x, y, z);
+                    self.get_voxel_world(position)
+                        .and_then(|voxel| voxel.material.filter(|_| voxel.occupancy > 0))
+                        .map(|material| (position, material))
+                }
+                _ => None,
+            };
+
+            if let Some((position, material)) = hit {
+                let mut normal = [0.0; 3];
+                if let Some(axis) = entered_through {
+                    normal[axis] = -(step[axis] as f32);
+                }
+
+                let t = t as f32;
+                let length = direction
+                    .iter()
+                    .map(|value| value * value)
+                    .sum::<f32>()
+                    .sqrt();
+
+                return Some(TerrainHit {
+                    position,
+                    point: Vector3::new(
+                        origin[0] + direction[0] * t,
+                        origin[1] + direction[1] * t,
+                        origin[2] + direction[2] * t,
+                    ),
+                    normal: Vector3::new(normal[0], normal[1], normal[2]),
+                    distance: length * t,
+                    material,
+                });
+            }
+
+            let mut axis = 0;
+            let mut boundary = next_boundary(voxel[0], 0);
+            for (other, &position) in voxel.iter().enumerate().skip(1) {
+                let other_boundary = next_boundary(position, other);
+                if other_boundary < boundary {
+                    axis = other;
+                    boundary = other_boundary;
+                }
+            }
+
+            voxel[axis] += step[axis];
+            if boundary > t_exit || voxel[axis] < lower[axis] || voxel[axis] >= upper[axis] {
+                return None;
+            }
+
+            t = boundary;
+            entered_through = Some(axis);
+        }
+    }
+
+    /// Returns the lowest corner of the box around every chunk in the grid,
+    /// and the corner just past its highest corner, measured in voxels.
+    /// Returns `None` if the grid has no chunks.
+    fn voxel_extent(&self) -> Option<([i64; 3], [i64; 3])> {
+        let components =
+            |position: &ChunkCoordinates| [position.x, position.y, position.z].map(i64::from);
+
+        let mut positions = self.chunks.keys();
+        let first = components(positions.next()?);
+        let (mut lower, mut upper) = (first, first);
+
+        for position in positions {
+            let position = components(position);
+            for axis in 0..3 {
+                lower[axis] = lower[axis].min(position[axis]);
+                upper[axis] = upper[axis].max(position[axis]);
+            }
+        }
+
+        let size = CHUNK_SIZE as i64;
+        Some((
+            lower.map(|low| low * size),
+            upper.map(|high| (high + 1) * size),
+        ))
+    }
+
+    /// Replaces every voxel between `min` and `max` inclusive with `voxel`,
+    /// creating chunks as needed, like `Terrain:FillBlock`.
+    ///
+    /// Chunks that are completely covered by an empty `voxel` are removed, and
+    /// no chunks are created to hold empty voxels.
+    pub fn fill_block(&mut self, min: TerrainVec, max: TerrainVec, voxel: Voxel) {
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            return;
+        }
+
+        let min_chunk = min.chunk_coordinates();
+        let max_chunk = max.chunk_coordinates();
+
+        for chunk_y in min_chunk.y..=max_chunk.y {
+            for chunk_z in min_chunk.z..=max_chunk.z {
+                for chunk_x in min_chunk.x..=max_chunk.x {
+                    let chunk_position = ChunkCoordinates::new(chunk_x, chunk_y, chunk_z);
+                    let (lower, upper) = chunk_overlap(chunk_position, min, max);
+
+                    let last = (CHUNK_SIZE - 1) as i32;
+                    let extent = (upper.x - lower.x, upper.y - lower.y, upper.z - lower.z);
+                    self.mark_dirty(chunk_position);
+
+                    if extent == (last, last, last) {
+                        if voxel.is_empty() {
+                            self.chunks.remove(&chunk_position);
+                        } else {
+                            self.chunks
+                                .insert(chunk_position, Chunk::new_with_base(voxel));
+                        }
+                        continue;
+                    }
+
+                    let chunk = match self.chunks.entry(chunk_position) {
+                        btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                        btree_map::Entry::Vacant(_) if voxel.is_empty() => continue,
+                        btree_map::Entry::Vacant(entry) => entry.insert(Chunk::new()),
+                    };
+
+                    for y in lower.y..=upper.y {
+                        for z in lower.z..=upper.z {
+                            for x in lower.x..=upper.x {
+                                let position = TerrainVec::new(x, y, z);
+                                chunk.write_voxel(position.voxel_coordinates(), voxel);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fills `region` with `material`, like `Terrain:FillRegion` with a
+    /// resolution of 4. `region` is measured in studs.
+    ///
+    /// `region` is expanded outwards to the edges of the voxels it touches, so
+    /// every voxel that it overlaps is filled completely. Like every method
+    /// that takes a region in studs, the parts of it outside of the range
+    /// Roblox allows terrain to be placed in are ignored.
+    pub fn fill_region(&mut self, region: Region3, material: TerrainMaterials) {
+        let (min, max) = region_voxels(region);
+        self.fill_block(min, max, Voxel::solid(material));
+    }
+
+    /// Fills every voxel in `region` that is below the height `level` with
+    /// water, like the sea level tool in Studio. `level` and `region` are
+    /// measured in studs, and `region` is expanded outwards to the edges of
+    /// the voxels it touches.
+    ///
+    /// Voxels that straddle `level` are filled in proportion to how much of
+    /// them is below it. Completely solid voxels are left alone, and partly
+    /// solid voxels keep their material and take on water as shorelines.
+    /// Water that is already in a voxel is never reduced. A `level` of `NaN`
+    /// fills nothing.
+    pub fn fill_water_to_level(&mut self, level: f32, region: Region3) {
+        if level.is_nan() {
+            return;
+        }
+
+        let water_at = |y: i32| {
+            let below = (level / VOXEL_SIZE - y as f32).clamp(0.0, 1.0);
+            (below * u8::MAX as f32).round() as u8
+        };
+
+        // The highest layer of voxels that gets any water.
+        // Levels beyond the range of an `i32` saturate instead of overflowing.
+        let mut top = ((level / VOXEL_SIZE).ceil() as i32).saturating_sub(1);
+        if water_at(top) == 0 {
+            top = top.saturating_sub(1);
+        }
+
+        let (min, mut max) = region_voxels(region);
+        max.y = max.y.min(top);
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            return;
+        }
+
+        let min_chunk = min.chunk_coordinates();
+        let max_chunk = max.chunk_coordinates();
+
+        for chunk_y in min_chunk.y..=max_chunk.y {
+            for chunk_z in min_chunk.z..=max_chunk.z {
+                for chunk_x in min_chunk.x..=max_chunk.x {
+                    let chunk_position = ChunkCoordinates::new(chunk_x, chunk_y, chunk_z);
+                    let (lower, upper) = chunk_overlap(chunk_position, min, max);
+
+                    let last = (CHUNK_SIZE - 1) as i32;
+                    let extent = (upper.x - lower.x, upper.y - lower.y, upper.z - lower.z);
+                    let covered = extent == (last, last, last) && water_at(upper.y) == u8::MAX;
+                    self.mark_dirty(chunk_position);
+
+                    let chunk = match self.chunks.entry(chunk_position) {
+                        btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                        btree_map::Entry::Vacant(entry) if covered => {
+                            entry.insert(Chunk::new_with_base(Voxel::water(u8::MAX)));
+                            continue;
+                        }
+                        btree_map::Entry::Vacant(entry) => entry.insert(Chunk::new()),
+                    };
+
+                    for y in lower.y..=upper.y {
+                        let water = water_at(y);
+
+                        for z in lower.z..=upper.z {
+                            for x in lower.x..=upper.x {
+                                let position = TerrainVec::new(x, y, z).voxel_coordinates();
+                                let existing = *chunk.get_voxel(position);
+                                let water_occupancy = existing.water_occupancy.max(water);
+
+                                let voxel = match existing.material {
+                                    Some(_) if existing.occupancy == u8::MAX => continue,
+                                    Some(_) if existing.occupancy > 0 => Voxel {
+                                        water_occupancy,
+                                        ..existing
+                                    },
+                                    _ => Voxel::water(water_occupancy),
+                                };
+
+                                chunk.write_voxel(position, voxel);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fills a sphere with `material`, like `Terrain:FillBall`. `center` and
+    /// `radius` are measured in studs.
+    ///
+    /// Voxels that straddle the surface of the sphere are given partial
+    /// occupancy based on how far their center is from the surface. Voxels
+    /// that are already more occupied keep their occupancy but take on the
+    /// new material.
+    pub fn fill_ball(&mut self, center: Vector3, radius: f32, material: TerrainMaterials) {
+        let radius = radius.max(0.0);
+        let min = Vector3::new(center.x - radius, center.y - radius, center.z - radius);
+        let max = Vector3::new(center.x + radius, center.y + radius, center.z + radius);
+
+        self.fill_shape(min, max, material, |position| {
+            let (dx, dy, dz) = (
+                position.x - center.x,
+                position.y - center.y,
+                position.z - center.z,
+            );
+
+            radius - (dx * dx + dy * dy + dz * dz).sqrt()
+        });
+    }
+
+    /// Fills an upright cylinder with `material`, like `Terrain:FillCylinder`
+    /// with an unrotated `CFrame`. `center`, `height`, and `radius` are
+    /// measured in studs, and the cylinder's height runs along the Y axis.
+    ///
+    /// Voxels along the cylinder's round side and flat caps are given partial
+    /// occupancy in the same way as [`SmoothGrid::fill_ball`].
+    pub fn fill_cylinder(
+        &mut self,
+        center: Vector3,
+        height: f32,
+        radius: f32,
+        material: TerrainMaterials,
+    ) {
+        let radius = radius.max(0.0);
+        let half_height = height.max(0.0) / 2.0;
+        let min = Vector3::new(center.x - radius, center.y - half_height, center.z - radius);
+        let max = Vector3::new(center.x + radius, center.y + half_height, center.z + radius);
+
+        self.fill_shape(min, max, material, |position| {
+            let (dx, dz) = (position.x - center.x, position.z - center.z);
+            let side = radius - (dx * dx + dz * dz).sqrt();
+            let cap = half_height - (position.y - center.y).abs();
+
+            side.min(cap)
+        });
+    }
+
+    /// Fills a wedge with `material`, like `Terrain:FillWedge` with an
+    /// unrotated `CFrame`. `center` and `size` are measured in studs.
+    ///
+    /// The wedge has the same shape as a `WedgePart`: it is full height at
+    /// its back (+Z) and slopes down to nothing at its front (-Z). Voxels
+    /// along the slope and the other faces are given partial occupancy in
+    /// the same way as [`SmoothGrid::fill_ball`].
+    pub fn fill_wedge(&mut self, center: Vector3, size: Vector3, material: TerrainMaterials) {
+        let half_size = Vector3::new(
+            size.x.max(0.0) / 2.0,
+            size.y.max(0.0) / 2.0,
+            size.z.max(0.0) / 2.0,
+        );
+        let min = Vector3::new(
+            center.x - half_size.x,
+            center.y - half_size.y,
+            center.z - half_size.z,
+        );
+        let max = Vector3::new(
+            center.x + half_size.x,
+            center.y + half_size.y,
+            center.z + half_size.z,
+        );
+        let slope_length = (half_size.y * half_size.y + half_size.z * half_size.z).sqrt();
+
+        self.fill_shape(min, max, material, |position| {
+            let (x, y, z) = (
+                position.x - center.x,
+                position.y - center.y,
+                position.z - center.z,
+            );
+            let block = (half_size.x - x.abs())
+                .min(half_size.y - y.abs())
+                .min(half_size.z - z.abs());
+
+            // The slope runs from the bottom front edge to the top back edge,
+            // which passes through the wedge's center.
+            let slope = (z * half_size.y - y * half_size.z) / slope_length;
+
+            block.min(slope)
+        });
+    }
+
+    /// Replaces every voxel connected to `start` for which `predicate`
+    /// returns `true` with `voxel`, like the paint bucket in an image editor.
+    /// Voxels are connected through their faces, and only voxels between
+    /// `min` and `max` inclusive are filled.
+    ///
+    /// For example, a cave can be flooded by filling with water from a voxel
+    /// inside of it, with [`Voxel::is_empty`] as the predicate. If the cave
+    /// has an opening that the fill escapes through, it stops at the bounds
+    /// and [`FloodFill::reached_bounds`] is set, which makes it possible to
+    /// find leaks in builds that should be enclosed.
+    ///
+    /// Nothing is filled if `start` doesn't match `predicate` or is outside of
+    /// the bounds. Voxels in chunks that have not been written are empty.
+    pub fn flood_fill<F>(
+        &mut self,
+        start: TerrainVec,
+        voxel: Voxel,
+        min: TerrainVec,
+        max: TerrainVec,
+        mut predicate: F,
+    ) -> FloodFill
+    where
+        F: FnMut(&Voxel) -> bool,
+    {
+        let in_bounds = |position: TerrainVec| {
+            (min.x..=max.x).contains(&position.x)
+                && (min.y..=max.y).contains(&position.y)
+                && (min.z..=max.z).contains(&position.z)
+        };
+
+        let mut result = FloodFill {
+            filled: 0,
+            reached_bounds: false,
+        };
+
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![start];
+
+        while let Some(position) = pending.pop() {
+            if !visited.insert(position) {
+                continue;
+            }
+
+            let existing = self.get_voxel_world(position).unwrap_or(&EMPTY_VOXEL);
+            if !predicate(existing) {
+                continue;
+            }
+
+            // The fill would continue here if it weren't for the bounds.
+            if !in_bounds(position) {
+                result.reached_bounds |= position != start;
+                continue;
+            }
+
+            self.write_voxel_world(position, voxel);
+            result.filled += 1;
+
+            pending.extend(position.neighbors().iter().flatten());
+        }
+
+        result
+    }
+
+    /// Softens the solid terrain in `region` by replacing the occupancy of
+    /// each voxel with the average occupancy of the 3×3×3 block of voxels
+    /// around it, `iterations` times. `region` is measured in studs, and is
+    /// expanded outwards to the edges of the voxels it touches.
+    ///
+    /// Voxels outside of the region are read but never changed. A voxel that
+    /// becomes solid takes the material that fills the most of the voxels
+    /// around it, and one that becomes empty loses its material. Water isn't
+    /// changed.
+    pub fn smooth(&mut self, region: Region3, iterations: u32) {
+        let (min, max) = region_voxels(region);
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            return;
+        }
+
+        for _ in 0..iterations {
+            let source = self.read_voxels(
+                TerrainVec::new(min.x - 1, min.y - 1, min.z - 1),
+                TerrainVec::new(max.x + 1, max.y + 1, max.z + 1),
+            );
+            let solid = |position: TerrainVec| {
+                let index = source.index(position).unwrap();
+                source.materials[index].map(|material| (material, source.occupancy[index]))
+            };
+
+            let mut changes = Vec::new();
+
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    for x in min.x..=max.x {
+                        let mut total = 0u32;
+                        let mut materials: Vec<(TerrainMaterials, u32)> = Vec::new();
+
+                        for (dx, dy, dz) in NEIGHBORHOOD.iter() {
+                            let neighbor = TerrainVec::new(x + dx, y + dy, z + dz);
+                            if let Some((material, occupancy)) = solid(neighbor) {
+                                total += u32::from(occupancy);
+
+                                match materials.iter_mut().find(|(m, _)| *m == material) {
+                                    Some((_, sum)) => *sum += u32::from(occupancy),
+                                    None => materials.push((material, u32::from(occupancy))),
+                                }
+                            }
+                        }
+
+                        let position = TerrainVec::new(x, y, z);
+                        let index = source.index(position).unwrap();
+                        let occupancy = ((total + 13) / 27) as u8;
+                        let material = match occupancy {
+                            0 => None,
+                            // Ties go to the material found first.
+                            _ => source.materials[index].or_else(|| {
+                                materials
+                                    .iter()
+                                    .rev()
+                                    .max_by_key(|(_, sum)| *sum)
+                                    .map(|(material, _)| *material)
+                            }),
+                        };
+
+                        // Voxels that become completely solid have no room
+                        // left for water.
+                        let existing = source.get_voxel(position).unwrap();
+                        let voxel = Voxel {
+                            material,
+                            occupancy,
+                            water_occupancy: if occupancy == u8::MAX {
+                                0
+                            } else {
+                                existing.water_occupancy
+                            },
+                        };
+
+                        if voxel != existing {
+                            changes.push((position, voxel));
+                        }
+                    }
+                }
+            }
+
+            if changes.is_empty() {
+                break;
+            }
+
+            for (position, voxel) in changes {
+                self.write_voxel_world(position, voxel);
+            }
+        }
+    }
+
+    /// Calls `distance` with the center of every voxel that overlaps the box
+    /// from `min` to `max`, in studs. `distance` returns how far inside of the
+    /// shape the point is, which is negative outside of it. Voxels are then
+    /// filled with `material` in proportion to that distance.
+    fn fill_shape<F>(&mut self, min: Vector3, max: Vector3, material: TerrainMaterials, distance: F)
+    where
+        F: Fn(Vector3) -> f32,
+    {
+        let (min, max) = (TerrainVec::from_world(min), TerrainVec::from_world(max));
+
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let position = TerrainVec::new(x, y, z);
+                    let voxel_center = position.world_center();
+
+                    // A voxel whose center is exactly on the surface is half
+                    // full, and one whose center is a whole voxel inside is
+                    // completely full.
+                    let fill = (distance(voxel_center) / VOXEL_SIZE + 0.5).clamp(0.0, 1.0);
+                    let occupancy = (fill * u8::MAX as f32).round() as u8;
+                    if occupancy == 0 {
+                        continue;
+                    }
+
+                    let existing = self.get_voxel_world(position).copied().unwrap_or_default();
+
+                    self.write_voxel_world(
+                        position,
+                        Voxel {
+                            material: Some(material),
+                            occupancy: occupancy.max(existing.occupancy),
+                            water_occupancy: existing.water_occupancy,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Copies the voxels of `other` into this grid, using `policy` to decide
+    /// what happens where both grids have something in the same voxel.
+    pub fn merge(&mut self, other: &SmoothGrid, policy: MergePolicy) {
+        for (position, incoming) in &other.chunks {
+            let existed = self.chunks.contains_key(position);
+            self.mark_dirty(*position);
+            let chunk = self.chunks.entry(*position).or_default();
+
+            for (existing, incoming) in chunk.voxels.iter_mut().zip(&incoming.voxels) {
+                *existing = policy.resolve(*existing, *incoming);
+            }
+
+            // Don't leave behind new chunks that only hold empty voxels.
+            if !existed && chunk.voxels.iter().all(Voxel::is_empty) {
+                self.chunks.remove(position);
+            }
+        }
+    }
+
+    /// Carves the solid material of `other` out of this grid, like stamping a
+    /// tunnel or a cave into existing terrain.
+    ///
+    /// Only how much of each voxel `other` fills with solid material matters,
+    /// not which material it is. Each voxel of this grid keeps at most the
+    /// part of itself that `other` leaves unfilled, so a voxel that `other`
+    /// fills completely loses its material. Water is left alone.
+    pub fn subtract(&mut self, other: &SmoothGrid) {
+        for (position, mask) in &other.chunks {
+            self.mark_dirty(*position);
+            if let Some(chunk) = self.chunks.get_mut(position) {
+                for (voxel, mask) in chunk.voxels.iter_mut().zip(mask.voxels.iter()) {
+                    let carved = u8::MAX - solid_occupancy(mask);
+                    *voxel = with_solid_occupancy(*voxel, voxel.occupancy.min(carved));
+                }
+            }
+        }
+    }
+
+    /// Keeps only the parts of this grid that are inside of the solid
+    /// material of `other`, like cutting terrain to the shape of a prefab.
+    ///
+    /// Only how much of each voxel `other` fills with solid material matters,
+    /// not which material it is. Each voxel of this grid keeps at most as much
+    /// material and water as `other` fills it with solid material, and chunks
+    /// that `other` doesn't have are removed.
+    pub fn intersect(&mut self, other: &SmoothGrid) {
+        self.mark_all_dirty();
+        self.chunks
+            .retain(|position, chunk| match other.chunks.get(position) {
+                Some(mask) => {
+                    for (voxel, mask) in chunk.voxels.iter_mut().zip(mask.voxels.iter()) {
+                        let kept = solid_occupancy(mask);
+                        *voxel = with_solid_occupancy(*voxel, voxel.occupancy.min(kept));
+                        voxel.water_occupancy = voxel.water_occupancy.min(kept);
+                    }
+
+                    true
+                }
+                None => false,
+            });
+    }
+
+    /// Removes redundant data from the grid without changing how it looks.
+    ///
+    /// Voxels that hold no material, like a material with an occupancy of
+    /// `0`, are rewritten to the single form that [`SmoothGrid::decode`]
+    /// produces for them, so that runs of them encode as one run. Chunks that
+    /// are left completely empty are removed, since a missing chunk is
+    /// treated as empty.
+    ///
+    /// A normalized grid is unchanged by encoding and decoding it again.
+    pub fn normalize(&mut self) {
+        self.mark_all_dirty();
+        self.chunks.retain(|_, chunk| {
+            let mut is_empty = true;
+
+            for voxel in chunk.voxels.iter_mut() {
+                if voxel.material.is_none() || voxel.occupancy == 0 {
+                    *voxel = Voxel::water(voxel.water_occupancy);
+                }
+
+                is_empty &= voxel.is_empty();
+            }
+
+            !is_empty
+        });
+    }
+
+    /// Moves every voxel in the grid by `offset`.
+    ///
+    /// When the offset is a whole number of chunks along every axis, chunks
+    /// are moved as they are. Otherwise, every voxel that isn't empty is
+    /// copied into the chunks that now contain it, and chunks that only held
+    /// empty voxels are dropped.
+    ///
+    /// Returns an error without changing the grid if any chunk would be moved
+    /// past the limits of an `i32`, or if a chunk's voxels need to be moved
+    /// but their positions don't fit in a `TerrainVec`.
+    pub fn translate(&mut self, offset: TerrainVec) -> Result<(), CrateError> {
+        let size = CHUNK_SIZE as i32;
+
+        if offset.x % size == 0 && offset.y % size == 0 && offset.z % size == 0 {
+            let chunk_offset = offset.chunk_coordinates();
+
+            // Chunks are only moved once every new position is known to fit.
+            let mut positions = Vec::with_capacity(self.chunks.len());
+            for position in self.chunks.keys() {
+                match position.offset(chunk_offset.x, chunk_offset.y, chunk_offset.z) {
+                    Some(moved) => positions.push(moved),
+                    None => return Err(TerrainError::ChunkOutOfRange(*position).into()),
+                }
+            }
+
+            self.mark_all_dirty();
+            let chunks = std::mem::take(&mut self.chunks);
+            self.chunks = positions.into_iter().zip(chunks.into_values()).collect();
+            self.mark_all_dirty();
+        } else {
+            let last = (CHUNK_SIZE - 1) as u8;
+            let moved = |chunk: ChunkCoordinates, voxel: VoxelCoordinates| {
+                TerrainVec::try_from_chunk(chunk, voxel)
+                    .ok()
+                    .and_then(|position| position.offset(offset.x, offset.y, offset.z))
+            };
+
+            // Every voxel of a chunk moves if its first and last voxels do.
+            for &position in self.chunks.keys() {
+                if moved(position, VoxelCoordinates::default()).is_none()
+                    || moved(position, VoxelCoordinates::new(last, last, last)).is_none()
+                {
+                    return Err(TerrainError::ChunkOutOfRange(position).into());
+                }
+            }
+
+            self.move_voxels(|position| position + offset);
+        }
+
+        Ok(())
+    }
+
+    /// Rotates every voxel in the grid by `turns` quarter turns around the Y
+    /// axis, counterclockwise when viewed from above. This is the same
+    /// direction as `CFrame.Angles(0, math.rad(90), 0)`.
+    ///
+    /// The grid is rotated around the corner of the voxel at the origin, so
+    /// every voxel lands exactly on another and rotating four times gives
+    /// back the same grid. Chunks that only held empty voxels are dropped.
+    /// Chunks too far from the origin for their voxels' positions to fit in a
+    /// [`TerrainVec`] are left where they are.
+    pub fn rotate_y_90(&mut self, turns: u8) {
+        for _ in 0..turns % 4 {
+            self.move_voxels(|position| TerrainVec::new(position.z, position.y, -1 - position.x));
+        }
+    }
+
+    /// Rotates every voxel in the grid by `turns` quarter turns around the X
+    /// axis, in the same direction as `CFrame.Angles(math.rad(90), 0, 0)`.
+    /// See [`SmoothGrid::rotate_y_90`] for details.
+    pub fn rotate_x_90(&mut self, turns: u8) {
+        for _ in 0..turns % 4 {
+            self.move_voxels(|position| TerrainVec::new(position.x, -1 - position.z, position.y));
+        }
+    }
+
+    /// Rotates every voxel in the grid by `turns` quarter turns around the Z
+    /// axis, in the same direction as `CFrame.Angles(0, 0, math.rad(90))`.
+    /// See [`SmoothGrid::rotate_y_90`] for details.
+    pub fn rotate_z_90(&mut self, turns: u8) {
+        for _ in 0..turns % 4 {
+            self.move_voxels(|position| TerrainVec::new(-1 - position.y, position.x, position.z));
+        }
+    }
+
+    /// Reflects every voxel in the grid along each of the given axes. For
+    /// example, mirroring along [`Axes::X`] swaps the voxels on either side
+    /// of the plane where X is 0.
+    ///
+    /// The plane passes through the corner of the voxel at the origin, so
+    /// mirroring twice gives back the same grid. Use
+    /// [`SmoothGrid::translate`] afterwards to mirror across a different
+    /// plane. Chunks that only held empty voxels are dropped, and chunks too
+    /// far from the origin for their voxels' positions to fit in a
+    /// [`TerrainVec`] are left where they are.
+    pub fn mirror(&mut self, axes: Axes) {
+        if axes == Axes::empty() {
+            return;
+        }
+
+        let flip = |flipped: bool, value: i32| if flipped { -1 - value } else { value };
+        let (x, y, z) = (
+            axes.contains(Axes::X),
+            axes.contains(Axes::Y),
+            axes.contains(Axes::Z),
+        );
+
+        self.move_voxels(|position| {
+            TerrainVec::new(
+                flip(x, position.x),
+                flip(y, position.y),
+                flip(z, position.z),
+            )
+        });
+    }
+
+    /// Returns a coarser copy of the grid, where each voxel covers a cube of
+    /// `factor` voxels along each side of this grid. The voxel at the origin
+    /// of the new grid covers the voxels from the origin up to `factor - 1`
+    /// along each axis.
+    ///
+    /// Each new voxel has the average occupancy and water occupancy of the
+    /// voxels it covers, counting missing voxels as empty. Its material is
+    /// the one with the most total occupancy among them, with ties going to
+    /// the material that was found first. A `factor` of `1` returns an
+    /// unchanged copy.
+    ///
+    /// Chunks outside of the range Roblox allows terrain to be placed in are
+    /// left out, since their voxels' positions may not fit in a
+    /// [`TerrainVec`]. [`SmoothGrid::validate`] reports them.
+    ///
+    /// ## Panics
+    /// Panics if `factor` is `0`.
+    pub fn downsample(&self, factor: u8) -> SmoothGrid {
+        assert!(factor > 0, "cannot downsample terrain by a factor of 0");
+
+        let size = i32::from(factor);
+        let mut cells: BTreeMap<TerrainVec, DownsampledVoxel> = BTreeMap::new();
+
+        for (chunk_position, chunk) in &self.chunks {
+            if !chunk_position.is_in_range() {
+                continue;
+            }
+
+            for (voxel_position, voxel) in chunk.iter_voxels() {
+                let position = TerrainVec::from_chunk(*chunk_position, voxel_position);
+                let cell = TerrainVec::new(
+                    position.x.div_euclid(size),
+                    position.y.div_euclid(size),
+                    position.z.div_euclid(size),
+                );
+
+                cells.entry(cell).or_default().add(voxel);
+            }
+        }
+
+        let volume = u64::from(factor).pow(3);
+        let mut grid = SmoothGrid::new();
+
+        for (position, cell) in cells {
+            let voxel = cell.finish(volume);
+
+            if !voxel.is_empty() {
+                grid.write_voxel_world(position, voxel);
+            }
+        }
+
+        grid
+    }
+
+    /// Moves every voxel that isn't empty to the position returned by `map`,
+    /// which must not send two voxels to the same position.
+    ///
+    /// Chunks whose voxels' positions don't fit in a `TerrainVec` are kept as
+    /// they are, so `map` must not send any voxels into them.
+    fn move_voxels<F: Fn(TerrainVec) -> TerrainVec>(&mut self, map: F) {
+        self.mark_all_dirty();
+
+        let (chunks, kept): (BTreeMap<_, _>, BTreeMap<_, _>) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|(chunk_position, _)| chunk_position.fits_in_terrain_vec());
+        self.chunks = kept;
+
+        for (chunk_position, chunk) in &chunks {
+            for (voxel_position, voxel) in chunk.iter_voxels() {
+                let position = TerrainVec::from_chunk(*chunk_position, voxel_position);
+                self.write_voxel_world(map(position), *voxel);
+            }
+        }
+    }
+
+    /// Compares this grid to `other` and returns the voxels that would need
+    /// to change to turn this grid into `other`.
+    ///
+    /// Chunks that are missing from a grid are treated as if they were
+    /// completely empty.
+    pub fn diff(&self, other: &SmoothGrid) -> TerrainDiff {
+        let empty = Chunk::new();
+        let positions: BTreeSet<_> = self.chunks.keys().chain(other.chunks.keys()).collect();
+        let mut chunks = BTreeMap::new();
+
+        for &position in positions {
+            let before = self.chunks.get(&position);
+            let after = other.chunks.get(&position);
+
+            let mut chunk_diff = ChunkDiff {
+                existed: before.is_some(),
+                exists: after.is_some(),
+                added: 0,
+                removed: 0,
+                changed: 0,
+                voxels: Vec::new(),
+            };
+
+            let before_voxels = &before.unwrap_or(&empty).voxels;
+            let after_voxels = &after.unwrap_or(&empty).voxels;
+
+            for (index, (old, new)) in before_voxels.iter().zip(after_voxels).enumerate() {
+                if old == new {
+                    continue;
+                }
+
+                match (old.is_empty(), new.is_empty()) {
+                    (true, false) => chunk_diff.added += 1,
+                    (false, true) => chunk_diff.removed += 1,
+                    _ => chunk_diff.changed += 1,
+                }
+
+                chunk_diff
+                    .voxels
+                    .push((VoxelCoordinates::from_index(index), *new));
+            }
+
+            if !chunk_diff.voxels.is_empty() || chunk_diff.existed != chunk_diff.exists {
+                chunks.insert(position, chunk_diff);
+            }
+        }
+
+        TerrainDiff { chunks }
+    }
+
+    /// Counts the chunks and voxels in the grid, and the size of the blob
+    /// [`SmoothGrid::encode`] would produce, without keeping the blob around.
+    /// Useful for keeping terrain within a budget.
+    pub fn stats(&self) -> TerrainStats {
+        let mut stats = TerrainStats {
+            chunks: self.chunks.len(),
+            material_voxels: BTreeMap::new(),
+            water_voxels: 0,
+            encoded_size: 2,
+        };
+
+        let mut buffer = Vec::new();
+        let mut previous = ChunkCoordinates::default();
+
+        for (position, chunk) in &self.chunks {
+            for voxel in chunk.voxels.iter() {
+                if let Some(material) = voxel.material.filter(|_| voxel.occupancy > 0) {
+                    *stats.material_voxels.entry(material).or_insert(0) += 1;
+                }
+                if voxel.water_occupancy > 0 {
+                    stats.water_voxels += 1;
+                }
+            }
+
+            buffer.clear();
+            encode_chunk_position(&mut buffer, *position, previous);
+            encode_chunk_voxels(&mut buffer, &chunk.voxels, EncodeOptions::default());
+            previous = *position;
+
+            stats.encoded_size += buffer.len();
+        }
+
+        stats
+    }
+
+    /// Looks for data that Roblox would reject or quietly change when loading
+    /// the grid, like chunks too far from the origin or water in voxels that
+    /// are already full. Grids read with [`SmoothGrid::decode`] can contain
+    /// problems with their voxels, and grids that were edited directly can
+    /// contain any of these.
+    ///
+    /// Problems are returned in the order their chunks are encoded, and in
+    /// the same order as [`Chunk::iter_voxels`] within each chunk. The voxels
+    /// of chunks that are out of range aren't checked, since their positions
+    /// may not fit in a [`TerrainVec`].
+    pub fn validate(&self) -> Vec<TerrainDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for (&chunk_position, chunk) in &self.chunks {
+            if !chunk_position.is_in_range() {
+                diagnostics.push(TerrainDiagnostic::ChunkOutOfRange {
+                    position: chunk_position,
+                });
+                continue;
+            }
+
+            // Voxels that count as empty can still hold bad data, so every
+            // voxel is checked instead of using `iter_voxels`.
+            for (index, voxel) in chunk.voxels.iter().enumerate() {
+                let position =
+                    TerrainVec::from_chunk(chunk_position, VoxelCoordinates::from_index(index));
+
+                match voxel.material {
+                    Some(material) if voxel.occupancy == 0 => diagnostics
+                        .push(TerrainDiagnostic::MaterialWithoutOccupancy { position, material }),
+                    Some(_) if voxel.occupancy == u8::MAX && voxel.water_occupancy > 0 => {
+                        diagnostics.push(TerrainDiagnostic::WaterInFullSolid {
+                            position,
+                            water_occupancy: voxel.water_occupancy,
+                        })
+                    }
+                    None if voxel.occupancy > 0 => {
+                        diagnostics.push(TerrainDiagnostic::OccupancyWithoutMaterial {
+                            position,
+                            occupancy: voxel.occupancy,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Returns an iterator over every chunk in the grid, in the order they are
+    /// encoded.
+    pub fn chunks(&self) -> btree_map::Iter<'_, ChunkCoordinates, Chunk> {
+        self.chunks.iter()
+    }
+
+    /// Returns an iterator over every voxel in the grid that isn't empty,
+    /// along with the position of its center in world space, measured in
+    /// studs. This is what exporters to meshes and point clouds need.
+    ///
+    /// Chunks are visited in the order they are encoded, and the voxels within
+    /// each chunk in the same order as [`Chunk::iter_voxels`].
+    pub fn iter_voxel_centers(&self) -> VoxelCenters<'_> {
+        VoxelCenters {
+            chunks: self.chunks.iter(),
+            current: None,
+        }
+    }
+
+    /// Returns an iterator over every chunk in the grid, starting with the
+    /// chunks closest to `center`.
+    ///
+    /// Chunks are ordered by their straight-line distance from `center`, and
+    /// chunks at the same distance are returned in the order they are
+    /// encoded.
+    pub fn iter_chunks_near(&self, center: ChunkCoordinates) -> ChunksNear<'_> {
+        let distance = |position: &ChunkCoordinates| {
+            let dx = i128::from(position.x) - i128::from(center.x);
+            let dy = i128::from(position.y) - i128::from(center.y);
+            let dz = i128::from(position.z) - i128::from(center.z);
+            dx * dx + dy * dy + dz * dz
+        };
+
+        let mut chunks: Vec<_> = self.chunks.iter().collect();
+        // The sort is stable, so ties keep the order of the BTreeMap.
+        chunks.sort_by_key(|(position, _)| distance(position));
+
+        ChunksNear {
+            inner: chunks.into_iter(),
+        }
+    }
+
+    /// Encodes the `SmoothGrid` into a binary blob that can be understood by
+    /// Roblox.
+    ///
+    /// When the `rayon` feature is enabled, chunks are encoded in parallel.
+    /// The result is the same either way.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.encode_into(&mut buffer);
+
+        buffer
+    }
+
+    /// Encodes the `SmoothGrid` in the same way as [`SmoothGrid::encode`],
+    /// using the given options.
+    ///
+    /// Returns an error if the options change the size of the chunks and the
+    /// grid has a chunk too far from the origin for its voxels' positions to
+    /// fit in a [`TerrainVec`], since it can't be split up.
+    pub fn encode_with(&self, options: EncodeOptions) -> Result<Vec<u8>, CrateError> {
+        let mut buffer = Vec::new();
+        self.encode_into_with(&mut buffer, options)?;
+
+        Ok(buffer)
+    }
+
+    /// Encodes the `SmoothGrid` in the same way as [`SmoothGrid::encode`],
+    /// replacing the contents of `buffer`. The buffer's allocation is reused,
+    /// which helps when the same grid is encoded over and over, like while
+    /// syncing it live.
+    pub fn encode_into(&self, buffer: &mut Vec<u8>) {
+        buffer.clear();
+
+        // Writing to a Vec can't fail, and chunks only need to fit in a
+        // `TerrainVec` when they're resized.
+        self.encode_to_with(buffer, EncodeOptions::default())
+            .unwrap();
+    }
+
+    /// Encodes the `SmoothGrid` in the same way as
+    /// [`SmoothGrid::encode_into`], using the given options. Returns an error
+    /// in the same cases as [`SmoothGrid::encode_with`].
+    pub fn encode_into_with(
+        &self,
+        buffer: &mut Vec<u8>,
+        options: EncodeOptions,
+    ) -> Result<(), CrateError> {
+        buffer.clear();
+        self.check_resizable(options)?;
+
+        // Writing to a Vec can't fail, and the grid was just checked.
+        self.encode_to_with(buffer, options).unwrap();
+
+        Ok(())
+    }
+
+    /// Encodes the `SmoothGrid` in the same way as [`SmoothGrid::encode`],
+    /// writing it to `writer` one chunk at a time instead of building the
+    /// whole blob in memory first.
+    pub fn encode_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.encode_to_with(writer, EncodeOptions::default())
+    }
+
+    /// Encodes the `SmoothGrid` in the same way as [`SmoothGrid::encode_to`],
+    /// using the given options.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidInput`] without
+    /// writing anything in the same cases as [`SmoothGrid::encode_with`].
+    pub fn encode_to_with<W: Write>(
+        &self,
+        mut writer: W,
+        options: EncodeOptions,
+    ) -> io::Result<()> {
+        self.check_resizable(options)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+
+        writer.write_all(&[SMOOTH_GRID_VERSION, options.chunk_size_log2])?;
+
+        if options.chunk_size_log2 != CHUNK_SIZE_LOG2 {
+            return self.encode_resized(writer, options);
+        }
+
+        let mut buffer = Vec::new();
+        let mut previous = ChunkCoordinates::default();
+
+        // Chunk voxels don't depend on any other chunk, so with the `rayon`
+        // feature they're encoded in parallel, a batch at a time. Only the
+        // chunk positions, which are relative to the chunk before them, are
+        // written in order.
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            let chunks: Vec<_> = self.chunks.iter().collect();
+
+            for batch in chunks.chunks(PARALLEL_ENCODE_BATCH) {
+                let encoded: Vec<_> = batch
+                    .par_iter()
+                    .map(|(_, chunk)| {
+                        let mut encoded = Vec::new();
+                        encode_chunk_voxels(&mut encoded, &chunk.voxels, options);
+                        encoded
+                    })
+                    .collect();
+
+                for ((position, _), encoded) in batch.iter().zip(encoded) {
+                    buffer.clear();
+                    encode_chunk_position(&mut buffer, **position, previous);
+                    previous = **position;
+
+                    writer.write_all(&buffer)?;
+                    writer.write_all(&encoded)?;
+                }
+            }
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        for (position, chunk) in &self.chunks {
+            buffer.clear();
+            encode_chunk_position(&mut buffer, *position, previous);
+            previous = *position;
+
+            encode_chunk_voxels(&mut buffer, &chunk.voxels, options);
+            writer.write_all(&buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if `options` change the size of the chunks and the
+    /// grid has a chunk whose voxels' positions don't fit in a `TerrainVec`.
+    fn check_resizable(&self, options: EncodeOptions) -> Result<(), TerrainError> {
+        if options.chunk_size_log2 == CHUNK_SIZE_LOG2 {
+            return Ok(());
+        }
+
+        match self
+            .chunks
+            .keys()
+            .find(|position| !position.fits_in_terrain_vec())
+        {
+            Some(position) => Err(TerrainError::ChunkOutOfRange(*position)),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the chunks of the grid after splitting or combining them into
+    /// chunks of the size set by `options`. Every chunk that overlaps one of
+    /// the grid's chunks is written.
+    fn encode_resized<W: Write>(&self, mut writer: W, options: EncodeOptions) -> io::Result<()> {
+        let size = 1i32 << options.chunk_size_log2;
+
+        let mut positions = BTreeSet::new();
+        for position in self.chunks.keys() {
+            let lower = TerrainVec::from_chunk(*position, VoxelCoordinates::default());
+            let upper = TerrainVec::new(
+                lower.x + (CHUNK_SIZE - 1) as i32,
+                lower.y + (CHUNK_SIZE - 1) as i32,
+                lower.z + (CHUNK_SIZE - 1) as i32,
+            );
+
+            for x in lower.x.div_euclid(size)..=upper.x.div_euclid(size) {
+                for y in lower.y.div_euclid(size)..=upper.y.div_euclid(size) {
+                    for z in lower.z.div_euclid(size)..=upper.z.div_euclid(size) {
+                        positions.insert(ChunkCoordinates::new(x, y, z));
+                    }
+                }
+            }
+        }
+
+        let mut buffer = Vec::new();
+        let mut voxels = Vec::new();
+        let mut previous = ChunkCoordinates::default();
+
+        for position in positions {
+            buffer.clear();
+            encode_chunk_position(&mut buffer, position, previous);
+            previous = position;
+
+            voxels.clear();
+            voxels.extend(resized_chunk_voxels(position, options.chunk_size_log2).map(
+                |voxel_position| *self.get_voxel_world(voxel_position).unwrap_or(&EMPTY_VOXEL),
+            ));
+
+            encode_chunk_voxels(&mut buffer, &voxels, options);
+            writer.write_all(&buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Decodes a `SmoothGrid` from a binary blob. The blob must be the same
+    /// format used by `encode` and Roblox. Terrain from before smooth terrain
+    /// is stored in a different format, which can't be read.
+    ///
+    /// Version `0` blobs, written by older versions of Roblox, can be read
+    /// too. They can't have voxels with both a solid material and water, so
+    /// an error is returned if one does. Grids are always encoded as version
+    /// `1`, so decoding and encoding a version `0` blob upgrades it.
+    ///
+    /// Chunks must be within the range Roblox allows terrain to be placed in,
+    /// like those made with [`ChunkCoordinates::try_new`], so that the
+    /// position of every voxel fits in a [`TerrainVec`].
+    ///
+    /// Blobs with chunks of a size other than [`CHUNK_SIZE`], like ones
+    /// written with [`EncodeOptions::chunk_size_log2`], can be read too.
+    /// Their voxels are moved into chunks of `CHUNK_SIZE`, and chunks are
+    /// only created for voxels that aren't empty.
+    pub fn decode(buffer: &[u8]) -> Result<Self, CrateError> {
+        Self::decode_from(buffer)
+    }
+
+    /// Decodes a `SmoothGrid` in the same way as [`SmoothGrid::decode`],
+    /// reading it from `reader` one chunk at a time instead of requiring the
+    /// whole blob in memory first.
+    ///
+    /// The reader is read until it ends, a few bytes at a time, so it should
+    /// be buffered.
+    pub fn decode_from<R: Read>(reader: R) -> Result<Self, CrateError> {
+        let mut grid = Self::new();
+
+        visit_chunks(reader, |position, chunk_size_log2, voxels| {
+            grid.insert_decoded_chunk(position, chunk_size_log2, mem::take(voxels));
+            Ok(())
+        })?;
+
+        Ok(grid)
+    }
+
+    /// Decodes a binary blob from `reader` one chunk at a time, calling
+    /// `visit` with the position and contents of each chunk instead of
+    /// building a `SmoothGrid`. Only one chunk is held in memory at a time,
+    /// which suits jobs that look at each chunk on its own, like gathering
+    /// statistics about a huge place.
+    ///
+    /// The blob is validated the same way as by [`SmoothGrid::decode`], and
+    /// chunks are visited in the order they're stored. Unlike `decode`, the
+    /// blob's chunks must be [`CHUNK_SIZE`] voxels along each side, since
+    /// they're passed along as they're read.
+    ///
+    /// The reader is read until it ends, a few bytes at a time, so it should
+    /// be buffered.
+    pub fn decode_chunks<R, F>(reader: R, mut visit: F) -> Result<(), CrateError>
+    where
+        R: Read,
+        F: FnMut(ChunkCoordinates, &Chunk),
+    {
+        visit_chunks(reader, |position, chunk_size_log2, voxels| {
+            if chunk_size_log2 != CHUNK_SIZE_LOG2 {
+                return Err(TerrainError::UnsupportedChunkSize(chunk_size_log2).into());
+            }
+
+            // The chunk's voxels are handed back afterwards so that their
+            // allocation is reused for the next chunk.
+            let chunk = Chunk {
+                voxels: mem::take(voxels).into_boxed_slice(),
+            };
+            visit(position, &chunk);
+            *voxels = chunk.voxels.into_vec();
+
+            Ok(())
+        })
+    }
+
+    /// Stores the voxels of a chunk read from a blob whose chunks are
+    /// `2^chunk_size_log2` voxels on each side, moving them into chunks of
+    /// `CHUNK_SIZE` if they're a different size.
+    fn insert_decoded_chunk(
+        &mut self,
+        position: ChunkCoordinates,
+        chunk_size_log2: u8,
+        voxels: Vec<Voxel>,
+    ) {
+        if chunk_size_log2 == CHUNK_SIZE_LOG2 {
+            self.chunks.insert(
+                position,
+                Chunk {
+                    voxels: voxels.into_boxed_slice(),
+                },
+            );
+            return;
+        }
+
+        for (voxel_position, voxel) in resized_chunk_voxels(position, chunk_size_log2).zip(voxels) {
+            if !voxel.is_empty() {
+                self.write_voxel_world(voxel_position, voxel);
+            }
+        }
+    }
+
+    /// Counts the chunks in a binary blob without decoding them into a
+    /// `SmoothGrid`. The blob is validated the same way as by `decode`, so
+    /// this returns an error whenever `decode` would.
+    pub fn count_chunks(buffer: &[u8]) -> Result<usize, CrateError> {
+        let mut reader = BlobReader { reader: buffer };
+        let BlobHeader {
+            version,
+            chunk_size_log2,
+        } = decode_header(&mut reader)?;
+        let volume = 1 << (3 * chunk_size_log2);
+
+        let mut positions = BTreeSet::new();
+        let mut previous = ChunkCoordinates::default();
+
+        while let Some(header) = reader.next_u8()? {
+            let position = decode_chunk_position(&mut reader, header, previous)?;
+            previous = position;
+
+            if !position.is_in_range_for(chunk_size_log2) {
+                return Err(TerrainError::ChunkOutOfRange(position).into());
+            }
+
+            let mut len = 0;
+            while len < volume {
+                let (_, count) = decode_run(&mut reader, version)?;
+
+                len += count;
+                if len > volume {
+                    return Err(TerrainError::RunOverflow.into());
+                }
+            }
+
+            if !positions.insert(position) {
+                return Err(TerrainError::DuplicateChunk(position).into());
+            }
+        }
+
+        Ok(positions.len())
+    }
+}
+
+/// Returns the lowest and highest voxels that overlap `region`, which is
+/// measured in studs.
+///
+/// The voxels are clamped to the range Roblox allows terrain to be placed in,
+/// which also keeps huge and infinite regions from overflowing. Regions that
+/// are entirely outside of that range have no voxels.
+fn region_voxels(region: Region3) -> (TerrainVec, TerrainVec) {
+    let lower = |value: f32| {
+        ((value / VOXEL_SIZE).floor() as i32).clamp(-MAX_VOXEL_COORDINATE, MAX_VOXEL_COORDINATE)
+    };
+    let upper = |value: f32| {
+        ((value / VOXEL_SIZE).ceil() as i32)
+            .saturating_sub(1)
+            .clamp(-MAX_VOXEL_COORDINATE - 1, MAX_VOXEL_COORDINATE - 1)
+    };
+
+    (
+        TerrainVec::new(
+            lower(region.min.x),
+            lower(region.min.y),
+            lower(region.min.z),
+        ),
+        TerrainVec::new(
+            upper(region.max.x),
+            upper(region.max.y),
+            upper(region.max.z),
+        ),
+    )
+}
+
+/// Returns how much of `voxel` is filled with solid material.
+fn solid_occupancy(voxel: &Voxel) -> u8 {
+    if voxel.material.is_some() {
+        voxel.occupancy
+    } else {
+        0
+    }
+}
+
+/// Returns `voxel` with its solid occupancy changed to `occupancy`, removing
+/// its material if that leaves it with none.
+fn with_solid_occupancy(voxel: Voxel, occupancy: u8) -> Voxel {
+    if voxel.material.is_none() || occupancy == 0 {
+        Voxel::water(voxel.water_occupancy)
+    } else {
+        Voxel { occupancy, ..voxel }
+    }
+}
+
+/// Returns the lowest and highest voxels that are both inside of the chunk at
+/// `chunk_position` and between `min` and `max` inclusive.
+fn chunk_overlap(
+    chunk_position: ChunkCoordinates,
+    min: TerrainVec,
+    max: TerrainVec,
+) -> (TerrainVec, TerrainVec) {
+    let chunk_min = TerrainVec::from_chunk(chunk_position, VoxelCoordinates::new(0, 0, 0));
+    let last = (CHUNK_SIZE - 1) as i32;
+
+    let lower = TerrainVec::new(
+        min.x.max(chunk_min.x),
+        min.y.max(chunk_min.y),
+        min.z.max(chunk_min.z),
+    );
+    let upper = TerrainVec::new(
+        max.x.min(chunk_min.x + last),
+        max.y.min(chunk_min.y + last),
+        max.z.min(chunk_min.z + last),
+    );
+
+    (lower, upper)
+}
+
+/// The voxels covered by one voxel of a downsampled grid, summed up by
+/// [`SmoothGrid::downsample`].
+#[derive(Default)]
+struct DownsampledVoxel {
+    occupancy: u64,
+    water_occupancy: u64,
+    /// The total occupancy of each material, in the order they were found.
+    materials: Vec<(TerrainMaterials, u64)>,
+}
+
+impl DownsampledVoxel {
+    fn add(&mut self, voxel: &Voxel) {
+        self.water_occupancy += u64::from(voxel.water_occupancy);
+
+        let material = match voxel.material {
+            Some(material) if voxel.occupancy > 0 => material,
+            _ => return,
+        };
+        let occupancy = u64::from(voxel.occupancy);
+        self.occupancy += occupancy;
+
+        match self
+            .materials
+            .iter_mut()
+            .find(|(found, _)| *found == material)
+        {
+            Some((_, total)) => *total += occupancy,
+            None => self.materials.push((material, occupancy)),
+        }
+    }
+
+    fn finish(&self, volume: u64) -> Voxel {
+        let average = |total: u64| ((total + volume / 2) / volume) as u8;
+
+        let mut material = None;
+        let mut most = 0;
+        for (found, total) in &self.materials {
+            if *total > most {
+                material = Some(*found);
+                most = *total;
+            }
+        }
+
+        match material {
+            Some(material) => Voxel {
+                material: Some(material),
+                occupancy: average(self.occupancy),
+                water_occupancy: average(self.water_occupancy),
+            },
+            None => Voxel::water(average(self.water_occupancy)),
+        }
+    }
+}
+
+/// A dense copy of the voxels in a box-shaped region of a [`SmoothGrid`],
+/// created by [`SmoothGrid::read_voxels`].
+///
+/// The material, occupancy, and water occupancy of each voxel are stored in
+/// separate arrays. In each array, X changes fastest, then Y, then Z.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxelRegion {
+    min: TerrainVec,
+    size: [usize; 3],
+    materials: Vec<Option<TerrainMaterials>>,
+    occupancy: Vec<u8>,
+    water_occupancy: Vec<u8>,
+}
+
+impl VoxelRegion {
+    fn new(min: TerrainVec, max: TerrainVec) -> Self {
+        let length = |min: i32, max: i32| (i64::from(max) - i64::from(min) + 1).max(0) as usize;
+        let size = [
+            length(min.x, max.x),
+            length(min.y, max.y),
+            length(min.z, max.z),
+        ];
+        let volume = size[0] * size[1] * size[2];
+
+        Self {
+            min,
+            size,
+            materials: vec![None; volume],
+            occupancy: vec![0; volume],
+            water_occupancy: vec![0; volume],
+        }
+    }
+
+    /// The position of the voxel at the lowest corner of the region.
+    pub fn min(&self) -> TerrainVec {
+        self.min
+    }
+
+    /// The number of voxels along the X, Y, and Z axes of the region.
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    /// Returns `true` if the region contains no voxels.
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// The material of each voxel, or `None` for voxels that are empty or
+    /// only contain water.
+    pub fn materials(&self) -> &[Option<TerrainMaterials>] {
+        &self.materials
+    }
+
+    /// The solid occupancy of each voxel.
+    pub fn occupancy(&self) -> &[u8] {
+        &self.occupancy
+    }
+
+    /// The water occupancy of each voxel.
+    pub fn water_occupancy(&self) -> &[u8] {
+        &self.water_occupancy
+    }
+
+    /// Returns the index of the voxel at `position` in the region's arrays, or
+    /// `None` if the region does not contain it.
+    pub fn index(&self, position: TerrainVec) -> Option<usize> {
+        let offset = |position: i32, min: i32, size: usize| {
+            usize::try_from(i64::from(position) - i64::from(min))
+                .ok()
+                .filter(|&offset| offset < size)
+        };
+
+        let x = offset(position.x, self.min.x, self.size[0])?;
+        let y = offset(position.y, self.min.y, self.size[1])?;
+        let z = offset(position.z, self.min.z, self.size[2])?;
+
+        Some(x + y * self.size[0] + z * self.size[0] * self.size[1])
+    }
+
+    /// Returns the voxel at `position`, or `None` if the region does not
+    /// contain it.
+    pub fn get_voxel(&self, position: TerrainVec) -> Option<Voxel> {
+        self.index(position).map(|index| Voxel {
+            material: self.materials[index],
+            occupancy: self.occupancy[index],
+            water_occupancy: self.water_occupancy[index],
+        })
+    }
+}
+
+/// A dense 3D array of terrain, created by [`SmoothGrid::to_volume`].
+///
+/// Both arrays hold one byte per texel, with X changing fastest, then Y, then
+/// Z, so they can be uploaded directly as 8-bit 3D textures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Volume {
+    /// The number of texels along the X, Y, and Z axes.
+    pub dims: [usize; 3],
+
+    /// How full each texel is, where `255` is completely full. This is the
+    /// water occupancy for texels that only contain water.
+    pub occupancy: Vec<u8>,
+
+    /// The material ID of each texel. `0` is air, `1` is water, and solid
+    /// materials are numbered from `2` in the order of [`TerrainMaterials::ALL`],
+    /// the same IDs used by the `SmoothGrid` format.
+    pub material: Vec<u8>,
+}
+
+/// Counts of what a [`SmoothGrid`] holds, created by [`SmoothGrid::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainStats {
+    /// The number of chunks in the grid.
+    pub chunks: usize,
+
+    /// The number of voxels containing each solid material. Materials that
+    /// aren't used have no entry.
+    pub material_voxels: BTreeMap<TerrainMaterials, usize>,
+
+    /// The number of voxels containing any water, including voxels that also
+    /// contain a solid material.
+    pub water_voxels: usize,
+
+    /// The length in bytes of the blob [`SmoothGrid::encode`] would produce.
+    pub encoded_size: usize,
+}
+
+/// A problem with the data in a [`SmoothGrid`], found by
+/// [`SmoothGrid::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum TerrainDiagnostic {
+    /// The chunk holds voxels more than 2^23 voxels from the origin, which
+    /// Roblox doesn't allow.
+    #[error("chunk {position:?} is outside of the range terrain can be placed in")]
+    ChunkOutOfRange {
+        /// The position of the chunk.
+        position: ChunkCoordinates,
+    },
+
+    /// The voxel is completely full of a solid material but also has water,
+    /// which Roblox drops.
+    #[error("voxel {position:?} is completely solid but also has water ({water_occupancy})")]
+    WaterInFullSolid {
+        /// The position of the voxel.
+        position: TerrainVec,
+        /// How much water the voxel has.
+        water_occupancy: u8,
+    },
+
+    /// The voxel has a material but an occupancy of `0`, so the material is
+    /// ignored.
+    #[error("voxel {position:?} has the material {material:?} but no occupancy")]
+    MaterialWithoutOccupancy {
+        /// The position of the voxel.
+        position: TerrainVec,
+        /// The material the voxel has.
+        material: TerrainMaterials,
+    },
+
+    /// The voxel has an occupancy but no material, so the occupancy is
+    /// ignored.
+    #[error("voxel {position:?} has an occupancy of {occupancy} but no material")]
+    OccupancyWithoutMaterial {
+        /// The position of the voxel.
+        position: TerrainVec,
+        /// The occupancy the voxel has.
+        occupancy: u8,
+    },
+}
+
+/// The voxel hit by [`SmoothGrid::raycast`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainHit {
+    /// The position of the voxel that was hit.
+    pub position: TerrainVec,
+
+    /// Where the ray entered the voxel, measured in studs.
+    pub point: Vector3,
+
+    /// The direction the face of the voxel that the ray entered through is
+    /// facing, which is zero if the ray started inside of the voxel.
+    pub normal: Vector3,
+
+    /// How far the ray went before hitting the voxel, measured in studs.
+    pub distance: f32,
+
+    /// The solid material of the voxel.
+    pub material: TerrainMaterials,
+}
+
+/// The outcome of [`SmoothGrid::flood_fill`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloodFill {
+    /// The number of voxels that were filled.
+    pub filled: usize,
+
+    /// Whether the fill stopped at the bounds somewhere it would otherwise
+    /// have kept going, meaning the filled area isn't enclosed within them.
+    pub reached_bounds: bool,
+}
+
+/// An iterator over the chunks in a [`SmoothGrid`] ordered by distance,
+/// created by [`SmoothGrid::iter_chunks_near`].
+#[derive(Debug, Clone)]
+pub struct ChunksNear<'a> {
+    inner: vec::IntoIter<(&'a ChunkCoordinates, &'a Chunk)>,
+}
+
+impl<'a> Iterator for ChunksNear<'a> {
+    type Item = (&'a ChunkCoordinates, &'a Chunk);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ChunksNear<'_> {}
+
+/// An iterator over the voxels in a [`SmoothGrid`] that aren't empty and the
+/// centers of them in world space, created by
+/// [`SmoothGrid::iter_voxel_centers`].
+#[derive(Debug, Clone)]
+pub struct VoxelCenters<'a> {
+    chunks: btree_map::Iter<'a, ChunkCoordinates, Chunk>,
+
+    /// The chunk currently being visited and its remaining voxels.
+    current: Option<(ChunkCoordinates, ChunkVoxels<'a>)>,
+}
+
+impl<'a> Iterator for VoxelCenters<'a> {
+    type Item = (Vector3, &'a Voxel);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((chunk_position, voxels)) = &mut self.current {
+                if let Some((position, voxel)) = voxels.next() {
+                    // Chunks can be far enough away that their voxels'
+                    // positions don't fit in a `TerrainVec`, so the center is
+                    // worked out directly instead.
+                    let center = |chunk: i32, voxel: u8| {
+                        let voxel = f64::from(chunk) * CHUNK_SIZE as f64 + f64::from(voxel);
+                        ((voxel + 0.5) * f64::from(VOXEL_SIZE)) as f32
+                    };
+
+                    let center = Vector3::new(
+                        center(chunk_position.x, position.x()),
+                        center(chunk_position.y, position.y()),
+                        center(chunk_position.z, position.z()),
+                    );
+                    return Some((center, voxel));
+                }
+            }
+
+            let (chunk_position, chunk) = self.chunks.next()?;
+            self.current = Some((*chunk_position, chunk.iter_voxels()));
+        }
+    }
+}
+
+/// An iterator over every voxel in a box-shaped region of a [`SmoothGrid`],
+/// created by [`SmoothGrid::iter_region`].
+#[derive(Debug, Clone)]
+pub struct RegionVoxels<'a> {
+    grid: &'a SmoothGrid,
+    min: TerrainVec,
+    max: TerrainVec,
+
+    /// The chunk currently being visited, or `None` if it hasn't been
+    /// written.
+    chunk: Option<&'a Chunk>,
+
+    /// The lowest and highest voxels in the region that are in the current
+    /// chunk.
+    lower: TerrainVec,
+    upper: TerrainVec,
+
+    /// The next voxel to return from the current chunk, or `None` once the
+    /// chunk is finished.
+    position: Option<TerrainVec>,
+
+    /// The chunk to visit after the current one, or `None` if this is the
+    /// last.
+    next_chunk: Option<ChunkCoordinates>,
+}
+
+impl<'a> RegionVoxels<'a> {
+    /// Moves on to the next chunk that overlaps the region, returning `false`
+    /// if there are none left.
+    fn advance_chunk(&mut self) -> bool {
+        let chunk_position = match self.next_chunk.take() {
+            Some(chunk_position) => chunk_position,
+            None => return false,
+        };
+
+        let (lower, upper) = chunk_overlap(chunk_position, self.min, self.max);
+        self.chunk = self.grid.get_chunk(chunk_position);
+        self.lower = lower;
+        self.upper = upper;
+        self.position = Some(lower);
+
+        let min_chunk = self.min.chunk_coordinates();
+        let max_chunk = self.max.chunk_coordinates();
+        let ChunkCoordinates {
+            mut x,
+            mut y,
+            mut z,
+        } = chunk_position;
+
+        x += 1;
+        if x > max_chunk.x {
+            x = min_chunk.x;
+            z += 1;
+        }
+        if z > max_chunk.z {
+            z = min_chunk.z;
+            y += 1;
+        }
+
+        if y <= max_chunk.y {
+            self.next_chunk = Some(ChunkCoordinates::new(x, y, z));
+        }
+
+        true
+    }
+}
+
+impl<'a> Iterator for RegionVoxels<'a> {
+    type Item = (TerrainVec, &'a Voxel);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(position) = self.position {
+                let mut next = position;
+
+                next.x += 1;
+                if next.x > self.upper.x {
+                    next.x = self.lower.x;
+                    next.z += 1;
+                }
+                if next.z > self.upper.z {
+                    next.z = self.lower.z;
+                    next.y += 1;
+                }
+                self.position = Some(next).filter(|next| next.y <= self.upper.y);
+
+                let voxel = match self.chunk {
+                    Some(chunk) => chunk.get_voxel(position.voxel_coordinates()),
+                    None => &EMPTY_VOXEL,
+                };
+
+                return Some((position, voxel));
+            }
+
+            if !self.advance_chunk() {
+                return None;
+            }
+        }
+    }
+}
+
+/// The height and material of the top solid voxel in each column of a region,
+/// created by [`SmoothGrid::heightmap`].
+///
+/// Heights are stored as 16-bit grayscale, where 0 is the bottom of the region
+/// and `u16::MAX` is the top. The occupancy of the top voxel is included, so
+/// a voxel that is half full is half a voxel shorter than one that is
+/// completely full. Columns without any solid voxels have a height of 0 and
+/// no material.
+///
+/// Both arrays have one entry per column, where X changes fastest, then Z.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heightmap {
+    width: usize,
+    depth: usize,
+    heights: Vec<u16>,
+    materials: Vec<Option<TerrainMaterials>>,
+}
+
+impl Heightmap {
+    fn from_region(region: &VoxelRegion) -> Self {
+        let [width, height, depth] = region.size();
+        let mut heights = vec![0; width * depth];
+        let mut materials = vec![None; width * depth];
+
+        for z in 0..depth {
+            for x in 0..width {
+                let column = x + z * width;
+
+                for y in (0..height).rev() {
+                    let index = x + y * width + z * width * height;
+                    let occupancy = region.occupancy[index];
+
+                    if let (Some(material), true) = (region.materials[index], occupancy > 0) {
+                        let top = y as f64 + f64::from(occupancy) / f64::from(u8::MAX);
+                        heights[column] =
+                            (top / height as f64 * f64::from(u16::MAX)).round() as u16;
+                        materials[column] = Some(material);
+                        break;
+                    }
+                }
+            }
+        }
+
+        Self {
+            width,
+            depth,
+            heights,
+            materials,
+        }
+    }
+
+    /// The number of columns along the X axis.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of columns along the Z axis.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The height of each column as 16-bit grayscale.
+    pub fn heights(&self) -> &[u16] {
+        &self.heights
+    }
+
+    /// The material of the top solid voxel in each column.
+    pub fn materials(&self) -> &[Option<TerrainMaterials>] {
+        &self.materials
+    }
+}
+
+/// Controls how [`SmoothGrid::encode_with`] writes a grid.
+///
+/// By default, grids are written exactly as Roblox writes them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeOptions {
+    shorelines: bool,
+    chunk_size_log2: u8,
+}
+
+impl EncodeOptions {
+    /// Creates an `EncodeOptions` with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether voxels can hold both a solid material and water, which
+    /// Roblox calls shorelines. Older clients and some tools can't read
+    /// voxels like this.
+    ///
+    /// If this is `false`, each of those voxels is written with whichever of
+    /// its material or water fills more of it, and solid material is kept
+    /// when they fill it equally.
+    pub fn shorelines(self, shorelines: bool) -> Self {
+        Self { shorelines, ..self }
+    }
+
+    /// Sets the size of the chunks that are written, as the base 2 logarithm
+    /// of the number of voxels along each side. Roblox only reads chunks of
+    /// [`CHUNK_SIZE`], which is `5`, so other sizes are only useful for tools
+    /// that expect them.
+    ///
+    /// Values above `7` are treated as `7`.
+    pub fn chunk_size_log2(self, chunk_size_log2: u8) -> Self {
+        Self {
+            chunk_size_log2: chunk_size_log2.min(MAX_CHUNK_SIZE_LOG2),
+            ..self
+        }
+    }
+
+    fn prepare(&self, voxel: Voxel) -> Voxel {
+        if self.shorelines || voxel.material.is_none() || voxel.water_occupancy == 0 {
+            voxel
+        } else if voxel.water_occupancy > voxel.occupancy {
+            Voxel::water(voxel.water_occupancy)
+        } else {
+            Voxel {
+                water_occupancy: 0,
+                ..voxel
+            }
+        }
+    }
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            shorelines: true,
+            chunk_size_log2: CHUNK_SIZE_LOG2,
+        }
+    }
+}
+
+/// Controls how [`SmoothGrid::merge`] combines two grids.
+///
+/// By default, empty voxels in the incoming grid are ignored, and the incoming
+/// grid wins wherever both grids have something in the same voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergePolicy {
+    replace_with_empty: bool,
+    conflicts: MergeConflict,
+}
+
+impl MergePolicy {
+    /// Creates a `MergePolicy` with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether empty voxels in the incoming grid clear the voxels they
+    /// land on. Only voxels inside of chunks that the incoming grid contains
+    /// are affected.
+    pub fn replace_with_empty(self, replace_with_empty: bool) -> Self {
+        Self {
+            replace_with_empty,
+            ..self
+        }
+    }
+
+    /// Sets which voxel is kept when both grids have something in the same
+    /// voxel.
+    pub fn conflicts(self, conflicts: MergeConflict) -> Self {
+        Self { conflicts, ..self }
+    }
+
+    fn resolve(&self, existing: Voxel, incoming: Voxel) -> Voxel {
+        if incoming.is_empty() {
+            return if self.replace_with_empty {
+                incoming
+            } else {
+                existing
+            };
+        }
+
+        if existing.is_empty() {
+            return incoming;
+        }
+
+        match self.conflicts {
+            MergeConflict::Incoming => incoming,
+            MergeConflict::Existing => existing,
+            MergeConflict::MostOccupied => {
+                let solid = |voxel: &Voxel| voxel.material.map_or(0, |_| voxel.occupancy);
+
+                if solid(&existing) > solid(&incoming) {
+                    existing
+                } else {
+                    incoming
+                }
+            }
+        }
+    }
+}
+
+/// Decides which voxel [`SmoothGrid::merge`] keeps when both grids have
+/// something in the same voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum MergeConflict {
+    /// Keep the voxel from the grid being merged in. This is the default.
+    #[default]
+    Incoming,
+
+    /// Keep the voxel that was already in the grid.
+    Existing,
+
+    /// Keep the voxel with more solid occupancy, or the incoming voxel if
+    /// they are tied.
+    MostOccupied,
+}
+
+/// The differences between two [`SmoothGrid`]s, created by
+/// [`SmoothGrid::diff`].
+///
+/// Only chunks that differ are stored, and within them, only the voxels that
+/// differ.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TerrainDiff {
+    chunks: BTreeMap<ChunkCoordinates, ChunkDiff>,
+}
+
+impl TerrainDiff {
+    /// Returns `true` if the two grids were the same.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns the differences in the chunk at `position`, if there are any.
+    pub fn get_chunk(&self, position: ChunkCoordinates) -> Option<&ChunkDiff> {
+        self.chunks.get(&position)
+    }
+
+    /// Returns an iterator over every chunk that differs, in the same order
+    /// as [`SmoothGrid::chunks`].
+    pub fn chunks(&self) -> btree_map::Iter<'_, ChunkCoordinates, ChunkDiff> {
+        self.chunks.iter()
+    }
+
+    /// Applies the differences to `grid`. Applying a diff created by
+    /// `a.diff(&b)` to `a` makes it equal to `b`.
+    pub fn apply(&self, grid: &mut SmoothGrid) {
+        for (position, chunk_diff) in &self.chunks {
+            grid.mark_dirty(*position);
+
+            if !chunk_diff.exists {
+                grid.chunks.remove(position);
+                continue;
+            }
+
+            let chunk = grid.chunks.entry(*position).or_default();
+            for (voxel_position, voxel) in &chunk_diff.voxels {
+                chunk.write_voxel(*voxel_position, *voxel);
+            }
+        }
+    }
+}
+
+/// The differences in a single chunk between two [`SmoothGrid`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkDiff {
+    existed: bool,
+    exists: bool,
+    added: usize,
+    removed: usize,
+    changed: usize,
+    voxels: Vec<(VoxelCoordinates, Voxel)>,
+}
+
+impl ChunkDiff {
+    /// Returns `true` if the chunk was in the first grid.
+    pub fn existed(&self) -> bool {
+        self.existed
+    }
+
+    /// Returns `true` if the chunk is in the second grid.
+    pub fn exists(&self) -> bool {
+        self.exists
+    }
+
+    /// The number of voxels that were empty and are now filled.
+    pub fn added(&self) -> usize {
+        self.added
+    }
+
+    /// The number of voxels that were filled and are now empty.
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// The number of voxels whose contents changed but that were not added
+    /// or removed.
+    pub fn changed(&self) -> usize {
+        self.changed
+    }
+
+    /// The position and new value of every voxel that differs, in the order
+    /// they are stored in the chunk.
+    pub fn voxels(&self) -> &[(VoxelCoordinates, Voxel)] {
+        &self.voxels
+    }
+}
+
+/// Reads every chunk in a blob from `reader`, calling `visit` with its
+/// position, the base 2 logarithm of the blob's chunk size, and its voxels.
+/// `visit` can take the voxels, or leave them to have their allocation reused
+/// for the next chunk.
+fn visit_chunks<R, F>(reader: R, mut visit: F) -> Result<(), CrateError>
+where
+    R: Read,
+    F: FnMut(ChunkCoordinates, u8, &mut Vec<Voxel>) -> Result<(), CrateError>,
+{
+    let mut reader = BlobReader { reader };
+    let BlobHeader {
+        version,
+        chunk_size_log2,
+    } = decode_header(&mut reader)?;
+    let volume = 1 << (3 * chunk_size_log2);
+
+    let mut positions = BTreeSet::new();
+    let mut previous = ChunkCoordinates::default();
+    let mut voxels = Vec::new();
+
+    while let Some(header) = reader.next_u8()? {
+        let position = decode_chunk_position(&mut reader, header, previous)?;
+        previous = position;
+
+        // Positions are relative to the chunk before, which is in range, so
+        // one that wraps around is always out of range too.
+        if !position.is_in_range_for(chunk_size_log2) {
+            return Err(TerrainError::ChunkOutOfRange(position).into());
+        }
+
+        voxels.clear();
+        voxels.reserve(volume.min(CHUNK_VOLUME));
+
+        while voxels.len() < volume {
+            let (voxel, count) = decode_run(&mut reader, version)?;
+
+            if voxels.len() + count > volume {
+                return Err(TerrainError::RunOverflow.into());
+            }
+
+            voxels.resize(voxels.len() + count, voxel);
+        }
+
+        if !positions.insert(position) {
+            return Err(TerrainError::DuplicateChunk(position).into());
+        }
+
+        visit(position, chunk_size_log2, &mut voxels)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the position of every voxel in the chunk at `position` of a blob
+/// whose chunks are `2^chunk_size_log2` voxels on each side, in the order
+/// they're stored.
+fn resized_chunk_voxels(
+    position: ChunkCoordinates,
+    chunk_size_log2: u8,
+) -> impl Iterator<Item = TerrainVec> {
+    let size = 1i32 << chunk_size_log2;
+    let lower = (
+        position.x.wrapping_mul(size),
+        position.y.wrapping_mul(size),
+        position.z.wrapping_mul(size),
+    );
+
+    (0..size * size * size).map(move |index| {
+        TerrainVec::new(
+            lower.0.wrapping_add(index % size),
+            lower.1.wrapping_add(index / (size * size)),
+            lower.2.wrapping_add(index / size % size),
+        )
+    })
+}
+
+/// Writes the voxels of a chunk as runs of identical voxels.
+fn encode_chunk_voxels(buffer: &mut Vec<u8>, voxels: &[Voxel], options: EncodeOptions) {
+    let mut voxels = voxels
+        .iter()
+        .map(|voxel| options.prepare(*voxel))
+        .peekable();
+
+    while let Some(voxel) = voxels.next() {
+        let mut count = 1;
+        while count < MAX_RUN_LENGTH && voxels.peek() == Some(&voxel) {
+            voxels.next();
+            count += 1;
+        }
+
+        encode_run(buffer, &voxel, count);
+    }
+}
+
+/// Writes the position of a chunk relative to the chunk before it. Each
+/// component is written using the smallest integer that fits it, and the
+/// widths are described by a leading byte using two bits per component.
+fn encode_chunk_position(
+    buffer: &mut Vec<u8>,
+    position: ChunkCoordinates,
+    previous: ChunkCoordinates,
+) {
+    let deltas = [
+        position.x.wrapping_sub(previous.x),
+        position.y.wrapping_sub(previous.y),
+        position.z.wrapping_sub(previous.z),
+    ];
+
+    let widths = deltas.map(|delta| {
+        if delta == 0 {
+            0
+        } else if i8::try_from(delta).is_ok() {
+            1
+        } else if i16::try_from(delta).is_ok() {
+            2
+        } else {
+            3
+        }
+    });
+
+    buffer.push(widths[0] | widths[1] << 2 | widths[2] << 4);
+
+    for (delta, width) in deltas.iter().zip(widths) {
+        match width {
+            0 => {}
+            1 => buffer.extend_from_slice(&(*delta as i8).to_le_bytes()),
+            2 => buffer.extend_from_slice(&(*delta as i16).to_le_bytes()),
+            _ => buffer.extend_from_slice(&delta.to_le_bytes()),
+        }
+    }
+}
+
+/// Reads the header of a blob, returning the base 2 logarithm of the size of
+/// its chunks.
+fn decode_header<R: Read>(reader: &mut BlobReader<R>) -> Result<BlobHeader, TerrainError> {
+    let version = reader.read_u8()?;
+    if version != SMOOTH_GRID_VERSION && version != LEGACY_SMOOTH_GRID_VERSION {
+        return Err(TerrainError::UnknownVersion(version));
+    }
+
+    let chunk_size_log2 = reader.read_u8()?;
+    if chunk_size_log2 > MAX_CHUNK_SIZE_LOG2 {
+        return Err(TerrainError::UnsupportedChunkSize(chunk_size_log2));
+    }
+
+    Ok(BlobHeader {
+        version,
+        chunk_size_log2,
+    })
+}
+
+/// The header at the start of a blob.
+struct BlobHeader {
+    version: u8,
+
+    /// The base 2 logarithm of the size of the blob's chunks.
+    chunk_size_log2: u8,
+}
+
+fn decode_chunk_position<R: Read>(
+    reader: &mut BlobReader<R>,
+    header: u8,
+    previous: ChunkCoordinates,
+) -> Result<ChunkCoordinates, TerrainError> {
+    if header & 0b1100_0000 != 0 {
+        return Err(TerrainError::InvalidChunkHeader(header));
+    }
+
+    let mut deltas = [0i32; 3];
+    for (i, delta) in deltas.iter_mut().enumerate() {
+        *delta = match (header >> (i * 2)) & 0b11 {
+            0 => 0,
+            1 => i8::from_le_bytes(reader.read_array()?) as i32,
+            2 => i16::from_le_bytes(reader.read_array()?) as i32,
+            _ => i32::from_le_bytes(reader.read_array()?),
+        };
+    }
+
+    Ok(ChunkCoordinates {
+        x: previous.x.wrapping_add(deltas[0]),
+        y: previous.y.wrapping_add(deltas[1]),
+        z: previous.z.wrapping_add(deltas[2]),
+    })
+}
+
+/// Writes a run of `count` identical voxels.
+///
+/// Voxels that only contain water are written with the water material. Solid
+/// voxels along a shoreline can also contain water, which is written as an
+/// extra byte after the occupancy.
+fn encode_run(buffer: &mut Vec<u8>, voxel: &Voxel, count: usize) {
+    let (id, occupancy, water_occupancy) = match voxel.material {
+        _ if voxel.is_empty() => (AIR_ID, None, None),
+        None => (WATER_ID, Some(voxel.water_occupancy), None),
+        Some(material) => {
+            let water_occupancy = Some(voxel.water_occupancy).filter(|&water| water > 0);
+            (
+                material_id(material),
+                Some(voxel.occupancy),
+                water_occupancy,
+            )
+        }
+    };
+
+    // Full voxels are the most common, so their occupancy is left implied.
+    let occupancy = occupancy.filter(|&occupancy| occupancy != u8::MAX);
+
+    let mut flags = id;
+    if water_occupancy.is_some() {
+        flags |= RUN_HAS_WATER;
+    }
+    if occupancy.is_some() {
+        flags |= RUN_HAS_OCCUPANCY;
+    }
+    if count > 1 {
+        flags |= RUN_HAS_COUNT;
+    }
+
+    buffer.push(flags);
+    buffer.extend(occupancy);
+    buffer.extend(water_occupancy);
+    if count > 1 {
+        buffer.push((count - 1) as u8);
+    }
+}
+
+fn decode_run<R: Read>(
+    reader: &mut BlobReader<R>,
+    version: u8,
+) -> Result<(Voxel, usize), TerrainError> {
+    let run = read_run(reader)?;
+    if version == LEGACY_SMOOTH_GRID_VERSION && run.has_water {
+        return Err(TerrainError::LegacyShoreline);
+    }
+
+    Ok((run.voxel(), run.count))
+}
+
+/// A run of voxels as it's stored.
+struct RawRun {
+    id: u8,
+    occupancy: u8,
+    has_water: bool,
+    water_occupancy: u8,
+    count: usize,
+}
+
+impl RawRun {
+    fn voxel(&self) -> Voxel {
+        match self.id {
+            AIR_ID => Voxel::default(),
+            WATER_ID => Voxel::water(self.occupancy),
+            id => Voxel {
+                material: Some(material_from_id(id)),
+                occupancy: self.occupancy,
+                water_occupancy: self.water_occupancy,
+            },
+        }
+    }
+}
+
+fn read_run<R: Read>(reader: &mut BlobReader<R>) -> Result<RawRun, TerrainError> {
+    let flags = reader.read_u8()?;
+    let id = flags & RUN_MATERIAL_MASK;
+
+    let occupancy = if flags & RUN_HAS_OCCUPANCY != 0 {
+        reader.read_u8()?
+    } else {
+        u8::MAX
+    };
+
+    let has_water = flags & RUN_HAS_WATER != 0;
+    let water_occupancy = if has_water { reader.read_u8()? } else { 0 };
+
+    let count = if flags & RUN_HAS_COUNT != 0 {
+        reader.read_u8()? as usize + 1
+    } else {
+        1
+    };
+
+    Ok(RawRun {
+        id,
+        occupancy,
+        has_water,
+        water_occupancy,
+        count,
+    })
+}
+
+fn material_id(material: TerrainMaterials) -> u8 {
+    match material {
+        TerrainMaterials::Unknown(material) => material.id(),
+        material => {
+            let index = MATERIAL_ORDER
+                .iter()
+                .position(|&other| other == material)
+                .unwrap();
+
+            index as u8 + FIRST_SOLID_ID
+        }
+    }
+}
+
+/// Returns the material with the given solid material ID. IDs past the last
+/// known material are kept as `TerrainMaterials::Unknown`, so blobs with
+/// materials added after this crate was released still round trip.
+fn material_from_id(id: u8) -> TerrainMaterials {
+    id.checked_sub(FIRST_SOLID_ID)
+        .and_then(|index| MATERIAL_ORDER.get(index as usize))
+        .copied()
+        .unwrap_or(TerrainMaterials::Unknown(UnknownMaterial(id)))
+}
+
+struct BlobReader<R> {
+    reader: R,
+}
+
+impl<R: Read> BlobReader<R> {
+    fn read_u8(&mut self) -> Result<u8, TerrainError> {
+        Ok(u8::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], TerrainError> {
+        let mut bytes = [0; N];
+        self.reader.read_exact(&mut bytes).map_err(|err| {
+            if err.kind() == io::ErrorKind::UnexpectedEof {
+                TerrainError::UnexpectedEof
+            } else {
+                TerrainError::Io(err)
+            }
+        })?;
+
+        Ok(bytes)
+    }
+
+    /// Reads the next byte, or returns `None` if the blob has ended.
+    fn next_u8(&mut self) -> Result<Option<u8>, TerrainError> {
+        let mut byte = [0];
+
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(byte[0])),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(TerrainError::Io(err)),
+            }
+        }
+    }
+}
+
+/// An error that can occur when decoding a `SmoothGrid`.
+#[derive(Debug, Error)]
+pub(crate) enum TerrainError {
+    #[error("SmoothGrid blob ended unexpectedly")]
+    UnexpectedEof,
+
+    #[error("failed to read SmoothGrid blob")]
+    Io(#[source] io::Error),
+
+    #[error("unknown SmoothGrid version {0}")]
+    UnknownVersion(u8),
+
+    #[error("unsupported SmoothGrid chunk size (2^{0} voxels)")]
+    UnsupportedChunkSize(u8),
+
+    #[error("SmoothGrid version 0 blob has a voxel with both a solid material and water")]
+    LegacyShoreline,
+
+    #[error("invalid SmoothGrid chunk header {0:#010b}")]
+    InvalidChunkHeader(u8),
+
+    #[error("SmoothGrid voxel run extended past the end of its chunk")]
+    RunOverflow,
+
+    #[error("SmoothGrid contained chunk {0:?} more than once")]
+    DuplicateChunk(ChunkCoordinates),
+
+    #[error("voxel coordinates ({0}, {1}, {2}) are outside of a chunk")]
+    InvalidVoxelCoordinates(u8, u8, u8),
+
+    #[error("chunk {0:?} is outside of the range terrain can be placed in")]
+    ChunkOutOfRange(ChunkCoordinates),
+
+    #[error("unknown chunk lock blob version {0}")]
+    UnknownChunkLocksVersion(u8),
+
+    #[error("invalid chunk lock blob: {0}")]
+    InvalidChunkLocks(&'static str),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns the occupancy of the voxel at the given position, or 0 if its
+    /// chunk doesn't exist.
+    fn occupancy(grid: &SmoothGrid, x: i32, y: i32, z: i32) -> u8 {
+        grid.get_voxel_world(TerrainVec::new(x, y, z))
+            .map(|voxel| voxel.occupancy)
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn empty() {
+        let grid = SmoothGrid::new();
+        let encoded = grid.encode();
+
+        assert_eq!(encoded, [1, 5]);
+        assert_eq!(SmoothGrid::decode(&encoded).unwrap(), grid);
+    }
+
+    #[test]
+    fn single_material_chunk() {
+        let mut grid = SmoothGrid::new();
+        grid.write_chunk(
+            ChunkCoordinates::new(0, -1, 0),
+            Chunk::new_with_base(Voxel::solid(TerrainMaterials::Grass)),
+        );
+
+        let encoded = grid.encode();
+
+        // A Y delta of -1 as an i8, then 128 runs of 256 grass voxels.
+        let mut expected = vec![1, 5, 0b0000_0100, 0xff];
+        for _ in 0..CHUNK_VOLUME / MAX_RUN_LENGTH {
+            expected.extend_from_slice(&[RUN_HAS_COUNT | 2, 255]);
+        }
+
+        assert_eq!(encoded, expected);
+        assert_eq!(SmoothGrid::decode(&encoded).unwrap(), grid);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut chunk = Chunk::new();
+        chunk.write_voxel(
+            VoxelCoordinates::new(0, 0, 0),
+            Voxel::solid(TerrainMaterials::Pavement),
+        );
+        chunk.write_voxel(VoxelCoordinates::new(1, 0, 0), Voxel::water(128));
+        chunk.write_voxel(
+            VoxelCoordinates::new(31, 31, 31),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 100,
+                water_occupancy: 200,
+            },
+        );
+
+        let mut grid = SmoothGrid::new();
+        grid.write_chunk(ChunkCoordinates::new(0, 0, 0), chunk.clone());
+        grid.write_chunk(ChunkCoordinates::new(-300, 5, 70_000), chunk);
+
+        let decoded = SmoothGrid::decode(&grid.encode()).unwrap();
+        assert_eq!(decoded, grid);
+        assert_eq!(SmoothGrid::count_chunks(&grid.encode()).unwrap(), 2);
+
+        let chunk = decoded
+            .get_chunk(ChunkCoordinates::new(-300, 5, 70_000))
+            .unwrap();
+        assert_eq!(
+            chunk
+                .get_voxel(VoxelCoordinates::new(31, 31, 31))
+                .water_occupancy,
+            200
+        );
+    }
+
+    #[test]
+    fn voxel_coordinates_clamp() {
+        let position = VoxelCoordinates::new(40, 3, 255);
+
+        assert_eq!((position.x(), position.y(), position.z()), (31, 3, 31));
+    }
+
+    #[test]
+    fn world_voxels() {
+        let mut grid = SmoothGrid::new();
+        let position = TerrainVec::new(-1, 32, 65);
+
+        assert_eq!(grid.get_voxel_world(position), None);
+
+        grid.write_voxel_world(position, Voxel::solid(TerrainMaterials::Rock));
+
+        let chunk_position = ChunkCoordinates::new(-1, 1, 2);
+        let voxel_position = VoxelCoordinates::new(31, 0, 1);
+        assert_eq!(position.chunk_coordinates(), chunk_position);
+        assert_eq!(position.voxel_coordinates(), voxel_position);
+        assert_eq!(
+            TerrainVec::from_chunk(chunk_position, voxel_position),
+            position
+        );
+        assert_eq!(
+            TerrainVec::try_from_chunk(chunk_position, voxel_position).unwrap(),
+            position
+        );
+
+        let far = ChunkCoordinates::new(i32::MAX, i32::MIN, 0);
+        assert_eq!(
+            TerrainVec::from_chunk(far, voxel_position),
+            TerrainVec::new(i32::MAX, i32::MIN, 1)
+        );
+        assert!(TerrainVec::try_from_chunk(far, voxel_position).is_err());
+
+        assert_eq!(
+            grid.get_chunk(chunk_position)
+                .unwrap()
+                .get_voxel(voxel_position),
+            &Voxel::solid(TerrainMaterials::Rock)
+        );
+        assert_eq!(
+            grid.get_voxel_world(position),
+            Some(&Voxel::solid(TerrainMaterials::Rock))
+        );
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(-2, 32, 65)),
+            Some(&Voxel::default())
+        );
+    }
+
+    #[test]
+    fn read_voxels() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(-1, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_voxel_world(TerrainVec::new(0, 1, 0), Voxel::water(200));
+        grid.write_voxel_world(
+            TerrainVec::new(1, 0, 1),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        // Outside of the region that's read.
+        grid.write_voxel_world(
+            TerrainVec::new(2, 0, 0),
+            Voxel::solid(TerrainMaterials::Sand),
+        );
+
+        let region = grid.read_voxels(TerrainVec::new(-1, 0, 0), TerrainVec::new(1, 1, 1));
+        assert_eq!(region.size(), [3, 2, 2]);
+        assert_eq!(region.materials().len(), 12);
+
+        let index = |x, y, z| region.index(TerrainVec::new(x, y, z)).unwrap();
+        assert_eq!(index(-1, 0, 0), 0);
+        assert_eq!(index(1, 1, 1), 11);
+
+        assert_eq!(
+            region.materials()[index(-1, 0, 0)],
+            Some(TerrainMaterials::Rock)
+        );
+        assert_eq!(region.occupancy()[index(-1, 0, 0)], 255);
+        assert_eq!(region.water_occupancy()[index(0, 1, 0)], 200);
+        assert_eq!(
+            region.get_voxel(TerrainVec::new(1, 0, 1)),
+            Some(Voxel::solid(TerrainMaterials::Grass))
+        );
+        assert_eq!(
+            region.get_voxel(TerrainVec::new(0, 0, 1)),
+            Some(Voxel::default())
+        );
+        assert_eq!(region.get_voxel(TerrainVec::new(2, 0, 0)), None);
+
+        let filled = region.materials().iter().filter(|m| m.is_some()).count();
+        assert_eq!(filled, 2);
+
+        let empty = grid.read_voxels(TerrainVec::new(1, 0, 0), TerrainVec::new(0, 0, 0));
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn fill_block() {
+        let rock = Voxel::solid(TerrainMaterials::Rock);
+
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(TerrainVec::new(-2, 0, 0), TerrainVec::new(1, 0, 31), rock);
+
+        // The block spans two chunks along X.
+        assert_eq!(grid.chunks().count(), 2);
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(-2, 0, 31)),
+            Some(&rock)
+        );
+        assert_eq!(grid.get_voxel_world(TerrainVec::new(1, 0, 0)), Some(&rock));
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(2, 0, 0)),
+            Some(&Voxel::default())
+        );
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(0, 1, 0)),
+            Some(&Voxel::default())
+        );
+
+        // Covering a whole chunk replaces it outright.
+        grid.fill_block(TerrainVec::new(0, 0, 0), TerrainVec::new(31, 31, 31), rock);
+        assert_eq!(
+            grid.get_chunk(ChunkCoordinates::new(0, 0, 0)),
+            Some(&Chunk::new_with_base(rock))
+        );
+
+        // Clearing doesn't create chunks, and removes covered ones.
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(63, 31, 31),
+            Voxel::default(),
+        );
+        assert_eq!(grid.get_chunk(ChunkCoordinates::new(0, 0, 0)), None);
+        assert_eq!(grid.get_chunk(ChunkCoordinates::new(1, 0, 0)), None);
+        assert_eq!(grid.chunks().count(), 1);
+    }
+
+    #[test]
+    fn fill_region() {
+        let mut grid = SmoothGrid::new();
+
+        // Covers voxels -1 through 1 on X, 0 on Y, and 0 through 2 on Z.
+        grid.fill_region(
+            Region3::new(Vector3::new(-2.0, 0.0, 0.0), Vector3::new(6.0, 4.0, 9.5)),
+            TerrainMaterials::Ice,
+        );
+
+        for x in -1..=1 {
+            for z in 0..=2 {
+                assert_eq!(occupancy(&grid, x, 0, z), 255);
+            }
+        }
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(0, 0, 0)),
+            Some(&Voxel::solid(TerrainMaterials::Ice))
+        );
+
+        assert_eq!(occupancy(&grid, -2, 0, 0), 0);
+        assert_eq!(occupancy(&grid, 2, 0, 0), 0);
+        assert_eq!(occupancy(&grid, 0, 1, 0), 0);
+        assert_eq!(occupancy(&grid, 0, 0, 3), 0);
+
+        // An empty region fills nothing.
+        let mut empty = SmoothGrid::new();
+        let point = Vector3::new(4.0, 4.0, 4.0);
+        empty.fill_region(Region3::new(point, point), TerrainMaterials::Ice);
+        assert_eq!(empty, SmoothGrid::new());
+
+        // Regions outside of the range terrain can be placed in are clamped to
+        // it instead of overflowing.
+        let below = Region3::new(
+            Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            Vector3::new(-1e12, -1e12, -1e12),
+        );
+        empty.fill_region(below, TerrainMaterials::Ice);
+        empty.smooth(below, 1);
+        assert_eq!(empty, SmoothGrid::new());
+        assert_eq!(empty.to_volume(below, 1).dims, [0, 0, 0]);
+
+        assert_eq!(
+            region_voxels(Region3::new(
+                Vector3::new(f32::NEG_INFINITY, 0.0, 0.0),
+                Vector3::new(f32::INFINITY, 4.0, 4.0),
+            )),
+            (
+                TerrainVec::new(-MAX_VOXEL_COORDINATE, 0, 0),
+                TerrainVec::new(MAX_VOXEL_COORDINATE - 1, 0, 0),
+            )
+        );
+    }
+
+    #[test]
+    fn fill_water_to_level() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(3, 1, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        let shore = Voxel {
+            material: Some(TerrainMaterials::Sand),
+            occupancy: 100,
+            water_occupancy: 0,
+        };
+        grid.write_voxel_world(TerrainVec::new(1, 2, 0), shore);
+
+        // The water's surface is halfway up the voxels at Y 3.
+        let region = Region3::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(16.0, 64.0, 4.0));
+        grid.fill_water_to_level(14.0, region);
+
+        let voxel = |x: i32, y: i32| grid.get_voxel_world(TerrainVec::new(x, y, 0)).copied();
+        assert_eq!(voxel(0, 1), Some(Voxel::solid(TerrainMaterials::Rock)));
+        assert_eq!(voxel(0, 2), Some(Voxel::water(255)));
+        assert_eq!(voxel(0, 3), Some(Voxel::water(128)));
+        assert!(voxel(0, 4).unwrap().is_empty());
+        assert!(voxel(4, 2).unwrap().is_empty());
+        assert_eq!(
+            voxel(1, 2),
+            Some(Voxel {
+                water_occupancy: 255,
+                ..shore
+            })
+        );
+        assert!(grid.validate().is_empty());
+
+        // Chunks that are completely under water are created whole.
+        let mut ocean = SmoothGrid::new();
+        let region = Region3::new(
+            Vector3::new(0.0, -128.0, 0.0),
+            Vector3::new(128.0, 0.0, 128.0),
+        );
+        ocean.fill_water_to_level(0.0, region);
+        assert_eq!(ocean.chunks().count(), 1);
+
+        // Levels below the region, or that aren't numbers, fill nothing.
+        let before = ocean.clone();
+        for &level in &[-1e12, f32::NEG_INFINITY, f32::NAN] {
+            ocean.fill_water_to_level(level, region);
+            assert_eq!(ocean, before);
+        }
+        assert_eq!(
+            ocean.get_voxel_world(TerrainVec::new(0, -32, 31)),
+            Some(&Voxel::water(255))
+        );
+
+        // Nothing is filled above the level.
+        ocean.fill_water_to_level(-128.0, region);
+        assert_eq!(ocean.chunks().count(), 1);
+    }
+
+    #[test]
+    fn sample() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(1, 0, -1),
+            Voxel {
+                material: Some(TerrainMaterials::Mud),
+                occupancy: 51,
+                water_occupancy: 0,
+            },
+        );
+        grid.write_voxel_world(TerrainVec::new(2, 0, -1), Voxel::water(255));
+
+        assert_eq!(
+            grid.sample(Vector3::new(4.0, 3.9, -0.5)),
+            Some((TerrainMaterials::Mud, 0.2))
+        );
+        assert_eq!(grid.sample(Vector3::new(8.0, 0.0, -4.0)), None);
+        assert_eq!(grid.sample(Vector3::new(500.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn raycast() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(-4, -2, -4),
+            TerrainVec::new(4, -1, 4),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        grid.write_voxel_world(TerrainVec::new(0, 0, 0), Voxel::water(255));
+
+        // Straight down through the water onto the grass.
+        let hit = grid
+            .raycast(Vector3::new(2.0, 10.0, 2.0), Vector3::new(0.0, -20.0, 0.0))
+            .unwrap();
+        assert_eq!(hit.position, TerrainVec::new(0, -1, 0));
+        assert_eq!(hit.point, Vector3::new(2.0, 0.0, 2.0));
+        assert_eq!(hit.normal, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(hit.distance, 10.0);
+        assert_eq!(hit.material, TerrainMaterials::Grass);
+
+        // Sideways into the edge of the grass.
+        let hit = grid
+            .raycast(Vector3::new(-30.0, -6.0, 1.0), Vector3::new(40.0, 0.0, 0.0))
+            .unwrap();
+        assert_eq!(hit.position, TerrainVec::new(-4, -2, 0));
+        assert_eq!(hit.normal, Vector3::new(-1.0, 0.0, 0.0));
+        assert_eq!(hit.distance, 14.0);
+
+        // A ray that stops short, one that misses, and one that starts inside.
+        let origin = Vector3::new(2.0, 10.0, 2.0);
+        assert_eq!(grid.raycast(origin, Vector3::new(0.0, -9.0, 0.0)), None);
+        assert_eq!(grid.raycast(origin, Vector3::new(100.0, 0.0, 0.0)), None);
+
+        let inside = Vector3::new(1.0, -2.0, 1.0);
+        let hit = grid.raycast(inside, Vector3::new(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(hit.distance, 0.0);
+        assert_eq!(hit.normal, Vector3::new(0.0, 0.0, 0.0));
+
+        // Rays far from the origin, or far longer than the grid, finish
+        // without overflowing.
+        let far = Vector3::new(1e10, 0.0, 0.0);
+        let hit = grid.raycast(far, Vector3::new(-1e10, -4.0, 2.0)).unwrap();
+        assert_eq!(hit.position, TerrainVec::new(4, -1, 0));
+        assert_eq!(hit.normal, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(grid.raycast(far, Vector3::new(1.0, 0.0, 0.0)), None);
+
+        let hit = grid
+            .raycast(Vector3::new(2.0, 4e9, 2.0), Vector3::new(0.0, -8e9, 0.0))
+            .unwrap();
+        assert_eq!(hit.position, TerrainVec::new(0, -1, 0));
+        assert_eq!(hit.normal, Vector3::new(0.0, 1.0, 0.0));
+
+        let long = Vector3::new(4e9, 0.0, 0.0);
+        assert_eq!(
+            SmoothGrid::new().raycast(Vector3::new(0.0, 0.0, 0.0), long),
+            None
+        );
+    }
+
+    #[test]
+    fn columns() {
+        let mut grid = SmoothGrid::new();
+        assert_eq!(grid.column_height(0, 31), None);
+
+        // The column crosses from the first chunk into the one above it.
+        grid.set_column(0, 31, 200.0, TerrainMaterials::Rock);
+        assert_eq!(grid.column_height(0, 31), Some(200.0));
+        assert_eq!(grid.chunks().count(), 2);
+
+        grid.write_voxel_world(TerrainVec::new(0, 1, 31), Voxel::water(255));
+        grid.set_column(0, 31, 5.0, TerrainMaterials::Grass);
+        let height = grid.column_height(0, 31).unwrap();
+        assert!((height - 5.0).abs() < 0.01);
+
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(0, 1, 31)),
+            Some(&Voxel {
+                material: Some(TerrainMaterials::Grass),
+                occupancy: 64,
+                water_occupancy: 255,
+            })
+        );
+        assert!(grid
+            .get_voxel_world(TerrainVec::new(0, 40, 31))
+            .unwrap()
+            .is_empty());
+        assert_eq!(grid.column_height(0, 32), None);
+        assert_eq!(grid.column_height(1, 31), None);
+
+        // Terrain below 0 is left alone.
+        grid.write_voxel_world(
+            TerrainVec::new(0, -3, 31),
+            Voxel::solid(TerrainMaterials::Basalt),
+        );
+        grid.set_column(0, 31, -20.0, TerrainMaterials::Grass);
+        assert_eq!(grid.column_height(0, 31), Some(-8.0));
+    }
+
+    #[test]
+    fn heightmap() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(3, 1, 0),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(1, 2, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Rock),
+                occupancy: 51,
+                water_occupancy: 0,
+            },
+        );
+        grid.write_voxel_world(TerrainVec::new(2, 3, 0), Voxel::water(255));
+
+        // Voxels above the region are ignored.
+        grid.write_voxel_world(
+            TerrainVec::new(3, 4, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+
+        let heightmap = grid.heightmap(TerrainVec::new(0, 0, 0), TerrainVec::new(4, 3, 1));
+        assert_eq!((heightmap.width(), heightmap.depth()), (5, 2));
+
+        let half = 32768;
+        assert_eq!(&heightmap.heights()[..5], &[half, 36044, half, half, 0]);
+        assert_eq!(
+            &heightmap.materials()[..5],
+            &[
+                Some(TerrainMaterials::Grass),
+                Some(TerrainMaterials::Rock),
+                Some(TerrainMaterials::Grass),
+                Some(TerrainMaterials::Grass),
+                None,
+            ]
+        );
+        assert!(heightmap.heights()[5..].iter().all(|&height| height == 0));
+    }
+
+    #[test]
+    fn merge() {
+        let mut base = SmoothGrid::new();
+        base.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(3, 0, 0),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+
+        let partial_rock = Voxel {
+            material: Some(TerrainMaterials::Rock),
+            occupancy: 100,
+            water_occupancy: 0,
+        };
+
+        let mut incoming = SmoothGrid::new();
+        incoming.write_voxel_world(TerrainVec::new(0, 0, 0), partial_rock);
+        incoming.write_voxel_world(TerrainVec::new(5, 0, 0), partial_rock);
+        incoming.write_chunk(ChunkCoordinates::new(4, 0, 0), Chunk::new());
+
+        let material = |grid: &SmoothGrid, x| {
+            grid.get_voxel_world(TerrainVec::new(x, 0, 0))
+                .and_then(|voxel| voxel.material)
+        };
+
+        let mut merged = base.clone();
+        merged.merge(&incoming, MergePolicy::new());
+        assert_eq!(material(&merged, 0), Some(TerrainMaterials::Rock));
+        assert_eq!(material(&merged, 1), Some(TerrainMaterials::Grass));
+        assert_eq!(material(&merged, 5), Some(TerrainMaterials::Rock));
+        assert!(merged.get_chunk(ChunkCoordinates::new(4, 0, 0)).is_none());
+
+        let mut merged = base.clone();
+        merged.merge(
+            &incoming,
+            MergePolicy::new().conflicts(MergeConflict::MostOccupied),
+        );
+        assert_eq!(material(&merged, 0), Some(TerrainMaterials::Grass));
+        assert_eq!(material(&merged, 5), Some(TerrainMaterials::Rock));
+
+        let mut merged = base.clone();
+        merged.merge(&incoming, MergePolicy::new().replace_with_empty(true));
+        assert_eq!(material(&merged, 0), Some(TerrainMaterials::Rock));
+        assert_eq!(material(&merged, 1), None);
+    }
+
+    #[test]
+    fn diff() {
+        let mut before = SmoothGrid::new();
+        before.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(3, 0, 0),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        before.write_voxel_world(
+            TerrainVec::new(-1, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+
+        let mut after = before.clone();
+        after.write_voxel_world(
+            TerrainVec::new(4, 0, 0),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        after.write_voxel_world(TerrainVec::new(0, 0, 0), Voxel::default());
+        after.write_voxel_world(
+            TerrainVec::new(1, 0, 0),
+            Voxel::solid(TerrainMaterials::Mud),
+        );
+        after.remove_chunk(ChunkCoordinates::new(-1, 0, 0));
+
+        assert!(before.diff(&before).is_empty());
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.chunks().count(), 2);
+
+        let chunk_diff = diff.get_chunk(ChunkCoordinates::new(0, 0, 0)).unwrap();
+        assert!(chunk_diff.existed() && chunk_diff.exists());
+        assert_eq!(
+            (
+                chunk_diff.added(),
+                chunk_diff.removed(),
+                chunk_diff.changed()
+            ),
+            (1, 1, 1)
+        );
+        assert_eq!(
+            chunk_diff.voxels()[0],
+            (VoxelCoordinates::new(0, 0, 0), Voxel::default())
+        );
+
+        let removed = diff.get_chunk(ChunkCoordinates::new(-1, 0, 0)).unwrap();
+        assert!(removed.existed() && !removed.exists());
+        assert_eq!(removed.removed(), 1);
+
+        let mut patched = before.clone();
+        diff.apply(&mut patched);
+        assert_eq!(patched, after);
+
+        let mut reverted = after.clone();
+        after.diff(&before).apply(&mut reverted);
+        assert_eq!(reverted, before);
+    }
+
+    #[test]
+    fn fill_ball() {
+        let mut grid = SmoothGrid::new();
+
+        // A ball centered on the corner shared by the 8 voxels around the
+        // origin, with a radius of two voxels.
+        grid.fill_ball(Vector3::new(0.0, 0.0, 0.0), 8.0, TerrainMaterials::Rock);
+
+        // Voxels deep inside the ball are full.
+        assert_eq!(occupancy(&grid, 0, 0, 0), 255);
+        assert_eq!(occupancy(&grid, -1, -1, -1), 255);
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(0, 0, 0))
+                .unwrap()
+                .material,
+            Some(TerrainMaterials::Rock)
+        );
+
+        // Voxels on the surface are partially full.
+        let surface = occupancy(&grid, 1, 0, 0);
+        assert!(surface > 0 && surface < 255);
+
+        // Voxels outside of the ball are left alone.
+        assert_eq!(occupancy(&grid, 2, 2, 2), 0);
+        assert_eq!(occupancy(&grid, -3, 0, 0), 0);
+
+        // The ball is symmetric around its center.
+        assert_eq!(occupancy(&grid, 1, 0, 0), occupancy(&grid, -2, -1, -1));
+
+        // Filling again with a smaller ball doesn't reduce occupancy.
+        grid.fill_ball(Vector3::new(0.0, 0.0, 0.0), 4.0, TerrainMaterials::Sand);
+        assert_eq!(occupancy(&grid, 1, 0, 0), surface);
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(0, 0, 0))
+                .unwrap()
+                .material,
+            Some(TerrainMaterials::Sand)
+        );
+    }
+
+    #[test]
+    fn fill_cylinder() {
+        let mut grid = SmoothGrid::new();
+
+        // A cylinder 4 voxels tall and 2 voxels in radius, centered on the
+        // corner shared by the voxels around the origin.
+        grid.fill_cylinder(
+            Vector3::new(0.0, 0.0, 0.0),
+            16.0,
+            8.0,
+            TerrainMaterials::Asphalt,
+        );
+
+        // The full height of the cylinder is filled near its axis.
+        for y in -2..2 {
+            assert_eq!(occupancy(&grid, 0, y, 0), 255);
+        }
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(0, 0, 0))
+                .unwrap()
+                .material,
+            Some(TerrainMaterials::Asphalt)
+        );
+
+        // Voxels above and below the caps are left alone.
+        assert_eq!(occupancy(&grid, 0, 2, 0), 0);
+        assert_eq!(occupancy(&grid, 0, -3, 0), 0);
+
+        // The round side is partially filled, the same at every height.
+        let side = occupancy(&grid, 1, 0, 0);
+        assert!(side > 0 && side < 255);
+        assert_eq!(occupancy(&grid, 1, 1, 0), side);
+        assert_eq!(occupancy(&grid, -2, -2, -1), side);
+
+        // Voxels past the side are left alone.
+        assert_eq!(occupancy(&grid, 2, 0, 2), 0);
+    }
+
+    #[test]
+    fn fill_wedge() {
+        let mut grid = SmoothGrid::new();
+
+        // A wedge covering the 4x4x4 voxels starting at the origin.
+        grid.fill_wedge(
+            Vector3::new(8.0, 8.0, 8.0),
+            Vector3::new(16.0, 16.0, 16.0),
+            TerrainMaterials::Rock,
+        );
+
+        // The back of the wedge is full up to the voxel that its slope meets.
+        for y in 0..3 {
+            assert_eq!(occupancy(&grid, 1, y, 3), 255);
+        }
+        assert_eq!(occupancy(&grid, 1, 1, 2), 255);
+
+        // Voxels centered on the slope are half full, the same across the
+        // wedge's width.
+        for i in 0..4 {
+            assert_eq!(occupancy(&grid, 0, i, i), 128);
+            assert_eq!(occupancy(&grid, 3, i, i), 128);
+        }
+
+        // Voxels above the slope and outside of the wedge are left alone.
+        assert_eq!(occupancy(&grid, 1, 2, 1), 0);
+        assert_eq!(occupancy(&grid, 1, 3, 0), 0);
+        assert_eq!(occupancy(&grid, 4, 0, 3), 0);
+        assert_eq!(occupancy(&grid, 1, 0, 4), 0);
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(SmoothGrid::decode(&[]).is_err());
+        assert!(SmoothGrid::decode(&[2, 5]).is_err());
+        assert!(SmoothGrid::decode(&[1, 8]).is_err());
+
+        // A chunk header with no voxels following it.
+        assert!(SmoothGrid::decode(&[1, 5, 0]).is_err());
+
+        // A chunk that ends after its first voxel.
+        assert!(SmoothGrid::decode(&[1, 5, 0, 31]).is_err());
+        assert!(SmoothGrid::count_chunks(&[1, 5, 0, 31]).is_err());
+        assert_eq!(SmoothGrid::count_chunks(&[1, 5]).unwrap(), 0);
+
+        // A chunk whose voxels are too far from the origin to be placed.
+        let mut blob = vec![1, 5, 0b0000_0011];
+        blob.extend_from_slice(&i32::MAX.to_le_bytes());
+        for _ in 0..128 {
+            blob.extend_from_slice(&[RUN_HAS_COUNT | 2, 255]);
+        }
+
+        let expected = TerrainError::ChunkOutOfRange(ChunkCoordinates::new(i32::MAX, 0, 0));
+        assert_eq!(
+            SmoothGrid::decode(&blob).unwrap_err().to_string(),
+            expected.to_string()
+        );
+        assert!(SmoothGrid::decode_chunks(blob.as_slice(), |_, _| {}).is_err());
+        assert!(SmoothGrid::count_chunks(&blob).is_err());
+    }
+
+    #[test]
+    fn legacy_version() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(TerrainVec::new(3, -40, 7), Voxel::water(100));
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(40, 3, 5),
+            Voxel::solid(TerrainMaterials::Basalt),
+        );
+
+        // Version 0 blobs have the same layout, so they decode the same way
+        // and are upgraded to version 1 when encoded again.
+        let mut blob = grid.encode();
+        blob[0] = 0;
+        let decoded = SmoothGrid::decode(&blob).unwrap();
+        assert_eq!(decoded, grid);
+        assert_eq!(decoded.encode(), grid.encode());
+        assert_eq!(SmoothGrid::count_chunks(&blob).unwrap(), 3);
+
+        // They can't have shorelines, which version 1 blobs can.
+        grid.write_voxel_world(
+            TerrainVec::new(1, 1, 1),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 100,
+                water_occupancy: 155,
+            },
+        );
+        let mut blob = grid.encode();
+        assert!(SmoothGrid::decode(&blob).is_ok());
+        blob[0] = 0;
+        assert_eq!(
+            SmoothGrid::decode(&blob).unwrap_err().to_string(),
+            TerrainError::LegacyShoreline.to_string()
+        );
+        assert!(SmoothGrid::count_chunks(&blob).is_err());
+
+        let recovered = SmoothGrid::decode_lenient(&blob).unwrap();
+        assert_eq!(recovered.grid, grid);
+        assert!(recovered.warnings.is_empty());
+    }
+
+    #[test]
+    fn unknown_materials() {
+        // A chunk full of material ID 30, which doesn't exist yet.
+        let mut blob = vec![1, 5, 0];
+        for _ in 0..128 {
+            blob.extend_from_slice(&[RUN_HAS_COUNT | 30, 255]);
+        }
+
+        let grid = SmoothGrid::decode(&blob).unwrap();
+        let unknown = TerrainMaterials::Unknown(UnknownMaterial::try_from(30).unwrap());
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(31, 31, 31)),
+            Some(&Voxel::solid(unknown))
+        );
+        assert_eq!(SmoothGrid::decode(&grid.encode()).unwrap(), grid);
+        assert_eq!(grid.encode(), blob);
+    }
+
+    #[test]
+    fn try_new_coordinates() {
+        assert_eq!(
+            VoxelCoordinates::try_new(31, 0, 7).unwrap(),
+            VoxelCoordinates::new(31, 0, 7)
+        );
+        assert!(VoxelCoordinates::try_new(0, 32, 0).is_err());
+
+        let limit = MAX_VOXEL_COORDINATE / CHUNK_SIZE as i32;
+        assert!(ChunkCoordinates::try_new(-limit, 0, limit - 1).is_ok());
+        assert!(ChunkCoordinates::try_new(0, limit, 0).is_err());
+        assert!(ChunkCoordinates::try_new(-limit - 1, 0, 0).is_err());
+    }
+
+    #[test]
+    fn coordinate_ops() {
+        let position = TerrainVec::new(5, -3, 40);
+        assert_eq!(
+            position + TerrainVec::new(1, 2, -40),
+            TerrainVec::new(6, -1, 0)
+        );
+        assert_eq!(position - position, TerrainVec::default());
+        assert_eq!(position.offset(0, 3, 0), Some(TerrainVec::new(5, 0, 40)));
+        assert_eq!(position.neighbors()[3], Some(TerrainVec::new(5, -4, 40)));
+
+        let edge = TerrainVec::new(i32::MAX, 0, i32::MIN);
+        assert_eq!(edge.offset(1, 0, 0), None);
+        assert_eq!(edge.offset(0, 0, -1), None);
+        assert_eq!(
+            edge.neighbors(),
+            [
+                None,
+                Some(TerrainVec::new(i32::MAX - 1, 0, i32::MIN)),
+                Some(TerrainVec::new(i32::MAX, 1, i32::MIN)),
+                Some(TerrainVec::new(i32::MAX, -1, i32::MIN)),
+                Some(TerrainVec::new(i32::MAX, 0, i32::MIN + 1)),
+                None,
+            ]
+        );
+
+        let chunk = ChunkCoordinates::new(1, 1, 1);
+        assert_eq!(
+            chunk - ChunkCoordinates::new(2, 0, 1),
+            ChunkCoordinates::new(-1, 1, 0)
+        );
+        assert!(chunk.neighbors().iter().all(|neighbor| {
+            let distance = neighbor.unwrap() - chunk;
+            (distance.x.abs() + distance.y.abs() + distance.z.abs()) == 1
+        }));
+
+        let voxel = VoxelCoordinates::new(0, 31, 4);
+        assert_eq!(
+            voxel.offset(3, -1, 0),
+            Some(VoxelCoordinates::new(3, 30, 4))
+        );
+        assert_eq!(voxel.offset(-1, 0, 0), None);
+        assert_eq!(voxel.offset(0, 1, 0), None);
+        assert_eq!(voxel.offset(0, i32::MAX, 0), None);
+    }
+
+    #[test]
+    fn iter_voxels() {
+        let mut chunk = Chunk::new();
+        assert_eq!(chunk.iter_voxels().count(), 0);
+
+        let grass = Voxel::solid(TerrainMaterials::Grass);
+        chunk.write_voxel(VoxelCoordinates::new(3, 0, 1), grass);
+        chunk.write_voxel(VoxelCoordinates::new(1, 2, 0), Voxel::water(128));
+        chunk.write_voxel(
+            VoxelCoordinates::new(0, 1, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Rock),
+                occupancy: 0,
+                water_occupancy: 0,
+            },
+        );
+
+        let voxels: Vec<_> = chunk.iter_voxels().collect();
+        assert_eq!(
+            voxels,
+            [
+                (VoxelCoordinates::new(3, 0, 1), &grass),
+                (VoxelCoordinates::new(1, 2, 0), &Voxel::water(128)),
+            ]
+        );
+
+        for (_, voxel) in chunk.iter_voxels_mut() {
+            voxel.water_occupancy = 255;
+        }
+
+        assert_eq!(
+            chunk
+                .get_voxel(VoxelCoordinates::new(3, 0, 1))
+                .water_occupancy,
+            255
+        );
+        assert_eq!(
+            chunk.get_voxel(VoxelCoordinates::new(0, 0, 0)),
+            &Voxel::default()
+        );
+        assert_eq!(chunk.iter_voxels().count(), 2);
+    }
+
+    #[test]
+    fn iter_region() {
+        let mut grid = SmoothGrid::new();
+        let grass = Voxel::solid(TerrainMaterials::Grass);
+        grid.write_voxel_world(TerrainVec::new(31, 0, 0), grass);
+        grid.write_voxel_world(TerrainVec::new(32, 1, -1), Voxel::water(255));
+
+        let min = TerrainVec::new(30, 0, -1);
+        let max = TerrainVec::new(33, 1, 0);
+        let voxels: Vec<_> = grid.iter_region(min, max).collect();
+        assert_eq!(voxels.len(), 4 * 2 * 2);
+
+        let mut positions: Vec<_> = voxels.iter().map(|(position, _)| *position).collect();
+        positions.sort();
+        positions.dedup();
+        assert_eq!(positions.len(), voxels.len());
+        assert!(positions.iter().all(|position| {
+            (min.x..=max.x).contains(&position.x)
+                && (min.y..=max.y).contains(&position.y)
+                && (min.z..=max.z).contains(&position.z)
+        }));
+
+        let region = grid.read_voxels(min, max);
+        for (position, voxel) in &voxels {
+            assert_eq!(region.get_voxel(*position).as_ref(), Some(*voxel));
+        }
+
+        let filled: Vec<_> = voxels
+            .iter()
+            .filter(|(_, voxel)| !voxel.is_empty())
+            .map(|(position, _)| *position)
+            .collect();
+        assert_eq!(
+            filled,
+            [TerrainVec::new(32, 1, -1), TerrainVec::new(31, 0, 0)]
+        );
+
+        assert_eq!(grid.iter_region(max, min).count(), 0);
+    }
+
+    #[test]
+    fn encode_to() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(-40, 0, 0),
+            TerrainVec::new(40, 3, 3),
+            Voxel::solid(TerrainMaterials::Basalt),
+        );
+
+        let mut written = Vec::new();
+        grid.encode_to(&mut written).unwrap();
+        assert_eq!(written, grid.encode());
+        assert_eq!(SmoothGrid::decode(&written).unwrap(), grid);
+    }
+
+    #[test]
+    fn resized_chunks() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(-3, 0, 5),
+            TerrainVec::new(40, 2, 6),
+            Voxel::solid(TerrainMaterials::Slate),
+        );
+        grid.write_voxel_world(TerrainVec::new(100, -70, 0), Voxel::water(90));
+
+        for &chunk_size_log2 in &[2, 3, 6] {
+            let blob = grid
+                .encode_with(EncodeOptions::new().chunk_size_log2(chunk_size_log2))
+                .unwrap();
+            assert_eq!(blob[1], chunk_size_log2);
+            assert_eq!(SmoothGrid::decode(&blob).unwrap(), grid);
+            assert_eq!(SmoothGrid::decode_lenient(&blob).unwrap().grid, grid);
+        }
+
+        // Chunks of 64 voxels cover the grid's three chunks at Y 0 with two
+        // chunks, and the water with one more.
+        let blob = grid
+            .encode_with(EncodeOptions::new().chunk_size_log2(6))
+            .unwrap();
+        assert_eq!(SmoothGrid::count_chunks(&blob).unwrap(), 3);
+
+        // A chunk too far away for its voxels' positions to be found can't be
+        // split up, but can still be written as it is.
+        grid.write_chunk(
+            ChunkCoordinates::new(i32::MAX, 0, 0),
+            Chunk::new_with_base(Voxel::water(255)),
+        );
+        let options = EncodeOptions::new().chunk_size_log2(4);
+        assert!(grid.encode_with(options).is_err());
+
+        let mut written = Vec::new();
+        let error = grid.encode_to_with(&mut written, options).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(written.is_empty());
+        assert!(grid.encode_with(EncodeOptions::new()).is_ok());
+
+        assert_eq!(
+            EncodeOptions::new().chunk_size_log2(9),
+            EncodeOptions::new().chunk_size_log2(7)
+        );
+    }
+
+    #[test]
+    fn encode_into() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(2, 3, 4),
+            Voxel::solid(TerrainMaterials::Cobblestone),
+        );
+
+        let mut buffer = vec![0xff; 4096];
+        grid.encode_into(&mut buffer);
+        assert_eq!(buffer, grid.encode());
+        assert!(buffer.capacity() >= 4096);
+
+        // The blob is the header, the position of the only chunk, and then
+        // the chunk's voxels.
+        let mut chunk_buffer = vec![0xff];
+        let chunk = grid.get_chunk(ChunkCoordinates::new(0, 0, 0)).unwrap();
+        chunk.encode_into(&mut chunk_buffer);
+        assert_eq!(chunk_buffer, buffer[3..]);
+    }
+
+    #[test]
+    fn encode_dirty() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(40, 0, 0),
+            Voxel::solid(TerrainMaterials::Basalt),
+        );
+        assert!(grid.encode_dirty().is_empty());
+
+        grid.set_dirty_tracking(true);
+        assert_eq!(grid.dirty_chunks().count(), 0);
+
+        grid.write_voxel_world(TerrainVec::new(35, 0, 0), Voxel::default());
+        grid.remove_chunk(ChunkCoordinates::new(0, 0, 0));
+        assert!(grid.is_dirty(ChunkCoordinates::new(1, 0, 0)));
+
+        let payloads = grid.encode_dirty();
+        let mut expected = Vec::new();
+        grid.get_chunk(ChunkCoordinates::new(1, 0, 0))
+            .unwrap()
+            .encode_into(&mut expected);
+
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[&ChunkCoordinates::new(0, 0, 0)], None);
+        assert_eq!(payloads[&ChunkCoordinates::new(1, 0, 0)], Some(expected));
+        assert_eq!(grid.dirty_chunks().count(), 0);
+
+        // Moving every voxel marks both where they were and where they went.
+        grid.translate(TerrainVec::new(0, 32, 0)).unwrap();
+        assert_eq!(
+            grid.dirty_chunks().collect::<Vec<_>>(),
+            [
+                ChunkCoordinates::new(1, 0, 0),
+                ChunkCoordinates::new(1, 1, 0)
+            ]
+        );
+
+        // Dirty chunks don't make otherwise equal grids different.
+        let mut clean = grid.clone();
+        clean.set_dirty_tracking(false);
+        assert_eq!(grid, clean);
+        assert!(!clean.is_dirty(ChunkCoordinates::new(1, 1, 0)));
+    }
+
+    #[test]
+    fn decode_chunks() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(-10, 0, 0),
+            TerrainVec::new(40, 0, 0),
+            Voxel::solid(TerrainMaterials::Salt),
+        );
+        grid.write_chunk(ChunkCoordinates::new(0, 3, 0), Chunk::new());
+        let blob = grid.encode();
+
+        let mut visited = Vec::new();
+        SmoothGrid::decode_chunks(blob.as_slice(), |position, chunk| {
+            visited.push((position, chunk.iter_voxels().count()));
+        })
+        .unwrap();
+
+        assert_eq!(
+            visited,
+            [
+                (ChunkCoordinates::new(-1, 0, 0), 10),
+                (ChunkCoordinates::new(0, 0, 0), 32),
+                (ChunkCoordinates::new(0, 3, 0), 0),
+                (ChunkCoordinates::new(1, 0, 0), 9),
+            ]
+        );
+
+        let resized = grid
+            .encode_with(EncodeOptions::new().chunk_size_log2(4))
+            .unwrap();
+        assert!(SmoothGrid::decode_chunks(resized.as_slice(), |_, _| {}).is_err());
+        assert!(SmoothGrid::decode_chunks(&blob[..blob.len() - 1], |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn decode_from() {
+        /// A reader that only returns one byte at a time, then fails instead
+        /// of ending if `fail` is set.
+        struct Trickle<'a> {
+            bytes: &'a [u8],
+            fail: bool,
+        }
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.bytes.is_empty() && self.fail {
+                    return Err(io::Error::other("connection lost"));
+                }
+
+                let (mut first, rest) = self.bytes.split_at(self.bytes.len().min(1));
+                self.bytes = rest;
+                first.read(buf)
+            }
+        }
+
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(-40, 0, 0),
+            TerrainVec::new(40, 3, 3),
+            Voxel::solid(TerrainMaterials::Basalt),
+        );
+        grid.write_voxel_world(TerrainVec::new(0, 70, 0), Voxel::water(100));
+
+        let encoded = grid.encode();
+        let decoded = SmoothGrid::decode_from(Trickle {
+            bytes: &encoded,
+            fail: false,
+        })
+        .unwrap();
+        assert_eq!(decoded, grid);
+
+        let error = SmoothGrid::decode_from(Trickle {
+            bytes: &encoded[..encoded.len() - 1],
+            fail: false,
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), TerrainError::UnexpectedEof.to_string());
+
+        let error = SmoothGrid::decode_from(Trickle {
+            bytes: &encoded[..encoded.len() / 2],
+            fail: true,
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "failed to read SmoothGrid blob");
+    }
+
+    #[test]
+    fn chunk_fill() {
+        let mut chunk = Chunk::new_with_base(Voxel::water(255));
+        chunk.fill(TerrainMaterials::Sand, 128);
+
+        let expected = Voxel {
+            material: Some(TerrainMaterials::Sand),
+            occupancy: 128,
+            water_occupancy: 0,
+        };
+        assert_eq!(chunk.iter_voxels().count(), CHUNK_VOLUME);
+        assert!(chunk.iter_voxels().all(|(_, voxel)| *voxel == expected));
+
+        chunk.fill(TerrainMaterials::Sand, 0);
+        assert_eq!(
+            chunk,
+            Chunk::new_with_base(Voxel {
+                occupancy: 0,
+                ..expected
+            })
+        );
+        assert_eq!(chunk.iter_voxels().count(), 0);
+    }
+
+    #[test]
+    fn normalize() {
+        let mut grid = SmoothGrid::new();
+
+        let mut empty = Chunk::new();
+        empty.fill(TerrainMaterials::Grass, 0);
+        grid.write_chunk(ChunkCoordinates::new(1, 0, 0), empty);
+
+        let mut chunk = Chunk::new();
+        chunk.write_voxel(
+            VoxelCoordinates::new(0, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        chunk.write_voxel(
+            VoxelCoordinates::new(1, 0, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 0,
+                water_occupancy: 100,
+            },
+        );
+        chunk.write_voxel(
+            VoxelCoordinates::new(2, 0, 0),
+            Voxel {
+                material: None,
+                occupancy: 50,
+                water_occupancy: 0,
+            },
+        );
+        grid.write_chunk(ChunkCoordinates::new(0, 0, 0), chunk);
+
+        let before = grid.encode();
+        grid.normalize();
+
+        assert!(grid.get_chunk(ChunkCoordinates::new(1, 0, 0)).is_none());
+
+        let chunk = grid.get_chunk(ChunkCoordinates::new(0, 0, 0)).unwrap();
+        assert_eq!(
+            *chunk.get_voxel(VoxelCoordinates::new(0, 0, 0)),
+            Voxel::solid(TerrainMaterials::Rock)
+        );
+        assert_eq!(
+            *chunk.get_voxel(VoxelCoordinates::new(1, 0, 0)),
+            Voxel::water(100)
+        );
+        assert_eq!(
+            *chunk.get_voxel(VoxelCoordinates::new(2, 0, 0)),
+            Voxel::default()
+        );
+
+        let after = grid.encode();
+        assert!(after.len() < before.len());
+        assert_eq!(SmoothGrid::decode(&after).unwrap(), grid);
+    }
+
+    #[test]
+    fn iter_voxel_centers() {
+        let mut grid = SmoothGrid::new();
+        let rock = Voxel::solid(TerrainMaterials::Rock);
+        grid.write_voxel_world(TerrainVec::new(-1, 0, 0), rock);
+        grid.write_voxel_world(TerrainVec::new(33, 2, 0), Voxel::water(255));
+        grid.write_chunk(ChunkCoordinates::new(5, 5, 5), Chunk::new());
+
+        let centers: Vec<_> = grid.iter_voxel_centers().collect();
+        assert_eq!(
+            centers,
+            [
+                (Vector3::new(-2.0, 2.0, 2.0), &rock),
+                (Vector3::new(134.0, 10.0, 2.0), &Voxel::water(255)),
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_chunks_near() {
+        let mut grid = SmoothGrid::new();
+        let positions = [
+            ChunkCoordinates::new(3, 0, 0),
+            ChunkCoordinates::new(0, 0, 0),
+            ChunkCoordinates::new(-1, 0, 0),
+            ChunkCoordinates::new(1, 1, 0),
+            ChunkCoordinates::new(0, -2, 1),
+        ];
+        for position in positions {
+            grid.write_chunk(position, Chunk::new_with_base(Voxel::water(255)));
+        }
+
+        let order: Vec<_> = grid
+            .iter_chunks_near(ChunkCoordinates::new(1, 0, 0))
+            .map(|(position, _)| *position)
+            .collect();
+
+        assert_eq!(
+            order,
+            [
+                ChunkCoordinates::new(0, 0, 0),
+                ChunkCoordinates::new(1, 1, 0),
+                ChunkCoordinates::new(-1, 0, 0),
+                ChunkCoordinates::new(3, 0, 0),
+                ChunkCoordinates::new(0, -2, 1),
+            ]
+        );
+
+        // Distances between chunks at opposite corners don't overflow.
+        let far = ChunkCoordinates::new(i32::MAX, i32::MAX, i32::MAX);
+        grid.write_chunk(far, Chunk::new());
+        let farthest = grid
+            .iter_chunks_near(ChunkCoordinates::new(i32::MIN, i32::MIN, i32::MIN))
+            .last()
+            .map(|(position, _)| *position);
+        assert_eq!(farthest, Some(far));
+    }
+
+    #[test]
+    fn bounds() {
+        let mut grid = SmoothGrid::new();
+        assert_eq!(grid.bounds(), None);
+
+        grid.write_chunk(ChunkCoordinates::new(5, 5, 5), Chunk::new());
+        assert_eq!(grid.bounds(), None);
+
+        grid.write_voxel_world(TerrainVec::new(-3, 10, 40), Voxel::water(255));
+        grid.write_voxel_world(
+            TerrainVec::new(70, -2, 1),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        assert_eq!(
+            grid.bounds(),
+            Some((TerrainVec::new(-3, -2, 1), TerrainVec::new(70, 10, 40)))
+        );
+
+        // Chunks too far away to be placed don't count.
+        grid.write_chunk(
+            ChunkCoordinates::new(i32::MAX, 0, i32::MIN),
+            Chunk::new_with_base(Voxel::water(255)),
+        );
+        assert_eq!(
+            grid.bounds(),
+            Some((TerrainVec::new(-3, -2, 1), TerrainVec::new(70, 10, 40)))
+        );
+    }
+
+    #[test]
+    fn translate() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(TerrainVec::new(0, 0, 0), Voxel::water(255));
+        grid.write_voxel_world(
+            TerrainVec::new(31, -1, 5),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_chunk(ChunkCoordinates::new(4, 4, 4), Chunk::new());
+
+        let mut by_chunks = grid.clone();
+        by_chunks.translate(TerrainVec::new(32, -64, 0)).unwrap();
+        assert_eq!(by_chunks.chunks().count(), 3);
+        assert_eq!(
+            by_chunks.get_voxel_world(TerrainVec::new(63, -65, 5)),
+            Some(&Voxel::solid(TerrainMaterials::Rock))
+        );
+
+        let mut by_voxels = grid.clone();
+        by_voxels.translate(TerrainVec::new(1, 2, -3)).unwrap();
+        assert_eq!(
+            by_voxels.get_voxel_world(TerrainVec::new(1, 2, -3)),
+            Some(&Voxel::water(255))
+        );
+        assert_eq!(
+            by_voxels.get_voxel_world(TerrainVec::new(32, 1, 2)),
+            Some(&Voxel::solid(TerrainMaterials::Rock))
+        );
+        assert_eq!(
+            by_voxels.bounds(),
+            Some((TerrainVec::new(1, 1, -3), TerrainVec::new(32, 2, 2)))
+        );
+
+        // Moving the voxels back gives the same voxels, without the empty chunk.
+        by_voxels.translate(TerrainVec::new(-1, -2, 3)).unwrap();
+        grid.remove_chunk(ChunkCoordinates::new(4, 4, 4));
+        assert_eq!(by_voxels, grid);
+
+        // Moving terrain past the limits of an `i32` fails and leaves the grid
+        // alone, whether it's moved by whole chunks or not.
+        let before = grid.clone();
+        assert!(grid
+            .translate(TerrainVec::new(i32::MAX - 30, 0, 0))
+            .is_err());
+        assert!(grid.translate(TerrainVec::new(0, i32::MIN + 1, 0)).is_err());
+        assert_eq!(grid, before);
+
+        grid.write_chunk(
+            ChunkCoordinates::new(i32::MAX, 0, 0),
+            Chunk::new_with_base(Voxel::water(255)),
+        );
+        let before = grid.clone();
+        assert!(grid.translate(TerrainVec::new(1, 0, 0)).is_err());
+        assert!(grid.translate(TerrainVec::new(32, 0, 0)).is_err());
+        assert_eq!(grid, before);
+        assert!(grid.translate(TerrainVec::new(-32, 0, 0)).is_ok());
+    }
+
+    #[test]
+    fn rotate_90() {
+        let rock = Voxel::solid(TerrainMaterials::Rock);
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(TerrainVec::new(0, 0, 0), Voxel::water(255));
+        grid.write_voxel_world(TerrainVec::new(40, 3, 5), rock);
+
+        let mut rotated = grid.clone();
+        rotated.rotate_y_90(1);
+        assert_eq!(
+            rotated.get_voxel_world(TerrainVec::new(0, 0, -1)),
+            Some(&Voxel::water(255))
+        );
+        assert_eq!(
+            rotated.get_voxel_world(TerrainVec::new(5, 3, -41)),
+            Some(&rock)
+        );
+
+        rotated.rotate_y_90(3);
+        assert_eq!(rotated, grid);
+
+        let mut rotated = grid.clone();
+        rotated.rotate_x_90(1);
+        assert_eq!(
+            rotated.get_voxel_world(TerrainVec::new(40, -6, 3)),
+            Some(&rock)
+        );
+
+        let mut rotated = grid.clone();
+        rotated.rotate_z_90(2);
+        assert_eq!(
+            rotated.get_voxel_world(TerrainVec::new(-41, -4, 5)),
+            Some(&rock)
+        );
+
+        let mut rotated = grid.clone();
+        rotated.rotate_z_90(4);
+        assert_eq!(rotated, grid);
+    }
+
+    #[test]
+    fn mirror() {
+        let rock = Voxel::solid(TerrainMaterials::Rock);
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(TerrainVec::new(0, 0, 0), Voxel::water(255));
+        grid.write_voxel_world(TerrainVec::new(40, 3, -5), rock);
+
+        let mut mirrored = grid.clone();
+        mirrored.mirror(Axes::X);
+        assert_eq!(
+            mirrored.get_voxel_world(TerrainVec::new(-1, 0, 0)),
+            Some(&Voxel::water(255))
+        );
+        assert_eq!(
+            mirrored.get_voxel_world(TerrainVec::new(-41, 3, -5)),
+            Some(&rock)
+        );
+
+        mirrored.mirror(Axes::X);
+        assert_eq!(mirrored, grid);
+
+        let mut mirrored = grid.clone();
+        mirrored.mirror(Axes::all());
+        assert_eq!(
+            mirrored.get_voxel_world(TerrainVec::new(-41, -4, 4)),
+            Some(&rock)
+        );
+
+        let mut mirrored = grid.clone();
+        mirrored.mirror(Axes::empty());
+        assert_eq!(mirrored, grid);
+    }
+
+    /// Chunks too far away to move are left where they are by mirroring and
+    /// rotating, instead of overflowing.
+    #[test]
+    fn move_far_chunks() {
+        let rock = Voxel::solid(TerrainMaterials::Rock);
+        let far = ChunkCoordinates::new(i32::MAX, 0, i32::MIN);
+
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(TerrainVec::new(40, 3, -5), rock);
+        grid.write_chunk(far, Chunk::new_with_base(Voxel::water(255)));
+
+        let mut moved = grid.clone();
+        moved.mirror(Axes::all());
+        moved.rotate_y_90(1);
+        moved.rotate_x_90(1);
+        moved.rotate_z_90(1);
+
+        assert_eq!(moved.chunks().count(), 2);
+        assert_eq!(moved.get_chunk(far), grid.get_chunk(far));
+        assert_eq!(
+            moved.get_voxel_world(TerrainVec::new(40, 4, -4)),
+            Some(&rock)
+        );
+    }
+
+    #[test]
+    fn downsample() {
+        let rock = Voxel::solid(TerrainMaterials::Rock);
+        let grass = Voxel::solid(TerrainMaterials::Grass);
+
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(TerrainVec::new(0, 0, 0), grass);
+        grid.write_voxel_world(TerrainVec::new(1, 0, 0), rock);
+        grid.write_voxel_world(TerrainVec::new(0, 1, 0), rock);
+        grid.write_voxel_world(TerrainVec::new(1, 1, 0), rock);
+        grid.write_voxel_world(TerrainVec::new(0, 0, 1), grass);
+        grid.write_voxel_world(TerrainVec::new(1, 1, 1), Voxel::water(255));
+        grid.write_voxel_world(TerrainVec::new(-1, -1, -1), grass);
+        grid.write_voxel_world(TerrainVec::new(41, 0, 0), rock);
+
+        let coarse = grid.downsample(2);
+        assert_eq!(coarse.chunks().count(), 2);
+        assert_eq!(
+            coarse.get_voxel_world(TerrainVec::new(0, 0, 0)),
+            Some(&Voxel {
+                material: Some(TerrainMaterials::Rock),
+                occupancy: 159,
+                water_occupancy: 32,
+            })
+        );
+        assert_eq!(
+            coarse.get_voxel_world(TerrainVec::new(-1, -1, -1)),
+            Some(&Voxel {
+                material: Some(TerrainMaterials::Grass),
+                occupancy: 32,
+                water_occupancy: 0,
+            })
+        );
+        assert_eq!(
+            coarse
+                .get_voxel_world(TerrainVec::new(20, 0, 0))
+                .unwrap()
+                .material,
+            Some(TerrainMaterials::Rock)
+        );
+
+        assert_eq!(grid.downsample(1), grid);
+    }
+
+    /// Chunks at the edge of the range terrain can be placed in, read from a
+    /// blob, can be downsampled and have their voxels' centers found.
+    #[test]
+    fn extreme_chunk_positions() {
+        let limit = MAX_VOXEL_COORDINATE / CHUNK_SIZE as i32;
+
+        let water_chunk = |blob: &mut Vec<u8>| {
+            for _ in 0..128 {
+                blob.extend_from_slice(&[RUN_HAS_COUNT | WATER_ID, 255]);
+            }
+        };
+
+        // A chunk full of water at the lowest X allowed, then one at the
+        // highest.
+        let mut blob = vec![1, 5, 0b11];
+        blob.extend_from_slice(&(-limit).to_le_bytes());
+        water_chunk(&mut blob);
+        blob.push(0b11);
+        blob.extend_from_slice(&(2 * limit - 1).to_le_bytes());
+        water_chunk(&mut blob);
+
+        let grid = SmoothGrid::decode(&blob).unwrap();
+        assert_eq!(grid.chunks().count(), 2);
+
+        let coarse = grid.downsample(4);
+        let edge = MAX_VOXEL_COORDINATE / 4;
+        assert_eq!(
+            coarse.get_voxel_world(TerrainVec::new(-edge, 0, 0)),
+            Some(&Voxel::water(255))
+        );
+        assert_eq!(
+            coarse.get_voxel_world(TerrainVec::new(edge - 1, 7, 7)),
+            Some(&Voxel::water(255))
+        );
+
+        let (first, _) = grid.iter_voxel_centers().next().unwrap();
+        let (last, _) = grid.iter_voxel_centers().last().unwrap();
+        let edge = MAX_VOXEL_COORDINATE as f32 * VOXEL_SIZE;
+        assert_eq!(first, Vector3::new(-edge + 2.0, 2.0, 2.0));
+        assert_eq!(last, Vector3::new(edge - 2.0, 126.0, 126.0));
+
+        // Chunks too far away to be read from a blob can still be written
+        // directly. They're left out of downsampled grids.
+        let mut far = grid.clone();
+        let position = ChunkCoordinates::new(i32::MAX, 0, 0);
+        far.write_chunk(position, Chunk::new_with_base(Voxel::water(255)));
+        assert_eq!(far.downsample(4), coarse);
+
+        let (center, _) = far.iter_voxel_centers().last().unwrap();
+        assert_eq!(center.x, (i32::MAX as f64 * 32.0 * 4.0 + 126.0) as f32);
+    }
+
+    #[test]
+    fn to_volume() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(0, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(1, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_voxel_world(TerrainVec::new(2, 0, 0), Voxel::water(255));
+        grid.write_voxel_world(
+            TerrainVec::new(0, 1, 0),
+            Voxel::solid(TerrainMaterials::Sand),
+        );
+
+        // Three voxels wide, two tall, and one deep.
+        let region = Region3::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(12.0, 8.0, 3.0));
+
+        let volume = grid.to_volume(region, 1);
+        let rock = material_id(TerrainMaterials::Rock);
+        let sand = material_id(TerrainMaterials::Sand);
+        assert_eq!(volume.dims, [3, 2, 1]);
+        assert_eq!(
+            volume.material,
+            [rock, rock, WATER_ID, sand, AIR_ID, AIR_ID]
+        );
+        assert_eq!(volume.occupancy, [255, 255, 255, 255, 0, 0]);
+
+        // The texel on the right only covers the two voxels in its column.
+        let volume = grid.to_volume(region, 2);
+        assert_eq!(volume.dims, [2, 1, 1]);
+        assert_eq!(volume.material, [rock, WATER_ID]);
+        assert_eq!(volume.occupancy, [191, 128]);
+    }
+
+    #[test]
+    fn stats() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(1, 1, 1),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(-1, 0, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 100,
+                water_occupancy: 50,
+            },
+        );
+        grid.write_voxel_world(TerrainVec::new(2, 0, 0), Voxel::water(255));
+
+        let stats = grid.stats();
+        assert_eq!(stats.chunks, 2);
+        assert_eq!(
+            stats.material_voxels.into_iter().collect::<Vec<_>>(),
+            [(TerrainMaterials::Sand, 1), (TerrainMaterials::Rock, 8)]
+        );
+        assert_eq!(stats.water_voxels, 2);
+        assert_eq!(stats.encoded_size, grid.encode().len());
+        assert_eq!(SmoothGrid::new().stats().encoded_size, 2);
+    }
+
+    #[test]
+    fn flood_fill() {
+        // A hollow box of rock with one hole in its side.
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(4, 4, 4),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.fill_block(
+            TerrainVec::new(1, 1, 1),
+            TerrainVec::new(3, 3, 3),
+            Voxel::default(),
+        );
+
+        let (min, max) = (TerrainVec::new(-2, -2, -2), TerrainVec::new(6, 6, 6));
+
+        let mut flooded = grid.clone();
+        let result = flooded.flood_fill(
+            TerrainVec::new(2, 2, 2),
+            Voxel::water(255),
+            min,
+            max,
+            Voxel::is_empty,
+        );
+        assert_eq!(
+            result,
+            FloodFill {
+                filled: 27,
+                reached_bounds: false,
+            }
+        );
+        assert_eq!(
+            flooded.get_voxel_world(TerrainVec::new(1, 3, 1)),
+            Some(&Voxel::water(255))
+        );
+
+        grid.write_voxel_world(TerrainVec::new(4, 2, 2), Voxel::default());
+        let result = grid.flood_fill(
+            TerrainVec::new(2, 2, 2),
+            Voxel::water(255),
+            min,
+            max,
+            Voxel::is_empty,
+        );
+        assert!(result.reached_bounds);
+        assert!(grid
+            .get_voxel_world(TerrainVec::new(7, 2, 2))
+            .unwrap()
+            .is_empty());
+
+        // Starting on a voxel that doesn't match fills nothing.
+        let result = grid.flood_fill(
+            TerrainVec::new(0, 0, 0),
+            Voxel::water(255),
+            min,
+            max,
+            Voxel::is_empty,
+        );
+        assert_eq!(result.filled, 0);
+    }
+
+    #[test]
+    fn smooth() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(7, 3, 7),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        grid.write_voxel_world(TerrainVec::new(3, 4, 3), Voxel::water(200));
+
+        let region = Region3::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(32.0, 32.0, 32.0));
+        grid.smooth(region, 1);
+
+        // Deep inside, nothing changes.
+        assert_eq!(occupancy(&grid, 3, 1, 3), 255);
+        // The top layer loses a third of its neighbors, and the layer above
+        // it gains a third.
+        assert_eq!(occupancy(&grid, 3, 3, 3), 170);
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(3, 4, 3)),
+            Some(&Voxel {
+                material: Some(TerrainMaterials::Grass),
+                occupancy: 85,
+                water_occupancy: 200,
+            })
+        );
+        // Voxels below the region aren't changed.
+        assert_eq!(occupancy(&grid, 3, -1, 3), 0);
+
+        grid.smooth(region, 3);
+        assert!(occupancy(&grid, 3, 5, 3) > 0);
+
+        // A shoreline voxel that's filled in by its neighbors loses its water.
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(7, 3, 7),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(3, 1, 3),
+            Voxel {
+                material: Some(TerrainMaterials::Grass),
+                occupancy: 250,
+                water_occupancy: 5,
+            },
+        );
+
+        grid.smooth(region, 1);
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(3, 1, 3)),
+            Some(&Voxel::solid(TerrainMaterials::Grass))
+        );
+        assert!(grid.validate().is_empty());
+    }
+
+    #[test]
+    fn validate() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(0, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(1, 0, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Rock),
+                occupancy: 255,
+                water_occupancy: 10,
+            },
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(2, 0, 0),
+            Voxel {
+                material: None,
+                occupancy: 30,
+                water_occupancy: 0,
+            },
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(MAX_VOXEL_COORDINATE, 0, 0),
+            Voxel::water(255),
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(MAX_VOXEL_COORDINATE - 1, 0, 0),
+            Voxel::water(255),
+        );
+
+        assert_eq!(
+            grid.validate(),
+            [
+                TerrainDiagnostic::WaterInFullSolid {
+                    position: TerrainVec::new(1, 0, 0),
+                    water_occupancy: 10,
+                },
+                TerrainDiagnostic::OccupancyWithoutMaterial {
+                    position: TerrainVec::new(2, 0, 0),
+                    occupancy: 30,
+                },
+                TerrainDiagnostic::ChunkOutOfRange {
+                    position: TerrainVec::new(MAX_VOXEL_COORDINATE, 0, 0).chunk_coordinates(),
+                },
+            ]
+        );
+
+        // Chunks far enough away that their voxels' positions would overflow
+        // are still reported.
+        let mut far = SmoothGrid::new();
+        let position = ChunkCoordinates::new(i32::MAX / 16, 0, 0);
+        far.write_chunk(
+            position,
+            Chunk::new_with_base(Voxel {
+                material: Some(TerrainMaterials::Rock),
+                occupancy: 0,
+                water_occupancy: 0,
+            }),
+        );
+        assert_eq!(
+            far.validate(),
+            [TerrainDiagnostic::ChunkOutOfRange { position }]
+        );
+    }
+
+    #[test]
+    fn channels() {
+        let mut chunk = Chunk::new();
+        chunk.write_voxel(
+            VoxelCoordinates::new(1, 2, 3),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 40,
+                water_occupancy: 7,
+            },
+        );
+
+        let material = chunk.material_channel();
+        let occupancy = chunk.occupancy_channel();
+        let water = chunk.water_channel();
+
+        let index = 1 + 3 * CHUNK_SIZE + 2 * CHUNK_SIZE * CHUNK_SIZE;
+        assert_eq!(material.len(), CHUNK_VOLUME);
+        assert_eq!(material[index], Some(TerrainMaterials::Sand));
+        assert_eq!(occupancy[index], 40);
+        assert_eq!(water[index], 7);
+        assert_eq!(
+            occupancy.iter().map(|&value| value as usize).sum::<usize>(),
+            40
+        );
+
+        assert_eq!(Chunk::from_channels(&material, &occupancy, &water), chunk);
+    }
+
+    #[test]
+    fn encode_without_shorelines() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(0, 0, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 200,
+                water_occupancy: 50,
+            },
+        );
+        grid.write_voxel_world(
+            TerrainVec::new(1, 0, 0),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 30,
+                water_occupancy: 220,
+            },
+        );
+
+        let options = EncodeOptions::new();
+        assert_eq!(grid.encode_with(options).unwrap(), grid.encode());
+
+        let flattened = grid.encode_with(options.shorelines(false)).unwrap();
+        let decoded = SmoothGrid::decode(&flattened).unwrap();
+        assert_eq!(
+            decoded.get_voxel_world(TerrainVec::new(0, 0, 0)),
+            Some(&Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 200,
+                water_occupancy: 0,
+            })
+        );
+        assert_eq!(
+            decoded.get_voxel_world(TerrainVec::new(1, 0, 0)),
+            Some(&Voxel::water(220))
+        );
+    }
+
+    #[test]
+    fn world_coordinates() {
+        let position = TerrainVec::from_world(Vector3::new(-0.5, 4.0, 131.9));
+        assert_eq!(position, TerrainVec::new(-1, 1, 32));
+        assert_eq!(
+            position.chunk_coordinates(),
+            ChunkCoordinates::new(-1, 0, 1)
+        );
+        assert_eq!(
+            position.voxel_coordinates(),
+            VoxelCoordinates::new(31, 1, 0)
+        );
+        assert_eq!(position.world_center(), Vector3::new(-2.0, 6.0, 130.0));
+
+        assert_eq!(
+            ChunkCoordinates::from_world(Vector3::new(-128.0, -128.1, 127.9)),
+            ChunkCoordinates::new(-1, -2, 0)
+        );
+    }
+
+    #[test]
+    fn write_voxels() {
+        let (min, max) = (TerrainVec::new(-3, 30, 5), TerrainVec::new(2, 33, 6));
+
+        let mut expected = SmoothGrid::new();
+        expected.fill_block(min, max, Voxel::solid(TerrainMaterials::Snow));
+        expected.write_voxel_world(TerrainVec::new(0, 32, 6), Voxel::water(90));
+
+        let region = expected.read_voxels(min, max);
+        assert_eq!(region.size(), [6, 4, 2]);
+
+        let mut grid = SmoothGrid::new();
+        grid.write_voxels(
+            region.min(),
+            region.materials(),
+            region.occupancy(),
+            region.water_occupancy(),
+            region.size(),
+        );
+
+        assert_eq!(grid, expected);
+        assert_eq!(grid.chunks().count(), 4);
+    }
+
+    #[test]
+    fn subtract_and_intersect() {
+        let mut ground = SmoothGrid::new();
+        ground.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(3, 3, 3),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        ground.fill_block(
+            TerrainVec::new(40, 0, 0),
+            TerrainVec::new(40, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        ground.write_voxel_world(
+            TerrainVec::new(1, 4, 1),
+            Voxel {
+                material: Some(TerrainMaterials::Rock),
+                occupancy: 100,
+                water_occupancy: 150,
+            },
+        );
+
+        let mut tunnel = SmoothGrid::new();
+        tunnel.fill_block(
+            TerrainVec::new(0, 1, 1),
+            TerrainVec::new(3, 1, 1),
+            Voxel::solid(TerrainMaterials::Sand),
+        );
+        tunnel.write_voxel_world(
+            TerrainVec::new(0, 2, 1),
+            Voxel {
+                material: Some(TerrainMaterials::Sand),
+                occupancy: 55,
+                water_occupancy: 0,
+            },
+        );
+        tunnel.write_voxel_world(TerrainVec::new(1, 4, 1), Voxel::water(255));
+
+        let mut carved = ground.clone();
+        carved.subtract(&tunnel);
+        let voxel =
+            |grid: &SmoothGrid, x, y, z| *grid.get_voxel_world(TerrainVec::new(x, y, z)).unwrap();
+
+        assert!(voxel(&carved, 2, 1, 1).is_empty());
+        assert_eq!(voxel(&carved, 0, 2, 1).occupancy, 200);
+        assert_eq!(voxel(&carved, 1, 4, 1), voxel(&ground, 1, 4, 1));
+        assert_eq!(
+            voxel(&carved, 40, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock)
+        );
+
+        let mut masked = ground.clone();
+        masked.intersect(&tunnel);
+
+        assert_eq!(masked.chunks().count(), 1);
+        assert_eq!(
+            voxel(&masked, 2, 1, 1),
+            Voxel::solid(TerrainMaterials::Rock)
+        );
+        assert_eq!(voxel(&masked, 0, 2, 1).occupancy, 55);
+        assert!(voxel(&masked, 1, 4, 1).is_empty());
+        assert!(voxel(&masked, 0, 0, 0).is_empty());
+    }
+}
@@ -0,0 +1,282 @@
+use super::{solid_occupancy, with_solid_occupancy, SmoothGrid, TerrainVec, Voxel, VOXEL_SIZE};
+use crate::{TerrainMaterials, Vector3};
+
+/// A brush for sculpting a [`SmoothGrid`] a little at a time, like the tools
+/// in Studio's terrain editor. It's used with [`SmoothGrid::apply_add`],
+/// [`SmoothGrid::apply_subtract`], and [`SmoothGrid::apply_paint`].
+///
+/// Each voxel the brush covers is affected by the brush's strength, scaled by
+/// its falloff based on how far the voxel's center is from the center of the
+/// brush. Voxels at the edge of the brush are affected the least.
+///
+/// ```
+/// use rbx_types::{Brush, BrushShape, Falloff, SmoothGrid, TerrainMaterials, Vector3};
+///
+/// let brush = Brush::new(BrushShape::Sphere, 12.0)
+///     .strength(0.5)
+///     .falloff(Falloff::Linear);
+///
+/// let mut grid = SmoothGrid::new();
+/// let center = Vector3::new(0.0, 0.0, 0.0);
+/// grid.apply_add(&brush, center, TerrainMaterials::Sand);
+/// grid.apply_paint(&brush, center, TerrainMaterials::Mud);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Brush {
+    shape: BrushShape,
+    radius: f32,
+    strength: f32,
+    falloff: Falloff,
+}
+
+/// The shape of a [`Brush`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushShape {
+    /// A ball, whose radius is the brush's radius.
+    Sphere,
+
+    /// An upright cube, whose sides are twice the brush's radius.
+    Cube,
+}
+
+/// How the effect of a [`Brush`] fades from its center to its edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Falloff {
+    /// The whole brush has the same effect.
+    Constant,
+
+    /// The effect fades evenly, reaching nothing at the edge.
+    Linear,
+
+    /// The effect fades slowly near the center and the edge, and quickly in
+    /// between, which gives soft edges.
+    Smooth,
+}
+
+impl Brush {
+    /// Creates a brush of the given shape and radius, measured in studs, with
+    /// full strength and [`Falloff::Smooth`].
+    pub fn new(shape: BrushShape, radius: f32) -> Self {
+        Self {
+            shape,
+            radius: radius.max(0.0),
+            strength: 1.0,
+            falloff: Falloff::Smooth,
+        }
+    }
+
+    /// Sets how much the brush changes voxels each time it's applied, from
+    /// `0.0` to `1.0`. At `1.0`, the center of the brush is filled or cleared
+    /// completely in one application.
+    pub fn strength(self, strength: f32) -> Self {
+        Self {
+            strength: strength.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Sets how the effect of the brush fades towards its edge.
+    pub fn falloff(self, falloff: Falloff) -> Self {
+        Self { falloff, ..self }
+    }
+
+    /// Returns how much the brush centered at `center` affects the voxel at
+    /// `position`, as an amount of occupancy.
+    fn weight(&self, center: Vector3, position: TerrainVec) -> u8 {
+        if self.radius == 0.0 {
+            return 0;
+        }
+
+        let voxel_center = position.world_center();
+        let (dx, dy, dz) = (
+            voxel_center.x - center.x,
+            voxel_center.y - center.y,
+            voxel_center.z - center.z,
+        );
+
+        let distance = match self.shape {
+            BrushShape::Sphere => (dx * dx + dy * dy + dz * dz).sqrt(),
+            BrushShape::Cube => dx.abs().max(dy.abs()).max(dz.abs()),
+        };
+
+        let t = distance / self.radius;
+        if t > 1.0 {
+            return 0;
+        }
+
+        let falloff = match self.falloff {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Smooth => 1.0 - t * t * (3.0 - 2.0 * t),
+        };
+
+        (self.strength * falloff * u8::MAX as f32).round() as u8
+    }
+
+    /// Calls `apply` with the position of every voxel the brush centered at
+    /// `center` covers and how much it affects it.
+    fn for_each_voxel<F>(&self, center: Vector3, mut apply: F)
+    where
+        F: FnMut(TerrainVec, u8),
+    {
+        // Voxels are only affected if their center is inside of the brush.
+        let reach = self.radius - VOXEL_SIZE / 2.0;
+        let min = TerrainVec::from_world(Vector3::new(
+            center.x - reach,
+            center.y - reach,
+            center.z - reach,
+        ));
+        let max = TerrainVec::from_world(Vector3::new(
+            center.x + reach,
+            center.y + reach,
+            center.z + reach,
+        ));
+
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    let position = TerrainVec::new(x, y, z);
+                    let weight = self.weight(center, position);
+
+                    if weight > 0 {
+                        apply(position, weight);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl SmoothGrid {
+    /// Adds solid `material` to the voxels covered by `brush` centered at
+    /// `center`, measured in studs.
+    ///
+    /// Voxels that were already partly solid keep their material and only
+    /// become fuller. Voxels that become completely solid lose their water.
+    pub fn apply_add(&mut self, brush: &Brush, center: Vector3, material: TerrainMaterials) {
+        brush.for_each_voxel(center, |position, weight| {
+            let existing = self.get_voxel_world(position).copied().unwrap_or_default();
+            let occupancy = solid_occupancy(&existing).saturating_add(weight);
+
+            let voxel = Voxel {
+                material: existing
+                    .material
+                    .filter(|_| existing.occupancy > 0)
+                    .or(Some(material)),
+                occupancy,
+                water_occupancy: if occupancy == u8::MAX {
+                    0
+                } else {
+                    existing.water_occupancy
+                },
+            };
+
+            if voxel != existing {
+                self.write_voxel_world(position, voxel);
+            }
+        });
+    }
+
+    /// Removes solid material from the voxels covered by `brush` centered at
+    /// `center`, measured in studs. Water isn't changed.
+    pub fn apply_subtract(&mut self, brush: &Brush, center: Vector3) {
+        brush.for_each_voxel(center, |position, weight| {
+            let existing = match self.get_voxel_world(position) {
+                Some(existing) => *existing,
+                None => return,
+            };
+
+            let occupancy = solid_occupancy(&existing).saturating_sub(weight);
+            let voxel = with_solid_occupancy(existing, occupancy);
+
+            if voxel != existing {
+                self.write_voxel_world(position, voxel);
+            }
+        });
+    }
+
+    /// Changes the material of the solid voxels covered by `brush` centered
+    /// at `center`, measured in studs, to `material` without changing their
+    /// shape. Empty voxels and ones that only contain water are left alone.
+    ///
+    /// The brush's strength and falloff only decide which voxels are
+    /// covered, since a voxel can't be partly painted.
+    pub fn apply_paint(&mut self, brush: &Brush, center: Vector3, material: TerrainMaterials) {
+        brush.for_each_voxel(center, |position, _| {
+            let existing = match self.get_voxel_world(position) {
+                Some(existing) if solid_occupancy(existing) > 0 => *existing,
+                _ => return,
+            };
+
+            if existing.material != Some(material) {
+                self.write_voxel_world(
+                    position,
+                    Voxel {
+                        material: Some(material),
+                        ..existing
+                    },
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn add_and_subtract() {
+        let mut grid = SmoothGrid::new();
+        let center = Vector3::new(2.0, 2.0, 2.0);
+        let brush = Brush::new(BrushShape::Sphere, 8.0).falloff(Falloff::Linear);
+
+        grid.apply_add(&brush, center, TerrainMaterials::Sand);
+
+        let voxel = |grid: &SmoothGrid, x: i32| {
+            grid.get_voxel_world(TerrainVec::new(x, 0, 0))
+                .copied()
+                .unwrap_or_default()
+        };
+        assert_eq!(voxel(&grid, 0), Voxel::solid(TerrainMaterials::Sand));
+        assert_eq!(voxel(&grid, 1).occupancy, 128);
+        assert!(voxel(&grid, 2).is_empty());
+
+        // Adding again fills in the edge without changing its material.
+        grid.apply_add(&brush, center, TerrainMaterials::Snow);
+        assert_eq!(voxel(&grid, 1), Voxel::solid(TerrainMaterials::Sand));
+
+        grid.apply_subtract(&brush.strength(0.5), center);
+        assert_eq!(voxel(&grid, 0).occupancy, 127);
+        assert_eq!(voxel(&grid, 1).occupancy, 191);
+
+        grid.apply_subtract(&brush.falloff(Falloff::Constant), center);
+        assert!(voxel(&grid, 0).is_empty());
+        assert_eq!(voxel(&grid, 0).material, None);
+        assert!(grid.validate().is_empty());
+    }
+
+    #[test]
+    fn paint() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(3, 0, 0),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        grid.write_voxel_world(TerrainVec::new(0, 1, 0), Voxel::water(255));
+
+        let brush = Brush::new(BrushShape::Cube, 6.0).strength(0.1);
+        grid.apply_paint(&brush, Vector3::new(2.0, 2.0, 2.0), TerrainMaterials::Rock);
+
+        let material = |x: i32, y: i32| {
+            grid.get_voxel_world(TerrainVec::new(x, y, 0))
+                .unwrap()
+                .material
+        };
+        assert_eq!(material(0, 0), Some(TerrainMaterials::Rock));
+        assert_eq!(material(1, 0), Some(TerrainMaterials::Rock));
+        assert_eq!(material(2, 0), Some(TerrainMaterials::Grass));
+        assert_eq!(material(0, 1), None);
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::BTreeSet;
+
+use thiserror::Error;
+
+use super::{
+    decode_chunk_position, decode_header, read_run, BlobReader, ChunkCoordinates, SmoothGrid,
+    TerrainError, Voxel, CHUNK_VOLUME,
+};
+use crate::Error as CrateError;
+
+/// The terrain recovered from a damaged blob by [`SmoothGrid::decode_lenient`],
+/// along with everything that was wrong with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveredGrid {
+    /// The terrain that could be decoded.
+    pub grid: SmoothGrid,
+
+    /// The problems found in the blob, in the order they were found. This is
+    /// empty if the blob could be decoded by [`SmoothGrid::decode`].
+    pub warnings: Vec<DecodeWarning>,
+}
+
+/// A problem found in a blob by [`SmoothGrid::decode_lenient`], and what was
+/// done about it.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum DecodeWarning {
+    /// A run of voxels extended past the end of its chunk. The run was cut
+    /// short.
+    #[error("chunk {chunk:?} has a run of voxels past its end, which was cut short")]
+    RunOverflow {
+        /// The chunk the run is in.
+        chunk: ChunkCoordinates,
+    },
+
+    /// A chunk appeared in the blob more than once. Only the first copy was
+    /// kept.
+    #[error("chunk {chunk:?} appears more than once, so only the first copy was kept")]
+    DuplicateChunk {
+        /// The chunk that was repeated.
+        chunk: ChunkCoordinates,
+    },
+
+    /// A chunk was outside of the range Roblox allows terrain to be placed
+    /// in. The chunk was skipped.
+    #[error("chunk {chunk:?} is outside of the range terrain can be placed in, so it was skipped")]
+    ChunkOutOfRange {
+        /// The chunk that was out of range.
+        chunk: ChunkCoordinates,
+    },
+
+    /// A chunk's header was invalid, so the position of it and every chunk
+    /// after it are unknown. The rest of the blob was skipped.
+    #[error("invalid chunk header {header:#010b}, so the rest of the blob was skipped")]
+    InvalidChunkHeader {
+        /// The invalid header.
+        header: u8,
+    },
+
+    /// The blob ended in the middle of a chunk. The voxels that were missing
+    /// were left empty.
+    #[error(
+        "the blob ended in the middle of chunk {chunk:?}, whose remaining voxels were left empty"
+    )]
+    TruncatedChunk {
+        /// The chunk that was cut short.
+        chunk: ChunkCoordinates,
+    },
+
+    /// The blob ended in the middle of a chunk's position, so that chunk was
+    /// skipped.
+    #[error("the blob ended in the middle of a chunk position")]
+    TruncatedPosition,
+}
+
+impl SmoothGrid {
+    /// Decodes a `SmoothGrid` in the same way as [`SmoothGrid::decode`], but
+    /// recovers what it can from blobs that are damaged instead of failing,
+    /// like corrupted terrain from free models.
+    ///
+    /// Voxels that can't be read are left empty, and chunks that can't be
+    /// read are skipped. Each problem is recorded in
+    /// [`RecoveredGrid::warnings`].
+    ///
+    /// Voxels with both a solid material and water are kept even in version
+    /// `0` blobs, which can't normally have them.
+    ///
+    /// Returns an error only if the blob's header is invalid, since nothing
+    /// after it can be understood.
+    pub fn decode_lenient(buffer: &[u8]) -> Result<RecoveredGrid, CrateError> {
+        let mut reader = BlobReader { reader: buffer };
+        let chunk_size_log2 = decode_header(&mut reader)?.chunk_size_log2;
+        let volume = 1 << (3 * chunk_size_log2);
+
+        let mut grid = SmoothGrid::new();
+        let mut positions = BTreeSet::new();
+        let mut warnings = Vec::new();
+        let mut previous = ChunkCoordinates::default();
+
+        // Reading from a slice can't fail except by running out of data, so
+        // every error is treated as the blob ending.
+        while let Ok(Some(header)) = reader.next_u8() {
+            let position = match decode_chunk_position(&mut reader, header, previous) {
+                Ok(position) => position,
+                Err(TerrainError::InvalidChunkHeader(header)) => {
+                    warnings.push(DecodeWarning::InvalidChunkHeader { header });
+                    break;
+                }
+                Err(_) => {
+                    warnings.push(DecodeWarning::TruncatedPosition);
+                    break;
+                }
+            };
+            previous = position;
+
+            let mut voxels = Vec::with_capacity(volume.min(CHUNK_VOLUME));
+            let mut truncated = false;
+
+            while voxels.len() < volume {
+                let run = match read_run(&mut reader) {
+                    Ok(run) => run,
+                    Err(_) => {
+                        warnings.push(DecodeWarning::TruncatedChunk { chunk: position });
+                        truncated = true;
+                        break;
+                    }
+                };
+
+                let voxel = run.voxel();
+                let remaining = volume - voxels.len();
+                if run.count > remaining {
+                    warnings.push(DecodeWarning::RunOverflow { chunk: position });
+                }
+
+                voxels.resize(voxels.len() + run.count.min(remaining), voxel);
+            }
+
+            voxels.resize(volume, Voxel::default());
+
+            if !position.is_in_range_for(chunk_size_log2) {
+                warnings.push(DecodeWarning::ChunkOutOfRange { chunk: position });
+            } else if positions.insert(position) {
+                grid.insert_decoded_chunk(position, chunk_size_log2, voxels);
+            } else {
+                warnings.push(DecodeWarning::DuplicateChunk { chunk: position });
+            }
+
+            if truncated {
+                break;
+            }
+        }
+
+        Ok(RecoveredGrid { grid, warnings })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        material_colors::MATERIAL_ORDER,
+        terrain::{TerrainVec, RUN_HAS_COUNT},
+        TerrainMaterials,
+    };
+
+    /// Appends `count` runs of 256 empty voxels.
+    fn empty_runs(blob: &mut Vec<u8>, count: usize) {
+        for _ in 0..count {
+            blob.extend_from_slice(&[RUN_HAS_COUNT, 255]);
+        }
+    }
+
+    #[test]
+    fn decode_lenient() {
+        let mut blob = vec![1, 5];
+
+        // Chunk (0, 0, 0) is empty.
+        blob.push(0);
+        empty_runs(&mut blob, 128);
+
+        // It's then repeated, full of the first material.
+        blob.push(0);
+        for _ in 0..128 {
+            blob.extend_from_slice(&[RUN_HAS_COUNT | 2, 255]);
+        }
+
+        // Chunk (1, 0, 0) ends with a run that's one voxel too long.
+        blob.extend_from_slice(&[0b01, 1, 0]);
+        empty_runs(&mut blob, 127);
+        blob.extend_from_slice(&[RUN_HAS_COUNT | 2, 255]);
+
+        // Chunk (2, 0, 0) is cut off after 10 voxels.
+        blob.extend_from_slice(&[0b01, 1, RUN_HAS_COUNT | 2, 9]);
+
+        let recovered = SmoothGrid::decode_lenient(&blob).unwrap();
+        assert!(SmoothGrid::decode(&blob).is_err());
+
+        assert_eq!(
+            recovered.warnings,
+            [
+                DecodeWarning::DuplicateChunk {
+                    chunk: ChunkCoordinates::new(0, 0, 0),
+                },
+                DecodeWarning::RunOverflow {
+                    chunk: ChunkCoordinates::new(1, 0, 0),
+                },
+                DecodeWarning::TruncatedChunk {
+                    chunk: ChunkCoordinates::new(2, 0, 0),
+                },
+            ]
+        );
+
+        let grid = &recovered.grid;
+        let first = Voxel::solid(MATERIAL_ORDER[0]);
+        assert_eq!(grid.chunks().count(), 3);
+        assert!(grid
+            .get_voxel_world(TerrainVec::new(0, 0, 0))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(63, 31, 31)),
+            Some(&first)
+        );
+        assert_eq!(
+            grid.get_voxel_world(TerrainVec::new(73, 0, 0)),
+            Some(&first)
+        );
+        assert!(grid
+            .get_voxel_world(TerrainVec::new(74, 0, 0))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn decode_lenient_headers() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(5, -40, 9),
+            Voxel::solid(TerrainMaterials::Mud),
+        );
+
+        let recovered = SmoothGrid::decode_lenient(&grid.encode()).unwrap();
+        assert_eq!(recovered.grid, grid);
+        assert!(recovered.warnings.is_empty());
+
+        let recovered = SmoothGrid::decode_lenient(&[1, 5, 0b1100_0000]).unwrap();
+        assert!(recovered.grid.chunks().next().is_none());
+        assert_eq!(
+            recovered.warnings,
+            [DecodeWarning::InvalidChunkHeader {
+                header: 0b1100_0000
+            }]
+        );
+
+        assert!(SmoothGrid::decode_lenient(&[2, 5]).is_err());
+    }
+
+    #[test]
+    fn decode_lenient_out_of_range() {
+        // A chunk too far away to be placed, then one back at the origin.
+        let mut blob = vec![1, 5, 0b11];
+        blob.extend_from_slice(&i32::MAX.to_le_bytes());
+        empty_runs(&mut blob, 128);
+        blob.push(0b11);
+        blob.extend_from_slice(&(-i32::MAX).to_le_bytes());
+        empty_runs(&mut blob, 128);
+
+        let recovered = SmoothGrid::decode_lenient(&blob).unwrap();
+        assert_eq!(
+            recovered.warnings,
+            [DecodeWarning::ChunkOutOfRange {
+                chunk: ChunkCoordinates::new(i32::MAX, 0, 0),
+            }]
+        );
+
+        let positions: Vec<_> = recovered
+            .grid
+            .chunks()
+            .map(|(position, _)| *position)
+            .collect();
+        assert_eq!(positions, [ChunkCoordinates::new(0, 0, 0)]);
+    }
+}
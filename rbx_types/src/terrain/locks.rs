@@ -0,0 +1,333 @@
+use std::{
+    collections::{btree_map, BTreeMap},
+    convert::TryFrom,
+};
+
+use thiserror::Error;
+
+use super::{ChunkCoordinates, TerrainDiff, TerrainError, TerrainVec};
+use crate::Error as CrateError;
+
+/// The version of the blob written by [`ChunkLocks::encode`].
+const CHUNK_LOCKS_VERSION: u8 = 1;
+
+/// Who owns each chunk of a [`SmoothGrid`][super::SmoothGrid] and whether
+/// they've locked it, so that several people can edit the same terrain
+/// without overwriting each other's work.
+///
+/// `ChunkLocks` is kept separately from the grid. It can be stored alongside
+/// it with [`ChunkLocks::encode`], like in a `BinaryString` attribute on the
+/// `Terrain` instance. Edits are checked against it with
+/// [`ChunkLocks::check_region`] or [`ChunkLocks::check_diff`] before they're
+/// made.
+///
+/// ```
+/// use rbx_types::{ChunkCoordinates, ChunkLocks, SmoothGrid, TerrainMaterials, TerrainVec, Voxel};
+///
+/// let mut locks = ChunkLocks::new();
+/// locks.lock(ChunkCoordinates::new(0, 0, 0), "alice");
+///
+/// let (min, max) = (TerrainVec::new(0, 0, 0), TerrainVec::new(3, 3, 3));
+/// assert!(locks.check_region("bob", min, max).is_err());
+///
+/// let mut grid = SmoothGrid::new();
+/// if locks.check_region("alice", min, max).is_ok() {
+///     grid.fill_block(min, max, Voxel::solid(TerrainMaterials::Rock));
+/// }
+///
+/// let decoded = ChunkLocks::decode(&locks.encode()).unwrap();
+/// assert_eq!(decoded, locks);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChunkLocks {
+    locks: BTreeMap<ChunkCoordinates, ChunkLock>,
+}
+
+/// The owner of a single chunk in [`ChunkLocks`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkLock {
+    /// The name of whoever owns the chunk.
+    pub owner: String,
+
+    /// Whether the owner is the only one allowed to edit the chunk. Chunks
+    /// that are owned but not locked can be edited by anyone.
+    pub locked: bool,
+}
+
+/// An edit touched a chunk locked by someone else, found by
+/// [`ChunkLocks::check_region`] or [`ChunkLocks::check_diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("chunk {position:?} is locked by {owner}")]
+pub struct LockConflict {
+    /// The locked chunk.
+    pub position: ChunkCoordinates,
+
+    /// The owner of the lock.
+    pub owner: String,
+}
+
+impl ChunkLocks {
+    /// Creates an empty `ChunkLocks`, where every chunk can be edited by
+    /// anyone.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the owner of the chunk at `position`, if it has one.
+    pub fn get(&self, position: ChunkCoordinates) -> Option<&ChunkLock> {
+        self.locks.get(&position)
+    }
+
+    /// Makes `owner` the owner of the chunk at `position` without locking it.
+    /// Will return the previous owner if there was one.
+    pub fn claim<S: Into<String>>(
+        &mut self,
+        position: ChunkCoordinates,
+        owner: S,
+    ) -> Option<ChunkLock> {
+        self.locks.insert(
+            position,
+            ChunkLock {
+                owner: owner.into(),
+                locked: false,
+            },
+        )
+    }
+
+    /// Makes `owner` the owner of the chunk at `position` and locks it, so
+    /// only they can edit it. Will return the previous owner if there was
+    /// one.
+    pub fn lock<S: Into<String>>(
+        &mut self,
+        position: ChunkCoordinates,
+        owner: S,
+    ) -> Option<ChunkLock> {
+        self.locks.insert(
+            position,
+            ChunkLock {
+                owner: owner.into(),
+                locked: true,
+            },
+        )
+    }
+
+    /// Removes the owner of the chunk at `position`, letting anyone edit it.
+    /// Will return the owner that was there if one existed.
+    pub fn release(&mut self, position: ChunkCoordinates) -> Option<ChunkLock> {
+        self.locks.remove(&position)
+    }
+
+    /// Removes every chunk owned by `owner`, like when they finish a session.
+    pub fn release_all(&mut self, owner: &str) {
+        self.locks.retain(|_, lock| lock.owner != owner);
+    }
+
+    /// Returns an iterator over every owned chunk, in the same order as
+    /// [`SmoothGrid::chunks`][super::SmoothGrid::chunks].
+    pub fn iter(&self) -> btree_map::Iter<'_, ChunkCoordinates, ChunkLock> {
+        self.locks.iter()
+    }
+
+    /// Returns the number of owned chunks.
+    pub fn len(&self) -> usize {
+        self.locks.len()
+    }
+
+    /// Returns true if no chunks are owned.
+    pub fn is_empty(&self) -> bool {
+        self.locks.is_empty()
+    }
+
+    /// Returns whether `editor` is allowed to edit the chunk at `position`.
+    pub fn can_edit(&self, position: ChunkCoordinates, editor: &str) -> bool {
+        self.check_chunk(position, editor).is_ok()
+    }
+
+    /// Checks that `editor` is allowed to edit every voxel between `min` and
+    /// `max` inclusive, like before calling
+    /// [`SmoothGrid::fill_block`][super::SmoothGrid::fill_block].
+    ///
+    /// Returns the first chunk in the region that is locked by someone else,
+    /// if there is one.
+    pub fn check_region(
+        &self,
+        editor: &str,
+        min: TerrainVec,
+        max: TerrainVec,
+    ) -> Result<(), LockConflict> {
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            return Ok(());
+        }
+
+        let min_chunk = min.chunk_coordinates();
+        let max_chunk = max.chunk_coordinates();
+
+        for &position in self
+            .locks
+            .range(min_chunk..=max_chunk)
+            .map(|(position, _)| position)
+        {
+            // The range is ordered by X first, so chunks outside of the
+            // region along Y or Z can still show up in it.
+            if (min_chunk.y..=max_chunk.y).contains(&position.y)
+                && (min_chunk.z..=max_chunk.z).contains(&position.z)
+            {
+                self.check_chunk(position, editor)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `editor` is allowed to make every change in `diff`, like
+    /// before calling [`TerrainDiff::apply`].
+    ///
+    /// Returns the first changed chunk that is locked by someone else, if
+    /// there is one.
+    pub fn check_diff(&self, editor: &str, diff: &TerrainDiff) -> Result<(), LockConflict> {
+        for (&position, _) in diff.chunks() {
+            self.check_chunk(position, editor)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_chunk(&self, position: ChunkCoordinates, editor: &str) -> Result<(), LockConflict> {
+        match self.locks.get(&position) {
+            Some(lock) if lock.locked && lock.owner != editor => Err(LockConflict {
+                position,
+                owner: lock.owner.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Encodes the `ChunkLocks` into a binary blob.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = vec![CHUNK_LOCKS_VERSION];
+        buffer.extend_from_slice(&(self.locks.len() as u32).to_le_bytes());
+
+        for (position, lock) in &self.locks {
+            for component in [position.x, position.y, position.z] {
+                buffer.extend_from_slice(&component.to_le_bytes());
+            }
+
+            buffer.push(lock.locked as u8);
+            buffer.extend_from_slice(&(lock.owner.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(lock.owner.as_bytes());
+        }
+
+        buffer
+    }
+
+    /// Decodes a `ChunkLocks` from a binary blob. The blob must be the same
+    /// format used by `encode`.
+    pub fn decode(buffer: &[u8]) -> Result<Self, CrateError> {
+        let mut reader = LockReader { buffer };
+
+        let version = reader.read(1)?[0];
+        if version != CHUNK_LOCKS_VERSION {
+            return Err(TerrainError::UnknownChunkLocksVersion(version).into());
+        }
+
+        let count = reader.read_u32()?;
+        let mut locks = BTreeMap::new();
+
+        for _ in 0..count {
+            let x = reader.read_i32()?;
+            let y = reader.read_i32()?;
+            let z = reader.read_i32()?;
+            let position = ChunkCoordinates::new(x, y, z);
+
+            let locked = reader.read(1)?[0] != 0;
+            let length = reader.read_u32()? as usize;
+            let owner = String::from_utf8(reader.read(length)?.to_vec())
+                .map_err(|_| TerrainError::InvalidChunkLocks("owner is not valid UTF-8"))?;
+
+            if locks
+                .insert(position, ChunkLock { owner, locked })
+                .is_some()
+            {
+                return Err(TerrainError::InvalidChunkLocks("chunk listed more than once").into());
+            }
+        }
+
+        if !reader.buffer.is_empty() {
+            return Err(
+                TerrainError::InvalidChunkLocks("unexpected data after the last chunk").into(),
+            );
+        }
+
+        Ok(Self { locks })
+    }
+}
+
+struct LockReader<'a> {
+    buffer: &'a [u8],
+}
+
+impl<'a> LockReader<'a> {
+    fn read(&mut self, length: usize) -> Result<&'a [u8], TerrainError> {
+        if self.buffer.len() < length {
+            return Err(TerrainError::InvalidChunkLocks("blob ended unexpectedly"));
+        }
+
+        let (bytes, rest) = self.buffer.split_at(length);
+        self.buffer = rest;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, TerrainError> {
+        Ok(u32::from_le_bytes(
+            <[u8; 4]>::try_from(self.read(4)?).unwrap(),
+        ))
+    }
+
+    fn read_i32(&mut self) -> Result<i32, TerrainError> {
+        Ok(i32::from_le_bytes(
+            <[u8; 4]>::try_from(self.read(4)?).unwrap(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{SmoothGrid, TerrainMaterials, Voxel};
+
+    #[test]
+    fn check_locks() {
+        let mut locks = ChunkLocks::new();
+        locks.lock(ChunkCoordinates::new(1, 0, 0), "alice");
+        locks.claim(ChunkCoordinates::new(0, 0, 0), "alice");
+        locks.lock(ChunkCoordinates::new(0, 5, 0), "carol");
+
+        let region = (TerrainVec::new(0, 0, 0), TerrainVec::new(40, 0, 0));
+        assert_eq!(
+            locks.check_region("bob", region.0, region.1),
+            Err(LockConflict {
+                position: ChunkCoordinates::new(1, 0, 0),
+                owner: "alice".to_owned(),
+            })
+        );
+        assert!(locks.check_region("alice", region.0, region.1).is_ok());
+        assert!(locks.can_edit(ChunkCoordinates::new(0, 0, 0), "bob"));
+
+        let mut edited = SmoothGrid::new();
+        edited.write_voxel_world(
+            TerrainVec::new(0, 170, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        let diff = SmoothGrid::new().diff(&edited);
+        assert!(locks.check_diff("alice", &diff).is_err());
+        assert!(locks.check_diff("carol", &diff).is_ok());
+
+        let decoded = ChunkLocks::decode(&locks.encode()).unwrap();
+        assert_eq!(decoded, locks);
+        assert!(ChunkLocks::decode(&locks.encode()[..10]).is_err());
+
+        locks.release_all("alice");
+        assert_eq!(locks.len(), 1);
+    }
+}
@@ -0,0 +1,317 @@
+//! Turns smooth terrain into triangle meshes that can be used outside of
+//! Roblox, using [surface nets].
+//!
+//! Only solid materials are meshed. Water is left out.
+//!
+//! ```
+//! use rbx_types::{mesh::TerrainMesh, SmoothGrid, TerrainMaterials, TerrainVec, Voxel};
+//!
+//! let mut grid = SmoothGrid::new();
+//! grid.write_voxel_world(TerrainVec::new(0, 0, 0), Voxel::solid(TerrainMaterials::Rock));
+//!
+//! let mesh = TerrainMesh::from_grid(&grid);
+//! assert_eq!(mesh.positions.len(), 8);
+//! assert_eq!(mesh.indices.len(), 36);
+//!
+//! let mut obj = Vec::new();
+//! mesh.write_obj(&mut obj).unwrap();
+//! ```
+//!
+//! [surface nets]: https://0fps.net/2012/07/12/smooth-voxel-terrain-part-2/
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, Write},
+};
+
+use super::{SmoothGrid, TerrainVec, VOXEL_SIZE};
+use crate::{TerrainMaterials, Vector3};
+
+/// How full a voxel must be to count as inside of the surface.
+const ISO_LEVEL: f32 = 0.5;
+
+/// A triangle mesh of the surface of a [`SmoothGrid`], created by
+/// [`TerrainMesh::from_grid`].
+///
+/// Positions are measured in studs, in the same space as the terrain in
+/// Roblox. Triangles are wound counterclockwise when viewed from outside of
+/// the terrain.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TerrainMesh {
+    /// The position of each vertex.
+    pub positions: Vec<Vector3>,
+
+    /// The normal of each vertex, pointing out of the terrain.
+    pub normals: Vec<Vector3>,
+
+    /// The material of each vertex, which is the material of the fullest
+    /// voxel around it.
+    pub materials: Vec<TerrainMaterials>,
+
+    /// Three indices into the vertex arrays for each triangle.
+    pub indices: Vec<u32>,
+}
+
+impl TerrainMesh {
+    /// Creates a mesh of the surface of every solid voxel in `grid`.
+    ///
+    /// Voxels are treated as samples at their centers, and the surface passes
+    /// between them where their occupancy crosses half full. Each cube
+    /// between eight neighbouring voxel centers that the surface passes
+    /// through gets one vertex, so vertices are shared between triangles.
+    pub fn from_grid(grid: &SmoothGrid) -> Self {
+        let sampler = Sampler { grid };
+        let mut mesh = TerrainMesh::default();
+
+        // Every cell is identified by the voxel at its lowest corner.
+        let mut vertices: HashMap<TerrainVec, u32> = HashMap::new();
+
+        // Visiting solid voxels in order keeps the output stable.
+        let solid: BTreeSet<TerrainVec> = grid
+            .chunks()
+            .flat_map(|(chunk_position, chunk)| {
+                chunk.iter_voxels().map(move |(voxel_position, _)| {
+                    TerrainVec::from_chunk(*chunk_position, voxel_position)
+                })
+            })
+            .filter(|&position| sampler.is_inside(position))
+            .collect();
+
+        for &position in &solid {
+            for axis in 0..3 {
+                for &direction in &[1, -1] {
+                    let neighbor = offset(position, axis, direction);
+                    if sampler.is_inside(neighbor) {
+                        continue;
+                    }
+
+                    // The face between the two voxels is crossed by the edge
+                    // from `position` to `neighbor`, which is shared by four
+                    // cells. `u` and `w` follow `axis` in XYZ order, so
+                    // walking the cells in this order winds the face
+                    // counterclockwise around the positive direction.
+                    let (u, w) = ((axis + 1) % 3, (axis + 2) % 3);
+                    let base = if direction > 0 { position } else { neighbor };
+                    let corner = offset(offset(base, u, -1), w, -1);
+
+                    let mut quad = [
+                        corner,
+                        offset(corner, u, 1),
+                        offset(offset(corner, u, 1), w, 1),
+                        offset(corner, w, 1),
+                    ]
+                    .map(|cell| {
+                        *vertices
+                            .entry(cell)
+                            .or_insert_with(|| mesh.push_vertex(&sampler, cell))
+                    });
+
+                    if direction < 0 {
+                        quad.reverse();
+                    }
+
+                    mesh.indices
+                        .extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+                }
+            }
+        }
+
+        mesh
+    }
+
+    /// Writes the mesh as a Wavefront OBJ file. Faces are grouped by the
+    /// material of their first vertex, and each group uses a material named
+    /// after it, like `usemtl Grass`.
+    pub fn write_obj<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for position in &self.positions {
+            writeln!(writer, "v {} {} {}", position.x, position.y, position.z)?;
+        }
+
+        for normal in &self.normals {
+            writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+        }
+
+        let mut triangles: Vec<&[u32]> = self.indices.chunks_exact(3).collect();
+        triangles.sort_by_key(|triangle| self.materials[triangle[0] as usize]);
+
+        let mut current = None;
+        for triangle in triangles {
+            let material = self.materials[triangle[0] as usize];
+            if current != Some(material) {
+                writeln!(writer, "usemtl {:?}", material)?;
+                current = Some(material);
+            }
+
+            // OBJ indices start at 1.
+            let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+            writeln!(writer, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the vertex of the cell with its lowest corner at `cell`, and
+    /// returns its index.
+    fn push_vertex(&mut self, sampler: &Sampler<'_>, cell: TerrainVec) -> u32 {
+        let mut corners = [0.0; 8];
+        for (index, density) in corners.iter_mut().enumerate() {
+            *density = sampler.density(cell_corner(cell, index));
+        }
+
+        // Place the vertex at the average of the points where the surface
+        // crosses the edges of the cell.
+        let mut sum = [0.0; 3];
+        let mut crossings = 0.0;
+        for a in 0..8 {
+            for axis in 0..3 {
+                let b = a | 1 << axis;
+                if b == a || (corners[a] > ISO_LEVEL) == (corners[b] > ISO_LEVEL) {
+                    continue;
+                }
+
+                let t = (ISO_LEVEL - corners[a]) / (corners[b] - corners[a]);
+                for (component, sum) in sum.iter_mut().enumerate() {
+                    let start = (a >> component & 1) as f32;
+                    let end = (b >> component & 1) as f32;
+                    *sum += start + (end - start) * t;
+                }
+                crossings += 1.0;
+            }
+        }
+
+        let local = sum.map(|sum| sum / crossings);
+        let position = Vector3::new(
+            (cell.x as f32 + 0.5 + local[0]) * VOXEL_SIZE,
+            (cell.y as f32 + 0.5 + local[1]) * VOXEL_SIZE,
+            (cell.z as f32 + 0.5 + local[2]) * VOXEL_SIZE,
+        );
+
+        // The normal points away from the fuller corners.
+        let mut gradient = [0.0f32; 3];
+        for (index, density) in corners.iter().enumerate() {
+            for (axis, gradient) in gradient.iter_mut().enumerate() {
+                if index >> axis & 1 == 1 {
+                    *gradient += density;
+                } else {
+                    *gradient -= density;
+                }
+            }
+        }
+        let length = gradient
+            .iter()
+            .map(|value| value * value)
+            .sum::<f32>()
+            .sqrt();
+        let normal = if length > 0.0 {
+            Vector3::new(
+                -gradient[0] / length,
+                -gradient[1] / length,
+                -gradient[2] / length,
+            )
+        } else {
+            Vector3::new(0.0, 1.0, 0.0)
+        };
+
+        let material = (0..8)
+            .filter_map(|index| sampler.solid(cell_corner(cell, index)))
+            .max_by_key(|&(_, occupancy)| occupancy)
+            .map(|(material, _)| material)
+            .expect("cells on the surface always contain a solid voxel");
+
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.materials.push(material);
+
+        (self.positions.len() - 1) as u32
+    }
+}
+
+/// Reads how full voxels are from a grid.
+struct Sampler<'a> {
+    grid: &'a SmoothGrid,
+}
+
+impl Sampler<'_> {
+    fn solid(&self, position: TerrainVec) -> Option<(TerrainMaterials, u8)> {
+        let voxel = self.grid.get_voxel_world(position)?;
+        voxel
+            .material
+            .filter(|_| voxel.occupancy > 0)
+            .map(|material| (material, voxel.occupancy))
+    }
+
+    fn density(&self, position: TerrainVec) -> f32 {
+        self.solid(position)
+            .map_or(0.0, |(_, occupancy)| f32::from(occupancy) / 255.0)
+    }
+
+    fn is_inside(&self, position: TerrainVec) -> bool {
+        self.density(position) > ISO_LEVEL
+    }
+}
+
+fn offset(position: TerrainVec, axis: usize, amount: i32) -> TerrainVec {
+    let mut components = [position.x, position.y, position.z];
+    components[axis] += amount;
+    TerrainVec::new(components[0], components[1], components[2])
+}
+
+/// Returns one of the eight corners of a cell, where each bit of `index`
+/// selects the upper side along X, Y, and Z.
+fn cell_corner(cell: TerrainVec, index: usize) -> TerrainVec {
+    TerrainVec::new(
+        cell.x + (index & 1) as i32,
+        cell.y + (index >> 1 & 1) as i32,
+        cell.z + (index >> 2 & 1) as i32,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::Voxel;
+
+    #[test]
+    fn triangles_face_outwards() {
+        let mut grid = SmoothGrid::new();
+        for x in 0..3 {
+            grid.write_voxel_world(
+                TerrainVec::new(x, 0, 0),
+                Voxel::solid(TerrainMaterials::Rock),
+            );
+        }
+        grid.write_voxel_world(
+            TerrainVec::new(1, 1, 0),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+        grid.write_voxel_world(TerrainVec::new(5, 0, 0), Voxel::water(255));
+
+        let mesh = TerrainMesh::from_grid(&grid);
+        assert!(!mesh.indices.is_empty());
+
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|index| mesh.positions[triangle[index] as usize]);
+            let (u, v) = (
+                [b.x - a.x, b.y - a.y, b.z - a.z],
+                [c.x - a.x, c.y - a.y, c.z - a.z],
+            );
+            let face_normal = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+
+            // The center of the solid voxels, which every face should point
+            // away from.
+            let center = Vector3::new(6.0, 4.0, 2.0);
+            let outwards = [a.x - center.x, a.y - center.y, a.z - center.z];
+            let dot: f32 = (0..3).map(|axis| face_normal[axis] * outwards[axis]).sum();
+            assert!(dot > 0.0, "triangle {:?} faces inwards", triangle);
+        }
+
+        // Water isn't meshed, so nothing reaches past the solid voxels.
+        assert!(mesh.positions.iter().all(|position| position.x < 12.0));
+        assert!(mesh.materials.contains(&TerrainMaterials::Grass));
+    }
+}
@@ -0,0 +1,237 @@
+use std::convert::TryFrom;
+
+use super::{chunk_overlap, SmoothGrid, TerrainVec, Voxel};
+use crate::{Error as CrateError, Vector3int16};
+
+/// A copy of part of a [`SmoothGrid`], created by
+/// [`SmoothGrid::copy_region`]. This is the data held by a `TerrainRegion`
+/// instance.
+///
+/// Voxels keep the positions they had in the grid they were copied from, and
+/// the extents record which box of voxels was copied. A `TerrainRegion` is
+/// made of the values of [`extents_min`](Self::extents_min),
+/// [`extents_max`](Self::extents_max), and [`encode`](Self::encode), which
+/// go in its `ExtentsMin`, `ExtentsMax`, and `SmoothGrid` properties.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainRegionData {
+    extents_min: Vector3int16,
+    extents_max: Vector3int16,
+    grid: SmoothGrid,
+}
+
+impl TerrainRegionData {
+    /// Creates a `TerrainRegionData` from the `ExtentsMin`, `ExtentsMax`, and
+    /// `SmoothGrid` properties of a `TerrainRegion`.
+    pub fn from_properties(
+        extents_min: Vector3int16,
+        extents_max: Vector3int16,
+        smooth_grid: &[u8],
+    ) -> Result<Self, CrateError> {
+        Ok(Self {
+            extents_min,
+            extents_max,
+            grid: SmoothGrid::decode(smooth_grid)?,
+        })
+    }
+
+    /// Returns the lowest voxel of the copied box, inclusive.
+    pub fn extents_min(&self) -> Vector3int16 {
+        self.extents_min
+    }
+
+    /// Returns the highest voxel of the copied box, inclusive.
+    pub fn extents_max(&self) -> Vector3int16 {
+        self.extents_max
+    }
+
+    /// Returns the number of voxels along each side of the copied box, like
+    /// the `SizeInCells` property of `TerrainRegion`.
+    pub fn size(&self) -> [usize; 3] {
+        let (min, max) = (self.min(), self.max());
+
+        [max.x - min.x, max.y - min.y, max.z - min.z].map(|extent| (extent + 1).max(0) as usize)
+    }
+
+    /// Returns the copied voxels, at the positions they were copied from.
+    pub fn grid(&self) -> &SmoothGrid {
+        &self.grid
+    }
+
+    /// Encodes the copied voxels for the `SmoothGrid` property of
+    /// `TerrainRegion`.
+    pub fn encode(&self) -> Vec<u8> {
+        self.grid.encode()
+    }
+
+    fn min(&self) -> TerrainVec {
+        let Vector3int16 { x, y, z } = self.extents_min;
+        TerrainVec::new(x.into(), y.into(), z.into())
+    }
+
+    fn max(&self) -> TerrainVec {
+        let Vector3int16 { x, y, z } = self.extents_max;
+        TerrainVec::new(x.into(), y.into(), z.into())
+    }
+}
+
+impl SmoothGrid {
+    /// Copies every voxel between `min` and `max` inclusive, like
+    /// `Terrain:CopyRegion`.
+    ///
+    /// ## Panics
+    /// Panics if `min` or `max` can't be stored in a `Vector3int16`, since
+    /// `TerrainRegion` can't hold them.
+    pub fn copy_region(&self, min: TerrainVec, max: TerrainVec) -> TerrainRegionData {
+        let mut grid = SmoothGrid::new();
+
+        let min_chunk = min.chunk_coordinates();
+        let max_chunk = max.chunk_coordinates();
+
+        if min.x <= max.x && min.y <= max.y && min.z <= max.z {
+            for (&chunk_position, chunk) in self.chunks.range(min_chunk..=max_chunk) {
+                // The range is ordered by X first, so chunks outside of the
+                // region along Y or Z can still show up in it.
+                if chunk_position.y < min_chunk.y
+                    || chunk_position.y > max_chunk.y
+                    || chunk_position.z < min_chunk.z
+                    || chunk_position.z > max_chunk.z
+                {
+                    continue;
+                }
+
+                let (lower, upper) = chunk_overlap(chunk_position, min, max);
+
+                for y in lower.y..=upper.y {
+                    for z in lower.z..=upper.z {
+                        for x in lower.x..=upper.x {
+                            let position = TerrainVec::new(x, y, z);
+                            let voxel = chunk.get_voxel(position.voxel_coordinates());
+
+                            if !voxel.is_empty() {
+                                grid.write_voxel_world(position, *voxel);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        TerrainRegionData {
+            extents_min: to_vector3int16(min),
+            extents_max: to_vector3int16(max),
+            grid,
+        }
+    }
+
+    /// Writes the voxels from `data` so that the lowest corner of its box
+    /// lands on `corner`, like `Terrain:PasteRegion`.
+    ///
+    /// If `paste_empty` is `true`, empty voxels in the box replace the voxels
+    /// already there. Otherwise, only voxels with material or water are
+    /// written.
+    pub fn paste_region(
+        &mut self,
+        corner: TerrainVec,
+        data: &TerrainRegionData,
+        paste_empty: bool,
+    ) {
+        let (min, max) = (data.min(), data.max());
+        let offset = TerrainVec::new(corner.x - min.x, corner.y - min.y, corner.z - min.z);
+        let moved = |position: TerrainVec| {
+            TerrainVec::new(
+                position.x + offset.x,
+                position.y + offset.y,
+                position.z + offset.z,
+            )
+        };
+
+        if paste_empty {
+            self.fill_block(corner, moved(max), Voxel::default());
+        }
+
+        for (position, voxel) in data.grid.iter_region(min, max) {
+            if !voxel.is_empty() {
+                self.write_voxel_world(moved(position), *voxel);
+            }
+        }
+    }
+}
+
+fn to_vector3int16(position: TerrainVec) -> Vector3int16 {
+    let component = |value: i32| {
+        i16::try_from(value).unwrap_or_else(|_| {
+            panic!(
+                "terrain region corner {:?} does not fit in a Vector3int16",
+                position
+            )
+        })
+    };
+
+    Vector3int16::new(
+        component(position.x),
+        component(position.y),
+        component(position.z),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::TerrainMaterials;
+
+    #[test]
+    fn copy_and_paste_region() {
+        let mut grid = SmoothGrid::new();
+        grid.fill_block(
+            TerrainVec::new(0, 0, 0),
+            TerrainVec::new(3, 1, 3),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_voxel_world(TerrainVec::new(2, 2, 2), Voxel::water(128));
+
+        let region = grid.copy_region(TerrainVec::new(1, 0, 1), TerrainVec::new(2, 2, 2));
+        assert_eq!(region.extents_min(), Vector3int16::new(1, 0, 1));
+        assert_eq!(region.size(), [2, 3, 2]);
+
+        let region = TerrainRegionData::from_properties(
+            region.extents_min(),
+            region.extents_max(),
+            &region.encode(),
+        )
+        .unwrap();
+
+        let mut target = SmoothGrid::new();
+        target.fill_block(
+            TerrainVec::new(40, 0, 0),
+            TerrainVec::new(42, 3, 2),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+
+        let mut kept = target.clone();
+        kept.paste_region(TerrainVec::new(40, 0, 0), &region, false);
+        target.paste_region(TerrainVec::new(40, 0, 0), &region, true);
+
+        for grid in [&kept, &target] {
+            let voxel = |x, y, z| *grid.get_voxel_world(TerrainVec::new(x, y, z)).unwrap();
+
+            assert_eq!(voxel(40, 1, 0), Voxel::solid(TerrainMaterials::Rock));
+            assert_eq!(voxel(41, 2, 1), Voxel::water(128));
+            assert_eq!(voxel(42, 0, 0), Voxel::solid(TerrainMaterials::Grass));
+        }
+
+        // Only the empty voxels inside of the box are pasted.
+        assert_eq!(
+            *kept.get_voxel_world(TerrainVec::new(40, 2, 0)).unwrap(),
+            Voxel::solid(TerrainMaterials::Grass)
+        );
+        assert!(target
+            .get_voxel_world(TerrainVec::new(40, 2, 0))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            *target.get_voxel_world(TerrainVec::new(40, 3, 0)).unwrap(),
+            Voxel::solid(TerrainMaterials::Grass)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, convert::TryFrom};
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Chunk, ChunkCoordinates, SmoothGrid, Voxel, CHUNK_VOLUME};
+use crate::TerrainMaterials;
+
+/// The version of the human-readable form of `SmoothGrid`. This only needs
+/// to change if a file written by an older version would be read differently.
+const TEXT_GRID_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextGrid {
+    version: u32,
+    chunks: Vec<TextChunk>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextChunk {
+    position: [i32; 3],
+    voxels: Vec<TextRun>,
+}
+
+/// A run of identical voxels, in the order they're stored in a chunk.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextRun {
+    count: usize,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    material: Option<TerrainMaterials>,
+
+    #[serde(default, skip_serializing_if = "is_zero")]
+    occupancy: u8,
+
+    #[serde(default, skip_serializing_if = "is_zero")]
+    water: u8,
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
+
+impl TextRun {
+    fn voxel(&self) -> Voxel {
+        Voxel {
+            material: self.material,
+            occupancy: self.occupancy,
+            water_occupancy: self.water,
+        }
+    }
+}
+
+impl From<&SmoothGrid> for TextGrid {
+    fn from(grid: &SmoothGrid) -> Self {
+        let chunks = grid
+            .chunks
+            .iter()
+            .map(|(position, chunk)| {
+                let mut voxels: Vec<TextRun> = Vec::new();
+
+                for voxel in chunk.voxels.iter() {
+                    match voxels.last_mut() {
+                        Some(run) if run.voxel() == *voxel => run.count += 1,
+                        _ => voxels.push(TextRun {
+                            count: 1,
+                            material: voxel.material,
+                            occupancy: voxel.occupancy,
+                            water: voxel.water_occupancy,
+                        }),
+                    }
+                }
+
+                TextChunk {
+                    position: [position.x, position.y, position.z],
+                    voxels,
+                }
+            })
+            .collect();
+
+        Self {
+            version: TEXT_GRID_VERSION,
+            chunks,
+        }
+    }
+}
+
+impl TryFrom<TextGrid> for SmoothGrid {
+    type Error = String;
+
+    fn try_from(text: TextGrid) -> Result<Self, Self::Error> {
+        if text.version != TEXT_GRID_VERSION {
+            return Err(format!("unknown SmoothGrid version {}", text.version));
+        }
+
+        let mut chunks = BTreeMap::new();
+
+        for TextChunk { position, voxels } in text.chunks {
+            let [x, y, z] = position;
+            let position = ChunkCoordinates::new(x, y, z);
+
+            let mut chunk_voxels = Vec::with_capacity(CHUNK_VOLUME);
+            for run in &voxels {
+                if run.count > CHUNK_VOLUME - chunk_voxels.len() {
+                    return Err(format!("chunk {:?} has too many voxels", position));
+                }
+
+                chunk_voxels.resize(chunk_voxels.len() + run.count, run.voxel());
+            }
+
+            if chunk_voxels.len() != CHUNK_VOLUME {
+                return Err(format!(
+                    "chunk {:?} has {} voxels, but chunks must have {}",
+                    position,
+                    chunk_voxels.len(),
+                    CHUNK_VOLUME
+                ));
+            }
+
+            let chunk = Chunk {
+                voxels: chunk_voxels.into_boxed_slice(),
+            };
+
+            if chunks.insert(position, chunk).is_some() {
+                return Err(format!("chunk {:?} is listed more than once", position));
+            }
+        }
+
+        Ok(Self {
+            chunks,
+            dirty: None,
+        })
+    }
+}
+
+impl Serialize for SmoothGrid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            TextGrid::from(self).serialize(serializer)
+        } else {
+            self.encode().serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SmoothGrid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let text = TextGrid::deserialize(deserializer)?;
+            SmoothGrid::try_from(text).map_err(D::Error::custom)
+        } else {
+            let buffer = <Vec<u8>>::deserialize(deserializer)?;
+            SmoothGrid::decode(&buffer).map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::terrain::TerrainVec;
+
+    #[test]
+    fn human() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(-31, 0, 0),
+            Voxel::solid(TerrainMaterials::Rock),
+        );
+        grid.write_voxel_world(TerrainVec::new(-30, 0, 0), Voxel::water(128));
+
+        let ser = serde_json::to_string(&grid).unwrap();
+        assert_eq!(
+            ser,
+            r#"{"version":1,"chunks":[{"position":[-1,0,0],"voxels":[{"count":1},{"count":1,"material":"Rock","occupancy":255},{"count":1,"water":128},{"count":32765}]}]}"#
+        );
+
+        let de: SmoothGrid = serde_json::from_str(&ser).unwrap();
+        assert_eq!(de, grid);
+
+        let short = r#"{"version":1,"chunks":[{"position":[0,0,0],"voxels":[{"count":5}]}]}"#;
+        assert!(serde_json::from_str::<SmoothGrid>(short).is_err());
+
+        let future = r#"{"version":2,"chunks":[]}"#;
+        assert!(serde_json::from_str::<SmoothGrid>(future).is_err());
+    }
+
+    #[test]
+    fn non_human() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            TerrainVec::new(3, 40, 7),
+            Voxel::solid(TerrainMaterials::Grass),
+        );
+
+        let ser = bincode::serialize(&grid).unwrap();
+        let de: SmoothGrid = bincode::deserialize(&ser).unwrap();
+
+        assert_eq!(de, grid);
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     Attributes, Axes, BinaryString, BrickColor, CFrame, Color3, Color3uint8, ColorSequence,
     Content, ContentId, Enum, EnumItem, Faces, Font, MaterialColors, NumberRange, NumberSequence,
     PhysicalProperties, Ray, Rect, Ref, Region3, Region3int16, SecurityCapabilities, SharedString,
-    Tags, UDim, UDim2, UniqueId, Vector2, Vector2int16, Vector3, Vector3int16,
+    SmoothGrid, Tags, UDim, UDim2, UniqueId, Vector2, Vector2int16, Vector3, Vector3int16,
 };
 
 /// Reduces boilerplate from listing different values of Variant by wrapping
@@ -131,6 +131,7 @@ make_variant! {
     SecurityCapabilities(SecurityCapabilities),
     EnumItem(EnumItem),
     Content(Content),
+    SmoothGrid(SmoothGrid),
 }
 
 impl From<&'_ str> for Variant {
@@ -163,4 +164,28 @@ mod serde_test {
         let de: Variant = bincode::deserialize(&ser).unwrap();
         assert_eq!(de, vec2);
     }
+
+    #[test]
+    fn terrain() {
+        let mut grid = SmoothGrid::new();
+        grid.write_voxel_world(
+            crate::TerrainVec::new(0, 0, 0),
+            crate::Voxel::solid(crate::TerrainMaterials::Rock),
+        );
+        let grid = Variant::SmoothGrid(grid);
+        let colors = Variant::MaterialColors(MaterialColors::new());
+
+        assert_eq!(grid.ty(), VariantType::SmoothGrid);
+        assert_eq!(colors.ty(), VariantType::MaterialColors);
+
+        for value in [grid, colors] {
+            let ser = serde_json::to_string(&value).unwrap();
+            let de: Variant = serde_json::from_str(&ser).unwrap();
+            assert_eq!(de, value);
+
+            let ser = bincode::serialize(&value).unwrap();
+            let de: Variant = bincode::deserialize(&ser).unwrap();
+            assert_eq!(de, value);
+        }
+    }
 }
@@ -49,12 +49,25 @@ impl ConvertCommand {
                 let options = rbx_xml::EncodeOptions::new()
                     .property_behavior(rbx_xml::EncodePropertyBehavior::WriteUnknown);
 
+                for lossy in rbx_xml::check_fidelity(&dom, &options) {
+                    let instance = dom.get_by_ref(lossy.referent).unwrap();
+                    warn_lossy(&instance.name, &lossy.property, lossy.fidelity);
+                }
+
                 rbx_xml::to_writer(output_file, &dom, root_ids, options)
                     .with_context(|| format!("Failed to write {}", self.output_path.display()))?;
             }
 
             ModelKind::Binary => {
-                rbx_binary::to_writer(output_file, &dom, root_ids)
+                let serializer = rbx_binary::Serializer::new();
+
+                for lossy in rbx_binary::fidelity::check_fidelity(&dom, &serializer) {
+                    let instance = dom.get_by_ref(lossy.referent).unwrap();
+                    warn_lossy(&instance.name, &lossy.property, lossy.fidelity);
+                }
+
+                serializer
+                    .serialize(output_file, &dom, root_ids)
                     .with_context(|| format!("Failed to write {}", self.output_path.display()))?;
             }
         }
@@ -62,3 +75,12 @@ impl ConvertCommand {
         Ok(())
     }
 }
+
+fn warn_lossy(instance_name: &str, property: &str, fidelity: rbx_xml::Fidelity) {
+    log::warn!(
+        "Property {} of {} will not convert exactly: {:?}",
+        property,
+        instance_name,
+        fidelity
+    );
+}
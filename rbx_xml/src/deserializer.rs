@@ -4,7 +4,7 @@ use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use log::trace;
 use rbx_dom_weak::{
     types::{Ref, SharedString, Variant, VariantType},
-    InstanceBuilder, Ustr, WeakDom,
+    Diagnostic, Diagnostics, DomBudget, InstanceBuilder, SourceLocation, Ustr, WeakDom,
 };
 use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
@@ -19,6 +19,7 @@ use crate::deserializer_core::{XmlEventReader, XmlReadEvent};
 
 pub fn decode_internal<R: Read>(source: R, options: DecodeOptions) -> Result<WeakDom, DecodeError> {
     let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+    tree.set_budget(options.budget);
 
     let root_id = tree.root_ref();
 
@@ -27,11 +28,44 @@ pub fn decode_internal<R: Read>(source: R, options: DecodeOptions) -> Result<Wea
 
     deserialize_root(&mut iterator, &mut state, root_id)?;
     apply_referent_rewrites(&mut state);
-    apply_shared_string_rewrites(&mut state);
+    apply_shared_string_rewrites(&mut iterator, &mut state)?;
 
     Ok(tree)
 }
 
+pub fn orphaned_shared_strings_internal<R: Read>(
+    source: R,
+    options: DecodeOptions,
+) -> Result<Vec<SharedString>, DecodeError> {
+    let mut tree = WeakDom::new(InstanceBuilder::new("DataModel"));
+
+    let root_id = tree.root_ref();
+
+    let mut iterator = XmlEventReader::from_source(source);
+    let mut state = ParseState::new(&mut tree, options);
+
+    deserialize_root(&mut iterator, &mut state, root_id)?;
+    apply_shared_string_rewrites(&mut iterator, &mut state)?;
+
+    let mut known_shared_strings: Vec<_> = state.known_shared_strings.drain().collect();
+    known_shared_strings.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+    let referenced: HashSet<&SharedString> = tree
+        .descendants()
+        .flat_map(|instance| instance.properties.values())
+        .filter_map(|value| match value {
+            Variant::SharedString(shared_string) => Some(shared_string),
+            _ => None,
+        })
+        .collect();
+
+    Ok(known_shared_strings
+        .into_iter()
+        .map(|(_, shared_string)| shared_string)
+        .filter(|shared_string| !referenced.contains(shared_string))
+        .collect())
+}
+
 /// Describes the strategy that rbx_xml should use when deserializing
 /// properties.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -65,11 +99,33 @@ pub enum DecodePropertyBehavior {
     NoReflection,
 }
 
+/// Describes what rbx_xml should do when an instance contains the same
+/// property more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DuplicatePropertyBehavior {
+    /// Keeps the first value of the property and ignores the rest.
+    KeepFirst,
+
+    /// Keeps the last value of the property, overwriting earlier ones.
+    ///
+    /// This is the default.
+    KeepLast,
+
+    /// Returns an error pointing to the repeated property.
+    ErrorOnDuplicate,
+}
+
 /// Options available for deserializing an XML-format model or place.
 #[derive(Debug, Clone)]
 pub struct DecodeOptions<'db> {
     property_behavior: DecodePropertyBehavior,
     database: &'db ReflectionDatabase<'db>,
+    legacy_compatibility: bool,
+    duplicate_property_behavior: DuplicatePropertyBehavior,
+    track_source_locations: bool,
+    budget: DomBudget,
+    diagnostics: Diagnostics,
 }
 
 impl<'db> DecodeOptions<'db> {
@@ -79,6 +135,11 @@ impl<'db> DecodeOptions<'db> {
         DecodeOptions {
             property_behavior: DecodePropertyBehavior::IgnoreUnknown,
             database: rbx_reflection_database::get(),
+            legacy_compatibility: false,
+            duplicate_property_behavior: DuplicatePropertyBehavior::KeepLast,
+            track_source_locations: false,
+            budget: DomBudget::new(),
+            diagnostics: Diagnostics::default(),
         }
     }
 
@@ -99,6 +160,71 @@ impl<'db> DecodeOptions<'db> {
         DecodeOptions { database, ..self }
     }
 
+    /// Determines what rbx_xml will do when an instance contains the same
+    /// property more than once, which some buggy exporters produce.
+    ///
+    /// Properties are considered the same if they resolve to the same
+    /// canonical property, so `Size` and `size` on a `Part` are duplicates.
+    #[inline]
+    pub fn duplicate_property_behavior(
+        self,
+        duplicate_property_behavior: DuplicatePropertyBehavior,
+    ) -> Self {
+        DecodeOptions {
+            duplicate_property_behavior,
+            ..self
+        }
+    }
+
+    /// Determines whether rbx_xml will accept quirks found in files saved by
+    /// very old versions of Roblox. This is off by default.
+    ///
+    /// With this set, rbx_xml will:
+    /// - Accept any document version instead of only version 4, such as
+    ///   `version="Roblox 1.0"`
+    /// - Read `CFrame` property tags as if they were `CoordinateFrame` tags
+    /// - Skip unknown elements like `PhysicsSerializedValues` that appear
+    ///   outside of an instance's `Properties` instead of returning an error
+    #[inline]
+    pub fn legacy_compatibility(self, legacy_compatibility: bool) -> Self {
+        DecodeOptions {
+            legacy_compatibility,
+            ..self
+        }
+    }
+
+    /// Determines whether rbx_xml will record where each instance was
+    /// declared. When enabled, every instance is given a
+    /// [`SourceLocation::Lines`](rbx_dom_weak::SourceLocation::Lines) in its
+    /// metadata, spanning from its `<Item>` tag to its `</Item>` tag. This is
+    /// off by default.
+    #[inline]
+    pub fn track_source_locations(self, track_source_locations: bool) -> Self {
+        DecodeOptions {
+            track_source_locations,
+            ..self
+        }
+    }
+
+    /// Sets the [`DomBudget`] given to decoded doms. Decoding stops with an
+    /// error as soon as the document's instances or properties no longer fit.
+    /// By default, there are no limits.
+    #[inline]
+    pub fn budget(self, budget: DomBudget) -> Self {
+        DecodeOptions { budget, ..self }
+    }
+
+    /// Sets where rbx_xml reports problems it recovers from while decoding,
+    /// like unknown properties it ignores, properties it migrates, and Ref
+    /// properties that point to referents missing from the document.
+    #[inline]
+    pub fn diagnostics(self, diagnostics: Diagnostics) -> Self {
+        DecodeOptions {
+            diagnostics,
+            ..self
+        }
+    }
+
     /// A utility function to determine whether or not we should reference the
     /// reflection database at all.
     pub(crate) fn use_reflection(&self) -> bool {
@@ -175,6 +301,11 @@ impl<'dom, 'db> ParseState<'dom, 'db> {
         }
     }
 
+    /// Whether quirks from very old files should be accepted.
+    pub fn legacy_compatibility(&self) -> bool {
+        self.options.legacy_compatibility
+    }
+
     /// Called when the deserializer encounters an unknown property type.
     pub fn unknown_type_visited(&mut self, id: Ref, property_name: &str, type_name: &str) {
         if self.unknown_type_names.contains(type_name) {
@@ -227,7 +358,13 @@ fn apply_referent_rewrites(state: &mut ParseState) {
     for rewrite in &state.referent_rewrites {
         let new_value = match state.referents_to_ids.get(&rewrite.referent_value) {
             Some(id) => *id,
-            None => continue,
+            None => {
+                state.options.diagnostics.report(Diagnostic::RefCleared {
+                    referent: rewrite.id,
+                    property: rewrite.property_name,
+                });
+                continue;
+            }
         };
 
         let instance = state
@@ -242,22 +379,29 @@ fn apply_referent_rewrites(state: &mut ParseState) {
     }
 }
 
-fn apply_shared_string_rewrites(state: &mut ParseState) {
+fn apply_shared_string_rewrites<R: Read>(
+    reader: &mut XmlEventReader<R>,
+    state: &mut ParseState,
+) -> Result<(), DecodeError> {
     for rewrite in &state.shared_string_rewrites {
         let new_value = match state.known_shared_strings.get(&rewrite.shared_string_hash) {
             Some(v) => v.clone(),
             None => continue,
         };
 
-        let instance = state.tree.get_by_ref_mut(rewrite.id).expect(
-            "rbx_xml bug: had ID in SharedString rewrite list that didn't end up in the tree",
-        );
-
-        instance.properties.insert(
-            rewrite.property_name.as_str().into(),
-            Variant::SharedString(new_value),
-        );
+        // SharedStrings can be large, so they're counted against the tree's
+        // budget like any other property.
+        state
+            .tree
+            .try_set_property(
+                rewrite.id,
+                rewrite.property_name.as_str(),
+                Variant::SharedString(new_value),
+            )
+            .map_err(|err| reader.error(err))?;
     }
+
+    Ok(())
 }
 
 fn deserialize_root<R: Read>(
@@ -284,7 +428,11 @@ fn deserialize_root<R: Read>(
         doc_version.ok_or_else(|| reader.error(DecodeErrorKind::MissingAttribute("version")))?;
 
     if doc_version != "4" {
-        return Err(reader.error(DecodeErrorKind::WrongDocVersion(doc_version)));
+        if state.options.legacy_compatibility {
+            log::debug!("Reading document with legacy version {doc_version:?}");
+        } else {
+            return Err(reader.error(DecodeErrorKind::WrongDocVersion(doc_version)));
+        }
     }
 
     loop {
@@ -305,6 +453,10 @@ fn deserialize_root<R: Read>(
                     "SharedStrings" => {
                         deserialize_shared_string_dict(reader, state)?;
                     }
+                    unknown if state.options.legacy_compatibility => {
+                        log::debug!("Skipping unknown top-level element {unknown}");
+                        reader.eat_unknown_tag()?;
+                    }
                     _ => {
                         let event = reader.expect_next().unwrap();
                         return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
@@ -423,8 +575,11 @@ fn deserialize_instance<R: Read>(
     state: &mut ParseState,
     parent_id: Ref,
 ) -> Result<(), DecodeError> {
+    let start_line;
+
     let (class_name, referent) = {
         let attributes = reader.expect_start_with_name("Item")?;
+        start_line = reader.line();
 
         let mut class = None;
         let mut referent = None;
@@ -454,23 +609,37 @@ fn deserialize_instance<R: Read>(
         .unwrap_or(0);
 
     let builder = InstanceBuilder::with_property_capacity(class_name, prop_capacity);
-    let instance_id = state.tree.insert(parent_id, builder);
+    let instance_id = state
+        .tree
+        .try_insert(parent_id, builder)
+        .map_err(|err| reader.error(err))?;
 
     if let Some(referent) = referent {
         state.referents_to_ids.insert(referent, instance_id);
     }
 
     let mut properties: HashMap<Ustr, Variant> = HashMap::new();
+    let mut seen_properties: HashSet<Ustr> = HashSet::new();
 
-    loop {
+    let end_line = loop {
         match reader.expect_peek()? {
             XmlReadEvent::StartElement { name, .. } => match name.local_name.as_str() {
                 "Properties" => {
-                    deserialize_properties(reader, state, instance_id, &mut properties)?;
+                    deserialize_properties(
+                        reader,
+                        state,
+                        instance_id,
+                        &mut properties,
+                        &mut seen_properties,
+                    )?;
                 }
                 "Item" => {
                     deserialize_instance(reader, state, instance_id)?;
                 }
+                unknown if state.options.legacy_compatibility => {
+                    log::debug!("Skipping unknown element {unknown} in instance");
+                    reader.eat_unknown_tag()?;
+                }
                 _ => {
                     let event = reader.expect_next().unwrap();
                     return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
@@ -482,16 +651,17 @@ fn deserialize_instance<R: Read>(
                     return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
                 }
 
+                let end_line = reader.line();
                 reader.expect_next().unwrap();
 
-                break;
+                break end_line;
             }
             _ => {
                 let event = reader.expect_next().unwrap();
                 return Err(reader.error(DecodeErrorKind::UnexpectedXmlEvent(event)));
             }
         }
-    }
+    };
 
     let instance = state.tree.get_by_ref_mut(instance_id).unwrap();
 
@@ -507,7 +677,23 @@ fn deserialize_instance<R: Read>(
         None => instance.class.to_string(),
     };
 
-    instance.properties = properties.into_iter().collect();
+    if state.options.track_source_locations {
+        instance.metadata.insert(SourceLocation::Lines {
+            start: start_line,
+            end: end_line,
+        });
+    }
+
+    if state.options.budget.is_unlimited() {
+        instance.properties = properties.into_iter().collect();
+    } else {
+        for (key, value) in properties {
+            state
+                .tree
+                .try_set_property(instance_id, key, value)
+                .map_err(|err| reader.error(err))?;
+        }
+    }
 
     Ok(())
 }
@@ -517,6 +703,7 @@ fn deserialize_properties<R: Read>(
     state: &mut ParseState,
     instance_id: Ref,
     props: &mut HashMap<Ustr, Variant>,
+    seen_properties: &mut HashSet<Ustr>,
 ) -> Result<(), DecodeError> {
     reader.expect_start_with_name("Properties")?;
 
@@ -587,6 +774,31 @@ fn deserialize_properties<R: Read>(
             None
         };
 
+        let property_key: Ustr = match maybe_descriptor {
+            Some(descriptor) => descriptor.name.as_ref().into(),
+            None => xml_property_name.as_str().into(),
+        };
+
+        if !seen_properties.insert(property_key) {
+            match state.options.duplicate_property_behavior {
+                DuplicatePropertyBehavior::KeepLast => {}
+                DuplicatePropertyBehavior::KeepFirst => {
+                    log::debug!("Skipping duplicate property {class_name}.{property_key}");
+
+                    // Reading the value normally could queue up a Ref or
+                    // SharedString rewrite, which would clobber the first value.
+                    reader.eat_unknown_tag()?;
+                    continue;
+                }
+                DuplicatePropertyBehavior::ErrorOnDuplicate => {
+                    return Err(reader.error(DecodeErrorKind::DuplicateProperty {
+                        class_name: class_name.to_string(),
+                        property_name: property_key.to_string(),
+                    }));
+                }
+            }
+        }
+
         if let Some(descriptor) = maybe_descriptor {
             let value =
                 match read_value_xml(reader, state, &xml_type_name, instance_id, &descriptor.name)?
@@ -645,6 +857,14 @@ fn deserialize_properties<R: Read>(
                         match migration.perform(&value) {
                             Ok(migrated_value) => {
                                 entry.insert(migrated_value);
+                                state
+                                    .options
+                                    .diagnostics
+                                    .report(Diagnostic::MigrationApplied {
+                                        class: class_name,
+                                        property: old_property_name.as_ref().into(),
+                                        new_property: new_property_name.as_str().into(),
+                                    });
                                 log::trace!(
                                     "Successfully migrated property {old_property_name} to {new_property_name}"
                                 );
@@ -672,6 +892,14 @@ fn deserialize_properties<R: Read>(
                         instance_id,
                         &xml_property_name,
                     )?;
+
+                    state
+                        .options
+                        .diagnostics
+                        .report(Diagnostic::PropertySkipped {
+                            class: class_name,
+                            property: xml_property_name.as_str().into(),
+                        });
                 }
                 DecodePropertyBehavior::ReadUnknown | DecodePropertyBehavior::NoReflection => {
                     // We'll take this value as-is with no conversions on either
@@ -1,7 +1,7 @@
 use std::io::Read;
 
 use log::trace;
-use xml::{attribute::OwnedAttribute, reader::ParserConfig};
+use xml::{attribute::OwnedAttribute, common::Position, reader::ParserConfig};
 
 use crate::{
     core::XmlType,
@@ -74,6 +74,12 @@ impl<R: Read> XmlEventReader<R> {
         self.peeked.as_ref()
     }
 
+    /// Returns the line, counted from 1, of the most recently read or peeked
+    /// event.
+    pub fn line(&self) -> usize {
+        (self.reader.position().row + 1) as usize
+    }
+
     pub(crate) fn error<T: Into<DecodeErrorKind>>(&self, kind: T) -> NewDecodeError {
         NewDecodeError::new_from_reader(kind.into(), &self.reader)
     }
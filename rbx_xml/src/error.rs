@@ -3,7 +3,7 @@ use std::{
     io::{self, Read, Write},
 };
 
-use rbx_dom_weak::types::VariantType;
+use rbx_dom_weak::{types::VariantType, BudgetError};
 
 /// An error that can occur when deserializing an XML-format model or place.
 #[derive(Debug)]
@@ -75,6 +75,7 @@ pub(crate) enum DecodeErrorKind {
     DecodeBase64(base64::DecodeError),
     MigrationError(rbx_reflection::MigrationError),
     TypeError(rbx_dom_weak::types::Error),
+    Budget(BudgetError),
 
     // Errors specific to rbx_xml
     WrongDocVersion(String),
@@ -87,6 +88,10 @@ pub(crate) enum DecodeErrorKind {
     },
     InvalidContent(&'static str),
     NameMustBeString(VariantType),
+    DuplicateProperty {
+        class_name: String,
+        property_name: String,
+    },
     UnsupportedPropertyConversion {
         class_name: String,
         property_name: String,
@@ -107,6 +112,7 @@ impl fmt::Display for DecodeErrorKind {
             DecodeBase64(err) => write!(output, "{}", err),
             MigrationError(err) => write!(output, "{}", err),
             TypeError(err) => write!(output, "{}", err),
+            Budget(err) => write!(output, "{}", err),
 
             WrongDocVersion(version) => {
                 write!(output, "Invalid version '{}', expected version 4", version)
@@ -130,6 +136,14 @@ impl fmt::Display for DecodeErrorKind {
                 "The 'Name' property must be of type String, but it was {:?}",
                 ty
             ),
+            DuplicateProperty {
+                class_name,
+                property_name,
+            } => write!(
+                output,
+                "Property {}.{} was specified more than once",
+                class_name, property_name
+            ),
             UnsupportedPropertyConversion {
                 class_name,
                 property_name,
@@ -156,6 +170,7 @@ impl std::error::Error for DecodeErrorKind {
             ParseInt(err) => Some(err),
             DecodeBase64(err) => Some(err),
             MigrationError(err) => Some(err),
+            Budget(err) => Some(err),
 
             _ => None,
         }
@@ -180,6 +195,12 @@ impl From<std::num::ParseIntError> for DecodeErrorKind {
     }
 }
 
+impl From<BudgetError> for DecodeErrorKind {
+    fn from(error: BudgetError) -> DecodeErrorKind {
+        DecodeErrorKind::Budget(error)
+    }
+}
+
 impl From<base64::DecodeError> for DecodeErrorKind {
     fn from(error: base64::DecodeError) -> DecodeErrorKind {
         DecodeErrorKind::DecodeBase64(error)
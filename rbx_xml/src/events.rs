@@ -0,0 +1,370 @@
+use std::io::Read;
+
+use rbx_dom_weak::types::{SharedString, Variant};
+use xml::attribute::OwnedAttribute;
+
+use crate::{
+    deserializer_core::{XmlEventReader, XmlReadEvent},
+    error::{DecodeError, DecodeErrorKind},
+    types::{read_plain_value_xml, REF_TAG_NAME, SHARED_STRING_TAG_NAME},
+};
+
+/// A single piece of an XML model or place, read by [`XmlEvents`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum XmlEvent {
+    /// The start of an instance. Every property event until the matching
+    /// [`XmlEvent::EndInstance`] belongs to this instance, except for those
+    /// between the start and end of one of its children.
+    StartInstance {
+        /// The ClassName of the instance.
+        class: String,
+        /// The ID other instances use to refer to this one, if it has one.
+        referent: Option<String>,
+    },
+
+    /// The end of the most recently started instance.
+    EndInstance,
+
+    /// A property of the current instance. Names and values are exactly as
+    /// they appear in the file, so they are not converted to their canonical
+    /// forms like [`from_reader`][crate::from_reader] would.
+    Property {
+        /// The name of the property.
+        name: String,
+        /// The value of the property.
+        value: Variant,
+    },
+
+    /// A Ref property of the current instance.
+    RefProperty {
+        /// The name of the property.
+        name: String,
+        /// The `referent` of the instance it points to, or `None` if it
+        /// doesn't point to an instance.
+        referent: Option<String>,
+    },
+
+    /// A SharedString property of the current instance. The value of the
+    /// string is given by an [`XmlEvent::SharedString`] event with the same
+    /// hash, which comes after every instance.
+    SharedStringProperty {
+        /// The name of the property.
+        name: String,
+        /// The hash of the string.
+        hash: String,
+    },
+
+    /// A property of the current instance with a type rbx_xml can't read.
+    /// Its value is skipped.
+    UnknownProperty {
+        /// The name of the property.
+        name: String,
+        /// The name of the property's XML tag.
+        type_name: String,
+    },
+
+    /// A piece of metadata about the file, like `ExplicitAutoJoints`.
+    Metadata {
+        /// The name of the metadata.
+        name: String,
+        /// The value of the metadata.
+        value: String,
+    },
+
+    /// A string used by SharedString properties.
+    SharedString {
+        /// The hash that properties use to refer to the string.
+        hash: String,
+        /// The contents of the string.
+        value: SharedString,
+    },
+}
+
+/// Reads an XML model or place one piece at a time, without building a
+/// [`WeakDom`][rbx_dom_weak::WeakDom].
+///
+/// This is useful for tools that only need part of a file, or that filter it
+/// as it is read. Instances that aren't needed can be passed over with
+/// [`XmlEvents::skip_instance`] without reading their properties.
+///
+/// The reflection database isn't used, so properties aren't migrated or
+/// converted, and Ref and SharedString properties are given as they appear in
+/// the file instead of being resolved.
+///
+/// ```
+/// use rbx_xml::{XmlEvent, XmlEvents};
+///
+/// let document = r#"
+///     <roblox version="4">
+///         <Item class="Folder" referent="RBX1">
+///             <Properties>
+///                 <string name="Name">Tools</string>
+///             </Properties>
+///             <Item class="Script" referent="RBX2">
+///                 <Properties>
+///                     <ProtectedString name="Source">print("hi")</ProtectedString>
+///                 </Properties>
+///             </Item>
+///         </Item>
+///     </roblox>
+/// "#;
+///
+/// let mut events = XmlEvents::new(document.as_bytes());
+/// let mut classes = Vec::new();
+///
+/// while let Some(event) = events.next() {
+///     if let XmlEvent::StartInstance { class, .. } = event? {
+///         if class == "Script" {
+///             events.skip_instance()?;
+///         } else {
+///             classes.push(class);
+///         }
+///     }
+/// }
+///
+/// assert_eq!(classes, ["Folder"]);
+/// # Ok::<(), rbx_xml::DecodeError>(())
+/// ```
+pub struct XmlEvents<R: Read> {
+    reader: XmlEventReader<R>,
+    started: bool,
+    finished: bool,
+
+    /// The number of instances that have started and not ended.
+    depth: usize,
+    in_properties: bool,
+    in_shared_strings: bool,
+}
+
+impl<R: Read> XmlEvents<R> {
+    /// Creates an `XmlEvents` that reads from the given source.
+    pub fn new(source: R) -> Self {
+        Self {
+            reader: XmlEventReader::from_source(source),
+            started: false,
+            finished: false,
+            depth: 0,
+            in_properties: false,
+            in_shared_strings: false,
+        }
+    }
+
+    /// Skips the rest of the current instance, including all of its
+    /// properties and children. The [`XmlEvent::EndInstance`] event for it
+    /// is skipped as well.
+    ///
+    /// This is usually called right after the instance's
+    /// [`XmlEvent::StartInstance`] event. Does nothing if no instance has
+    /// started.
+    pub fn skip_instance(&mut self) -> Result<(), DecodeError> {
+        if self.depth == 0 || self.finished {
+            return Ok(());
+        }
+
+        // The number of elements inside of the instance that are still open.
+        let mut open = if self.in_properties { 1 } else { 0 };
+        self.in_properties = false;
+
+        let result = loop {
+            match self.reader.expect_next() {
+                Ok(XmlReadEvent::StartElement { .. }) => open += 1,
+                Ok(XmlReadEvent::EndElement { .. }) if open == 0 => break Ok(()),
+                Ok(XmlReadEvent::EndElement { .. }) => open -= 1,
+                Ok(_) => {}
+                Err(error) => break Err(error),
+            }
+        };
+
+        self.depth -= 1;
+        self.fail_on_error(result)
+    }
+
+    fn fail_on_error<T>(&mut self, result: Result<T, DecodeError>) -> Result<T, DecodeError> {
+        if result.is_err() {
+            self.finished = true;
+        }
+
+        result
+    }
+
+    fn read_next(&mut self) -> Result<Option<XmlEvent>, DecodeError> {
+        if !self.started {
+            self.started = true;
+            self.read_header()?;
+        }
+
+        loop {
+            let (name, attributes) = match self.reader.expect_peek()? {
+                XmlReadEvent::StartElement {
+                    name, attributes, ..
+                } => (name.local_name.clone(), attributes.clone()),
+                XmlReadEvent::EndElement { name } => {
+                    let in_container = self.in_properties || self.in_shared_strings;
+
+                    match name.local_name.as_str() {
+                        "Properties" if self.in_properties => self.in_properties = false,
+                        "SharedStrings" if self.in_shared_strings => self.in_shared_strings = false,
+                        "Item" if self.depth > 0 && !in_container => {
+                            self.reader.expect_next()?;
+                            self.depth -= 1;
+                            return Ok(Some(XmlEvent::EndInstance));
+                        }
+                        "roblox" if self.depth == 0 && !in_container => {
+                            self.reader.expect_next()?;
+                            self.finished = true;
+                            return Ok(None);
+                        }
+                        _ => return Err(self.unexpected_next()),
+                    }
+
+                    self.reader.expect_next()?;
+                    continue;
+                }
+                XmlReadEvent::EndDocument if self.depth == 0 => {
+                    self.finished = true;
+                    return Ok(None);
+                }
+                _ => return Err(self.unexpected_next()),
+            };
+
+            if self.in_properties {
+                return self.read_property(&name, &attributes).map(Some);
+            }
+
+            if self.in_shared_strings {
+                if name != SHARED_STRING_TAG_NAME {
+                    return Err(self.unexpected_next());
+                }
+
+                let hash = self.required_attribute(&attributes, "md5")?;
+                self.reader.expect_next()?;
+                let value = SharedString::new(self.reader.read_base64_characters()?);
+                self.reader.expect_end_with_name(SHARED_STRING_TAG_NAME)?;
+
+                return Ok(Some(XmlEvent::SharedString { hash, value }));
+            }
+
+            match name.as_str() {
+                "Item" => {
+                    let class = self.required_attribute(&attributes, "class")?;
+                    let referent = attribute(&attributes, "referent");
+                    self.reader.expect_next()?;
+                    self.depth += 1;
+
+                    return Ok(Some(XmlEvent::StartInstance { class, referent }));
+                }
+                "Properties" if self.depth > 0 => {
+                    self.reader.expect_next()?;
+                    self.in_properties = true;
+                }
+                "Meta" if self.depth == 0 => {
+                    let name = self.required_attribute(&attributes, "name")?;
+                    self.reader.expect_next()?;
+                    let value = self.reader.read_characters()?;
+                    self.reader.expect_end_with_name("Meta")?;
+
+                    return Ok(Some(XmlEvent::Metadata { name, value }));
+                }
+                "SharedStrings" if self.depth == 0 => {
+                    self.reader.expect_next()?;
+                    self.in_shared_strings = true;
+                }
+                "External" if self.depth == 0 => self.reader.eat_unknown_tag()?,
+                _ => return Err(self.unexpected_next()),
+            }
+        }
+    }
+
+    fn read_header(&mut self) -> Result<(), DecodeError> {
+        match self.reader.expect_next()? {
+            XmlReadEvent::StartDocument { .. } => {}
+            event => {
+                return Err(self
+                    .reader
+                    .error(DecodeErrorKind::UnexpectedXmlEvent(event)))
+            }
+        }
+
+        let attributes = self.reader.expect_start_with_name("roblox")?;
+        let version = self.required_attribute(&attributes, "version")?;
+
+        if version != "4" {
+            return Err(self.reader.error(DecodeErrorKind::WrongDocVersion(version)));
+        }
+
+        Ok(())
+    }
+
+    fn read_property(
+        &mut self,
+        type_name: &str,
+        attributes: &[OwnedAttribute],
+    ) -> Result<XmlEvent, DecodeError> {
+        let name = self.required_attribute(attributes, "name")?;
+
+        if type_name == REF_TAG_NAME {
+            let contents = self.reader.read_tag_contents(REF_TAG_NAME)?;
+            let referent = Some(contents).filter(|contents| contents != "null");
+
+            return Ok(XmlEvent::RefProperty { name, referent });
+        }
+
+        if type_name == SHARED_STRING_TAG_NAME {
+            let hash = self.reader.read_tag_contents(SHARED_STRING_TAG_NAME)?;
+            return Ok(XmlEvent::SharedStringProperty { name, hash });
+        }
+
+        match read_plain_value_xml(&mut self.reader, type_name, false)? {
+            Some(value) => Ok(XmlEvent::Property { name, value }),
+            None => {
+                self.reader.eat_unknown_tag()?;
+
+                Ok(XmlEvent::UnknownProperty {
+                    name,
+                    type_name: type_name.to_owned(),
+                })
+            }
+        }
+    }
+
+    fn required_attribute(
+        &self,
+        attributes: &[OwnedAttribute],
+        name: &'static str,
+    ) -> Result<String, DecodeError> {
+        attribute(attributes, name)
+            .ok_or_else(|| self.reader.error(DecodeErrorKind::MissingAttribute(name)))
+    }
+
+    /// Consumes the next event and returns an error for it, since it isn't
+    /// allowed where it is.
+    fn unexpected_next(&mut self) -> DecodeError {
+        match self.reader.expect_next() {
+            Ok(event) => self
+                .reader
+                .error(DecodeErrorKind::UnexpectedXmlEvent(event)),
+            Err(error) => error,
+        }
+    }
+}
+
+impl<R: Read> Iterator for XmlEvents<R> {
+    type Item = Result<XmlEvent, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let result = self.read_next();
+        self.fail_on_error(result).transpose()
+    }
+}
+
+fn attribute(attributes: &[OwnedAttribute], name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .map(|attribute| attribute.value.clone())
+}
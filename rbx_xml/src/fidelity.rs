@@ -0,0 +1,101 @@
+use rbx_dom_weak::{types::VariantType, Fidelity, LossyProperty, WeakDom};
+
+use crate::{
+    core::find_serialized_property_descriptor,
+    serializer::{EncodeOptions, EncodePropertyBehavior},
+};
+
+/// Returns how faithfully values of the given type are written by rbx_xml when
+/// the property isn't known to the reflection database.
+///
+/// Floating point values are written using the shortest decimal form that
+/// reads back as the same value, so no precision is lost. Properties known to
+/// the reflection database are converted to the type the database expects
+/// and are always read back as that type.
+pub fn type_fidelity(ty: VariantType) -> Fidelity {
+    match ty {
+        VariantType::Axes
+        | VariantType::BinaryString
+        | VariantType::Bool
+        | VariantType::CFrame
+        | VariantType::Color3
+        | VariantType::Color3uint8
+        | VariantType::ColorSequence
+        | VariantType::Content
+        | VariantType::ContentId
+        | VariantType::Enum
+        | VariantType::Faces
+        | VariantType::Float32
+        | VariantType::Float64
+        | VariantType::Font
+        | VariantType::Int32
+        | VariantType::Int64
+        | VariantType::NumberRange
+        | VariantType::NumberSequence
+        | VariantType::OptionalCFrame
+        | VariantType::PhysicalProperties
+        | VariantType::Ray
+        | VariantType::Rect
+        | VariantType::Ref
+        | VariantType::SecurityCapabilities
+        | VariantType::SharedString
+        | VariantType::String
+        | VariantType::UDim
+        | VariantType::UDim2
+        | VariantType::UniqueId
+        | VariantType::Vector2
+        | VariantType::Vector2int16
+        | VariantType::Vector3
+        | VariantType::Vector3int16 => Fidelity::Exact,
+
+        VariantType::BrickColor => Fidelity::Lossy {
+            reason: "written as an int, and read back as an Int32",
+        },
+        VariantType::Tags
+        | VariantType::Attributes
+        | VariantType::MaterialColors
+        | VariantType::SmoothGrid => Fidelity::Lossy {
+            reason: "written as a BinaryString, and read back as one",
+        },
+
+        _ => Fidelity::Unsupported,
+    }
+}
+
+/// Lists every property in the dom that would be lost or changed by writing
+/// the dom with the given options and reading it back.
+///
+/// Properties that the options would skip, like unknown properties with
+/// [`EncodePropertyBehavior::IgnoreUnknown`], are reported as lossy.
+pub fn check_fidelity(dom: &WeakDom, options: &EncodeOptions) -> Vec<LossyProperty> {
+    let mut lossy = Vec::new();
+
+    for instance in dom.descendants() {
+        let mut properties: Vec<_> = instance.properties.iter().collect();
+        properties.sort_unstable_by_key(|(name, _)| name.as_str());
+
+        for (name, value) in properties {
+            if options.use_reflection()
+                && find_serialized_property_descriptor(&instance.class, name, options.database)
+                    .is_some()
+            {
+                continue;
+            }
+
+            let ty = value.ty();
+            let fidelity = match options.property_behavior {
+                EncodePropertyBehavior::IgnoreUnknown => Fidelity::Lossy {
+                    reason: "not known to the reflection database, so it is not written",
+                },
+                EncodePropertyBehavior::ErrorOnUnknown => Fidelity::Unsupported,
+                _ => type_fidelity(ty),
+            };
+
+            if fidelity != Fidelity::Exact {
+                lossy.push(LossyProperty::new(instance.referent(), *name, ty, fidelity));
+            }
+        }
+    }
+
+    lossy
+}
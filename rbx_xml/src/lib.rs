@@ -114,6 +114,8 @@ mod core;
 mod deserializer;
 mod deserializer_core;
 mod error;
+mod events;
+mod fidelity;
 mod serializer;
 mod serializer_core;
 mod types;
@@ -125,16 +127,29 @@ mod tests;
 
 use std::io::{Read, Write};
 
-use rbx_dom_weak::{types::Ref, WeakDom};
+use rbx_dom_weak::{
+    types::{Ref, SharedString},
+    WeakDom,
+};
 
-use crate::{deserializer::decode_internal, serializer::encode_internal};
+use crate::{
+    deserializer::{decode_internal, orphaned_shared_strings_internal},
+    serializer::encode_internal,
+};
 
 pub use crate::{
-    deserializer::{DecodeOptions, DecodePropertyBehavior},
+    deserializer::{DecodeOptions, DecodePropertyBehavior, DuplicatePropertyBehavior},
     error::{DecodeError, EncodeError},
-    serializer::{EncodeOptions, EncodePropertyBehavior},
+    events::{XmlEvent, XmlEvents},
+    fidelity::{check_fidelity, type_fidelity},
+    serializer::{
+        parse_studio_referent, studio_referent, EncodeOptions, EncodePropertyBehavior,
+        ReferentStyle,
+    },
 };
 
+pub use rbx_dom_weak::{Fidelity, LossyProperty};
+
 /// Decodes an XML-format model or place from something that implements the
 /// `std::io::Read` trait.
 pub fn from_reader<R: Read>(reader: R, options: DecodeOptions) -> Result<WeakDom, DecodeError> {
@@ -158,8 +173,25 @@ pub fn from_str_default<S: AsRef<str>>(reader: S) -> Result<WeakDom, DecodeError
     decode_internal(reader.as_ref().as_bytes(), DecodeOptions::default())
 }
 
+/// Finds the SharedStrings in the `SharedStrings` section of an XML-format
+/// model or place that no property refers to, sorted by their hash.
+///
+/// These strings are dropped when a decoded file is serialized again, since
+/// rbx_xml only writes SharedStrings that are used by a property.
+pub fn orphaned_shared_strings<R: Read>(
+    reader: R,
+    options: DecodeOptions,
+) -> Result<Vec<SharedString>, DecodeError> {
+    orphaned_shared_strings_internal(reader, options)
+}
+
 /// Serializes a subset of the given tree to an XML format model or place,
 /// writing to something that implements the `std::io::Write` trait.
+///
+/// `ids` can refer to any instances in the tree, not just the root, and each
+/// one is written along with its descendants. Ref properties that point at
+/// instances outside of what's written are written as `null`, the same as
+/// in rbx_binary.
 pub fn to_writer<W: Write>(
     writer: W,
     tree: &WeakDom,
@@ -1,9 +1,9 @@
-use std::{borrow::Cow, collections::BTreeMap, io::Write};
+use std::{borrow::Cow, collections::BTreeMap, io::Write, str::FromStr};
 
-use ahash::{HashMap, HashMapExt};
+use ahash::{HashMap, HashMapExt, HashSet};
 use rbx_dom_weak::{
     types::{Ref, SharedString, SharedStringHash, Variant, VariantType},
-    WeakDom,
+    Diagnostic, Diagnostics, WeakDom,
 };
 use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
@@ -23,7 +23,15 @@ pub fn encode_internal<W: Write>(
     options: EncodeOptions,
 ) -> Result<(), NewEncodeError> {
     let mut writer = XmlEventWriter::from_output(output);
-    let mut state = EmitState::new(options);
+
+    // Ref properties can only point at instances that are in the file, so we
+    // need to know every instance we're going to write before writing any.
+    let written = ids
+        .iter()
+        .flat_map(|id| tree.descendants_of(*id))
+        .map(|instance| instance.referent())
+        .collect();
+    let mut state = EmitState::new(options, written);
 
     writer.write(XmlWriteEvent::start_element("roblox").attr("version", "4"))?;
 
@@ -69,11 +77,55 @@ pub enum EncodePropertyBehavior {
     NoReflection,
 }
 
+/// Describes how rbx_xml should name the referents of the instances it
+/// writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum ReferentStyle {
+    /// Numbers counting up from 0 in the order instances are written.
+    ///
+    /// This is the default.
+    #[default]
+    Sequential,
+
+    /// `RBX` followed by 32 uppercase hexadecimal digits, which is how Roblox
+    /// Studio names referents. The digits come from each instance's `Ref`, so
+    /// an instance has the same referent no matter what else is written with
+    /// it.
+    Studio,
+}
+
+/// Formats `referent` the way Roblox Studio names referents, as `RBX`
+/// followed by 32 uppercase hexadecimal digits. This is how referents are
+/// written with [`ReferentStyle::Studio`].
+///
+/// New referents can be generated by formatting a new `Ref`, like
+/// `studio_referent(Ref::new())`.
+pub fn studio_referent(referent: Ref) -> String {
+    format!("RBX{}", referent.to_string().to_uppercase())
+}
+
+/// Parses a referent in the style of [`studio_referent`] back into the `Ref`
+/// it was made from. Hexadecimal digits of either case are accepted.
+///
+/// Returns `None` if `referent` isn't in that style.
+pub fn parse_studio_referent(referent: &str) -> Option<Ref> {
+    let digits = referent.strip_prefix("RBX")?;
+    if digits.len() != 32 || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Ref::from_str(digits).ok()
+}
+
 /// Options available for serializing an XML-format model or place.
 #[derive(Debug, Clone)]
 pub struct EncodeOptions<'db> {
-    property_behavior: EncodePropertyBehavior,
-    database: &'db ReflectionDatabase<'db>,
+    pub(crate) property_behavior: EncodePropertyBehavior,
+    pub(crate) database: &'db ReflectionDatabase<'db>,
+    canonical_ordering: bool,
+    referent_style: ReferentStyle,
+    diagnostics: Diagnostics,
 }
 
 impl<'db> EncodeOptions<'db> {
@@ -83,6 +135,9 @@ impl<'db> EncodeOptions<'db> {
         EncodeOptions {
             property_behavior: EncodePropertyBehavior::IgnoreUnknown,
             database: rbx_reflection_database::get(),
+            canonical_ordering: false,
+            referent_style: ReferentStyle::Sequential,
+            diagnostics: Diagnostics::default(),
         }
     }
 
@@ -103,6 +158,39 @@ impl<'db> EncodeOptions<'db> {
         EncodeOptions { database, ..self }
     }
 
+    /// Determines whether rbx_xml will write `Tags` values in sorted order, so
+    /// that the same set of tags always produces the same output regardless
+    /// of the order they were added in. This is off by default.
+    ///
+    /// `Attributes` are always written sorted by name.
+    #[inline]
+    pub fn canonical_ordering(self, canonical_ordering: bool) -> Self {
+        EncodeOptions {
+            canonical_ordering,
+            ..self
+        }
+    }
+
+    /// Determines how rbx_xml will name the referents of instances.
+    #[inline]
+    pub fn referent_style(self, referent_style: ReferentStyle) -> Self {
+        EncodeOptions {
+            referent_style,
+            ..self
+        }
+    }
+
+    /// Sets where rbx_xml reports problems it recovers from while encoding,
+    /// like unknown properties it ignores, properties it migrates, and Ref
+    /// properties that point outside of the instances being written.
+    #[inline]
+    pub fn diagnostics(self, diagnostics: Diagnostics) -> Self {
+        EncodeOptions {
+            diagnostics,
+            ..self
+        }
+    }
+
     pub(crate) fn use_reflection(&self) -> bool {
         self.property_behavior != EncodePropertyBehavior::NoReflection
     }
@@ -124,29 +212,44 @@ pub struct EmitState<'db> {
     /// The referent value that will be used for emitting the next instance.
     next_referent: u32,
 
+    /// Every instance that will be written to the file. Ref properties that
+    /// point at other instances are written as `null`.
+    written: HashSet<Ref>,
+
     /// A map of all shared strings referenced so far while generating XML. This
     /// map will be written as the file's SharedString dictionary.
     shared_strings_to_emit: BTreeMap<SharedStringHash, SharedString>,
 }
 
 impl<'db> EmitState<'db> {
-    pub fn new(options: EncodeOptions<'db>) -> EmitState<'db> {
+    pub fn new(options: EncodeOptions<'db>, written: HashSet<Ref>) -> EmitState<'db> {
         EmitState {
             options,
             referent_map: HashMap::new(),
             next_referent: 0,
+            written,
             shared_strings_to_emit: BTreeMap::new(),
         }
     }
 
-    pub fn map_id(&mut self, id: Ref) -> u32 {
+    /// Returns whether the instance with the given ID will be written to the
+    /// file.
+    pub fn is_written(&self, id: Ref) -> bool {
+        self.written.contains(&id)
+    }
+
+    pub fn map_id(&mut self, id: Ref) -> String {
+        if self.options.referent_style == ReferentStyle::Studio {
+            return studio_referent(id);
+        }
+
         match self.referent_map.get(&id) {
-            Some(&value) => value,
+            Some(&value) => value.to_string(),
             None => {
                 let referent = self.next_referent;
                 self.referent_map.insert(id, referent);
                 self.next_referent += 1;
-                referent
+                referent.to_string()
             }
         }
     }
@@ -154,6 +257,10 @@ impl<'db> EmitState<'db> {
     pub fn add_shared_string(&mut self, value: SharedString) {
         self.shared_strings_to_emit.insert(value.hash(), value);
     }
+
+    pub fn canonical_ordering(&self) -> bool {
+        self.options.canonical_ordering
+    }
 }
 
 /// Serialize a single instance.
@@ -173,7 +280,7 @@ fn serialize_instance<'dom, W: Write>(
     writer.write(
         XmlWriteEvent::start_element("Item")
             .attr("class", &instance.class)
-            .attr("referent", &mapped_id.to_string()),
+            .attr("referent", &mapped_id),
     )?;
 
     writer.write(XmlWriteEvent::start_element("Properties"))?;
@@ -234,18 +341,37 @@ fn serialize_instance<'dom, W: Write>(
                 // since old values will still load in Studio.
                 if let Ok(new_value) = migration.perform(&converted_value) {
                     converted_value = Cow::Owned(new_value);
-                    serialized_name = &migration.new_property_name
+                    serialized_name = &migration.new_property_name;
+
+                    state
+                        .options
+                        .diagnostics
+                        .report(Diagnostic::MigrationApplied {
+                            class: instance.class,
+                            property: property_name.into(),
+                            new_property: serialized_name.into(),
+                        });
                 }
             }
 
+            report_cleared_ref(state, id, property_name, &converted_value);
             write_value_xml(writer, state, serialized_name, &converted_value)?;
         } else {
             match state.options.property_behavior {
-                EncodePropertyBehavior::IgnoreUnknown => {}
+                EncodePropertyBehavior::IgnoreUnknown => {
+                    state
+                        .options
+                        .diagnostics
+                        .report(Diagnostic::PropertySkipped {
+                            class: instance.class,
+                            property: property_name.into(),
+                        });
+                }
                 EncodePropertyBehavior::WriteUnknown | EncodePropertyBehavior::NoReflection => {
                     // We'll take this value as-is with no conversions on
                     // either the name or value.
 
+                    report_cleared_ref(state, id, property_name, value);
                     write_value_xml(writer, state, property_name, value)?;
                 }
                 EncodePropertyBehavior::ErrorOnUnknown => {
@@ -269,6 +395,19 @@ fn serialize_instance<'dom, W: Write>(
     Ok(())
 }
 
+/// Reports a Ref property that will be written as `null` because it points to
+/// an instance that isn't being written.
+fn report_cleared_ref(state: &EmitState, id: Ref, property_name: &str, value: &Variant) {
+    if let Variant::Ref(target) = value {
+        if target.is_some() && !state.is_written(*target) {
+            state.options.diagnostics.report(Diagnostic::RefCleared {
+                referent: id,
+                property: property_name.into(),
+            });
+        }
+    }
+}
+
 fn serialize_shared_strings<W: Write>(
     writer: &mut XmlEventWriter<W>,
     state: &mut EmitState,
@@ -3,10 +3,10 @@
 use rbx_dom_weak::types::{
     Attributes, BinaryString, BrickColor, Color3, Color3uint8, ColorSequence,
     ColorSequenceKeypoint, Enum, EnumItem, Font, MaterialColors, NumberRange, NumberSequence,
-    NumberSequenceKeypoint, Rect, Tags, TerrainMaterials, UDim, UDim2, UniqueId, Variant,
-    VariantType, Vector2, Vector3,
+    NumberSequenceKeypoint, Rect, Ref, SharedString, SmoothGrid, Tags, TerrainMaterials,
+    TerrainVec, UDim, UDim2, UniqueId, Variant, VariantType, Vector2, Vector3, Voxel,
 };
-use rbx_dom_weak::{ustr, InstanceBuilder, WeakDom};
+use rbx_dom_weak::{ustr, DomBudget, InstanceBuilder, SourceLocation, WeakDom};
 
 #[test]
 fn with_bool() {
@@ -90,6 +90,35 @@ fn write_tags() {
     insta::assert_snapshot!(std::str::from_utf8(&encoded).unwrap());
 }
 
+#[test]
+fn write_tags_canonical_ordering() {
+    let _ = env_logger::try_init();
+
+    let encode = |members: &[&str], canonical_ordering: bool| {
+        let mut tags = Tags::new();
+        for member in members {
+            tags.push(member);
+        }
+
+        let part = InstanceBuilder::new("Part").with_property("Tags", tags);
+        let dom = WeakDom::new(part);
+
+        let mut encoded = Vec::new();
+        let options = crate::EncodeOptions::new().canonical_ordering(canonical_ordering);
+        crate::to_writer(&mut encoded, &dom, &[dom.root_ref()], options).unwrap();
+        encoded
+    };
+
+    assert_ne!(
+        encode(&["World", "Hello"], false),
+        encode(&["Hello", "World"], false)
+    );
+    assert_eq!(
+        encode(&["World", "Hello"], true),
+        encode(&["Hello", "World"], true)
+    );
+}
+
 #[test]
 fn read_attributes() {
     let _ = env_logger::try_init();
@@ -209,6 +238,31 @@ fn write_material_colors() {
     insta::assert_snapshot!(std::str::from_utf8(&encoded).unwrap());
 }
 
+#[test]
+fn write_smooth_grid() {
+    let _ = env_logger::try_init();
+
+    let mut grid = SmoothGrid::new();
+    grid.write_voxel_world(
+        TerrainVec::new(3, 1, -7),
+        Voxel::solid(TerrainMaterials::Glacier),
+    );
+
+    let terrain = InstanceBuilder::new("Terrain").with_property("SmoothGrid", grid.clone());
+    let dom = WeakDom::new(terrain);
+
+    let mut encoded = Vec::new();
+    crate::to_writer_default(&mut encoded, &dom, &[dom.root_ref()]).unwrap();
+
+    let decoded = crate::from_reader_default(encoded.as_slice()).unwrap();
+    let terrain = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+
+    assert_eq!(
+        terrain.properties.get(&"SmoothGrid".into()),
+        Some(&BinaryString::from(grid.encode()).into())
+    );
+}
+
 #[test]
 fn read_material_colors() {
     let _ = env_logger::try_init();
@@ -377,3 +431,486 @@ fn enum_item_to_enum() {
 
     assert_eq!(prop_type, VariantType::Enum);
 }
+
+#[test]
+fn legacy_compatibility() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="Roblox 1.0">
+            <Item class="Part" referent="RBX0">
+                <Properties>
+                    <string name="Name">Old Part</string>
+                    <CFrame name="CFrame">
+                        <X>1</X><Y>2</Y><Z>3</Z>
+                        <R00>1</R00><R01>0</R01><R02>0</R02>
+                        <R10>0</R10><R11>1</R11><R12>0</R12>
+                        <R20>0</R20><R21>0</R21><R22>1</R22>
+                    </CFrame>
+                </Properties>
+                <PhysicsSerializedValues>
+                    <Velocity>0 0 0</Velocity>
+                </PhysicsSerializedValues>
+            </Item>
+            <PhysicsSerializedValues />
+        </roblox>
+    "#;
+
+    assert!(crate::from_str_default(document).is_err());
+
+    let options = crate::DecodeOptions::new().legacy_compatibility(true);
+    let tree = crate::from_str(document, options).unwrap();
+    let part = tree.get_by_ref(tree.root().children()[0]).unwrap();
+
+    assert_eq!(part.name, "Old Part");
+    match part.properties.get(&"CFrame".into()) {
+        Some(Variant::CFrame(cframe)) => assert_eq!(cframe.position, Vector3::new(1.0, 2.0, 3.0)),
+        other => panic!("expected CFrame to be read, got {:?}", other),
+    }
+}
+
+#[test]
+fn duplicate_properties() {
+    use crate::{DecodeOptions, DuplicatePropertyBehavior};
+
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="StringValue" referent="RBX0">
+                <Properties>
+                    <string name="Value">first</string>
+                    <string name="Value">last</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let read_value = |behavior| {
+        let options = DecodeOptions::new().duplicate_property_behavior(behavior);
+        let tree = crate::from_str(document, options)?;
+        let value = tree.get_by_ref(tree.root().children()[0]).unwrap();
+
+        Ok::<_, crate::DecodeError>(value.properties.get(&"Value".into()).cloned())
+    };
+
+    assert_eq!(
+        read_value(DuplicatePropertyBehavior::KeepFirst).unwrap(),
+        Some(Variant::String("first".to_owned()))
+    );
+    assert_eq!(
+        read_value(DuplicatePropertyBehavior::KeepLast).unwrap(),
+        Some(Variant::String("last".to_owned()))
+    );
+
+    let error = read_value(DuplicatePropertyBehavior::ErrorOnDuplicate).unwrap_err();
+    assert_eq!(error.line(), 6);
+}
+
+#[test]
+fn orphaned_shared_strings() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="RBX0">
+                <Properties>
+                    <string name="Name">Folder</string>
+                    <SharedString name="UsedString">dXNlZA==</SharedString>
+                </Properties>
+            </Item>
+            <SharedStrings>
+                <SharedString md5="dXNlZA==">dXNlZA==</SharedString>
+                <SharedString md5="b3JwaGFu">b3JwaGFu</SharedString>
+            </SharedStrings>
+        </roblox>
+    "#;
+
+    let orphans = crate::orphaned_shared_strings(
+        document.as_bytes(),
+        crate::DecodeOptions::new().property_behavior(crate::DecodePropertyBehavior::ReadUnknown),
+    )
+    .unwrap();
+
+    assert_eq!(orphans, vec![SharedString::new(b"orphan".to_vec())]);
+}
+
+#[test]
+fn track_source_locations() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="Folder" referent="RBX0">
+                <Properties>
+                    <string name="Name">Outer</string>
+                </Properties>
+                <Item class="Folder" referent="RBX1">
+                    <Properties>
+                        <string name="Name">Inner</string>
+                    </Properties>
+                </Item>
+            </Item>
+        </roblox>
+    "#;
+
+    let tree = crate::from_str(
+        document,
+        crate::DecodeOptions::new().track_source_locations(true),
+    )
+    .unwrap();
+
+    let outer = tree.get_by_ref(tree.root().children()[0]).unwrap();
+    let inner = tree.get_by_ref(outer.children()[0]).unwrap();
+
+    assert_eq!(
+        outer.metadata.get::<SourceLocation>(),
+        Some(&SourceLocation::Lines { start: 3, end: 12 })
+    );
+    assert_eq!(
+        inner.metadata.get::<SourceLocation>(),
+        Some(&SourceLocation::Lines { start: 7, end: 11 })
+    );
+
+    let untracked = crate::from_str_default(document).unwrap();
+    let outer = untracked
+        .get_by_ref(untracked.root().children()[0])
+        .unwrap();
+    assert!(outer.metadata.get::<SourceLocation>().is_none());
+}
+
+#[test]
+fn budget() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="StringValue" referent="RBX0">
+                <Properties>
+                    <string name="Name">First</string>
+                    <string name="Value">Hello, world!</string>
+                </Properties>
+            </Item>
+            <Item class="Folder" referent="RBX1">
+                <Properties>
+                    <string name="Name">Second</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let error = crate::from_str(
+        document,
+        crate::DecodeOptions::new().budget(DomBudget::new().max_instances(2)),
+    )
+    .unwrap_err();
+    assert_eq!(error.line(), 9);
+    assert!(error.to_string().contains("dom budget exceeded"));
+
+    assert!(crate::from_str(
+        document,
+        crate::DecodeOptions::new().budget(DomBudget::new().max_property_bytes(8)),
+    )
+    .is_err());
+
+    let tree = crate::from_str(
+        document,
+        crate::DecodeOptions::new().budget(DomBudget::new().max_instances(3)),
+    )
+    .unwrap();
+    assert_eq!(tree.root().children().len(), 2);
+}
+
+#[test]
+fn studio_referents() {
+    let _ = env_logger::try_init();
+
+    let part = InstanceBuilder::new("Part");
+    let part_ref = part.referent();
+    let tree = WeakDom::new(
+        InstanceBuilder::new("Folder")
+            .with_child(part)
+            .with_child(InstanceBuilder::new("ObjectValue").with_property("Value", part_ref)),
+    );
+
+    let mut buffer = Vec::new();
+    crate::to_writer(
+        &mut buffer,
+        &tree,
+        tree.root().children(),
+        crate::EncodeOptions::new().referent_style(crate::ReferentStyle::Studio),
+    )
+    .unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+
+    let referent = crate::studio_referent(part_ref);
+    assert_eq!(referent.len(), 35);
+    assert_eq!(referent, referent.to_uppercase());
+    assert_eq!(crate::parse_studio_referent(&referent), Some(part_ref));
+    assert!(output.contains(&format!("referent=\"{}\"", referent)));
+    assert!(output.contains(&format!(">{}</Ref>", referent)));
+
+    let decoded = crate::from_str_default(&output).unwrap();
+    let children = decoded.root().children();
+    let value = decoded.get_by_ref(children[1]).unwrap();
+    assert_eq!(
+        value.properties.get(&ustr("Value")),
+        Some(&Variant::Ref(children[0]))
+    );
+}
+
+#[test]
+fn parse_studio_referents() {
+    let referent = Ref::new();
+    let lowercase = format!("RBX{}", referent);
+    assert_eq!(crate::parse_studio_referent(&lowercase), Some(referent));
+
+    assert_eq!(crate::parse_studio_referent("0"), None);
+    assert_eq!(crate::parse_studio_referent("RBX"), None);
+    assert_eq!(
+        crate::parse_studio_referent(&format!("rbx{}", referent)),
+        None
+    );
+    assert_eq!(
+        crate::parse_studio_referent("RBX+0000000000000000000000000000001"),
+        None
+    );
+    assert_eq!(
+        crate::parse_studio_referent("RBX0000000000000000000000000000000G"),
+        None
+    );
+}
+
+#[test]
+fn subtree_refs() {
+    let _ = env_logger::try_init();
+
+    let outside = InstanceBuilder::new("Part").with_name("Outside");
+    let inside = InstanceBuilder::new("Part").with_name("Inside");
+    let outside_ref = outside.referent();
+    let inside_ref = inside.referent();
+
+    let folder = InstanceBuilder::new("Folder").with_children([
+        inside,
+        InstanceBuilder::new("ObjectValue")
+            .with_name("ToInside")
+            .with_property("Value", inside_ref),
+        InstanceBuilder::new("ObjectValue")
+            .with_name("ToOutside")
+            .with_property("Value", outside_ref),
+    ]);
+    let folder_ref = folder.referent();
+    let tree = WeakDom::new(InstanceBuilder::new("DataModel").with_children([outside, folder]));
+
+    let mut buffer = Vec::new();
+    crate::to_writer_default(&mut buffer, &tree, &[folder_ref]).unwrap();
+    let output = String::from_utf8(buffer).unwrap();
+    assert!(!output.contains("Outside\""));
+
+    let decoded = crate::from_str_default(&output).unwrap();
+    let folder = decoded.get_by_ref(decoded.root().children()[0]).unwrap();
+    let children = folder.children();
+    assert_eq!(children.len(), 3);
+
+    let to_inside = decoded.get_by_ref(children[1]).unwrap();
+    assert_eq!(
+        to_inside.properties.get(&ustr("Value")),
+        Some(&Variant::Ref(children[0]))
+    );
+
+    let to_outside = decoded.get_by_ref(children[2]).unwrap();
+    assert_eq!(
+        to_outside.properties.get(&ustr("Value")),
+        Some(&Variant::Ref(rbx_dom_weak::types::Ref::none()))
+    );
+}
+
+#[test]
+fn check_fidelity() {
+    let dom = WeakDom::new(
+        InstanceBuilder::new("Part")
+            .with_property("Size", Vector3::new(1.0, 2.0, 3.0))
+            .with_property("Note", "hello")
+            .with_property("Shade", BrickColor::ReallyRed)
+            .with_property(
+                "Choice",
+                EnumItem {
+                    ty: "Material".to_owned(),
+                    value: 256,
+                },
+            ),
+    );
+
+    let ignore = crate::EncodeOptions::new();
+    let lossy = crate::check_fidelity(&dom, &ignore);
+    assert_eq!(lossy.len(), 3);
+    assert!(lossy
+        .iter()
+        .all(|property| matches!(property.fidelity, crate::Fidelity::Lossy { .. })));
+
+    let write =
+        crate::EncodeOptions::new().property_behavior(crate::EncodePropertyBehavior::WriteUnknown);
+    let lossy = crate::check_fidelity(&dom, &write);
+    let reported: Vec<_> = lossy
+        .iter()
+        .map(|property| (property.property.as_str(), property.fidelity))
+        .collect();
+
+    assert_eq!(
+        reported,
+        [
+            ("Choice", crate::Fidelity::Unsupported),
+            ("Shade", crate::type_fidelity(VariantType::BrickColor)),
+        ]
+    );
+
+    let mut buffer = Vec::new();
+    assert!(crate::to_writer(&mut buffer, &dom, &[dom.root_ref()], write).is_err());
+}
+
+#[test]
+fn diagnostics() {
+    use std::sync::{Arc, Mutex};
+
+    use rbx_dom_weak::{Diagnostic, Diagnostics};
+
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Item class="ObjectValue" referent="RBX0">
+                <Properties>
+                    <string name="Name">Pointer</string>
+                    <Ref name="Value">RBX9</Ref>
+                    <string name="NotARealProperty">Hello</string>
+                </Properties>
+            </Item>
+        </roblox>
+    "#;
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&reported);
+    let diagnostics = Diagnostics::new(move |diagnostic: &Diagnostic| {
+        sink.lock().unwrap().push(diagnostic.clone());
+    });
+
+    let tree = crate::from_str(
+        document,
+        crate::DecodeOptions::new().diagnostics(diagnostics),
+    )
+    .unwrap();
+    let pointer = tree.root().children()[0];
+
+    assert_eq!(
+        *reported.lock().unwrap(),
+        [
+            Diagnostic::PropertySkipped {
+                class: ustr("ObjectValue"),
+                property: ustr("NotARealProperty"),
+            },
+            Diagnostic::RefCleared {
+                referent: pointer,
+                property: ustr("Value"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn event_stream() {
+    let _ = env_logger::try_init();
+
+    let document = r#"
+        <roblox version="4">
+            <Meta name="ExplicitAutoJoints">true</Meta>
+            <Item class="ObjectValue" referent="RBX1">
+                <Properties>
+                    <string name="Name">Pointer</string>
+                    <Ref name="Value">RBX2</Ref>
+                    <Ref name="Other">null</Ref>
+                    <SharedString name="Data">TestHash</SharedString>
+                    <FancyNewType name="Mystery"><X>1</X></FancyNewType>
+                </Properties>
+                <Item class="Folder" referent="RBX2">
+                    <Properties>
+                        <string name="Name">Skipped</string>
+                    </Properties>
+                    <Item class="Folder" referent="RBX3" />
+                </Item>
+                <Item class="Folder" referent="RBX4" />
+            </Item>
+            <SharedStrings>
+                <SharedString md5="TestHash">SGVsbG8=</SharedString>
+            </SharedStrings>
+        </roblox>
+    "#;
+
+    let mut events = crate::XmlEvents::new(document.as_bytes());
+    let mut seen = Vec::new();
+
+    while let Some(event) = events.next() {
+        let event = event.unwrap();
+
+        if let crate::XmlEvent::Property { name, .. } = &event {
+            if name == "Name" {
+                if let Some(crate::XmlEvent::StartInstance { referent, .. }) = seen.last() {
+                    if referent.as_deref() == Some("RBX2") {
+                        events.skip_instance().unwrap();
+                        continue;
+                    }
+                }
+            }
+        }
+
+        seen.push(event);
+    }
+
+    use crate::XmlEvent::*;
+    assert_eq!(
+        seen,
+        [
+            Metadata {
+                name: "ExplicitAutoJoints".to_owned(),
+                value: "true".to_owned(),
+            },
+            StartInstance {
+                class: "ObjectValue".to_owned(),
+                referent: Some("RBX1".to_owned()),
+            },
+            Property {
+                name: "Name".to_owned(),
+                value: Variant::String("Pointer".to_owned()),
+            },
+            RefProperty {
+                name: "Value".to_owned(),
+                referent: Some("RBX2".to_owned()),
+            },
+            RefProperty {
+                name: "Other".to_owned(),
+                referent: None,
+            },
+            SharedStringProperty {
+                name: "Data".to_owned(),
+                hash: "TestHash".to_owned(),
+            },
+            UnknownProperty {
+                name: "Mystery".to_owned(),
+                type_name: "FancyNewType".to_owned(),
+            },
+            StartInstance {
+                class: "Folder".to_owned(),
+                referent: Some("RBX2".to_owned()),
+            },
+            StartInstance {
+                class: "Folder".to_owned(),
+                referent: Some("RBX4".to_owned()),
+            },
+            EndInstance,
+            EndInstance,
+            SharedString {
+                hash: "TestHash".to_owned(),
+                value: rbx_dom_weak::types::SharedString::new(b"Hello".to_vec()),
+            },
+        ]
+    );
+}
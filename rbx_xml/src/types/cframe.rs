@@ -9,6 +9,9 @@ use crate::{
     serializer_core::XmlEventWriter,
 };
 
+/// Very old files sometimes spell the `CoordinateFrame` tag as `CFrame`.
+pub const LEGACY_XML_TAG_NAME: &str = "CFrame";
+
 static TAG_NAMES: [&str; 12] = [
     "X", "Y", "Z", "R00", "R01", "R02", "R10", "R11", "R12", "R20", "R21", "R22",
 ];
@@ -69,6 +72,15 @@ impl XmlType for CFrame {
     }
 }
 
+/// Reads a CFrame stored under its legacy `CFrame` tag name.
+pub fn read_legacy_cframe<R: Read>(reader: &mut XmlEventReader<R>) -> Result<CFrame, DecodeError> {
+    reader.expect_start_with_name(LEGACY_XML_TAG_NAME)?;
+    let value = CFrame::read_xml(reader)?;
+    reader.expect_end_with_name(LEGACY_XML_TAG_NAME)?;
+
+    Ok(value)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
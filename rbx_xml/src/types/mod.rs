@@ -1,5 +1,6 @@
 //! This file packs up all of the type implementations in rbx_xml and exposes
-//! them through two methods, `read_value_xml` and `write_value_xml`.
+//! them through `read_value_xml`, `read_plain_value_xml`, and
+//! `write_value_xml`.
 //!
 //! To support a new type in rbx_xml:
 //!
@@ -29,6 +30,7 @@ mod rect;
 mod referent;
 mod security_capabilities;
 mod shared_string;
+mod smooth_grid;
 mod strings;
 mod tags;
 mod udims;
@@ -53,11 +55,17 @@ use crate::{
     serializer_core::XmlEventWriter,
 };
 
+pub use self::{
+    referent::XML_TAG_NAME as REF_TAG_NAME, shared_string::XML_TAG_NAME as SHARED_STRING_TAG_NAME,
+};
+
 use self::{
     attributes::write_attributes,
+    cframe::{read_legacy_cframe, LEGACY_XML_TAG_NAME as LEGACY_CFRAME_TAG_NAME},
     material_colors::write_material_colors,
     referent::{read_ref, write_ref},
     shared_string::{read_shared_string, write_shared_string},
+    smooth_grid::write_smooth_grid,
     tags::write_tags,
 };
 
@@ -75,6 +83,31 @@ macro_rules! declare_rbx_types {
             xml_type_name: &str,
             instance_id: Ref,
             property_name: &str,
+        ) -> Result<Option<Variant>, DecodeError> {
+            match xml_type_name {
+                self::referent::XML_TAG_NAME => Ok(Some(Variant::Ref(read_ref(reader, instance_id, property_name, state)?))),
+                self::shared_string::XML_TAG_NAME => read_shared_string(reader, instance_id, property_name, state).map(Some),
+
+                _ => match read_plain_value_xml(reader, xml_type_name, state.legacy_compatibility())? {
+                    Some(value) => Ok(Some(value)),
+                    None => {
+                        state.unknown_type_visited(instance_id, property_name, xml_type_name);
+                        reader.eat_unknown_tag()?;
+
+                        Ok(None)
+                    }
+                },
+            }
+        }
+
+        /// Reads a Roblox property value with the given type from the XML event
+        /// stream, for types that can be read without any other context. This
+        /// leaves the stream untouched and returns `None` for Refs,
+        /// SharedStrings, and unknown types.
+        pub fn read_plain_value_xml<R: Read>(
+            reader: &mut XmlEventReader<R>,
+            xml_type_name: &str,
+            legacy_compatibility: bool,
         ) -> Result<Option<Variant>, DecodeError> {
             match xml_type_name {
                 $(<$inner_type>::XML_TAG_NAME => Ok(Some(Variant::$variant_name(<$inner_type>::read_outer_xml(reader)?))),)*
@@ -85,15 +118,9 @@ macro_rules! declare_rbx_types {
                     Ok(Some(Variant::String(value.0)))
                 },
 
-                self::referent::XML_TAG_NAME => Ok(Some(Variant::Ref(read_ref(reader, instance_id, property_name, state)?))),
-                self::shared_string::XML_TAG_NAME => read_shared_string(reader, instance_id, property_name, state).map(Some),
+                LEGACY_CFRAME_TAG_NAME if legacy_compatibility => Ok(Some(Variant::CFrame(read_legacy_cframe(reader)?))),
 
-                _ => {
-                    state.unknown_type_visited(instance_id, property_name, xml_type_name);
-                    reader.eat_unknown_tag()?;
-
-                    Ok(None)
-                },
+                _ => Ok(None),
             }
         }
 
@@ -115,9 +142,10 @@ macro_rules! declare_rbx_types {
 
                 Variant::Ref(value) => write_ref(writer, xml_property_name, *value, state),
                 Variant::SharedString(value) => write_shared_string(writer, xml_property_name, value, state),
-                Variant::Tags(value) => write_tags(writer, xml_property_name, value),
+                Variant::Tags(value) => write_tags(writer, xml_property_name, value, state),
                 Variant::Attributes(value) => write_attributes(writer, xml_property_name, value),
                 Variant::MaterialColors(value) => write_material_colors(writer, xml_property_name, value),
+                Variant::SmoothGrid(value) => write_smooth_grid(writer, xml_property_name, value),
 
                 unknown => {
                     Err(writer.error(EncodeErrorKind::UnsupportedPropertyType(unknown.ty())))
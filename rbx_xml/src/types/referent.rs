@@ -31,7 +31,9 @@ pub fn write_ref<W: Write>(
 ) -> Result<(), EncodeError> {
     writer.write(XmlWriteEvent::start_element(XML_TAG_NAME).attr("name", xml_property_name))?;
 
-    if value.is_none() {
+    // Refs to instances that aren't in the file, like ones outside of the
+    // subtree being written, can't be resolved when the file is read again.
+    if value.is_none() || !state.is_written(value) {
         writer.write(XmlWriteEvent::characters("null"))?;
     } else {
         writer.write_characters(state.map_id(value))?;
@@ -3,6 +3,7 @@ use std::io::Write;
 use rbx_dom_weak::types::Tags;
 
 use crate::{
+    serializer::EmitState,
     serializer_core::{XmlEventWriter, XmlWriteEvent},
     EncodeError,
 };
@@ -13,8 +14,13 @@ pub fn write_tags<W: Write>(
     writer: &mut XmlEventWriter<W>,
     property_name: &str,
     value: &Tags,
+    state: &EmitState,
 ) -> Result<(), EncodeError> {
-    let encoded = value.encode();
+    let encoded = if state.canonical_ordering() {
+        value.encode_sorted()
+    } else {
+        value.encode()
+    };
 
     writer.write(XmlWriteEvent::start_element(XML_TAG_NAME).attr("name", property_name))?;
     writer.write_string(&base64::encode(encoded))?;